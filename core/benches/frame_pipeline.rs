@@ -0,0 +1,64 @@
+// Compares the old copy-per-stage frame handling with pooled, shared frames.
+//
+// Run with `cargo bench -p core --bench frame_pipeline`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use core::video::{FramePool, PixelFormat, VideoFrame};
+use image::{DynamicImage, RgbaImage};
+
+const FRAMES: usize = 300;
+
+/// Previous pipeline: copy out of the decoder, copy into an image, then clone
+/// the frame for the buffer and again for the renderer
+fn copying(width: u32, height: u32, decoded: &[u8]) -> Duration {
+    let start = Instant::now();
+    for index in 0..FRAMES {
+        let data = decoded.to_vec();
+        let image = RgbaImage::from_raw(width, height, data.clone()).unwrap();
+        let frame = VideoFrame::new(DynamicImage::ImageRgba8(image), index as f64, 0.04);
+        let buffered = frame.to_rgba_image();
+        let rendered = frame.to_rgba_image();
+        black_box((buffered, rendered));
+    }
+    start.elapsed()
+}
+
+/// Pooled pipeline: one write into a recycled buffer, clones share it
+fn pooled(width: u32, height: u32, decoded: &[u8]) -> Duration {
+    let pool = FramePool::new(8);
+    let stride = width as usize * 4;
+    let start = Instant::now();
+    for index in 0..FRAMES {
+        let (data, _) = pool.fill(decoded.len(), |buffer| buffer.copy_from_slice(decoded));
+        let frame = VideoFrame::from_data(
+            data,
+            width,
+            height,
+            stride,
+            PixelFormat::Rgba,
+            index as f64,
+            0.04,
+        );
+        let buffered = frame.clone();
+        let rendered = frame.clone();
+        black_box((buffered, rendered));
+    }
+    start.elapsed()
+}
+
+fn main() {
+    for (width, height) in [(160, 90), (640, 360), (1920, 1080)] {
+        let decoded = vec![128u8; width as usize * height as usize * 4];
+        let copy_time = copying(width, height, &decoded);
+        let pool_time = pooled(width, height, &decoded);
+        println!(
+            "{:>4}x{:<4}  copying: {:>8.3} ms/frame  pooled: {:>8.3} ms/frame",
+            width,
+            height,
+            copy_time.as_secs_f64() * 1000.0 / FRAMES as f64,
+            pool_time.as_secs_f64() * 1000.0 / FRAMES as f64,
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// Decode-only throughput benchmark: decodes a file without rendering and
+// reports frames per second for each decoder thread count.
+//
+//   cargo run --release --example decode_throughput -- <video_file> [threads...] [--size WxH]
+//
+// Thread count 0 lets ffmpeg pick one thread per CPU core. --size scales the
+// output like the player does for a small terminal.
+
+use anyhow::{Context, Result, anyhow};
+use core::VideoDecoder;
+use core::video::PixelFormat;
+use std::env;
+use std::time::Instant;
+
+fn parse_size(value: &str) -> Result<(u32, u32)> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| anyhow!("Expected WxH, got '{}'", value))?;
+    Ok((width.parse()?, height.parse()?))
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut args = env::args().skip(1);
+    let Some(video_path) = args.next() else {
+        eprintln!("Usage: decode_throughput <video_file> [threads...] [--size WxH]");
+        return Ok(());
+    };
+
+    let mut thread_counts = Vec::new();
+    let mut size = None;
+    while let Some(arg) = args.next() {
+        if arg == "--size" {
+            let value = args.next().context("--size needs a value")?;
+            size = Some(parse_size(&value)?);
+        } else {
+            thread_counts.push(arg.parse::<usize>().context("Invalid thread count")?);
+        }
+    }
+    if thread_counts.is_empty() {
+        thread_counts = vec![1, 0];
+    }
+
+    for threads in thread_counts {
+        let mut decoder = VideoDecoder::with_threads(&video_path, threads)?;
+        if let Some((width, height)) = size {
+            decoder.set_output(width, height, PixelFormat::Rgba)?;
+        }
+        let info = decoder.get_media_info();
+
+        let start = Instant::now();
+        let mut frames = 0u64;
+        while decoder.decode_next_frame()?.is_some() {
+            frames += 1;
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+
+        let (width, height) = decoder.output_size();
+        println!(
+            "{} ({}x{} -> {}x{}), threads {}: {} frames in {:.2}s, {:.1} fps ({:.1}x realtime)",
+            info.video_codec,
+            info.width,
+            info.height,
+            width,
+            height,
+            if threads == 0 {
+                "auto".to_string()
+            } else {
+                threads.to_string()
+            },
+            frames,
+            elapsed,
+            frames as f64 / elapsed,
+            frames as f64 / elapsed / info.frame_rate,
+        );
+    }
+
+    Ok(())
+}
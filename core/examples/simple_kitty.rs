@@ -172,6 +172,7 @@ fn main() -> Result<()> {
         enable_threading: true,          // Enable multi-threaded processing
         max_frame_dimension: Some(1024), // Limit max frame size for performance
         enable_gpu: false, // Disable GPU acceleration due to alignment issues in Kitty
+        ..Default::default()
     };
 
     // Store these values for display
@@ -199,6 +200,7 @@ fn main() -> Result<()> {
                     enable_threading: true,
                     max_frame_dimension: Some(1024),
                     enable_gpu: false,
+                    ..Default::default()
                 };
                 core::render::TerminalRenderer::new(fallback_config)?
             } else {
@@ -677,13 +679,13 @@ fn prepare_frame_fast(
     }
 
     // Try saving with different formats if PNG fails
-    let save_result = resized_frame.image.to_rgba8().save(output_path);
+    let save_result = resized_frame.to_rgba_image().save(output_path);
     if let Err(e) = save_result {
         log::warn!("Failed to save as PNG: {:?}, trying JPEG", e);
 
         // Try JPEG as fallback
         let mut jpeg_data = std::io::Cursor::new(Vec::new());
-        let img = resized_frame.image.to_rgba8();
+        let img = resized_frame.to_rgba_image();
         let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_data, 90);
         encoder.encode_image(&img)?;
 
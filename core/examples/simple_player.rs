@@ -55,6 +55,7 @@ fn main() -> Result<()> {
         enable_threading: true,       // Enable multi-threaded processing
         max_frame_dimension: Some(1024), // Limit max frame size for performance
         enable_gpu: true,            // Enable GPU acceleration for performance
+        ..Default::default()
     };
 
     let mut renderer = core::render::TerminalRenderer::new(config)
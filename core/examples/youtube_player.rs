@@ -87,6 +87,7 @@ fn main() -> Result<()> {
         enable_threading: true,
         max_frame_dimension: Some(1024),
         enable_gpu: true, // Use GPU acceleration if available
+        ..Default::default()
     };
 
     // Create a YouTube player
@@ -1,10 +1,16 @@
 mod local;
+mod schedule;
+mod stats;
 mod youtube;
 
 pub use local::LocalMediaPlayer;
+pub use schedule::{FrameStats, LatePolicy};
+pub use stats::{PlaybackStats, RateMeter};
 pub use youtube::{YouTubePlayer, YouTubeConfig, YouTubeVideoInfo, extract_youtube_id, is_youtube_url};
 use std::any::Any;
 
+use crate::render::{RenderedFrame, Viewport};
+
 /// Common for all media players
 pub trait MediaPlayer {
     /// Get information about the current media
@@ -36,6 +42,36 @@ pub trait MediaPlayer {
         Ok(()) // Default implementation does nothing
     }
     
+    /// Get the playback speed, 1.0 being normal
+    fn get_speed(&self) -> f64 {
+        1.0 // Default implementation always plays at normal speed
+    }
+    
+    /// Set the playback speed, 1.0 being normal
+    fn set_speed(&mut self, _speed: f64) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("This player does not support changing the speed"))
+    }
+    
+    /// Whether playback has reached the end of the media
+    fn is_finished(&self) -> bool {
+        false // Default implementation never reports the end
+    }
+    
+    /// Handle a terminal resize, optionally moving the video to a new region
+    fn resize(&mut self, _term_width: u16, _term_height: u16, _viewport: Option<Viewport>) {
+        // Default implementation does nothing
+    }
+
+    /// Take the latest rendered frame when the renderer uses buffered output
+    fn take_output(&mut self) -> Option<RenderedFrame> {
+        None // Default implementation renders directly to the terminal
+    }
+
+    /// Diagnostics for a stats overlay
+    fn playback_stats(&self) -> Option<PlaybackStats> {
+        None // Default implementation doesn't collect stats
+    }
+
     /// Stop playback and release resources
     fn stop(&mut self) -> anyhow::Result<()>;
     fn update(&mut self) -> anyhow::Result<()>;
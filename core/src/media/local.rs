@@ -1,24 +1,41 @@
-use crate::render::{RenderConfig, TerminalRenderer};
-use crate::{FrameBuffer, MediaInfo, MediaPlayer, VideoDecoder};
+use super::schedule::{FrameAction, FrameScheduler};
+use super::stats::{PlaybackStats, RateMeter};
+use crate::render::{RenderConfig, RenderedFrame, TerminalRenderer, Viewport};
+use crate::video::PixelFormat;
+use crate::{FrameBuffer, MediaInfo, MediaPlayer, VideoDecoder, VideoFrame};
 use anyhow::Result;
-use log::{debug, warn};
+use log::{debug, trace, warn};
 use std::any::Any;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// A player for local media files
 pub struct LocalMediaPlayer {
-    decoder: VideoDecoder,
+    // Shared with the decode thread that fills `frame_buffer`
+    decoder: Arc<Mutex<VideoDecoder>>,
+    info: MediaInfo,
     renderer: TerminalRenderer,
+    // Native video size, used to negotiate the decoder output with the renderer
+    source_size: (u32, u32),
+    // Output size last applied to the decoder
+    decoder_output: (u32, u32),
     _path: PathBuf,
     current_timestamp: f64,
-    last_frame_time: Instant,
-    frame_duration: Duration,
+    // Playback clock: `clock_base` seconds at `clock_origin`, frozen while paused
+    // and running `speed` times as fast as real time
+    clock_origin: Instant,
+    clock_base: f64,
+    speed: f64,
     paused: bool,
     audio_handle: Option<std::thread::JoinHandle<()>>,
-    audio_start: Option<Instant>,
     frame_buffer: FrameBuffer,
-    prefetching_active: bool,
+    scheduler: FrameScheduler,
+    // Tells the decode thread to skip non-reference frames
+    skip_non_reference: Arc<AtomicBool>,
+    decode_rate: RateMeter,
+    render_rate: RateMeter,
     volume: i32, // Volume level (0-100)
     muted: bool, // Whether audio is muted
     audio_control_tx: Option<std::sync::mpsc::Sender<AudioControl>>,
@@ -28,6 +45,7 @@ pub struct LocalMediaPlayer {
 enum AudioControl {
     SetVolume(i32),
     ToggleMute(bool),
+    SetSpeed(f32),
     Stop,
 }
 
@@ -35,22 +53,49 @@ impl LocalMediaPlayer {
     /// Create a new local media player
     pub fn new<P: AsRef<Path>>(path: P, render_config: Option<RenderConfig>) -> Result<Self> {
         let path_buf = path.as_ref().to_path_buf();
-        let decoder = VideoDecoder::new(&path_buf)?;
-        let info = decoder.get_media_info();
-        let frame_duration = Duration::from_secs_f64(1. / info.frame_rate);
         let render_config = render_config.unwrap_or_default();
+        let mut decoder = VideoDecoder::with_threads(&path_buf, render_config.decode_threads)?;
+        let info = decoder.get_media_info();
+        let late_policy = render_config.late_frames;
+        if let Err(e) = decoder.set_scale_filter(render_config.scale_filter) {
+            warn!("Failed to set decoder scaling filter: {}", e);
+        }
         let renderer = TerminalRenderer::new(render_config)?;
 
+        // Decode straight to the size the renderer draws at from the first frame
+        let decoder_output = renderer.output_size(info.width, info.height);
+        if let Err(e) = decoder.set_output(decoder_output.0, decoder_output.1, PixelFormat::Rgba) {
+            warn!("Failed to set decoder output size: {}", e);
+        }
+        let decoder = Arc::new(Mutex::new(decoder));
+
         // Initialize frame buffer - size based on frame rate (about 5 seconds of video)
         let buffer_capacity = (5.0 * info.frame_rate).ceil() as usize;
         debug!(
             "Creating frame buffer with capacity for {} frames",
             buffer_capacity
         );
-        let frame_buffer = FrameBuffer::new(buffer_capacity);
+        let mut frame_buffer = FrameBuffer::new(buffer_capacity);
+        let skip_non_reference = Arc::new(AtomicBool::new(false));
+        let skip = skip_non_reference.clone();
+        // Convert YUV in a compute pass once the GPU is ready. Until then (or
+        // without one) swscale converts, that beats the CPU fallback.
+        let gpu = renderer
+            .gpu()
+            .filter(|_| renderer.config().gpu_compute)
+            .cloned();
+        frame_buffer.start_prefetching(decoder.clone(), move |decoder: &mut VideoDecoder| {
+            decoder.set_skip_non_reference(skip.load(Ordering::Relaxed));
+            match &gpu {
+                Some(gpu) if gpu.is_available() => Ok(decoder
+                    .decode_next_planes()?
+                    .map(|planes| gpu.convert_yuv(&planes))),
+                _ => decoder.decode_next_frame(),
+            }
+        });
 
         // Check if media has audio stream before attempting extraction
-        let has_audio = decoder.get_media_info().audio_codec.is_some();
+        let has_audio = info.audio_codec.is_some();
 
         // Extract audio to temp file and prepare for playback if the video has audio
         let (audio_path, audio_start) = if has_audio {
@@ -116,6 +161,10 @@ impl LocalMediaPlayer {
                                             debug!("Audio unmuted");
                                         }
                                     }
+                                    AudioControl::SetSpeed(speed) => {
+                                        sink.set_speed(speed);
+                                        debug!("Audio speed set to {}", speed);
+                                    }
                                     AudioControl::Stop => {
                                         running = false;
                                         debug!("Audio playback stopping");
@@ -147,25 +196,103 @@ impl LocalMediaPlayer {
 
         Ok(Self {
             decoder,
+            source_size: (info.width, info.height),
+            info,
             renderer,
+            decoder_output,
             _path: path_buf,
             current_timestamp: 0.,
-            last_frame_time: Instant::now(),
-            frame_duration,
+            // Audio starts playing right after extraction, run the clock from there
+            clock_origin: audio_start.unwrap_or_else(Instant::now),
+            clock_base: 0.,
+            speed: 1.,
             paused: false,
             audio_handle,
-            audio_start,
             frame_buffer,
-            prefetching_active: false,
+            scheduler: FrameScheduler::new(late_policy),
+            skip_non_reference,
+            decode_rate: RateMeter::new(),
+            render_rate: RateMeter::new(),
             volume: 50,   // Default to 50% volume
             muted: false, // Start unmuted
             audio_control_tx: Some(audio_tx),
         })
     }
 
+    /// Have the decoder scale straight to the size the renderer draws at
+    ///
+    /// Called on resize and before every frame so adaptive quality changes
+    /// reach the decoder. If the decode thread is busy with the decoder the
+    /// change is left for the next call, `update` never waits for it.
+    fn sync_decoder_output(&mut self) {
+        let size = self
+            .renderer
+            .output_size(self.source_size.0, self.source_size.1);
+        if size == self.decoder_output {
+            return;
+        }
+        let Ok(mut decoder) = self.decoder.try_lock() else {
+            return;
+        };
+
+        if let Err(e) = decoder.set_output(size.0, size.1, PixelFormat::Rgba) {
+            warn!(
+                "Failed to change decoder output to {}x{}: {}",
+                size.0, size.1, e
+            );
+        }
+        // Frames already buffered keep the old size, the renderer scales them
+        self.decoder_output = size;
+    }
+
+    /// Current playback time in seconds
+    fn clock(&self) -> f64 {
+        if self.paused {
+            self.clock_base
+        } else {
+            self.clock_base + self.clock_origin.elapsed().as_secs_f64() * self.speed
+        }
+    }
+
+    /// Restart the clock at `position`
+    fn set_clock(&mut self, position: f64) {
+        self.clock_base = position;
+        self.clock_origin = Instant::now();
+    }
+
+    /// Take the frame to show now, dropping due frames the policy skips
+    fn take_due_frame(&mut self) -> Option<VideoFrame> {
+        let clock = self.clock();
+        let is_due = |buffer: &FrameBuffer| buffer.next_timestamp().is_some_and(|pts| pts <= clock);
+
+        let mut frame = None;
+        while is_due(&self.frame_buffer) {
+            let Some(due) = self.frame_buffer.next_frame() else {
+                break;
+            };
+            let newer_due = is_due(&self.frame_buffer);
+            match self.scheduler.schedule(due.timestamp, clock, newer_due) {
+                FrameAction::Drop => {
+                    trace!(
+                        "Dropped late frame at {:.3}s (clock {:.3}s)",
+                        due.timestamp, clock
+                    );
+                }
+                FrameAction::Render => {
+                    frame = Some(due);
+                    break;
+                }
+            }
+        }
+
+        self.skip_non_reference
+            .store(self.scheduler.skip_non_reference(), Ordering::Relaxed);
+        frame
+    }
+
     /// Get information about the current media
     pub fn get_media_info(&self) -> MediaInfo {
-        self.decoder.get_media_info()
+        self.info.clone()
     }
 
     /// Get the current buffer status
@@ -177,7 +304,7 @@ impl LocalMediaPlayer {
 impl MediaPlayer for LocalMediaPlayer {
     /// Get information about the current media
     fn get_media_info(&self) -> Option<crate::MediaInfo> {
-        Some(self.decoder.get_media_info())
+        Some(self.info.clone())
     }
 
     /// Get current playback position in seconds
@@ -202,7 +329,8 @@ impl MediaPlayer for LocalMediaPlayer {
             }
         }
 
-        // Clear the frame buffer
+        // Stop decoding and clear the frame buffer
+        self.frame_buffer.stop_prefetching();
         self.frame_buffer.clear();
 
         debug!("Player stopped and resources released");
@@ -249,56 +377,86 @@ impl MediaPlayer for LocalMediaPlayer {
         Ok(())
     }
 
+    /// Whether the last frame has been shown
+    fn is_finished(&self) -> bool {
+        self.frame_buffer.is_finished()
+    }
+
+    /// Get the playback speed, 1.0 being normal
+    fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Set the playback speed, audio follows with its pitch changed
+    fn set_speed(&mut self, speed: f64) -> anyhow::Result<()> {
+        if !(0.1..=10.0).contains(&speed) {
+            return Err(anyhow::anyhow!("Speed out of range: {}", speed));
+        }
+
+        // Keep the position, run the clock at the new rate from here
+        let clock = self.clock();
+        self.set_clock(clock);
+        self.speed = speed;
+
+        if let Some(tx) = &self.audio_control_tx {
+            let _ = tx.send(AudioControl::SetSpeed(speed as f32));
+        }
+        debug!("Speed set to {}", speed);
+        Ok(())
+    }
+
+    /// Forward terminal resizes to the renderer
+    fn resize(&mut self, term_width: u16, term_height: u16, viewport: Option<Viewport>) {
+        self.renderer.resize(term_width, term_height, viewport);
+        self.sync_decoder_output();
+    }
+
+    /// Take the latest frame from the renderer (buffered output only)
+    fn take_output(&mut self) -> Option<RenderedFrame> {
+        self.renderer.take_output()
+    }
+
+    fn playback_stats(&self) -> Option<PlaybackStats> {
+        let (buffered, buffer_capacity, _) = self.frame_buffer.status();
+        Some(PlaybackStats {
+            decode_fps: self.decode_rate.rate(),
+            render_fps: self.render_rate.rate(),
+            frames: self.scheduler.stats(),
+            av_drift: self.current_timestamp - self.clock(),
+            buffered,
+            buffer_capacity,
+            render: self.renderer.stats(),
+        })
+    }
+
     fn update(&mut self) -> Result<()> {
+        self.sync_decoder_output();
+        self.decode_rate.update(self.frame_buffer.frames_decoded());
+        self.render_rate.update(self.scheduler.stats().rendered);
+
         // CRITICAL FIX: If paused, render the current frame with special marker
         if self.paused {
             debug!("Player is paused - rendering current frame");
 
-            // Make sure prefetching is disabled when paused
-            if self.prefetching_active {
-                self.prefetching_active = false;
-                debug!("Disabled prefetching in paused state");
-            }
-
-            // First try to get frame from buffer
-            let frame_result = if let Some(mut frame) = self.frame_buffer.current_frame() {
-                debug!("Using frame from buffer for paused display");
-                // Mark this as a paused frame with negative timestamp
-                frame.timestamp = -1.0;
-                self.renderer.render(&frame)?;
-                debug!(
-                    "Rendered paused frame from buffer at position: {:.2}s",
-                    self.current_timestamp
-                );
-                Ok(Some(frame))
-            } else {
-                // Fall back to decoder if not in buffer
-                self.decoder.decode_current_frame()
-            };
-
-            // Process the frame result
-            match frame_result {
-                Ok(Some(mut frame)) => {
-                    if frame.timestamp >= 0.0 {
-                        // Only process if not already processed from buffer
-                        // Mark this as a paused frame with negative timestamp
-                        frame.timestamp = -1.0;
-                        self.renderer.render(&frame)?;
-                        debug!(
-                            "Rendered paused frame at position: {:.2}s",
-                            self.current_timestamp
-                        );
-
-                        // Add to buffer for future use if not already there
-                        let _ = self.frame_buffer.add_frame(frame.clone());
-                    }
+            // The frame playback stopped on, or the first one after a seek
+            let frame = self
+                .frame_buffer
+                .current_frame()
+                .or_else(|| self.frame_buffer.next_frame());
+            match frame {
+                Some(mut frame) => {
+                    self.current_timestamp = frame.timestamp;
+                    // Mark this as a paused frame with negative timestamp
+                    frame.timestamp = -1.0;
+                    self.renderer.render(&frame)?;
+                    debug!(
+                        "Rendered paused frame at position: {:.2}s",
+                        self.current_timestamp
+                    );
                 }
-                Ok(None) => {
+                None => {
                     debug!("No frame available at paused position");
                 }
-                Err(e) => {
-                    warn!("Error getting paused frame: {}", e);
-                }
             }
 
             // Sleep a bit to reduce CPU usage when paused
@@ -306,160 +464,56 @@ impl MediaPlayer for LocalMediaPlayer {
             return Ok(());
         }
 
-        // Use audio clock for sync if available
-        let elapsed = if let Some(start) = self.audio_start {
-            start.elapsed()
-        } else {
-            self.last_frame_time.elapsed()
-        };
-
-        // Check if it's time to render the next frame
-        if elapsed >= self.frame_duration {
-            if self.audio_start.is_none() {
-                self.last_frame_time = Instant::now();
-            }
-
-            // Try to get frame from buffer first
-            let frame = if let Some(frame) = self.frame_buffer.next_frame() {
-                debug!("Using frame from buffer at {:.2}s", frame.timestamp);
-                Some(frame)
-            } else {
-                // If not in buffer, decode it directly
-                match self.decoder.decode_next_frame()? {
-                    Some(frame) => {
-                        // Add to buffer for future use
-                        let _ = self.frame_buffer.add_frame(frame.clone());
-                        Some(frame)
-                    }
-                    None => None,
-                }
-            };
-
-            // Render the frame if we got one
-            if let Some(frame) = frame {
-                self.current_timestamp = frame.timestamp;
-                self.renderer.render(&frame)?;
-                debug!(
-                    "Rendered frame at timestamp: {:.2}s",
-                    self.current_timestamp
-                );
-
-                // Prefetch next few frames if we're not at capacity yet
-                let (frames_in_buffer, capacity, _) = self.frame_buffer.status();
-                if frames_in_buffer < capacity / 2 {
-                    // Try to decode one more frame ahead
-                    if let Ok(Some(next_frame)) = self.decoder.decode_next_frame() {
-                        debug!("Prefetched frame at {:.2}s", next_frame.timestamp);
-                        let _ = self.frame_buffer.add_frame(next_frame);
-                        // Set prefetching flag to true if not already set
-                        if !self.prefetching_active {
-                            self.prefetching_active = true;
-                            debug!("Prefetching activated");
-                        }
-                    }
-                } else if self.prefetching_active && frames_in_buffer >= capacity / 2 {
-                    // If buffer is filling up, we can stop aggressive prefetching
-                    self.prefetching_active = false;
-                    debug!("Prefetching deactivated - buffer sufficiently filled");
-                }
-            } else {
-                // EOF reached
-                debug!("End of video reached");
-            }
+        // Frames come from the decode thread, never wait for it here
+        if let Some(frame) = self.take_due_frame() {
+            self.current_timestamp = frame.timestamp;
+            self.renderer.render(&frame)?;
+            debug!(
+                "Rendered frame at timestamp: {:.2}s",
+                self.current_timestamp
+            );
+        } else if self.frame_buffer.is_finished() {
+            debug!("End of video reached");
+        } else if self.frame_buffer.next_timestamp().is_none() {
+            trace!("Frame buffer empty, waiting for the decoder");
         }
 
         Ok(())
     }
 
     fn toggle_pause(&mut self) {
+        // Freeze the clock on pause, continue from there on resume
+        let clock = self.clock();
+        self.set_clock(clock);
+
         // CRUCIAL FIX: Toggle pause state and log it prominently
         self.paused = !self.paused;
         log::warn!(
             "LOCAL PLAYER: Playback paused state changed to: {}",
             self.paused
         );
-        self.last_frame_time = Instant::now(); // Reset frame timing
-
-        if self.paused {
-            // Capture current frame to buffer if not already there
-            if self.frame_buffer.current_frame().is_none() {
-                if let Ok(Some(frame)) = self.decoder.decode_current_frame() {
-                    debug!("Adding current frame to buffer for pause state");
-                    let _ = self.frame_buffer.add_frame(frame);
-                }
-            }
-            // Stop prefetching when paused
-            if self.prefetching_active {
-                self.prefetching_active = false;
-                debug!("Stopped prefetching due to pause state");
-            }
-        } else {
-            // When unpausing, ensure buffer is up-to-date for the current position
-            self.frame_buffer.seek(self.current_timestamp);
-            // Consider starting prefetch when unpausing
-            if !self.prefetching_active {
-                self.prefetching_active = true;
-                debug!("Starting prefetching after unpause");
-            }
-        }
     }
 
     fn seek(&mut self, timestamp_secs: f64) -> Result<()> {
         // CRUCIAL FIX: Log seek action prominently
         log::warn!("LOCAL PLAYER: Seeking to {:.2}s", timestamp_secs);
 
-        // Clear the frame buffer as its contents are now invalid
-        self.frame_buffer.clear();
-
-        // Reset prefetching state
-        self.prefetching_active = false;
-
-        // Ensure seek operation completes before returning
-        let seek_result = self.decoder.seek(timestamp_secs);
-        if let Err(ref e) = seek_result {
-            log::warn!("LOCAL PLAYER: Seek failed: {}", e);
-            return seek_result;
-        }
-
-        // Update state
-        self.current_timestamp = timestamp_secs;
-        self.last_frame_time = Instant::now(); // Reset frame timing
-
-        // Update the buffer position
-        self.frame_buffer.seek(timestamp_secs);
-
-        // Force immediate frame update after seek
-        log::warn!("LOCAL PLAYER: Decoding frame at new position");
-        match self.decoder.decode_next_frame() {
-            Ok(Some(frame)) => {
-                log::warn!("LOCAL PLAYER: Rendering frame at new position");
-                // Add the frame to buffer
-                let _ = self.frame_buffer.add_frame(frame.clone());
-                // Important: Set timestamp from the decoded frame
-                self.current_timestamp = frame.timestamp;
-                self.renderer.render(&frame)?;
-
-                // Prefetch a few frames for smoother playback after seek
-                for _ in 0..3 {
-                    if let Ok(Some(next_frame)) = self.decoder.decode_next_frame() {
-                        debug!("Prefetched post-seek frame at {:.2}s", next_frame.timestamp);
-                        let _ = self.frame_buffer.add_frame(next_frame);
-                        // Enable prefetching after seek
-                        self.prefetching_active = true;
-                    } else {
-                        break; // Stop if we can't decode more frames
-                    }
-                }
-            }
-            Ok(None) => {
-                log::warn!("LOCAL PLAYER: No frame at seek position (EOF)");
-            }
-            Err(e) => {
-                log::warn!("LOCAL PLAYER: Error decoding frame after seek: {}", e);
+        {
+            let mut decoder = self.decoder.lock().unwrap();
+            if let Err(e) = decoder.seek(timestamp_secs) {
+                log::warn!("LOCAL PLAYER: Seek failed: {}", e);
                 return Err(e);
             }
+            // Flush while still holding the decoder so the decode thread
+            // can't buffer a frame from before the seek
+            self.frame_buffer.flush(timestamp_secs);
         }
 
+        // Update state, the next update shows the first frame at the new position
+        self.current_timestamp = timestamp_secs;
+        self.set_clock(timestamp_secs);
+        self.scheduler.reset_lag();
+
         Ok(())
     }
 
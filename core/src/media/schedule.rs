@@ -0,0 +1,175 @@
+// Frame scheduling against the playback clock.
+//
+// Players take every buffered frame whose PTS has come due. When more than one
+// is due the older ones are dropped before the renderer spends time resizing
+// and encoding them, and if frames keep arriving far behind the clock the
+// decoder is asked to skip non-reference frames until playback catches up.
+
+use std::time::Duration;
+
+/// How a player treats frames that miss their presentation time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatePolicy {
+    /// A rendered frame this far behind the clock counts as late
+    pub late_after: Duration,
+    /// Drop late frames when a newer frame is already due (false = render every frame in order)
+    pub drop_late: bool,
+    /// Skip decoding non-reference frames while frames are this far behind
+    /// (None = always decode every frame)
+    pub skip_decode_after: Option<Duration>,
+}
+
+impl Default for LatePolicy {
+    fn default() -> Self {
+        Self {
+            late_after: Duration::from_millis(20),
+            drop_late: true,
+            skip_decode_after: Some(Duration::from_millis(500)),
+        }
+    }
+}
+
+/// Frame counters for a stats overlay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Frames handed to the renderer
+    pub rendered: u64,
+    /// Rendered frames that were behind the clock
+    pub late: u64,
+    /// Frames dropped without rendering
+    pub dropped: u64,
+}
+
+/// What to do with a frame that has come due
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameAction {
+    Render,
+    Drop,
+}
+
+/// Applies a `LatePolicy` and keeps the counts
+#[derive(Debug, Default)]
+pub(crate) struct FrameScheduler {
+    policy: LatePolicy,
+    stats: FrameStats,
+    // Frames are far enough behind for the decoder to skip work
+    lagging: bool,
+}
+
+impl FrameScheduler {
+    pub fn new(policy: LatePolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Decide on a due frame at `pts`, `newer_due` says whether the frame
+    /// after it is due as well
+    pub fn schedule(&mut self, pts: f64, clock: f64, newer_due: bool) -> FrameAction {
+        let behind = Duration::from_secs_f64((clock - pts).max(0.0));
+        let late = behind > self.policy.late_after;
+
+        // Start skipping past the threshold, stop once frames are on time again
+        self.lagging = match self.policy.skip_decode_after {
+            Some(threshold) if behind > threshold => true,
+            Some(_) => self.lagging && late,
+            None => false,
+        };
+
+        if late && newer_due && self.policy.drop_late {
+            self.stats.dropped += 1;
+            return FrameAction::Drop;
+        }
+
+        self.stats.rendered += 1;
+        if late {
+            self.stats.late += 1;
+        }
+        FrameAction::Render
+    }
+
+    /// Whether the decoder should skip non-reference frames
+    pub fn skip_non_reference(&self) -> bool {
+        self.lagging
+    }
+
+    /// Forget the lag after a seek, counts are kept
+    pub fn reset_lag(&mut self) {
+        self.lagging = false;
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_time_frames_render() {
+        let mut scheduler = FrameScheduler::new(LatePolicy::default());
+
+        assert_eq!(scheduler.schedule(1.0, 1.005, true), FrameAction::Render);
+        assert_eq!(scheduler.schedule(1.04, 1.04, false), FrameAction::Render);
+        assert_eq!(
+            scheduler.stats(),
+            FrameStats {
+                rendered: 2,
+                late: 0,
+                dropped: 0
+            }
+        );
+    }
+
+    #[test]
+    fn late_frames_dropped_when_superseded() {
+        let mut scheduler = FrameScheduler::new(LatePolicy::default());
+
+        // Two frames due, the older one is dropped
+        assert_eq!(scheduler.schedule(1.0, 1.1, true), FrameAction::Drop);
+        // The newest due frame is rendered even though it is late
+        assert_eq!(scheduler.schedule(1.04, 1.1, false), FrameAction::Render);
+        assert_eq!(
+            scheduler.stats(),
+            FrameStats {
+                rendered: 1,
+                late: 1,
+                dropped: 1
+            }
+        );
+    }
+
+    #[test]
+    fn dropping_can_be_disabled() {
+        let mut scheduler = FrameScheduler::new(LatePolicy {
+            drop_late: false,
+            ..Default::default()
+        });
+
+        assert_eq!(scheduler.schedule(1.0, 2.0, true), FrameAction::Render);
+        assert_eq!(scheduler.stats().late, 1);
+        assert_eq!(scheduler.stats().dropped, 0);
+    }
+
+    #[test]
+    fn skips_decoding_while_far_behind() {
+        let mut scheduler = FrameScheduler::new(LatePolicy::default());
+
+        scheduler.schedule(1.0, 1.1, false);
+        assert!(!scheduler.skip_non_reference());
+        scheduler.schedule(1.0, 2.0, false);
+        assert!(scheduler.skip_non_reference());
+        // Still late but under the threshold: keep skipping until on time
+        scheduler.schedule(2.0, 2.1, false);
+        assert!(scheduler.skip_non_reference());
+        scheduler.schedule(3.0, 3.0, false);
+        assert!(!scheduler.skip_non_reference());
+
+        scheduler.schedule(1.0, 2.0, false);
+        scheduler.reset_lag();
+        assert!(!scheduler.skip_non_reference());
+    }
+}
@@ -0,0 +1,91 @@
+// Playback diagnostics for the player's stats overlay.
+
+use std::time::{Duration, Instant};
+
+use super::FrameStats;
+use crate::render::RenderStats;
+
+/// Snapshot of what the decoder, scheduler and renderer are doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStats {
+    /// Frames decoded per second
+    pub decode_fps: f64,
+    /// Frames rendered per second
+    pub render_fps: f64,
+    /// Rendered, late and dropped frame counts
+    pub frames: FrameStats,
+    /// Shown frame minus the playback clock in seconds (negative = video behind)
+    pub av_drift: f64,
+    /// Frames waiting in the frame buffer
+    pub buffered: usize,
+    /// Capacity of the frame buffer
+    pub buffer_capacity: usize,
+    /// Effective render method, quality factor and GPU state
+    pub render: RenderStats,
+}
+
+/// Events per second, measured over roughly one-second windows
+#[derive(Debug, Clone)]
+pub struct RateMeter {
+    window_start: Instant,
+    total_at_start: u64,
+    rate: f64,
+}
+
+impl RateMeter {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    pub fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            total_at_start: 0,
+            rate: 0.0,
+        }
+    }
+
+    /// Feed the running total, the rate changes once per window
+    pub fn update(&mut self, total: u64) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < Self::WINDOW {
+            return;
+        }
+
+        // Totals can go back when a counter is replaced, e.g. on a new player
+        let count = total.saturating_sub(self.total_at_start);
+        self.rate = count as f64 / elapsed.as_secs_f64();
+        self.window_start = Instant::now();
+        self.total_at_start = total;
+    }
+
+    /// Events per second in the last complete window
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+impl Default for RateMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_updates_once_per_window() {
+        let mut meter = RateMeter::new();
+        meter.update(10);
+        assert_eq!(meter.rate(), 0.0);
+
+        meter.window_start -= Duration::from_secs(2);
+        meter.update(10);
+        assert!((meter.rate() - 5.0).abs() < 0.1);
+
+        // A counter that restarted doesn't produce a huge rate
+        meter.window_start -= Duration::from_secs(1);
+        meter.update(3);
+        assert_eq!(meter.rate(), 0.0);
+    }
+}
@@ -6,7 +6,8 @@ use log::{debug, info, warn, error};
 // Using youtube_dl crate but will configure it to use yt-dlp executable
 use youtube_dl::{YoutubeDl, YoutubeDlOutput};
 
-use crate::render::{RenderConfig, TerminalRenderer};
+use crate::render::{RenderConfig, RenderedFrame, TerminalRenderer, Viewport};
+use crate::video::PixelFormat;
 use crate::{MediaInfo, MediaPlayer, VideoDecoder};
 use std::any::Any;
 
@@ -91,6 +92,8 @@ pub struct YouTubePlayer {
     video_info: Option<YouTubeVideoInfo>,
     /// Media information
     media_info: Option<MediaInfo>,
+    /// Whether the decoder has run out of frames
+    finished: bool,
 }
 
 impl YouTubePlayer {
@@ -121,6 +124,7 @@ impl YouTubePlayer {
             paused: false,
             video_info: None,
             media_info: None,
+            finished: false,
         })
     }
 
@@ -188,8 +192,12 @@ impl YouTubePlayer {
         if start_time.elapsed() > timeout {
             return Err(anyhow!("Timed out while creating video decoder"));
         }
-        let decoder = VideoDecoder::new(&stream_url)
+        let threads = self.renderer.config().decode_threads;
+        let mut decoder = VideoDecoder::with_threads(&stream_url, threads)
             .context("Failed to create video decoder for YouTube stream")?;
+        if let Err(e) = decoder.set_scale_filter(self.renderer.config().scale_filter) {
+            warn!("Failed to set decoder scaling filter: {}", e);
+        }
 
         // Get media info
         let media_info = decoder.get_media_info();
@@ -198,6 +206,7 @@ impl YouTubePlayer {
         self.frame_duration = Duration::from_secs_f64(1.0 / media_info.frame_rate);
         self.media_info = Some(media_info);
         self.decoder = Some(decoder);
+        self.sync_decoder_output();
 
         info!("YouTube player initialized successfully in {:?}", start_time.elapsed());
         Ok(())
@@ -350,6 +359,20 @@ impl YouTubePlayer {
         }
     }
 
+    /// Have the decoder scale straight to the size the renderer draws at
+    fn sync_decoder_output(&mut self) {
+        let (Some(decoder), Some(info)) = (self.decoder.as_mut(), self.media_info.as_ref()) else {
+            return;
+        };
+        let (width, height) = self.renderer.output_size(info.width, info.height);
+        if let Err(e) = decoder.set_output(width, height, PixelFormat::Rgba) {
+            warn!(
+                "Failed to change decoder output to {}x{}: {}",
+                width, height, e
+            );
+        }
+    }
+
     /// Get YouTube-specific video information
     pub fn get_youtube_info(&self) -> Option<YouTubeVideoInfo> {
         self.video_info.clone()
@@ -369,13 +392,30 @@ impl MediaPlayer for YouTubePlayer {
         self.paused
     }
 
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
     fn stop(&mut self) -> Result<()> {
         self.decoder = None;
         Ok(())
     }
 
+    /// Forward terminal resizes to the renderer
+    fn resize(&mut self, term_width: u16, term_height: u16, viewport: Option<Viewport>) {
+        self.renderer.resize(term_width, term_height, viewport);
+        self.sync_decoder_output();
+    }
+
+    /// Take the latest frame from the renderer (buffered output only)
+    fn take_output(&mut self) -> Option<RenderedFrame> {
+        self.renderer.take_output()
+    }
+
     /// Update player state and render the next frame if needed
     fn update(&mut self) -> Result<()> {
+        self.sync_decoder_output();
+
         // CRUCIAL FIX: Handle paused state first
         if self.paused {
             debug!("YouTube player is paused - rendering current frame");
@@ -465,7 +505,10 @@ impl MediaPlayer for YouTubePlayer {
                 },
                 Ok(None) => {
                     // EOF reached
-                    debug!("End of video reached");
+                    if !self.finished {
+                        debug!("End of video reached");
+                    }
+                    self.finished = true;
                 },
                 Err(e) => {
                     warn!("Error decoding frame: {}", e);
@@ -501,6 +544,7 @@ impl MediaPlayer for YouTubePlayer {
             
             // Update state
             self.current_timestamp = timestamp_secs;
+            self.finished = false;
             self.last_frame_time = Instant::now(); // Reset frame timing
 
             // Force immediate frame update after seek
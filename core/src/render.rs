@@ -1,61 +1,65 @@
 // Optimizations in this module:
-// - GPU acceleration for resizing (optional)
+// - GPU acceleration for resizing, YUV conversion and block cell colors (optional, see gpu.rs)
 // - Adaptive resolution: reduces quality in case of low FPS
 // - Terminal-specific graphics protocols: Kitty, iTerm2, Sixel, or fallback to Unicode blocks
 //
-// SIMD/Parallelization/Color Cache/Column Skipping/Dirty Row Diffing optimizations:
+// SIMD/Parallelization/Color Cache/Cell Diffing optimizations:
 // - simd_blend_alpha: SIMD-accelerated alpha blending for RGBA pixels
 // - get_color_code: caches ANSI color codes for fg/bg pairs
-// - render_blocks: skips fully transparent columns, parallelizes row rendering, minimizes ANSI output, and only redraws dirty rows
+// - render_blocks: diffs cells on (glyph, fg, bg) against the previous frame, parallelizes row
+//   encoding and only writes changed cells, coalescing runs so cursor moves and colors are rare
+//
+// Terminal capabilities are probed with escape-sequence queries (see terminal.rs)
+// instead of relying only on TERM and friends.
+// Inside tmux/screen, graphics escapes go through DCS passthrough and Kitty uses
+// Unicode placeholders (see passthrough.rs).
+// Protocol failures are tracked per renderer according to RenderConfig::fallback
+// (see fallback.rs), and the GPU processor is shared through an explicit
+// GpuHandle instead of a global.
+// With OutputTarget::Buffered nothing is written directly; the caller takes the
+// output and places it inside its own UI (see output.rs).
+// Escape-sequence bytes are counted per frame, and with a byte budget the color
+// depth, diff tolerance and resolution adapt to it (see bandwidth.rs).
 //
 // See tests in tests.rs for coverage of these features.
+mod bandwidth;
+mod fallback;
 mod gpu;
+mod output;
+mod passthrough;
+mod terminal;
 #[cfg(test)]
 mod tests;
 
+pub use bandwidth::{BandwidthStats, ColorDepth};
+pub use fallback::FallbackPolicy;
+pub use gpu::GpuHandle;
+pub use output::{BlockCell, CellGrid, OutputTarget, RenderedFrame};
+pub use passthrough::Multiplexer;
+pub use terminal::TerminalCapabilities;
+
 use log::{debug, error, info, trace, warn};
-use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use rayon::prelude::*;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::sync::Mutex as StdMutex;
 use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow};
 use crossterm::terminal;
 
-use crate::video::VideoFrame;
+use crate::media::LatePolicy;
+use crate::video::{FrameView, Resampler, ScaleFilter, VideoFrame};
+
+use crate::render::bandwidth::BandwidthController;
+use crate::render::fallback::FallbackState;
 
-use crate::render::gpu::GpuProcessor;
 use rodio::{Decoder as RodioDecoder, OutputStream, Sink};
 use std::fs::File;
 use std::io::BufReader;
 
-static GPU_PROCESSOR: Lazy<Mutex<Option<GpuProcessor>>> = Lazy::new(|| {
-    // Initialize with additional error handling
-    let processor = pollster::block_on(async {
-        match GpuProcessor::new().await {
-            Ok(processor) => {
-                info!("GPU processor initialized successfully");
-                Some(processor)
-            }
-            Err(e) => {
-                error!("Failed to initialize GPU processor: {}", e);
-                None
-            }
-        }
-    });
-    Mutex::new(processor)
-});
-
-fn get_gpu_processor() -> &'static Mutex<Option<GpuProcessor>> {
-    &GPU_PROCESSOR
-}
-
 /// Supported terminal graphics protocols
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RenderMethod {
     /// Kitty terminal graphics protocol
     Kitty,
@@ -80,6 +84,8 @@ pub struct RenderConfig {
     pub height: Option<u32>,
     /// Maintain aspect ratio when scaling
     pub maintain_aspect: bool,
+    /// Filter for every resize, in the decoder, on the GPU and on the CPU
+    pub scale_filter: ScaleFilter,
     /// X offset in terminal cells
     pub x: u16,
     /// Y offset in terminal cells
@@ -90,12 +96,28 @@ pub struct RenderConfig {
     pub adaptive_resolution: bool,
     /// Target FPS to maintain
     pub target_fps: f32,
+    /// Byte budget per second for terminal output, e.g. for slow SSH links
+    /// (None = unlimited)
+    pub max_bytes_per_second: Option<u64>,
     /// Enable multi-threaded processing when possible
     pub enable_threading: bool,
+    /// Threads for frame and slice threaded video decoding (0 = one per CPU core)
+    pub decode_threads: usize,
+    /// How players treat frames that miss their presentation time
+    pub late_frames: LatePolicy,
     /// Maximum frame size to process (to prevent memory issues)
     pub max_frame_dimension: Option<u32>,
     /// Enable GPU acceleration (disable for compatibility)
     pub enable_gpu: bool,
+    /// With `enable_gpu`, also convert YUV and pick block cell colors in
+    /// compute passes instead of only resizing
+    pub gpu_compute: bool,
+    /// Write to stdout or keep the output for the caller
+    pub output: OutputTarget,
+    /// What to do when a graphics protocol fails
+    pub fallback: FallbackPolicy,
+    /// GPU processor shared with other renderers (None = create one when enable_gpu is set)
+    pub gpu: Option<GpuHandle>,
 }
 
 impl Default for RenderConfig {
@@ -110,19 +132,49 @@ impl Default for RenderConfig {
             width: None,
             height: None,
             maintain_aspect: true,
+            scale_filter: ScaleFilter::default(),
             x: 0,
             y: 0,
             quality: 0.8,
             adaptive_resolution: true,
             target_fps: 30.0,
+            max_bytes_per_second: None,
             enable_threading: true,
+            decode_threads: 0,
+            late_frames: LatePolicy::default(),
             max_frame_dimension: Some(1024),
             // Disable GPU by default in CI environments
             enable_gpu: !is_ci,
+            gpu_compute: true,
+            output: OutputTarget::Stdout,
+            fallback: FallbackPolicy::default(),
+            gpu: None,
         }
     }
 }
 
+/// What a renderer is currently doing, for stats displays
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    /// Method actually used, after auto-detection
+    pub method: RenderMethod,
+    /// Current adaptive quality factor
+    pub quality: f32,
+    /// Whether a GPU processor is ready for resizing and compute passes
+    pub gpu: bool,
+    /// Output bytes and the adaptation to the byte budget
+    pub bandwidth: BandwidthStats,
+}
+
+/// Region of the terminal the video is drawn into, in cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Viewport {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
 /// Renderer for displaying video frames in the terminal
 pub struct TerminalRenderer {
     config: RenderConfig,
@@ -140,7 +192,24 @@ pub struct TerminalRenderer {
     #[allow(dead_code)]
     output_buffer: String,
     color_code_cache: Mutex<HashMap<([u16; 3], [u16; 3]), String>>,
-    prev_frame_hash: Option<Vec<u64>>, // For dirty rectangle/frame diffing
+    // Cells on screen from the last block frame, for cell-level diffing
+    prev_cells: Option<CellGrid>,
+    // Filter taps and buffers for CPU scaling, kept between frames
+    resampler: Resampler,
+    // Output byte counts and adaptation to `RenderConfig::max_bytes_per_second`
+    bandwidth: BandwidthController,
+    // Probed terminal capabilities (cell pixel size, supported protocols)
+    capabilities: TerminalCapabilities,
+    // tmux/screen passthrough for graphics escapes
+    multiplexer: Multiplexer,
+    // Image id for Kitty virtual placements (must fit in 24 bits)
+    kitty_image_id: u32,
+    // Latest frame kept for the caller in buffered mode
+    pending_output: Option<RenderedFrame>,
+    // Failed graphics protocols, see `RenderConfig::fallback`
+    fallback: FallbackState,
+    // GPU processor used for resizing, if enabled
+    gpu: Option<GpuHandle>,
 }
 
 impl TerminalRenderer {
@@ -149,6 +218,10 @@ impl TerminalRenderer {
         // Get terminal size
         let (term_width, term_height) = terminal::size().context("Failed to get terminal size")?;
 
+        // Probe the terminal once; the result is cached for the whole process
+        let capabilities = TerminalCapabilities::detect().clone();
+        let multiplexer = Multiplexer::detect();
+
         // Auto-detect the best rendering method if set to Auto
         let effective_method = if config.method == RenderMethod::Auto {
             Self::detect_best_method()
@@ -159,35 +232,38 @@ impl TerminalRenderer {
         info!("Creating renderer with method: {:?}", effective_method);
         debug!("Terminal size: {}x{}", term_width, term_height);
 
-        // Log GPU acceleration status
-        if config.enable_gpu {
-            // Pre-initialize GPU processor in background to speed up first frame
-            if effective_method != RenderMethod::Blocks {
-                std::thread::spawn(|| {
-                    debug!("Pre-initializing GPU processor in background thread");
-                    // Just accessing the static will trigger initialization
-                    let processor = get_gpu_processor();
-                    match processor.try_lock() {
-                        Some(guard) => {
-                            if guard.is_some() {
-                                debug!("GPU processor initialization complete");
-                            } else {
-                                warn!("GPU processor initialization failed");
-                            }
-                        }
-                        None => warn!("Could not acquire lock to check GPU processor"),
-                    }
-                });
-            }
+        // Blocks output is resized on the CPU, it only needs a GPU for cell colors
+        let gpu = if config.enable_gpu
+            && (effective_method != RenderMethod::Blocks || config.gpu_compute)
+        {
             log::info!("GPU acceleration enabled");
+            Some(match &config.gpu {
+                Some(handle) => handle.clone(),
+                // Initialize in the background to speed up the first frame
+                None => GpuHandle::spawn(),
+            })
         } else {
             log::info!("GPU acceleration disabled");
-        }
+            None
+        };
 
         // For Kitty and iTerm methods, verify terminal capabilities
         if (effective_method == RenderMethod::Kitty || effective_method == RenderMethod::ITerm)
             && effective_method != RenderMethod::Auto
         {
+            if capabilities.responded {
+                let supported = match effective_method {
+                    RenderMethod::Kitty => capabilities.kitty_graphics,
+                    _ => capabilities.supports_iterm(),
+                };
+                if !supported {
+                    log::warn!(
+                        "Selected {:?} but the terminal probe reports no support for it, continuing anyway",
+                        effective_method
+                    );
+                }
+            }
+
             // Check terminal environment variables
             let term = std::env::var("TERM").unwrap_or_default();
             if term.contains("kitty") || std::env::var("KITTY_WINDOW_ID").is_ok() {
@@ -205,6 +281,7 @@ impl TerminalRenderer {
 
         // Initialize with current time
         let now = std::time::Instant::now();
+        let bandwidth = BandwidthController::new(config.max_bytes_per_second);
 
         Ok(Self {
             config,
@@ -218,10 +295,129 @@ impl TerminalRenderer {
             last_kitty_temp_file: None,
             output_buffer: String::with_capacity(term_width as usize * term_height as usize * 25),
             color_code_cache: Mutex::new(HashMap::new()),
-            prev_frame_hash: None,
+            prev_cells: None,
+            resampler: Resampler::default(),
+            bandwidth,
+            capabilities,
+            multiplexer,
+            kitty_image_id: (std::process::id() & 0x00FF_FFFF).max(1),
+            pending_output: None,
+            fallback: FallbackState::default(),
+            gpu,
         })
     }
 
+    /// GPU processor used by this renderer, for sharing with other renderers
+    pub fn gpu(&self) -> Option<&GpuHandle> {
+        self.gpu.as_ref()
+    }
+
+    /// Configuration this renderer was created with
+    pub fn config(&self) -> &RenderConfig {
+        &self.config
+    }
+
+    /// Effective method, quality factor and GPU state
+    pub fn stats(&self) -> RenderStats {
+        RenderStats {
+            method: self.effective_method,
+            quality: self.current_quality_factor,
+            gpu: self.gpu.as_ref().is_some_and(GpuHandle::is_available),
+            bandwidth: self.bandwidth.stats(),
+        }
+    }
+
+    /// Pixel size frames are drawn at for a source of the given size
+    ///
+    /// Follows terminal size, viewport and the adaptive quality factor, so
+    /// decoders can scale straight to it (see `VideoDecoder::set_output`) and
+    /// `render` then skips its own resize.
+    pub fn output_size(&self, source_width: u32, source_height: u32) -> (u32, u32) {
+        self.calculate_dimensions_with_quality(source_width, source_height)
+    }
+
+    /// Forget graphics protocol failures so the configured method is tried again
+    pub fn reset_fallback(&mut self) {
+        self.fallback.reset();
+    }
+
+    /// Terminal capabilities reported by the probe
+    pub fn capabilities(&self) -> &TerminalCapabilities {
+        &self.capabilities
+    }
+
+    /// Handle a terminal resize, optionally moving the video to a new region
+    ///
+    /// Output dimensions are recomputed on the next frame. Stale Kitty
+    /// placements are removed and the cells on screen are forgotten so the next
+    /// frame is drawn in full.
+    pub fn resize(&mut self, term_width: u16, term_height: u16, viewport: Option<Viewport>) {
+        debug!(
+            "Renderer resize: terminal {}x{} -> {}x{}, viewport {:?}",
+            self.term_width, self.term_height, term_width, term_height, viewport
+        );
+        self.term_width = term_width;
+        self.term_height = term_height;
+
+        if let Some(viewport) = viewport {
+            self.config.x = viewport.x;
+            self.config.y = viewport.y;
+            self.config.width = Some(viewport.width as u32);
+            self.config.height = Some(viewport.height as u32);
+        }
+
+        self.invalidate();
+    }
+
+    /// Take the latest rendered frame (buffered output only)
+    pub fn take_output(&mut self) -> Option<RenderedFrame> {
+        self.pending_output.take()
+    }
+
+    /// Send escape sequences to the terminal, or keep them in buffered mode
+    fn emit(&mut self, bytes: Vec<u8>) -> Result<()> {
+        self.bandwidth
+            .record(bytes.len(), std::time::Instant::now());
+        match self.config.output {
+            OutputTarget::Stdout => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+            }
+            OutputTarget::Buffered => match &mut self.pending_output {
+                // Keep escapes the caller hasn't taken yet, e.g. a placement
+                // delete queued just before the next image
+                Some(RenderedFrame::Graphics(pending)) => pending.extend_from_slice(&bytes),
+                _ => self.pending_output = Some(RenderedFrame::Graphics(bytes)),
+            },
+        }
+        Ok(())
+    }
+
+    /// Clear the screen after a failed graphics render to remove artifacts
+    fn clear_artifacts(&mut self) {
+        // In buffered mode the screen belongs to the caller
+        if self.config.output == OutputTarget::Stdout {
+            let _ = write!(std::io::stdout(), "\x1B[2J\x1B[H");
+            std::io::stdout().flush().ok();
+        }
+        // None of the cells from an earlier block frame are left on screen
+        self.prev_cells = None;
+    }
+
+    /// Forget everything drawn so far so the next frame is rendered from scratch
+    pub fn invalidate(&mut self) {
+        if self.effective_method == RenderMethod::Kitty {
+            // Delete all placements; the image data is freed along with them.
+            // Buffered, this goes out after the UI's next flush like a frame
+            let clear = self.multiplexer.wrap(b"\x1B_Ga=d,d=A,q=2\x1B\\");
+            if let Err(e) = self.emit(clear) {
+                warn!("Failed to clear Kitty placements: {}", e);
+            }
+        }
+        self.prev_cells = None;
+    }
+
     /// Detect the best available rendering method for the current terminal
     pub fn detect_best_method() -> RenderMethod {
         // First, check if FORCE_RENDER_METHOD is set
@@ -249,6 +445,22 @@ impl TerminalRenderer {
             }
         }
 
+        // Trust the terminal's answers to the capability probe over environment variables
+        // tmux/screen answer most queries themselves and may drop the graphics
+        // replies, so a "blocks only" verdict there falls through to the heuristics
+        let capabilities = TerminalCapabilities::detect();
+        if let Some(method) = capabilities.preferred_method()
+            && (method != RenderMethod::Blocks || !Multiplexer::detect().is_active())
+        {
+            log::info!(
+                "Terminal probe selected {:?} renderer (version: {:?})",
+                method,
+                capabilities.version
+            );
+            return method;
+        }
+
+        // The terminal didn't answer, fall back to environment heuristics
         // More aggressive Kitty detection
         let has_kitty_env =
             std::env::var("KITTY_WINDOW_ID").is_ok() || std::env::var("KITTY_PID").is_ok();
@@ -301,54 +513,8 @@ impl TerminalRenderer {
 
     /// Check if the terminal supports Sixel by querying device attributes
     fn check_sixel_support() -> Result<bool> {
-        use std::io::{Read, Write};
-        use std::time::Duration;
-
-        // This is a somewhat hacky way to check for Sixel support
-        // We send a Device Attributes (DA) query and wait for a response
-        // The response should contain "4" in the list of attributes if Sixel is supported
-
-        // Save terminal settings
-        let _term = terminal::enable_raw_mode()?;
-
-        // Send Primary Device Attributes query
-        print!("\x1B[c");
-        std::io::stdout().flush()?;
-
-        // Read response with timeout
-        let mut buffer = [0u8; 64];
-        let mut response = Vec::new();
-
-        // Set stdin to non-blocking
-        let stdin = std::io::stdin();
-        let mut handle = stdin.lock();
-
-        // Wait up to 100ms for a response
-        let start = std::time::Instant::now();
-        while start.elapsed() < Duration::from_millis(100) {
-            match handle.read(&mut buffer) {
-                Ok(0) => break, // EOF
-                Ok(n) => response.extend_from_slice(&buffer[0..n]),
-                Err(_) => {
-                    // Wait a bit and try again
-                    std::thread::sleep(Duration::from_millis(5));
-                    continue;
-                }
-            }
-
-            // Check if we have a complete response
-            let response_str = String::from_utf8_lossy(&response);
-            if response_str.contains("\x1B[") && response_str.contains("c") {
-                break;
-            }
-        }
-
-        // Restore terminal settings
-        terminal::disable_raw_mode()?;
-
-        // Check response for Sixel support (attribute 4)
-        let response_str = String::from_utf8_lossy(&response);
-        Ok(response_str.contains("\x1B[?") && response_str.contains(";4;"))
+        // DA1 attribute 4 or a successful XTSMGRAPHICS reply means Sixel is supported
+        Ok(TerminalCapabilities::detect().supports_sixel())
     }
 
     /// Render a video frame to the terminal
@@ -356,102 +522,103 @@ impl TerminalRenderer {
         // Check if this is a paused frame (marked with negative timestamp)
         let is_paused_frame = frame.timestamp < 0.0;
 
-        // For regular playback frames, apply timing and FPS control
+        // Frame timing is up to the caller, players drop late frames by PTS
+        // before they get here (see LatePolicy)
         if !is_paused_frame {
             // Calculate FPS and adjust quality if needed
             self.update_performance_metrics();
-
-            // Check if enough time has passed since the last frame to maintain frame rate
-            let elapsed = self.last_frame_time.elapsed();
-            let min_frame_interval =
-                Duration::from_secs_f64(1.0 / (self.config.target_fps * 1.2) as f64);
-
-            // If we're trying to render too frequently, skip this frame (unless it's initial frame)
-            if elapsed < min_frame_interval && !frame.timestamp.eq(&0.0) {
-                // Skip this frame to maintain target FPS, but don't consider it an error
-                trace!("Skipping frame to maintain target FPS");
-                return Ok(());
-            }
         } else {
             debug!("Rendering paused frame");
         }
 
         // Calculate target dimensions with quality adjustment
-        let (width, height) = self.calculate_dimensions_with_quality(frame);
+        let (width, height) = self.calculate_dimensions_with_quality(frame.width, frame.height);
 
         // Skip resizing if dimensions already match to improve performance
         let resized_frame = if !frame.needs_resize(width, height) {
             log::trace!("Skipping resize - dimensions already match");
             frame.clone()
-        } else if width < 32 || height < 32 {
+        } else if (width < 32 || height < 32) && self.config.scale_filter == ScaleFilter::Nearest {
             // For extremely small sizes, use fast nearest-neighbor resizing
             log::trace!("Using fast resize for small target dimensions");
             frame.fast_thumbnail(width.max(height))
-        } else if self.effective_method != RenderMethod::Blocks && self.config.enable_gpu {
+        } else if let Some(gpu) = self
+            .gpu
+            .as_ref()
+            .filter(|_| self.effective_method != RenderMethod::Blocks)
+        {
             // Try GPU-accelerated resizing for graphical protocols with fallback to CPU
-            // Only if GPU acceleration is enabled in config
-            let processor_mutex = get_gpu_processor();
-
+            // (the renderer only holds a GPU handle if acceleration is enabled)
             // Safely lock the processor mutex with timeout to prevent deadlocks
-            let mut processor_guard = match processor_mutex.try_lock_for(Duration::from_millis(100))
-            {
+            let mut processor_guard = match gpu.try_lock_for(Duration::from_millis(100)) {
                 Some(guard) => guard,
                 None => {
                     warn!("GPU processor mutex lock timed out, falling back to CPU");
-                    // Don't return, just fall back to CPU rendering
-                    return self.render_blocks(&frame.resize(
-                        width,
-                        height,
-                        self.config.maintain_aspect,
-                    ));
+                    None
                 }
             };
 
-            // Check if GPU processor is available
-            if processor_guard.is_none() {
-                debug!("GPU processor not available, using CPU resizing");
-                // Fall back to CPU rendering
-                return self.render_blocks(&frame.resize(
-                    width,
-                    height,
-                    self.config.maintain_aspect,
-                ));
-            }
-
-            let processor = processor_guard.as_mut().unwrap();
-            trace!(
-                "Using GPU acceleration for {}x{} -> {}x{}",
-                frame.width, frame.height, width, height
-            );
-
-            // Attempt GPU processing with fallback to CPU on error, using a timeout
-            let process_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                // Use a timeout to prevent hanging on GPU operations
-                let start_time = std::time::Instant::now();
-                let timeout = Duration::from_millis(1000);
-
-                let result = processor.process_frame(frame, width, height);
-
-                if start_time.elapsed() > timeout {
-                    warn!(
-                        "GPU processing took too long ({:?}), consider disabling GPU acceleration",
-                        start_time.elapsed()
+            match processor_guard.as_mut().and_then(|guard| guard.as_mut()) {
+                Some(processor) => {
+                    trace!(
+                        "Using GPU acceleration for {}x{} -> {}x{}",
+                        frame.width, frame.height, width, height
                     );
-                }
 
-                result
-            }));
+                    // Attempt GPU processing with fallback to CPU on error, using a timeout
+                    let process_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(
+                        || {
+                            // Use a timeout to prevent hanging on GPU operations
+                            let start_time = std::time::Instant::now();
+                            let timeout = Duration::from_millis(1000);
+
+                            let result = processor.process_frame(
+                                frame,
+                                width,
+                                height,
+                                self.config.scale_filter,
+                            );
+
+                            if start_time.elapsed() > timeout {
+                                warn!(
+                                    "GPU processing took too long ({:?}), consider disabling GPU acceleration",
+                                    start_time.elapsed()
+                                );
+                            }
+
+                            result
+                        },
+                    ));
 
-            match process_result {
-                Ok(resized_data) => {
-                    // GPU processing succeeded - resize_from_data returns a VideoFrame directly
-                    frame.resize_from_data(width, height, resized_data)
+                    match process_result {
+                        Ok(resized_data) => {
+                            // GPU processing succeeded - resize_from_data returns a VideoFrame directly
+                            frame.resize_from_data(width, height, resized_data)
+                        }
+                        Err(e) => {
+                            // GPU processing failed, log error and fall back to CPU
+                            error!("GPU processing failed: {:?}", e);
+                            warn!("Falling back to CPU resizing");
+                            frame.resize_using(
+                                width,
+                                height,
+                                self.config.maintain_aspect,
+                                self.config.scale_filter,
+                                &mut self.resampler,
+                            )
+                        }
+                    }
                 }
-                Err(e) => {
-                    // GPU processing failed, log error and fall back to CPU
-                    error!("GPU processing failed: {:?}", e);
-                    warn!("Falling back to CPU resizing");
-                    frame.resize(width, height, self.config.maintain_aspect)
+                None => {
+                    // Still initializing, no adapter, or the lock timed out
+                    debug!("GPU processor not available, using CPU resizing");
+                    frame.resize_using(
+                        width,
+                        height,
+                        self.config.maintain_aspect,
+                        self.config.scale_filter,
+                        &mut self.resampler,
+                    )
                 }
             }
         } else {
@@ -461,169 +628,132 @@ impl TerminalRenderer {
             } else {
                 debug!("GPU acceleration disabled, using CPU resizing");
             }
-            frame.resize(width, height, self.config.maintain_aspect)
+            frame.resize_using(
+                width,
+                height,
+                self.config.maintain_aspect,
+                self.config.scale_filter,
+                &mut self.resampler,
+            )
         };
 
         // Position cursor at the specified location before rendering
         // This ensures the video is drawn at the correct position
-        // Use buffered output to prevent flickering
-        let mut stdout = std::io::stdout();
-        let _ = write!(stdout, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1);
-        // Don't flush here - we'll flush after rendering
-
-        // Track previous method for fallback mechanics
-        // Replace static mut fallback state with safe statics
-        static LAST_METHOD: Lazy<StdMutex<Option<RenderMethod>>> =
-            Lazy::new(|| StdMutex::new(None));
-        static KITTY_FAILED: Lazy<StdMutex<bool>> = Lazy::new(|| StdMutex::new(false));
-        static ITERM_FAILED: Lazy<StdMutex<bool>> = Lazy::new(|| StdMutex::new(false));
-        static SIXEL_FAILED: Lazy<StdMutex<bool>> = Lazy::new(|| StdMutex::new(false));
-
-        // Determine which method to use, with better fallback handling
-        let current_method = {
-            // If any method has previously failed, avoid using it
-            if *KITTY_FAILED.lock().unwrap() && self.effective_method == RenderMethod::Kitty {
-                debug!("Kitty previously failed, using Blocks instead");
-                RenderMethod::Blocks
-            } else if *ITERM_FAILED.lock().unwrap() && self.effective_method == RenderMethod::ITerm
-            {
-                debug!("iTerm previously failed, using Blocks instead");
-                RenderMethod::Blocks
-            } else if *SIXEL_FAILED.lock().unwrap() && self.effective_method == RenderMethod::Sixel
+        if self.config.output == OutputTarget::Stdout {
+            let _ = write!(
+                std::io::stdout(),
+                "\x1B[{};{}H",
+                self.config.y + 1,
+                self.config.x + 1
+            );
+            // Don't flush here - we'll flush after rendering
+        }
+
+        // Avoid methods that are cooling down or disabled after failures
+        let now = std::time::Instant::now();
+        let current_method = match self.effective_method {
+            RenderMethod::Kitty | RenderMethod::ITerm | RenderMethod::Sixel
+                if !self.fallback.allows(self.effective_method, now) =>
             {
-                debug!("Sixel previously failed, using Blocks instead");
-                RenderMethod::Blocks
-            } else if let Some(method) = *LAST_METHOD.lock().unwrap() {
-                if method != RenderMethod::Auto {
-                    method
-                } else {
+                trace!(
+                    "{:?} previously failed, using Blocks instead",
                     self.effective_method
-                }
-            } else {
-                self.effective_method
+                );
+                RenderMethod::Blocks
             }
+            method => method,
         };
 
-        // Render based on the effective method with timeout protection
+        // Render based on the effective method, retrying as the policy allows
         let result = match current_method {
-            RenderMethod::Kitty => {
-                // More robust Kitty rendering with better error handling
-                let kitty_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                    // Use a timeout for Kitty rendering
-                    let kitty_timeout = std::time::Duration::from_millis(3000); // Longer timeout
+            RenderMethod::Kitty | RenderMethod::ITerm | RenderMethod::Sixel => {
+                let attempts = self.config.fallback.attempts.max(1);
+                let render_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    // Use a timeout so retries can't stall playback
+                    let timeout = std::time::Duration::from_millis(3000);
                     let start_time = std::time::Instant::now();
+                    let mut attempt = 0;
+
+                    loop {
+                        attempt += 1;
+                        let result = match current_method {
+                            RenderMethod::Kitty => self.render_kitty(&resized_frame),
+                            RenderMethod::ITerm => self.render_iterm(&resized_frame),
+                            _ => self.render_sixel(&resized_frame),
+                        };
 
-                    // Try different approaches if necessary
-                    let mut attempts = 0;
-                    let max_attempts = 2;
-                    let mut last_error = None;
-
-                    while attempts < max_attempts && start_time.elapsed() < kitty_timeout {
-                        attempts += 1;
-
-                        match self.render_kitty(&resized_frame) {
+                        match result {
                             Ok(_) => return Ok(()),
+                            Err(e) if attempt >= attempts => return Err(e),
                             Err(e) => {
-                                warn!("Kitty rendering attempt {} failed: {}", attempts, e);
-                                last_error = Some(e);
+                                warn!(
+                                    "{:?} rendering attempt {} failed: {}",
+                                    current_method, attempt, e
+                                );
+                                if start_time.elapsed() >= timeout {
+                                    warn!(
+                                        "{:?} rendering timed out after {}ms",
+                                        current_method,
+                                        timeout.as_millis()
+                                    );
+                                    return Err(e);
+                                }
                                 // Small delay before retry
                                 std::thread::sleep(std::time::Duration::from_millis(100));
-                                
-                                // Check if we've exceeded the timeout
-                                if start_time.elapsed() >= kitty_timeout {
-                                    warn!("Kitty rendering timed out after {}ms", kitty_timeout.as_millis());
-                                    break;
-                                }
                             }
                         }
                     }
-
-                    // All attempts failed
-                    Err(anyhow::anyhow!(
-                        "Kitty rendering failed after {} attempts: {:?}",
-                        max_attempts,
-                        last_error
-                    ))
                 }));
 
-                match kitty_result {
+                match render_result {
                     Ok(Ok(_)) => {
-                        debug!("Kitty rendering succeeded");
-                        // Reset the failure flag if it was previously set
-                        if *KITTY_FAILED.lock().unwrap() {
-                            *KITTY_FAILED.lock().unwrap() = false;
-                            info!("Kitty rendering recovered after previous failure");
+                        // The image covers any block output, e.g. from a cool-down,
+                        // so the next block frame can't be diffed against it
+                        self.prev_cells = None;
+                        if self.fallback.record_success(current_method) {
+                            info!(
+                                "{:?} rendering recovered after previous failure",
+                                current_method
+                            );
                         }
                         Ok(())
                     }
                     Ok(Err(e)) => {
-                        error!("Kitty rendering failed: {}", e);
-                        warn!("Temporarily falling back to blocks rendering");
-                        // Only permanently fail after multiple consecutive failures
-                        let mut kitty_failed = KITTY_FAILED.lock().unwrap();
-                        if !*kitty_failed {
-                            warn!("First Kitty failure, will try again next frame");
-                            *kitty_failed = true;
+                        error!("{:?} rendering failed: {}", current_method, e);
+                        if self
+                            .fallback
+                            .record_failure(current_method, &self.config.fallback, now)
+                        {
+                            warn!("Permanently falling back to blocks rendering");
                         } else {
-                            warn!("Kitty rendering failed multiple times, using blocks for now");
+                            warn!(
+                                "Falling back to blocks rendering, retrying {:?} in {:?}",
+                                current_method, self.config.fallback.cool_down
+                            );
                         }
 
                         // Clear the screen to remove artifacts
-                        let _ = write!(std::io::stdout(), "\x1B[2J\x1B[H");
-                        std::io::stdout().flush().ok();
+                        self.clear_artifacts();
                         self.render_blocks(&resized_frame)
                     }
                     Err(e) => {
-                        error!("Kitty rendering panicked: {:?}", e);
-                        warn!("Falling back to blocks rendering after panic");
-                        *KITTY_FAILED.lock().unwrap() = true;
-                        *LAST_METHOD.lock().unwrap() = Some(RenderMethod::Blocks);
+                        error!("{:?} rendering panicked: {:?}", current_method, e);
+                        warn!("Permanently falling back to blocks rendering after panic");
+                        self.fallback.disable(current_method);
 
                         // Clear the screen to remove artifacts
-                        let _ = write!(std::io::stdout(), "\x1B[2J\x1B[H");
-                        std::io::stdout().flush().ok();
+                        self.clear_artifacts();
                         self.render_blocks(&resized_frame)
                     }
                 }
             }
-            RenderMethod::ITerm => match self.render_iterm(&resized_frame) {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    error!("iTerm rendering failed: {}", e);
-                    warn!("Permanently falling back to blocks rendering");
-                    *ITERM_FAILED.lock().unwrap() = true;
-                    *LAST_METHOD.lock().unwrap() = Some(RenderMethod::Blocks);
-                    let _ = write!(std::io::stdout(), "\x1B[2J\x1B[H");
-                    std::io::stdout().flush().ok();
-                    self.render_blocks(&resized_frame)
-                }
-            },
-            RenderMethod::Sixel => match self.render_sixel(&resized_frame) {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    error!("Sixel rendering failed: {}", e);
-                    warn!("Permanently falling back to blocks rendering");
-                    *SIXEL_FAILED.lock().unwrap() = true;
-                    *LAST_METHOD.lock().unwrap() = Some(RenderMethod::Blocks);
-                    let _ = write!(std::io::stdout(), "\x1B[2J\x1B[H");
-                    std::io::stdout().flush().ok();
-                    self.render_blocks(&resized_frame)
-                }
-            },
             RenderMethod::Blocks => self.render_blocks(&resized_frame),
             RenderMethod::Auto => {
                 debug!("Using auto rendering method, defaulting to blocks");
-                let result = self.render_blocks(&resized_frame);
-                *LAST_METHOD.lock().unwrap() = Some(RenderMethod::Blocks);
-                result
+                self.render_blocks(&resized_frame)
             }
         };
 
-        // Store the successful method for future frames
-        if result.is_ok() {
-            *LAST_METHOD.lock().unwrap() = Some(current_method);
-        }
-
         // Update frame time after rendering
         self.last_frame_time = std::time::Instant::now();
 
@@ -655,27 +785,35 @@ impl TerminalRenderer {
     // self.play_audio("/path/to/audio/file.mp3");
 
     // Calculate dimensions based on config and terminal size
-    fn calculate_dimensions(&self, frame: &VideoFrame) -> (u32, u32) {
-        let width = self
+    //
+    // The result is in image pixels: blocks use two pixels per cell vertically,
+    // graphics protocols use the cell size reported by the terminal probe.
+    fn calculate_dimensions(&self, source_width: u32, source_height: u32) -> (u32, u32) {
+        let columns = self
             .config
             .width
             .unwrap_or((self.term_width as u32).saturating_sub(self.config.x as u32));
-        let height = self
+        let rows = self
             .config
             .height
             .unwrap_or((self.term_height as u32).saturating_sub(self.config.y as u32));
 
-        if self.config.maintain_aspect {
-            let frame_aspect = frame.width as f32 / frame.height as f32;
+        let (cell_width, cell_height) = self.cell_pixel_size();
+        let width = columns * cell_width;
+        let height = rows * cell_height;
+
+        if self.config.maintain_aspect && source_width > 0 && source_height > 0 && height > 0 {
+            let frame_aspect = source_width as f32 / source_height as f32;
             let target_aspect = width as f32 / height as f32;
 
             if target_aspect > frame_aspect {
                 // Target is wider than source, constrain by height
-                let new_width = (height as f32 * frame_aspect) as u32;
+                // (rounded so a frame already decoded at this size maps to itself)
+                let new_width = (height as f32 * frame_aspect).round() as u32;
                 return (new_width, height);
             } else {
                 // Target is taller than source, constrain by width
-                let new_height = (width as f32 / frame_aspect) as u32;
+                let new_height = (width as f32 / frame_aspect).round() as u32;
                 return (width, new_height);
             }
         }
@@ -683,9 +821,29 @@ impl TerminalRenderer {
         (width, height)
     }
 
+    /// Pixels per terminal cell for the effective render method
+    fn cell_pixel_size(&self) -> (u32, u32) {
+        if self.effective_method != RenderMethod::Blocks {
+            if let Some((w, h)) = self
+                .capabilities
+                .cell_size(self.term_width, self.term_height)
+                .filter(|&(w, h)| w > 0 && h > 0)
+            {
+                return (w as u32, h as u32);
+            }
+        }
+        // Half blocks give one pixel per column and two per row, which also
+        // approximates the usual 1:2 cell shape when the real size is unknown
+        (1, 2)
+    }
+
     // Calculate dimensions with quality factor applied
-    fn calculate_dimensions_with_quality(&self, frame: &VideoFrame) -> (u32, u32) {
-        let (base_width, base_height) = self.calculate_dimensions(frame);
+    fn calculate_dimensions_with_quality(
+        &self,
+        source_width: u32,
+        source_height: u32,
+    ) -> (u32, u32) {
+        let (base_width, base_height) = self.calculate_dimensions(source_width, source_height);
 
         // Apply quality factor (adjust dimensions)
         let quality = self.config.quality * self.current_quality_factor;
@@ -699,6 +857,9 @@ impl TerminalRenderer {
             quality.max(0.4)
         };
 
+        // The byte budget can go below the quality floor, a smaller image beats a stalled one
+        let effective_quality = effective_quality * self.bandwidth.scale();
+
         let scaled_width = ((base_width as f32) * effective_quality) as u32;
         let scaled_height = ((base_height as f32) * effective_quality) as u32;
 
@@ -796,96 +957,43 @@ impl TerminalRenderer {
                 );
             }
         }
+
+        // Keep the output within the byte budget, color codes are cached per depth
+        let color_depth = self.bandwidth.color_depth();
+        let blocks = self.effective_method == RenderMethod::Blocks;
+        if self.bandwidth.adjust(self.config.target_fps as f64, blocks)
+            && self.bandwidth.color_depth() != color_depth
+        {
+            self.color_code_cache.lock().clear();
+        }
     }
 
     // Implement specific rendering methods
     /// Check if terminal actually supports Kitty graphics protocol
     fn check_kitty_support() -> bool {
-        use kitty_image::{Action, ActionTransmission, Command, Format, Medium, WrappedCommand};
-        
-        // Check for KITTY_WINDOW_ID environment variable
+        // The graphics query (a=q) answers this definitively when the terminal responds
+        let capabilities = TerminalCapabilities::detect();
+        if capabilities.responded
+            && (capabilities.kitty_graphics || !Multiplexer::detect().is_active())
+        {
+            if capabilities.kitty_graphics {
+                info!("Kitty graphics protocol confirmed by terminal probe");
+            } else {
+                warn!("Terminal did not acknowledge the Kitty graphics query");
+            }
+            return capabilities.kitty_graphics;
+        }
+
+        // No probe response (e.g. not attached to a tty), trust the environment
         let has_env = std::env::var("KITTY_WINDOW_ID").is_ok();
-        let term = std::env::var("TERM").unwrap_or_default();
-        let term_matches = term.contains("kitty");
         let kitty_version = std::env::var("KITTY_PID").is_ok();
-
-        // If we have clear evidence of Kitty, assume it works
-        if has_env || kitty_version {
+        let term_matches = std::env::var("TERM").unwrap_or_default().contains("kitty");
+        if has_env || kitty_version || term_matches {
             info!("Detected Kitty terminal via environment variables");
             return true;
         }
 
-        if !has_env && !term_matches {
-            return false;
-        }
-
-        // Test using kitty_image crate which handles the protocol properly
-        let test_result = std::panic::catch_unwind(|| -> bool {
-            // Create a tiny 1x1 test image
-            let mut test_image = image::RgbaImage::new(1, 1);
-            test_image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
-            
-            // Save to temp file for testing
-            let temp_path = std::env::temp_dir().join(format!("kitty_test_{}.png", std::process::id()));
-            if let Err(e) = test_image.save(&temp_path) {
-                warn!("Failed to save test image: {}", e);
-                return false;
-            }
-            
-            // Create an action for transmitting the image
-            let action = Action::TransmitAndDisplay(
-                ActionTransmission {
-                    format: Format::Png,
-                    medium: Medium::File,
-                    width: 1,
-                    height: 1,
-                    ..Default::default()
-                },
-                kitty_image::ActionPut::default(),
-            );
-            
-            // Create a command with the test image
-            let command = Command::with_payload_from_path(action, &temp_path);
-            
-            // Wrap the command in escape codes
-            let wrapped_command = WrappedCommand::new(command);
-            
-            // Try to send the command to stdout
-            let mut stdout = std::io::stdout();
-            if let Err(e) = write!(stdout, "{}", wrapped_command) {
-                warn!("Failed to write Kitty command: {}", e);
-                let _ = std::fs::remove_file(&temp_path);
-                return false;
-            }
-            
-            // Flush and ensure no errors
-            if let Err(e) = stdout.flush() {
-                warn!("Failed to flush stdout: {}", e);
-                let _ = std::fs::remove_file(&temp_path);
-                return false;
-            }
-            
-            // Clean up
-            let _ = std::fs::remove_file(&temp_path);
-            
-            true
-        });
-
-        match test_result {
-            Ok(true) => {
-                info!("Kitty graphics protocol test succeeded");
-                true
-            }
-            Ok(false) => {
-                warn!("Kitty graphics protocol test failed, will use fallback rendering");
-                false
-            }
-            Err(e) => {
-                error!("Kitty test panicked: {:?}", e);
-                warn!("Using fallback rendering due to Kitty test panic");
-                false
-            }
-        }
+        false
     }
 
     fn render_kitty(&mut self, frame: &VideoFrame) -> Result<()> {
@@ -895,14 +1003,14 @@ impl TerminalRenderer {
         log::debug!("Rendering frame with Kitty protocol: {}x{}", frame.width, frame.height);
 
         // Clear the area first to prevent artifacts
-        let mut stdout = std::io::stdout();
-        write!(stdout, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
+        let mut out = Vec::new();
+        write!(out, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
         for y in 0..((frame.height as u16) / 18) + 1 {
-            write!(stdout, "\x1B[{};{}H\x1B[K", self.config.y + 1 + y, self.config.x + 1)?;
+            write!(out, "\x1B[{};{}H\x1B[K", self.config.y + 1 + y, self.config.x + 1)?;
         }
         
         // Position cursor
-        write!(stdout, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
+        write!(out, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
         
         // Create a unique ID for this frame by combining process ID and timestamp
         let frame_id = ((std::process::id() as u32) ^ 
@@ -919,12 +1027,18 @@ impl TerminalRenderer {
         }
         
         // Save current frame to temp file
-        if let Err(e) = frame.image.save(&temp_path) {
+        if let Err(e) = frame.to_rgba_image().save(&temp_path) {
             return Err(anyhow::anyhow!("Failed to save frame to temp file: {}", e));
         }
         
         // Store this temp file path for later cleanup
         self.last_kitty_temp_file = Some(temp_path.clone());
+
+        // Inside tmux/screen, place the image with Unicode placeholders so the
+        // multiplexer tracks it like regular text
+        if self.multiplexer.is_active() {
+            return self.render_kitty_placeholders(frame, &temp_path, out);
+        }
         
         // Create an action to transmit and display the image
         let action = Action::TransmitAndDisplay(
@@ -949,24 +1063,55 @@ impl TerminalRenderer {
         // Wrap the command in escape codes
         let wrapped_command = WrappedCommand::new(command);
         
-        // Send the command to stdout
-        if let Err(e) = write!(stdout, "{}", wrapped_command) {
+        // Send the command to the terminal
+        if let Err(e) = write!(out, "{}", wrapped_command) {
             return Err(anyhow::anyhow!("Kitty image command failed: {}", e));
         }
         
-        stdout.flush()?;
+        self.emit(out)?;
         log::trace!("Kitty render complete for frame at t={:.2}s", frame.timestamp);
         Ok(())
     }
 
-    fn render_iterm(&self, frame: &VideoFrame) -> Result<()> {
+    /// Display a Kitty image through a virtual placement and placeholder cells
+    fn render_kitty_placeholders(
+        &mut self,
+        frame: &VideoFrame,
+        temp_path: &std::path::Path,
+        mut out: Vec<u8>,
+    ) -> Result<()> {
+        let (cell_width, cell_height) = self.cell_pixel_size();
+        let limit = passthrough::kitty_placeholder_limit();
+        let columns = (frame.width.div_ceil(cell_width) as u16).clamp(1, limit);
+        let rows = (frame.height.div_ceil(cell_height) as u16).clamp(1, limit);
+
+        let command =
+            passthrough::kitty_virtual_placement(self.kitty_image_id, temp_path, columns, rows);
+
+        out.extend_from_slice(&self.multiplexer.wrap_str(&command));
+        for row in 0..rows {
+            write!(
+                out,
+                "\x1B[{};{}H{}",
+                self.config.y + 1 + row,
+                self.config.x + 1,
+                passthrough::kitty_placeholder_row(self.kitty_image_id, row, columns)
+            )?;
+        }
+        self.emit(out)?;
+        log::trace!("Kitty placeholder render complete ({}x{} cells)", columns, rows);
+        Ok(())
+    }
+
+    fn render_iterm(&mut self, frame: &VideoFrame) -> Result<()> {
         use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
         use image::ImageEncoder;
         use std::io::Write;
         // Encode the frame as PNG in-memory
         let mut png_data = Vec::new();
         {
-            let img = frame.image.to_rgba8();
+            // PNG needs packed rows
+            let img = frame.to_rgba_image();
             let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
             encoder.write_image(
                 img.as_raw(),
@@ -977,20 +1122,19 @@ impl TerminalRenderer {
         }
         let encoded = BASE64.encode(&png_data);
         // iTerm2: ESC ] 1337 ; File = ... : base64 ST
-        let args = format!("inline=1;width={};height={}", frame.width, frame.height);
+        let args = format!("inline=1;width={}px;height={}px", frame.width, frame.height);
         let seq = format!("\x1B]1337;File={}:{}\x07", args, encoded);
-        let mut stdout = std::io::stdout();
-        write!(stdout, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
-        write!(stdout, "{}", seq)?;
-        stdout.flush()?;
-        Ok(())
+        let mut out = Vec::with_capacity(seq.len() + 16);
+        write!(out, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
+        out.extend_from_slice(&self.multiplexer.wrap_str(&seq));
+        self.emit(out)
     }
 
-    fn render_sixel(&self, frame: &VideoFrame) -> Result<()> {
+    fn render_sixel(&mut self, frame: &VideoFrame) -> Result<()> {
         use std::io::Write;
         // Use the 'sixel' crate if available, otherwise do a minimal quantization
         // Here, we do a minimal quantization and encode manually
-        let img = frame.image.to_rgba8();
+        let img = frame.view();
         let width = img.width() as usize;
         let height = img.height() as usize;
         // Quantize to 16 colors (simple median cut)
@@ -1056,11 +1200,10 @@ impl TerminalRenderer {
             sixel.push(b'-');
         }
         sixel.extend_from_slice(b"\x1B\\");
-        let mut stdout = std::io::stdout();
-        write!(stdout, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
-        stdout.write_all(&sixel)?;
-        stdout.flush()?;
-        Ok(())
+        let mut out = Vec::with_capacity(sixel.len() + 16);
+        write!(out, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1)?;
+        out.extend_from_slice(&self.multiplexer.wrap(&sixel));
+        self.emit(out)
     }
 
     // Helper method to find the closest color in the palette
@@ -1096,15 +1239,21 @@ impl TerminalRenderer {
     }
 
     fn get_color_code(&self, fg: [u16; 3], bg: [u16; 3]) -> String {
+        let color_depth = self.bandwidth.color_depth();
         let mut cache = self.color_code_cache.lock();
         let key = (fg, bg);
         cache
             .entry(key)
-            .or_insert_with(|| {
-                format!(
+            .or_insert_with(|| match color_depth {
+                ColorDepth::TrueColor => format!(
                     "\x1B[38;2;{};{};{};48;2;{};{};{}m",
                     fg[0], fg[1], fg[2], bg[0], bg[1], bg[2]
-                )
+                ),
+                ColorDepth::Indexed256 => format!(
+                    "\x1B[38;5;{};48;5;{}m",
+                    bandwidth::ansi256_index(fg.map(|c| c as u8)),
+                    bandwidth::ansi256_index(bg.map(|c| c as u8))
+                ),
             })
             .clone()
     }
@@ -1128,14 +1277,63 @@ impl TerminalRenderer {
         )
     }
 
+    /// Convert an image into half-block cells, two pixel rows per cell
+    fn build_cell_grid(
+        img: &FrameView,
+        columns: usize,
+        rows: usize,
+        color_depth: ColorDepth,
+    ) -> CellGrid {
+        let height = img.height() as usize;
+        let mut grid = CellGrid::new(columns as u16, rows as u16);
+        for y in 0..rows {
+            for x in 0..columns {
+                let top = img.get_pixel(x as u32, (y * 2) as u32).0;
+                let bot = if y * 2 + 1 < height {
+                    img.get_pixel(x as u32, (y * 2 + 1) as u32).0
+                } else {
+                    [0, 0, 0, 255]
+                };
+                // Leave fully transparent cells to whatever is behind the video
+                if top[3] == 0 && bot[3] == 0 {
+                    continue;
+                }
+                let (fg, bg) = Self::simd_blend_alpha(&top, &bot);
+                grid.set(
+                    x as u16,
+                    y as u16,
+                    Some(BlockCell {
+                        symbol: '▀',
+                        fg: color_depth.quantize(fg.map(|c| c as u8)),
+                        bg: color_depth.quantize(bg.map(|c| c as u8)),
+                    }),
+                );
+            }
+        }
+        grid
+    }
+
+    /// Columns and rows blocks may draw into: the viewport, clipped to the
+    /// terminal so a stale terminal size can't underflow
+    fn visible_cells(&self) -> (usize, usize) {
+        let columns = self.term_width.saturating_sub(self.config.x) as u32;
+        let rows = self.term_height.saturating_sub(self.config.y) as u32;
+        (
+            self.config.width.map_or(columns, |width| width.min(columns)) as usize,
+            self.config.height.map_or(rows, |height| height.min(rows)) as usize,
+        )
+    }
+
     fn render_blocks(&mut self, frame: &VideoFrame) -> Result<()> {
         use std::io::Write;
-        let img = frame.image.to_rgba8();
+        // Read pixels in place, the frame data may be shared and row-padded
+        let img = frame.view();
         let width = img.width() as usize;
         let height = img.height() as usize;
-        let visible_width = width.min(self.term_width as usize - self.config.x as usize);
-        let visible_height =
-            (height.min(self.term_height as usize * 2 - self.config.y as usize) + 1) / 2;
+        let (columns, rows) = self.visible_cells();
+        let visible_width = width.min(columns);
+        // Two pixel rows per cell
+        let visible_height = (height.min(rows * 2) + 1) / 2;
         if visible_width == 0 || visible_height == 0 {
             warn!(
                 "Cannot render frame - visible area is zero: {}x{}",
@@ -1143,152 +1341,159 @@ impl TerminalRenderer {
             );
             return Ok(());
         }
-        let mut stdout = std::io::stdout();
-        if let Err(e) = write!(stdout, "\x1B[s\x1B[?25l") {
-            error!("Failed to set cursor state: {}", e);
-            return Err(anyhow!("Failed to set cursor state: {}", e));
+        let color_depth = self.bandwidth.color_depth();
+        // Pick cell colors in a compute pass if possible, the CPU grid is identical
+        let gpu_grid = self
+            .gpu
+            .as_ref()
+            .filter(|_| self.config.gpu_compute)
+            .and_then(|gpu| {
+                gpu.block_cells(
+                    frame,
+                    visible_width as u16,
+                    visible_height as u16,
+                    color_depth,
+                )
+            });
+        let mut grid = match gpu_grid {
+            Some(grid) => grid,
+            None => Self::build_cell_grid(&img, visible_width, visible_height, color_depth),
+        };
+        if let Some(prev) = &self.prev_cells {
+            bandwidth::hold_similar_cells(&mut grid, prev, self.bandwidth.diff_tolerance());
         }
-        let mut output = String::with_capacity(visible_width * visible_height * 25);
-        output.clear();
-        write!(stdout, "\x1B[{};{}H", self.config.y + 1, self.config.x + 1).ok();
-        // Precompute column transparency for skipping
-        let mut col_transparent = vec![true; visible_width];
-        for x in 0..visible_width {
-            for y in 0..height {
-                if img.get_pixel(x as u32, y as u32).0[3] > 0 {
-                    col_transparent[x] = false;
-                    break;
-                }
-            }
+        // In buffered mode the caller draws the cells into its own buffer
+        if self.config.output == OutputTarget::Buffered {
+            // The caller's backend writes the bytes, count what it will have to send
+            let bytes =
+                bandwidth::estimate_cell_bytes(self.prev_cells.as_ref(), &grid, color_depth);
+            self.bandwidth.record(bytes, std::time::Instant::now());
+            self.prev_cells = Some(grid.clone());
+            self.pending_output = Some(RenderedFrame::Cells(grid));
+            return Ok(());
         }
-        // Frame diffing: hash each row for dirty rectangle
-        let mut row_hashes = vec![0u64; visible_height];
-        let mut dirty_rows = vec![true; visible_height];
-        for y in 0..visible_height {
-            let mut hasher = std::collections::hash_map::DefaultHasher::new();
-            for x in 0..visible_width {
-                let y_top = y * 2;
-                let y_bottom = y * 2 + 1;
-                let top = img.get_pixel(x as u32, y_top as u32).0;
-                let bot = if y_bottom < height {
-                    img.get_pixel(x as u32, y_bottom as u32).0
-                } else {
-                    [0, 0, 0, 255]
-                };
-                top.hash(&mut hasher);
-                bot.hash(&mut hasher);
-            }
-            row_hashes[y] = hasher.finish();
-            if let Some(prev) = &self.prev_frame_hash {
-                if prev.get(y) == Some(&row_hashes[y]) {
-                    dirty_rows[y] = false;
-                }
-            }
+        let output = self.encode_cell_diff(grid);
+        self.bandwidth
+            .record(output.len(), std::time::Instant::now());
+        let mut stdout = std::io::stdout();
+        if let Err(e) = write!(stdout, "{}", output) {
+            error!("Failed to write blocks output: {}", e);
+            return Err(anyhow!("Failed to write blocks output: {}", e));
+        }
+        if let Err(e) = stdout.flush() {
+            error!("Failed to flush stdout: {}", e);
+            return Err(anyhow!("Failed to flush stdout: {}", e));
         }
-        // Parallel row rendering
-        let rendered_rows: Vec<String> = (0..visible_height)
+        Ok(())
+    }
+
+    /// Escapes that turn the cells on screen into `grid`, which then becomes
+    /// the screen the next frame is compared with
+    fn encode_cell_diff(&mut self, grid: CellGrid) -> String {
+        let previous = self
+            .prev_cells
+            .take()
+            .filter(|prev| prev.width == grid.width && prev.height == grid.height);
+        // Rows are independent, each one starts and ends with default colors
+        let rows: Vec<String> = (0..grid.height)
             .into_par_iter()
-            .map(|y| {
-                if dirty_rows[y] {
-                    let mut row = String::with_capacity(visible_width * 25);
-                    row.push_str(&format!(
-                        "\x1B[{};{}H",
-                        self.config.y as usize + y + 1,
-                        self.config.x as usize + 1
-                    ));
-                    let mut last_fg_color = [999u16; 3];
-                    let mut last_bg_color = [999u16; 3];
-                    let mut last_code = String::new();
-                    let mut run_len = 0;
-                    let mut run_char = ' ';
-                    let mut top_rgba = [0u8; 4];
-                    let mut bot_rgba = [0u8; 4];
-                    for x in 0..visible_width {
-                        if col_transparent[x] {
-                            if run_char != ' ' {
-                                row.push_str(&last_code);
-                                for _ in 0..run_len {
-                                    row.push(run_char);
-                                }
-                                run_len = 0;
-                            }
-                            run_char = ' ';
-                            run_len += 1;
-                            continue;
-                        }
-                        let y_top = y * 2;
-                        let y_bottom = y * 2 + 1;
-                        top_rgba.copy_from_slice(&img.get_pixel(x as u32, y_top as u32).0);
-                        if y_bottom < height {
-                            bot_rgba.copy_from_slice(&img.get_pixel(x as u32, y_bottom as u32).0);
-                        } else {
-                            bot_rgba = [0, 0, 0, 255];
-                        }
-                        if top_rgba[3] == 0 && bot_rgba[3] == 0 {
-                            if run_char != ' ' {
-                                row.push_str(&last_code);
-                                for _ in 0..run_len {
-                                    row.push(run_char);
-                                }
-                                run_len = 0;
-                            }
-                            run_char = ' ';
-                            run_len += 1;
-                            continue;
-                        }
-                        let (top_rgb, bot_rgb) = Self::simd_blend_alpha(&top_rgba, &bot_rgba);
-                        let fg_changed = top_rgb != last_fg_color;
-                        let bg_changed = bot_rgb != last_bg_color;
-                        let code = if fg_changed || bg_changed {
-                            self.get_color_code(top_rgb, bot_rgb)
-                        } else {
-                            last_code.clone()
-                        };
-                        if run_char != '▀' || code != last_code {
-                            if run_len > 0 {
-                                row.push_str(&last_code);
-                                for _ in 0..run_len {
-                                    row.push(run_char);
-                                }
+            .map(|y| self.encode_row_diff(previous.as_ref(), &grid, y))
+            .collect();
+
+        let mut output = String::with_capacity(rows.iter().map(String::len).sum::<usize>() + 32);
+        output.push_str("\x1B[s\x1B[?25l");
+        for row in rows {
+            output.push_str(&row);
+        }
+        output.push_str("\x1B[0m\x1B[u\x1B[?25h");
+        self.prev_cells = Some(grid);
+        output
+    }
+
+    /// Write the cells of row `y` that differ from `previous` in glyph, fg or bg
+    ///
+    /// Each run of changed cells starts with a cursor move and colors are only
+    /// written when they change. A short gap of unchanged cells in the current
+    /// colors is rewritten when that's shorter than moving the cursor past it.
+    fn encode_row_diff(&self, previous: Option<&CellGrid>, grid: &CellGrid, y: u16) -> String {
+        let changed = |x: u16| previous.is_none_or(|prev| prev.get(x, y) != grid.get(x, y));
+        let mut row = String::new();
+        // Column the cursor is left at by the last write
+        let mut cursor: Option<u16> = None;
+        // Colors currently set, None for the terminal defaults
+        let mut pen: Option<([u8; 3], [u8; 3])> = None;
+
+        for x in 0..grid.width {
+            if !changed(x) {
+                continue;
+            }
+
+            if cursor != Some(x) {
+                let jump = format!("\x1B[{};{}H", self.config.y + y + 1, self.config.x + x + 1);
+                let bridge = cursor.and_then(|from| {
+                    let mut glyphs = String::new();
+                    for gx in from..x {
+                        match grid.get(gx, y) {
+                            Some(cell) if pen == Some((cell.fg, cell.bg)) => {
+                                glyphs.push(cell.symbol)
                             }
-                            run_char = '▀';
-                            run_len = 1;
-                            last_code = code.clone();
-                            last_fg_color = top_rgb;
-                            last_bg_color = bot_rgb;
-                        } else {
-                            run_len += 1;
+                            None if pen.is_none() => glyphs.push(' '),
+                            _ => return None,
                         }
                     }
-                    if run_len > 0 {
-                        row.push_str(&last_code);
-                        for _ in 0..run_len {
-                            row.push(run_char);
-                        }
+                    Some(glyphs)
+                });
+                match bridge {
+                    Some(glyphs) if glyphs.len() < jump.len() => row.push_str(&glyphs),
+                    _ => row.push_str(&jump),
+                }
+            }
+
+            match grid.get(x, y) {
+                Some(cell) => {
+                    self.push_colors(&mut row, pen, cell.fg, cell.bg);
+                    pen = Some((cell.fg, cell.bg));
+                    row.push(cell.symbol);
+                }
+                None => {
+                    // Transparent cells are cleared to the terminal background
+                    if pen.is_some() {
+                        row.push_str("\x1B[0m");
+                        pen = None;
                     }
-                    row.push_str("\x1B[0m");
-                    row
-                } else {
-                    String::new()
+                    row.push(' ');
                 }
-            })
-            .collect();
-        for row in rendered_rows {
-            if !row.is_empty() {
-                output.push_str(&row);
             }
+            cursor = Some(x + 1);
         }
-        output.push_str("\x1B[0m\x1B[u\x1B[?25h");
-        if let Err(e) = write!(stdout, "{}", output) {
-            error!("Failed to write blocks output: {}", e);
-            return Err(anyhow!("Failed to write blocks output: {}", e));
+
+        if pen.is_some() {
+            row.push_str("\x1B[0m");
         }
-        if let Err(e) = stdout.flush() {
-            error!("Failed to flush stdout: {}", e);
-            return Err(anyhow!("Failed to flush stdout: {}", e));
+        row
+    }
+
+    /// Switch from the `pen` colors to `fg` over `bg`, writing only what changed
+    fn push_colors(
+        &self,
+        out: &mut String,
+        pen: Option<([u8; 3], [u8; 3])>,
+        fg: [u8; 3],
+        bg: [u8; 3],
+    ) {
+        let color = |layer: u8, rgb: [u8; 3]| match self.bandwidth.color_depth() {
+            ColorDepth::TrueColor => {
+                format!("\x1B[{};2;{};{};{}m", layer, rgb[0], rgb[1], rgb[2])
+            }
+            ColorDepth::Indexed256 => {
+                format!("\x1B[{};5;{}m", layer, bandwidth::ansi256_index(rgb))
+            }
+        };
+        match pen {
+            Some(current) if current == (fg, bg) => {}
+            Some((current_fg, _)) if current_fg == fg => out.push_str(&color(48, bg)),
+            Some((_, current_bg)) if current_bg == bg => out.push_str(&color(38, fg)),
+            _ => out.push_str(&self.get_color_code(fg.map(u16::from), bg.map(u16::from))),
         }
-        // Save row hashes for next frame
-        self.prev_frame_hash = Some(row_hashes);
-        Ok(())
     }
 }
@@ -0,0 +1,469 @@
+// Output bandwidth tracking and adaptation.
+//
+// Over SSH the bottleneck is usually the link to the terminal rather than the
+// CPU. The renderer counts the escape-sequence bytes of every frame: exactly
+// for output it writes itself, and estimated for cell grids that the caller
+// draws (see `estimate_cell_bytes`). With a byte budget
+// (`RenderConfig::max_bytes_per_second`), the output needed at the target
+// frame rate is kept under that budget. When it goes over, the controller
+// first drops block colors to the 256-color palette, then raises the diff
+// tolerance so small color changes don't redraw cells, and finally lowers the
+// resolution. It steps back up in reverse order once there is room again.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::output::CellGrid;
+
+/// Colors used for block output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    /// 24-bit RGB escapes
+    #[default]
+    TrueColor,
+    /// xterm 256-color palette, roughly half the bytes per color change
+    Indexed256,
+}
+
+impl ColorDepth {
+    /// Snap a color to what this depth can show
+    pub fn quantize(self, rgb: [u8; 3]) -> [u8; 3] {
+        match self {
+            ColorDepth::TrueColor => rgb,
+            ColorDepth::Indexed256 => ansi256_rgb(ansi256_index(rgb)),
+        }
+    }
+
+    /// Length of the SGR escape setting one color, e.g. `\x1B[38;2;r;g;bm`
+    fn escape_len(self, rgb: [u8; 3]) -> usize {
+        match self {
+            ColorDepth::TrueColor => 10 + rgb.iter().map(|&c| digits(c)).sum::<usize>(),
+            ColorDepth::Indexed256 => 8 + digits(ansi256_index(rgb)),
+        }
+    }
+}
+
+fn digits(value: u8) -> usize {
+    match value {
+        0..=9 => 1,
+        10..=99 => 2,
+        _ => 3,
+    }
+}
+
+/// Levels of the xterm 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Closest xterm 256-color palette index, from the color cube or gray ramp
+pub fn ansi256_index(rgb: [u8; 3]) -> u8 {
+    let cube_level = |c: u8| -> usize {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (**level as i16 - c as i16).abs())
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    };
+    let [r, g, b] = rgb.map(cube_level);
+    let cube = 16 + 36 * r + 6 * g + b;
+
+    // Gray ramp 232..=255 covers 8..=238 in steps of 10
+    let average = rgb.iter().map(|&c| c as u32).sum::<u32>() / 3;
+    let gray = 232 + ((average.saturating_sub(3)) / 10).min(23) as usize;
+
+    let distance = |index: usize| -> u32 {
+        let candidate = ansi256_rgb(index as u8);
+        rgb.iter()
+            .zip(candidate)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    };
+    if distance(gray) < distance(cube) {
+        gray as u8
+    } else {
+        cube as u8
+    }
+}
+
+/// RGB value of an xterm 256-color palette index (cube and gray ramp only)
+fn ansi256_rgb(index: u8) -> [u8; 3] {
+    if index >= 232 {
+        let v = 8 + (index - 232) * 10;
+        return [v, v, v];
+    }
+    // The 16 system colors are terminal-defined, map them into the cube
+    let i = index.max(16) as usize - 16;
+    [
+        CUBE_LEVELS[i / 36],
+        CUBE_LEVELS[(i / 6) % 6],
+        CUBE_LEVELS[i % 6],
+    ]
+}
+
+/// Estimate the bytes a cell-diffing terminal backend writes to show `grid`
+/// when `previous` is on screen
+///
+/// Counts cursor moves for gaps, color escapes when fg or bg changes and the
+/// UTF-8 glyphs, which is how ratatui and similar backends draw.
+pub fn estimate_cell_bytes(
+    previous: Option<&CellGrid>,
+    grid: &CellGrid,
+    depth: ColorDepth,
+) -> usize {
+    let same_shape = previous.filter(|p| p.width == grid.width && p.height == grid.height);
+    let mut bytes = 0;
+    let mut last_fg = None;
+    let mut last_bg = None;
+    let mut cursor = None;
+
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let Some(cell) = grid.get(x, y) else {
+                continue;
+            };
+            if same_shape.is_some_and(|p| p.get(x, y) == Some(cell)) {
+                continue;
+            }
+
+            if cursor != Some((x, y)) {
+                // CSI row ; col H
+                bytes += 4 + (y + 1).to_string().len() + (x + 1).to_string().len();
+            }
+            if last_fg != Some(cell.fg) {
+                bytes += depth.escape_len(cell.fg);
+                last_fg = Some(cell.fg);
+            }
+            if last_bg != Some(cell.bg) {
+                bytes += depth.escape_len(cell.bg);
+                last_bg = Some(cell.bg);
+            }
+            bytes += cell.symbol.len_utf8();
+            cursor = Some((x + 1, y));
+        }
+    }
+    bytes
+}
+
+/// Keep cells from `previous` whose colors changed by less than `tolerance`
+/// per channel, so the backend's diff skips them
+pub fn hold_similar_cells(grid: &mut CellGrid, previous: &CellGrid, tolerance: u8) {
+    if tolerance == 0 || grid.width != previous.width || grid.height != previous.height {
+        return;
+    }
+    let close = |a: [u8; 3], b: [u8; 3]| a.iter().zip(b).all(|(&a, b)| a.abs_diff(b) < tolerance);
+    for (cell, old) in grid.cells.iter_mut().zip(&previous.cells) {
+        if let (Some(new), Some(old)) = (cell.as_ref(), old)
+            && new.symbol == old.symbol
+            && close(new.fg, old.fg)
+            && close(new.bg, old.bg)
+        {
+            *cell = Some(*old);
+        }
+    }
+}
+
+/// Output bandwidth measurements and the current adaptation, for stats displays
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BandwidthStats {
+    /// Average escape-sequence bytes per frame
+    pub bytes_per_frame: f64,
+    /// Bytes per second actually sent
+    pub throughput: f64,
+    /// Configured budget in bytes per second (None = unlimited)
+    pub budget: Option<u64>,
+    /// Color depth for block output
+    pub color_depth: ColorDepth,
+    /// Per-channel change below which cells are not redrawn
+    pub diff_tolerance: u8,
+    /// Resolution factor applied on top of the quality factor
+    pub scale: f32,
+}
+
+/// Measures output bytes and adapts output to a byte budget
+#[derive(Debug)]
+pub(crate) struct BandwidthController {
+    budget: Option<u64>,
+    // Bytes per frame over the last window
+    samples: VecDeque<(Instant, usize)>,
+    frames_since_adjust: usize,
+    color_depth: ColorDepth,
+    diff_tolerance: u8,
+    scale: f32,
+}
+
+impl BandwidthController {
+    /// Time the throughput is measured over
+    const WINDOW: Duration = Duration::from_secs(2);
+    /// Frames between adjustments, matching the frame-time adaptation
+    const ADJUST_EVERY: usize = 15;
+    /// Per-channel diff tolerance steps
+    const TOLERANCES: [u8; 4] = [0, 4, 8, 16];
+    const MIN_SCALE: f32 = 0.25;
+    /// Step back up only when the output would use less than this share of the budget
+    const HEADROOM: f64 = 0.5;
+
+    pub fn new(budget: Option<u64>) -> Self {
+        Self {
+            budget,
+            samples: VecDeque::new(),
+            frames_since_adjust: 0,
+            color_depth: ColorDepth::TrueColor,
+            diff_tolerance: 0,
+            scale: 1.0,
+        }
+    }
+
+    /// Count the bytes of one frame
+    pub fn record(&mut self, bytes: usize, now: Instant) {
+        self.samples.push_back((now, bytes));
+        while let Some(&(time, _)) = self.samples.front() {
+            if now.duration_since(time) <= Self::WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        self.frames_since_adjust += 1;
+    }
+
+    fn bytes_per_frame(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().map(|(_, b)| *b as f64).sum::<f64>() / self.samples.len() as f64
+    }
+
+    fn throughput(&self) -> f64 {
+        let (Some((first, _)), Some((last, _))) = (self.samples.front(), self.samples.back())
+        else {
+            return 0.0;
+        };
+        let span = last.duration_since(*first).as_secs_f64();
+        if span <= 0.0 {
+            return 0.0;
+        }
+        // The first sample opens the window, its bytes went out before it
+        let bytes: usize = self.samples.iter().skip(1).map(|(_, b)| b).sum();
+        bytes as f64 / span
+    }
+
+    /// Step output up or down to fit the budget at `target_fps`
+    ///
+    /// Color depth and diff tolerance only apply to block output, so for
+    /// graphics protocols (`blocks` false) only the resolution changes.
+    /// Returns whether anything changed.
+    pub fn adjust(&mut self, target_fps: f64, blocks: bool) -> bool {
+        let Some(budget) = self.budget else {
+            return false;
+        };
+        if self.frames_since_adjust < Self::ADJUST_EVERY {
+            return false;
+        }
+        self.frames_since_adjust = 0;
+
+        // Bytes per second needed to show every frame at the target rate
+        let demand = self.bytes_per_frame() * target_fps;
+        let pressure = demand / budget.max(1) as f64;
+        let tolerance_step = Self::TOLERANCES
+            .iter()
+            .position(|&t| t == self.diff_tolerance)
+            .unwrap_or(0);
+
+        let changed = if pressure > 1.0 {
+            if blocks && self.color_depth == ColorDepth::TrueColor {
+                self.color_depth = ColorDepth::Indexed256;
+                true
+            } else if blocks && tolerance_step + 1 < Self::TOLERANCES.len() {
+                self.diff_tolerance = Self::TOLERANCES[tolerance_step + 1];
+                true
+            } else if self.scale > Self::MIN_SCALE {
+                // Bytes scale with the area, so shrink each side by the square root
+                let factor = (1.0 / pressure).sqrt().min(0.9) as f32;
+                self.scale = (self.scale * factor).max(Self::MIN_SCALE);
+                true
+            } else {
+                false
+            }
+        } else if pressure < Self::HEADROOM {
+            if self.scale < 1.0 {
+                self.scale = (self.scale * 1.1).min(1.0);
+                true
+            } else if tolerance_step > 0 {
+                self.diff_tolerance = Self::TOLERANCES[tolerance_step - 1];
+                true
+            } else if self.color_depth != ColorDepth::TrueColor {
+                self.color_depth = ColorDepth::TrueColor;
+                true
+            } else {
+                false
+            }
+        } else {
+            false
+        };
+
+        if changed {
+            log::debug!(
+                "Output {:.0} B/s at target fps vs budget {} B/s: colors {:?}, diff tolerance {}, scale {:.2}",
+                demand,
+                budget,
+                self.color_depth,
+                self.diff_tolerance,
+                self.scale
+            );
+            // Measure the new settings from scratch
+            self.samples.clear();
+        }
+        changed
+    }
+
+    pub fn color_depth(&self) -> ColorDepth {
+        self.color_depth
+    }
+
+    pub fn diff_tolerance(&self) -> u8 {
+        self.diff_tolerance
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn stats(&self) -> BandwidthStats {
+        BandwidthStats {
+            bytes_per_frame: self.bytes_per_frame(),
+            throughput: self.throughput(),
+            budget: self.budget,
+            color_depth: self.color_depth,
+            diff_tolerance: self.diff_tolerance,
+            scale: self.scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::BlockCell;
+
+    fn feed(controller: &mut BandwidthController, bytes: usize) {
+        let start = Instant::now();
+        for i in 0..BandwidthController::ADJUST_EVERY {
+            controller.record(bytes, start + Duration::from_millis(33 * i as u64));
+        }
+    }
+
+    fn cell(fg: [u8; 3], bg: [u8; 3]) -> Option<BlockCell> {
+        Some(BlockCell {
+            symbol: '▀',
+            fg,
+            bg,
+        })
+    }
+
+    #[test]
+    fn ansi256_round_trip() {
+        assert_eq!(ansi256_index([0, 0, 0]), 16);
+        assert_eq!(ansi256_index([255, 255, 255]), 231);
+        assert_eq!(ansi256_index([255, 0, 0]), 196);
+        assert_eq!(ansi256_index([128, 128, 128]), 244);
+        // Palette colors map to themselves
+        for index in 16..=255u8 {
+            assert_eq!(
+                ColorDepth::Indexed256.quantize(ansi256_rgb(index)),
+                ansi256_rgb(index)
+            );
+        }
+    }
+
+    #[test]
+    fn no_budget_never_adapts() {
+        let mut controller = BandwidthController::new(None);
+        feed(&mut controller, 1_000_000);
+        assert!(!controller.adjust(30.0, true));
+        assert_eq!(controller.scale(), 1.0);
+    }
+
+    #[test]
+    fn steps_down_colors_then_tolerance_then_resolution() {
+        let mut controller = BandwidthController::new(Some(100_000));
+
+        // 10 KB per frame at 30 fps is three times the budget
+        feed(&mut controller, 10_000);
+        assert!(controller.adjust(30.0, true));
+        assert_eq!(controller.color_depth(), ColorDepth::Indexed256);
+
+        for expected in [4, 8, 16] {
+            feed(&mut controller, 10_000);
+            assert!(controller.adjust(30.0, true));
+            assert_eq!(controller.diff_tolerance(), expected);
+        }
+
+        feed(&mut controller, 10_000);
+        assert!(controller.adjust(30.0, true));
+        assert!(controller.scale() < 0.6);
+
+        // Plenty of room again: resolution comes back first
+        feed(&mut controller, 100);
+        assert!(controller.adjust(30.0, true));
+        assert!(controller.scale() > 0.6);
+        assert_eq!(controller.diff_tolerance(), 16);
+    }
+
+    #[test]
+    fn graphics_only_change_resolution() {
+        let mut controller = BandwidthController::new(Some(100_000));
+        feed(&mut controller, 10_000);
+        assert!(controller.adjust(30.0, false));
+        assert_eq!(controller.color_depth(), ColorDepth::TrueColor);
+        assert!(controller.scale() < 1.0);
+    }
+
+    #[test]
+    fn waits_for_enough_frames() {
+        let mut controller = BandwidthController::new(Some(100));
+        controller.record(10_000, Instant::now());
+        assert!(!controller.adjust(30.0, true));
+    }
+
+    #[test]
+    fn throughput_over_window() {
+        let mut controller = BandwidthController::new(None);
+        let start = Instant::now();
+        for i in 0..=10 {
+            controller.record(1000, start + Duration::from_millis(100 * i));
+        }
+        let stats = controller.stats();
+        assert_eq!(stats.bytes_per_frame, 1000.0);
+        assert!((stats.throughput - 10_000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn unchanged_cells_cost_nothing() {
+        let mut grid = CellGrid::new(2, 1);
+        grid.set(0, 0, cell([10, 20, 30], [0, 0, 0]));
+        grid.set(1, 0, cell([10, 20, 30], [0, 0, 0]));
+
+        // CSI 1;1H, two color escapes and two 3-byte glyphs
+        let full = estimate_cell_bytes(None, &grid, ColorDepth::TrueColor);
+        assert_eq!(full, 6 + 16 + 13 + 6);
+        assert_eq!(
+            estimate_cell_bytes(Some(&grid), &grid, ColorDepth::TrueColor),
+            0
+        );
+        assert!(estimate_cell_bytes(None, &grid, ColorDepth::Indexed256) < full);
+    }
+
+    #[test]
+    fn similar_cells_are_held() {
+        let mut previous = CellGrid::new(2, 1);
+        previous.set(0, 0, cell([100, 100, 100], [0, 0, 0]));
+        previous.set(1, 0, cell([100, 100, 100], [0, 0, 0]));
+
+        let mut grid = CellGrid::new(2, 1);
+        grid.set(0, 0, cell([102, 100, 99], [1, 0, 0]));
+        grid.set(1, 0, cell([150, 100, 100], [0, 0, 0]));
+        hold_similar_cells(&mut grid, &previous, 4);
+
+        assert_eq!(grid.get(0, 0), previous.get(0, 0));
+        assert_eq!(grid.get(1, 0).unwrap().fg, [150, 100, 100]);
+    }
+}
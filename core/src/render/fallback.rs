@@ -0,0 +1,183 @@
+// Per-renderer fallback state for graphics protocols.
+//
+// When Kitty, iTerm2 or Sixel output fails the renderer draws blocks instead.
+// `FallbackPolicy` decides how often a method is retried within a frame, how
+// long it is avoided after a failure and when it is given up on for good.
+// The state lives in each `TerminalRenderer`, so one renderer's failures don't
+// change what another renderer draws.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::RenderMethod;
+
+/// How a renderer reacts when a graphics protocol fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackPolicy {
+    /// Attempts per frame before falling back to blocks for that frame
+    pub attempts: u32,
+    /// How long to draw blocks after a failed frame before trying the method again
+    pub cool_down: Duration,
+    /// Consecutive failed frames after which the method is disabled for good
+    /// (None = keep retrying after every cool-down)
+    pub permanent_after: Option<u32>,
+}
+
+impl FallbackPolicy {
+    /// Give up on a method after its first failed frame
+    pub fn permanent() -> Self {
+        Self {
+            attempts: 1,
+            cool_down: Duration::ZERO,
+            permanent_after: Some(1),
+        }
+    }
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 2,
+            cool_down: Duration::from_secs(2),
+            permanent_after: Some(3),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MethodState {
+    consecutive_failures: u32,
+    retry_at: Option<Instant>,
+    disabled: bool,
+}
+
+/// Failure bookkeeping for the methods a renderer has tried
+#[derive(Debug, Default)]
+pub(crate) struct FallbackState {
+    methods: HashMap<RenderMethod, MethodState>,
+}
+
+impl FallbackState {
+    /// Whether `method` may be tried at `now`
+    pub fn allows(&self, method: RenderMethod, now: Instant) -> bool {
+        match self.methods.get(&method) {
+            Some(state) if state.disabled => false,
+            Some(state) => state.retry_at.is_none_or(|at| now >= at),
+            None => true,
+        }
+    }
+
+    /// Record a successful frame, returns true if the method had been failing
+    pub fn record_success(&mut self, method: RenderMethod) -> bool {
+        self.methods
+            .remove(&method)
+            .is_some_and(|state| state.consecutive_failures > 0)
+    }
+
+    /// Record a failed frame, returns true if the method is now disabled
+    pub fn record_failure(
+        &mut self,
+        method: RenderMethod,
+        policy: &FallbackPolicy,
+        now: Instant,
+    ) -> bool {
+        let state = self.methods.entry(method).or_default();
+        state.consecutive_failures += 1;
+        state.retry_at = Some(now + policy.cool_down);
+        if policy
+            .permanent_after
+            .is_some_and(|limit| state.consecutive_failures >= limit)
+        {
+            state.disabled = true;
+        }
+        state.disabled
+    }
+
+    /// Give up on `method` regardless of the policy, e.g. after a panic
+    pub fn disable(&mut self, method: RenderMethod) {
+        self.methods.entry(method).or_default().disabled = true;
+    }
+
+    /// Forget all failures
+    pub fn reset(&mut self) {
+        self.methods.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cool_down_delays_retry() {
+        let policy = FallbackPolicy {
+            attempts: 1,
+            cool_down: Duration::from_secs(2),
+            permanent_after: None,
+        };
+        let mut state = FallbackState::default();
+        let now = Instant::now();
+
+        assert!(state.allows(RenderMethod::Kitty, now));
+        assert!(!state.record_failure(RenderMethod::Kitty, &policy, now));
+        assert!(!state.allows(RenderMethod::Kitty, now + Duration::from_secs(1)));
+        assert!(state.allows(RenderMethod::Kitty, now + Duration::from_secs(2)));
+        // Other methods are unaffected
+        assert!(state.allows(RenderMethod::Sixel, now));
+    }
+
+    #[test]
+    fn disabled_after_consecutive_failures() {
+        let policy = FallbackPolicy {
+            attempts: 1,
+            cool_down: Duration::ZERO,
+            permanent_after: Some(2),
+        };
+        let mut state = FallbackState::default();
+        let now = Instant::now();
+
+        assert!(!state.record_failure(RenderMethod::Sixel, &policy, now));
+        assert!(state.record_failure(RenderMethod::Sixel, &policy, now));
+        assert!(!state.allows(RenderMethod::Sixel, now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn success_resets_failure_count() {
+        let policy = FallbackPolicy {
+            attempts: 1,
+            cool_down: Duration::ZERO,
+            permanent_after: Some(2),
+        };
+        let mut state = FallbackState::default();
+        let now = Instant::now();
+
+        state.record_failure(RenderMethod::ITerm, &policy, now);
+        assert!(state.record_success(RenderMethod::ITerm));
+        assert!(!state.record_success(RenderMethod::ITerm));
+        assert!(!state.record_failure(RenderMethod::ITerm, &policy, now));
+        assert!(state.allows(RenderMethod::ITerm, now));
+    }
+
+    #[test]
+    fn permanent_policy_disables_on_first_failure() {
+        let mut state = FallbackState::default();
+        assert!(state.record_failure(
+            RenderMethod::Kitty,
+            &FallbackPolicy::permanent(),
+            Instant::now()
+        ));
+
+        state.reset();
+        assert!(state.allows(RenderMethod::Kitty, Instant::now()));
+    }
+
+    #[test]
+    fn renderers_keep_separate_state() {
+        let mut first = FallbackState::default();
+        let second = FallbackState::default();
+        first.disable(RenderMethod::Kitty);
+
+        assert!(!first.allows(RenderMethod::Kitty, Instant::now()));
+        assert!(second.allows(RenderMethod::Kitty, Instant::now()));
+    }
+}
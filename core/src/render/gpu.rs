@@ -2,11 +2,21 @@ use wgpu::{util::DeviceExt, *};
 use bytemuck::{Pod, Zeroable};
 use futures_intrusive::channel::shared;
 use anyhow::{Result, Context, anyhow};
+use std::sync::Arc;
 use std::time::Duration;
-use log::{warn, error};
+use log::{debug, error, info, warn};
+use parking_lot::{Mutex, MutexGuard};
 use futures::FutureExt;
 
-use crate::video::VideoFrame;
+use crate::render::{BlockCell, CellGrid, ColorDepth};
+use crate::video::pool::DEFAULT_POOL_SLOTS;
+use crate::video::{FrameData, FramePool, ScaleFilter, VideoFrame, YuvFrame};
+
+/// Marks a packed cell from `GpuProcessor::block_cells` as fully transparent
+pub const TRANSPARENT_CELL: u32 = 1 << 24;
+
+/// Size of the uniform buffer shared by the storage passes
+const PASS_PARAMS_SIZE: u64 = 64;
 
 // GPU context for processing frames
 pub struct GpuProcessor {
@@ -14,7 +24,9 @@ pub struct GpuProcessor {
     queue: Queue,
     _staging_belt: util::StagingBelt,
     texture_format: TextureFormat,
-    resize_pipeline: ComputePipeline,
+    // Separable resize, rows then columns (see shaders/resize.wgsl)
+    resize_rows_pipeline: ComputePipeline,
+    resize_columns_pipeline: ComputePipeline,
     bind_group_layout: BindGroupLayout,
     // Cached resources
     input_texture: Option<(u32, u32, Texture)>,
@@ -22,6 +34,26 @@ pub struct GpuProcessor {
     output_buffer: Option<(u32, u32, Buffer)>,
     uniform_buffer: Option<Buffer>,
     bind_group: Option<(u32, u32, u32, u32, BindGroup)>,
+    // Rows filtered by the first resize pass, recreated with the bind group
+    intermediate_buffer: Option<Buffer>,
+    // Compute passes over storage buffers (YUV conversion, block cells)
+    storage_layout: BindGroupLayout,
+    yuv_pipeline: ComputePipeline,
+    cells_pipeline: ComputePipeline,
+    // Grow-only buffers shared by the storage passes
+    pass_input: Option<Buffer>,
+    pass_output: Option<Buffer>,
+    pass_readback: Option<Buffer>,
+    pass_params: Option<Buffer>,
+    // Recycled buffers for converted frames, shared with the frames handed out
+    rgba_pool: Arc<FramePool>,
+}
+
+/// Compute passes that read and write storage buffers
+#[derive(Clone, Copy, Debug)]
+enum StoragePass {
+    Yuv,
+    Cells,
 }
 
 // Define a structure for our resize parameters
@@ -32,6 +64,36 @@ struct ResizeParams {
     input_height: f32,
     output_width: f32,
     output_height: f32,
+    filter: u32,
+    _padding: [u32; 3],
+}
+
+// Parameters for shaders/yuv.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Debug)]
+struct YuvParams {
+    width: u32,
+    height: u32,
+    luma_stride: u32,
+    chroma_stride: u32,
+    u_offset: u32,
+    v_offset: u32,
+    y_scale: i32,
+    y_offset: i32,
+    rv: i32,
+    gu: i32,
+    gv: i32,
+    bu: i32,
+}
+
+// Parameters for shaders/cells.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Debug)]
+struct CellParams {
+    columns: u32,
+    rows: u32,
+    height: u32,
+    stride: u32,
 }
 
 impl GpuProcessor {
@@ -134,6 +196,17 @@ impl GpuProcessor {
                     },
                     count: None,
                 },
+                // Rows filtered by the first pass
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -144,27 +217,91 @@ impl GpuProcessor {
             push_constant_ranges: &[],
         });
 
-        let resize_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-            label: Some("Resize pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader,
-            entry_point: Some("main"),
-            compilation_options: PipelineCompilationOptions::default(),
-            cache: None,
+        let resize_pass = |label, entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        let resize_rows_pipeline = resize_pass("Resize rows pipeline", "horizontal");
+        let resize_columns_pipeline = resize_pass("Resize columns pipeline", "vertical");
+
+        // Storage passes read bytes from binding 0 and write binding 1
+        let storage_entry = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Storage pass bind group layout"),
+            entries: &[
+                storage_entry(0, true),
+                storage_entry(1, false),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let storage_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Storage pass pipeline layout"),
+            bind_group_layouts: &[&storage_layout],
+            push_constant_ranges: &[],
         });
+        let storage_pipeline = |label, source: &str| {
+            let module = device.create_shader_module(ShaderModuleDescriptor {
+                label: Some(label),
+                source: ShaderSource::Wgsl(source.into()),
+            });
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&storage_pipeline_layout),
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        let yuv_pipeline = storage_pipeline("YUV conversion", include_str!("shaders/yuv.wgsl"));
+        let cells_pipeline = storage_pipeline("Block cells", include_str!("shaders/cells.wgsl"));
 
         Ok(Self {
             device,
             queue,
             _staging_belt: staging_belt,
             texture_format,
-            resize_pipeline,
+            resize_rows_pipeline,
+            resize_columns_pipeline,
             bind_group_layout,
             input_texture: None,
             output_texture: None,
             output_buffer: None,
             uniform_buffer: None,
             bind_group: None,
+            intermediate_buffer: None,
+            storage_layout,
+            yuv_pipeline,
+            cells_pipeline,
+            pass_input: None,
+            pass_output: None,
+            pass_readback: None,
+            pass_params: None,
+            rgba_pool: Arc::new(FramePool::new(DEFAULT_POOL_SLOTS)),
         })
     }
 
@@ -174,6 +311,7 @@ impl GpuProcessor {
         frame: &VideoFrame,
         target_width: u32,
         target_height: u32,
+        filter: ScaleFilter,
     ) -> Vec<u8> {
         let input_width = frame.width as u32;
         let input_height = frame.height as u32;
@@ -242,7 +380,8 @@ impl GpuProcessor {
             frame.as_rgba_bytes(),
             TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(4 * input_width),
+                // Upload straight from the shared frame data, padding included
+                bytes_per_row: Some(frame.stride as u32),
                 rows_per_image: Some(input_height),
             },
             texture_size,
@@ -338,6 +477,8 @@ impl GpuProcessor {
             input_height: input_height as f32,
             output_width: target_width as f32,
             output_height: target_height as f32,
+            filter: filter.shader_index(),
+            _padding: [0; 3],
         };
 
         let uniform_buffer = if let Some(ref buffer) = self.uniform_buffer {
@@ -356,38 +497,17 @@ impl GpuProcessor {
             self.uniform_buffer.as_ref().unwrap()
         };
 
-        // Create or reuse bind group 
-        let bind_group = if let Some((iw, ih, ow, oh, ref group)) = self.bind_group {
-            if iw == input_width && ih == input_height && ow == target_width && oh == target_height {
-                group
-            } else {
-                let new_group = self.device.create_bind_group(&BindGroupDescriptor {
-                    label: Some("Resize bind group"),
-                    layout: &self.bind_group_layout,
-                    entries: &[
-                        BindGroupEntry {
-                            binding: 0,
-                            resource: BindingResource::TextureView(
-                                &input_texture.create_view(&TextureViewDescriptor::default()),
-                            ),
-                        },
-                        BindGroupEntry {
-                            binding: 1,
-                            resource: BindingResource::TextureView(
-                                &output_texture.create_view(&TextureViewDescriptor::default()),
-                            ),
-                        },
-                        BindGroupEntry {
-                            binding: 2,
-                            resource: uniform_buffer.as_entire_binding(),
-                        },
-                    ],
-                });
-                
-                self.bind_group = Some((input_width, input_height, target_width, target_height, new_group));
-                &self.bind_group.as_ref().unwrap().4
-            }
-        } else {
+        // Create or reuse bind group, along with the buffer the row pass
+        // fills (output width x input height)
+        let bind_group_key = (input_width, input_height, target_width, target_height);
+        if !matches!(self.bind_group, Some((iw, ih, ow, oh, _)) if (iw, ih, ow, oh) == bind_group_key)
+        {
+            let intermediate_buffer = self.device.create_buffer(&BufferDescriptor {
+                label: Some("Resize rows buffer"),
+                size: target_width as u64 * input_height as u64 * 16,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            });
             let new_group = self.device.create_bind_group(&BindGroupDescriptor {
                 label: Some("Resize bind group"),
                 layout: &self.bind_group_layout,
@@ -408,12 +528,23 @@ impl GpuProcessor {
                         binding: 2,
                         resource: uniform_buffer.as_entire_binding(),
                     },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: intermediate_buffer.as_entire_binding(),
+                    },
                 ],
             });
-            
-            self.bind_group = Some((input_width, input_height, target_width, target_height, new_group));
-            &self.bind_group.as_ref().unwrap().4
-        };
+
+            self.intermediate_buffer = Some(intermediate_buffer);
+            self.bind_group = Some((
+                input_width,
+                input_height,
+                target_width,
+                target_height,
+                new_group,
+            ));
+        }
+        let bind_group = &self.bind_group.as_ref().unwrap().4;
 
         // Encode compute pass - optimized for performance
         let mut encoder = self
@@ -428,14 +559,19 @@ impl GpuProcessor {
                 timestamp_writes: None,
             });
 
-            compute_pass.set_pipeline(&self.resize_pipeline);
             compute_pass.set_bind_group(0, bind_group, &[]);
 
             // Use larger workgroups for better performance
             let workgroup_size = 16; // Increased from 8 to 16 for better GPU utilization
-            let workgroup_count_x = (target_width + workgroup_size - 1) / workgroup_size;
-            let workgroup_count_y = (target_height + workgroup_size - 1) / workgroup_size;
+            let workgroup_count_x = target_width.div_ceil(workgroup_size);
+
+            // Filter rows first, then the columns of the filtered rows
+            compute_pass.set_pipeline(&self.resize_rows_pipeline);
+            let row_count_y = input_height.div_ceil(workgroup_size);
+            compute_pass.dispatch_workgroups(workgroup_count_x, row_count_y, 1);
 
+            compute_pass.set_pipeline(&self.resize_columns_pipeline);
+            let workgroup_count_y = target_height.div_ceil(workgroup_size);
             compute_pass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
         }
 
@@ -572,6 +708,355 @@ impl GpuProcessor {
             vec![0; (4 * target_width * target_height) as usize]
         }
     }
+
+    /// Convert decoder planes to packed RGBA in a compute pass
+    ///
+    /// The output matches `YuvFrame::to_rgba` byte for byte. It is read back
+    /// into a pooled buffer, so steady-state playback doesn't allocate.
+    pub fn convert_yuv(&mut self, frame: &YuvFrame) -> Result<FrameData> {
+        let coefficients = frame.matrix.coefficients(frame.full_range);
+        let (u_offset, v_offset) = frame.chroma_offsets();
+        let params = YuvParams {
+            width: frame.width,
+            height: frame.height,
+            luma_stride: frame.luma_stride as u32,
+            chroma_stride: frame.chroma_stride as u32,
+            u_offset: u_offset as u32,
+            v_offset: v_offset as u32,
+            y_scale: coefficients.y_scale,
+            y_offset: coefficients.y_offset,
+            rv: coefficients.rv,
+            gu: coefficients.gu,
+            gv: coefficients.gv,
+            bu: coefficients.bu,
+        };
+        let pool = self.rgba_pool.clone();
+        self.run_storage_pass(
+            StoragePass::Yuv,
+            frame.data(),
+            bytemuck::bytes_of(&params),
+            frame.width as u64 * frame.height as u64 * 4,
+            (frame.width, frame.height),
+            |rgba| pool.fill(rgba.len(), |buffer| buffer.copy_from_slice(rgba)).0,
+        )
+    }
+
+    /// Pick the colors of `columns` x `rows` half-block cells in a compute pass
+    ///
+    /// Returns one `[fg, bg]` pair per cell, row-major, each color packed as
+    /// 0x00BBGGRR. Fully transparent cells have `TRANSPARENT_CELL` as fg. See
+    /// `cell_grid_from_packed`.
+    pub fn block_cells(
+        &mut self,
+        frame: &VideoFrame,
+        columns: u32,
+        rows: u32,
+    ) -> Result<Vec<[u32; 2]>> {
+        if frame.format != crate::video::PixelFormat::Rgba || !frame.stride.is_multiple_of(4) {
+            return Err(anyhow!("GPU block cells need word aligned RGBA frames"));
+        }
+        if columns == 0 || rows == 0 || columns > frame.width || rows > frame.height.div_ceil(2) {
+            return Err(anyhow!(
+                "Cannot build {}x{} cells from a {}x{} frame",
+                columns,
+                rows,
+                frame.width,
+                frame.height
+            ));
+        }
+
+        let params = CellParams {
+            columns,
+            rows,
+            height: frame.height,
+            stride: frame.stride as u32,
+        };
+        let pixels = frame.as_rgba_bytes();
+        let len = (frame.stride * frame.height as usize).min(pixels.len());
+        self.run_storage_pass(
+            StoragePass::Cells,
+            &pixels[..len],
+            bytemuck::bytes_of(&params),
+            columns as u64 * rows as u64 * 8,
+            (columns, rows),
+            bytemuck::pod_collect_to_vec,
+        )
+    }
+
+    /// Upload `input`, run one pass over a `size` grid of invocations and hand
+    /// the first `output_len` bytes it wrote to `read` while they are mapped
+    fn run_storage_pass<R>(
+        &mut self,
+        pass: StoragePass,
+        input: &[u8],
+        params: &[u8],
+        output_len: u64,
+        size: (u32, u32),
+        read: impl FnOnce(&[u8]) -> R,
+    ) -> Result<R> {
+        let max_binding = self.device.limits().max_storage_buffer_binding_size as u64;
+        // Buffer copies work in whole words
+        let input_len = (input.len() as u64).next_multiple_of(COPY_BUFFER_ALIGNMENT);
+        if input_len > max_binding || output_len > max_binding {
+            return Err(anyhow!(
+                "{:?} pass needs {} bytes in and {} bytes out, the device allows {}",
+                pass,
+                input_len,
+                output_len,
+                max_binding
+            ));
+        }
+        debug_assert!(params.len() as u64 <= PASS_PARAMS_SIZE);
+
+        let device = &self.device;
+        let input_buffer = reserve_buffer(
+            device,
+            &mut self.pass_input,
+            "Pass input buffer",
+            input_len.max(COPY_BUFFER_ALIGNMENT),
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        );
+        let output_buffer = reserve_buffer(
+            device,
+            &mut self.pass_output,
+            "Pass output buffer",
+            output_len.max(COPY_BUFFER_ALIGNMENT),
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        );
+        let readback_buffer = reserve_buffer(
+            device,
+            &mut self.pass_readback,
+            "Pass readback buffer",
+            output_len.max(COPY_BUFFER_ALIGNMENT),
+            BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        );
+        let params_buffer = reserve_buffer(
+            device,
+            &mut self.pass_params,
+            "Pass params buffer",
+            PASS_PARAMS_SIZE,
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+
+        // Frame data is shared and can't be padded in place, pad the last word
+        let aligned = input.len() - input.len() % 4;
+        self.queue.write_buffer(&input_buffer, 0, &input[..aligned]);
+        if aligned < input.len() {
+            let mut tail = [0u8; 4];
+            tail[..input.len() - aligned].copy_from_slice(&input[aligned..]);
+            self.queue
+                .write_buffer(&input_buffer, aligned as u64, &tail);
+        }
+        self.queue.write_buffer(&params_buffer, 0, params);
+
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Storage pass bind group"),
+            layout: &self.storage_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: input_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: output_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let pipeline = match pass {
+            StoragePass::Yuv => &self.yuv_pipeline,
+            StoragePass::Cells => &self.cells_pipeline,
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Storage pass encoder"),
+            });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Storage pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            // Both shaders use 16x16 workgroups
+            compute_pass.dispatch_workgroups(size.0.div_ceil(16), size.1.div_ceil(16), 1);
+        }
+        encoder.copy_buffer_to_buffer(&output_buffer, 0, &readback_buffer, 0, output_len);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback_buffer.slice(..output_len);
+        let (sender, receiver) = shared::oneshot_channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result).ok();
+        });
+        self.device
+            .poll(PollType::Wait)
+            .map_err(|e| anyhow!("{:?} pass failed: {}", pass, e))?;
+        match pollster::block_on(receiver.receive()) {
+            Some(Ok(())) => {}
+            Some(Err(e)) => return Err(anyhow!("Failed to read back {:?} pass: {}", pass, e)),
+            None => return Err(anyhow!("{:?} pass readback was cancelled", pass)),
+        }
+
+        let output = read(&slice.get_mapped_range());
+        readback_buffer.unmap();
+        Ok(output)
+    }
+}
+
+/// Reuse the buffer in `slot` if it holds `size` bytes, otherwise replace it
+fn reserve_buffer(
+    device: &Device,
+    slot: &mut Option<Buffer>,
+    label: &str,
+    size: u64,
+    usage: BufferUsages,
+) -> Buffer {
+    if let Some(buffer) = slot.as_ref().filter(|buffer| buffer.size() >= size) {
+        return buffer.clone();
+    }
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some(label),
+        size,
+        usage,
+        mapped_at_creation: false,
+    });
+    *slot = Some(buffer.clone());
+    buffer
+}
+
+/// Turn packed cells from `GpuProcessor::block_cells` into a grid
+pub(crate) fn cell_grid_from_packed(
+    cells: &[[u32; 2]],
+    columns: u16,
+    rows: u16,
+    color_depth: ColorDepth,
+) -> CellGrid {
+    let unpack =
+        |color: u32| color_depth.quantize([color as u8, (color >> 8) as u8, (color >> 16) as u8]);
+    let mut grid = CellGrid::new(columns, rows);
+    for (slot, &[fg, bg]) in grid.cells.iter_mut().zip(cells) {
+        if fg != TRANSPARENT_CELL {
+            *slot = Some(BlockCell {
+                symbol: '▀',
+                fg: unpack(fg),
+                bg: unpack(bg),
+            });
+        }
+    }
+    grid
 }
 
+/// Shared handle to a `GpuProcessor`
+///
+/// Renderers no longer reach for a process-wide processor. Create a handle once
+/// and pass clones to every renderer that should share the device (through
+/// `RenderConfig::gpu`); the processor is locked for the duration of each frame.
+#[derive(Clone, Default)]
+pub struct GpuHandle {
+    processor: Arc<Mutex<Option<GpuProcessor>>>,
+}
+
+impl GpuHandle {
+    /// Initialize a processor, blocking until the device is ready
+    ///
+    /// The handle is still returned when no adapter is available; renderers
+    /// then resize on the CPU.
+    pub fn new() -> Self {
+        let handle = Self::default();
+        handle.initialize();
+        handle
+    }
+
+    /// Initialize a processor on a background thread
+    ///
+    /// Frames rendered before initialization finishes are resized on the CPU.
+    pub fn spawn() -> Self {
+        let handle = Self::default();
+        let background = handle.clone();
+        std::thread::spawn(move || {
+            debug!("Initializing GPU processor in background thread");
+            background.initialize();
+        });
+        handle
+    }
+
+    fn initialize(&self) {
+        // Build the processor before taking the lock so renderers aren't blocked
+        let processor = match pollster::block_on(GpuProcessor::new()) {
+            Ok(processor) => {
+                info!("GPU processor initialized successfully");
+                Some(processor)
+            }
+            Err(e) => {
+                error!("Failed to initialize GPU processor: {}", e);
+                None
+            }
+        };
+        *self.processor.lock() = processor;
+    }
+
+    /// Whether a processor is ready for use
+    pub fn is_available(&self) -> bool {
+        self.processor.lock().is_some()
+    }
+
+    /// Lock the processor, giving up after `timeout`
+    pub(crate) fn try_lock_for(
+        &self,
+        timeout: Duration,
+    ) -> Option<MutexGuard<'_, Option<GpuProcessor>>> {
+        self.processor.try_lock_for(timeout)
+    }
+
+    /// Run `pass` on the processor, `None` if it isn't ready, busy or fails
+    fn with_processor<R>(
+        &self,
+        pass: &str,
+        run: impl FnOnce(&mut GpuProcessor) -> Result<R>,
+    ) -> Option<R> {
+        let mut guard = self.try_lock_for(Duration::from_millis(100))?;
+        let processor = guard.as_mut()?;
+        match run(processor) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                warn!("GPU {} failed, using the CPU: {}", pass, e);
+                None
+            }
+        }
+    }
 
+    /// Convert decoder planes to an RGBA frame
+    ///
+    /// Runs as a compute pass when the processor is ready and falls back to
+    /// `YuvFrame::to_frame` otherwise, with identical output.
+    pub fn convert_yuv(&self, frame: &YuvFrame) -> VideoFrame {
+        match self.with_processor("YUV conversion", |processor| processor.convert_yuv(frame)) {
+            Some(rgba) => frame.frame_from_rgba(rgba),
+            None => frame.to_frame(),
+        }
+    }
+
+    /// Half-block cells for the top-left `columns` x `rows` of `frame`
+    ///
+    /// `None` when the processor isn't ready or the pass fails, the caller
+    /// then builds the grid on the CPU.
+    pub(crate) fn block_cells(
+        &self,
+        frame: &VideoFrame,
+        columns: u16,
+        rows: u16,
+        color_depth: ColorDepth,
+    ) -> Option<CellGrid> {
+        let cells = self.with_processor("block cells", |processor| {
+            processor.block_cells(frame, columns as u32, rows as u32)
+        })?;
+        Some(cell_grid_from_packed(&cells, columns, rows, color_depth))
+    }
+}
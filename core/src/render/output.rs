@@ -0,0 +1,69 @@
+// Buffered render output for embedding the video in a TUI.
+//
+// With `OutputTarget::Stdout` the renderer writes escape sequences straight to
+// the terminal at `config.x`/`config.y`. A TUI that redraws the same screen
+// (ratatui) races with those writes, so with `OutputTarget::Buffered` the
+// renderer keeps its output instead:
+// - block rendering produces a `CellGrid` the UI copies into its own cell buffer
+// - graphics protocols produce escape bytes the UI writes after it has flushed
+
+/// Where the renderer sends its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// Write escape sequences directly to stdout
+    Stdout,
+    /// Keep output for the caller, see `TerminalRenderer::take_output`
+    Buffered,
+}
+
+/// One terminal cell of block output: `symbol` drawn with `fg` over `bg`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCell {
+    pub symbol: char,
+    pub fg: [u8; 3],
+    pub bg: [u8; 3],
+}
+
+/// Grid of block cells, row-major, `None` for fully transparent cells
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CellGrid {
+    pub width: u16,
+    pub height: u16,
+    pub cells: Vec<Option<BlockCell>>,
+}
+
+impl CellGrid {
+    /// Create a grid with every cell transparent
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![None; width as usize * height as usize],
+        }
+    }
+
+    /// Cell at the given column and row
+    pub fn get(&self, x: u16, y: u16) -> Option<&BlockCell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells[y as usize * self.width as usize + x as usize].as_ref()
+    }
+
+    /// Replace the cell at the given column and row
+    pub fn set(&mut self, x: u16, y: u16, cell: Option<BlockCell>) {
+        if x < self.width && y < self.height {
+            self.cells[y as usize * self.width as usize + x as usize] = cell;
+        }
+    }
+}
+
+/// A rendered frame kept for the caller in buffered mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderedFrame {
+    /// Block output to be drawn into the UI's cell buffer
+    Cells(CellGrid),
+    /// Graphics protocol escapes (including cursor positioning) to be written
+    /// after the UI has flushed
+    Graphics(Vec<u8>),
+}
@@ -0,0 +1,338 @@
+// Graphics escapes inside terminal multiplexers.
+//
+// tmux and GNU screen parse everything the application writes and drop the
+// escape sequences they don't understand, which includes the Kitty, iTerm2
+// and Sixel graphics protocols. Both offer a DCS passthrough that forwards
+// the wrapped bytes to the outer terminal untouched:
+// - tmux: ESC P tmux; <sequence with every ESC doubled> ESC \
+//   (requires `set -g allow-passthrough on` since tmux 3.3)
+// - screen: the sequence split into ESC P <chunk> ESC \ packets of at most
+//   768 bytes, which is the size of screen's string buffer
+//
+// Passthrough bytes bypass the multiplexer's own grid, so an image placed at
+// the outer cursor would be drawn at the wrong spot and would not follow pane
+// switches or scrolling. For Kitty we therefore use Unicode placeholders: the
+// image is transmitted as a virtual placement (U=1) and the multiplexer only
+// sees regular text cells (U+10EEEE) whose foreground color encodes the image
+// id and whose combining diacritics encode the row/column inside the image.
+use log::debug;
+
+/// Maximum payload of a single screen DCS packet
+const SCREEN_CHUNK_SIZE: usize = 768;
+
+/// Placeholder character for Kitty virtual placements
+pub const KITTY_PLACEHOLDER: char = '\u{10EEEE}';
+
+/// Terminal multiplexer the player is running inside
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    /// No multiplexer, escapes reach the terminal directly
+    None,
+    /// tmux (TMUX is set)
+    Tmux,
+    /// GNU screen (STY is set)
+    Screen,
+}
+
+impl Multiplexer {
+    /// Detect the multiplexer from the environment
+    pub fn detect() -> Self {
+        let multiplexer = Self::from_env(
+            std::env::var_os("TMUX").is_some(),
+            std::env::var_os("STY").is_some(),
+        );
+        if multiplexer != Multiplexer::None {
+            debug!(
+                "Running inside {:?}, using DCS passthrough for graphics",
+                multiplexer
+            );
+        }
+        multiplexer
+    }
+
+    fn from_env(has_tmux: bool, has_sty: bool) -> Self {
+        // tmux started from inside screen sets both; the innermost one wins
+        if has_tmux {
+            Multiplexer::Tmux
+        } else if has_sty {
+            Multiplexer::Screen
+        } else {
+            Multiplexer::None
+        }
+    }
+
+    /// Whether graphics escapes need wrapping
+    pub fn is_active(&self) -> bool {
+        *self != Multiplexer::None
+    }
+
+    /// Wrap an escape sequence so it reaches the outer terminal
+    pub fn wrap(&self, sequence: &[u8]) -> Vec<u8> {
+        match self {
+            Multiplexer::None => sequence.to_vec(),
+            Multiplexer::Tmux => {
+                let escapes = sequence.iter().filter(|&&b| b == 0x1B).count();
+                let mut wrapped = Vec::with_capacity(sequence.len() + escapes + 9);
+                wrapped.extend_from_slice(b"\x1BPtmux;");
+                for &byte in sequence {
+                    if byte == 0x1B {
+                        wrapped.push(0x1B);
+                    }
+                    wrapped.push(byte);
+                }
+                wrapped.extend_from_slice(b"\x1B\\");
+                wrapped
+            }
+            Multiplexer::Screen => {
+                let packets = sequence.len() / SCREEN_CHUNK_SIZE + 1;
+                let mut wrapped = Vec::with_capacity(sequence.len() + packets * 4);
+                for chunk in screen_chunks(sequence) {
+                    wrapped.extend_from_slice(b"\x1BP");
+                    wrapped.extend_from_slice(chunk);
+                    wrapped.extend_from_slice(b"\x1B\\");
+                }
+                wrapped
+            }
+        }
+    }
+
+    /// Wrap a string escape sequence, see `wrap`
+    pub fn wrap_str(&self, sequence: &str) -> Vec<u8> {
+        self.wrap(sequence.as_bytes())
+    }
+}
+
+/// Split a sequence into screen-sized chunks
+///
+/// A chunk never contains an ESC followed by a backslash, since screen would
+/// treat that as the end of the packet. An embedded string terminator is
+/// split across two packets instead.
+fn screen_chunks(sequence: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < sequence.len() {
+        let mut end = (start + SCREEN_CHUNK_SIZE).min(sequence.len());
+        if let Some(pos) = sequence[start..end].windows(2).position(|w| w == b"\x1B\\") {
+            // Cut right after the ESC
+            end = start + pos + 1;
+        }
+        chunks.push(&sequence[start..end]);
+        start = end;
+    }
+
+    chunks
+}
+
+/// Build the Kitty command that transmits a PNG file as a virtual placement
+///
+/// The image is displayed wherever placeholder cells with a matching id are
+/// drawn (see `kitty_placeholder_row`). Re-sending the command with the same
+/// id replaces the image, so every frame can reuse one id.
+pub fn kitty_virtual_placement(
+    image_id: u32,
+    path: &std::path::Path,
+    columns: u16,
+    rows: u16,
+) -> String {
+    use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+    let encoded_path = BASE64.encode(path.to_string_lossy().as_bytes());
+    format!(
+        "\x1B_Ga=T,U=1,f=100,t=f,i={},c={},r={},q=2;{}\x1B\\",
+        image_id, columns, rows, encoded_path
+    )
+}
+
+/// Build one row of Kitty placeholder cells for the given image
+///
+/// The foreground color carries the low 24 bits of the image id, the first
+/// diacritic the row and the second the column.
+pub fn kitty_placeholder_row(image_id: u32, row: u16, columns: u16) -> String {
+    let mut line = String::with_capacity(columns as usize * 10 + 24);
+    line.push_str(&format!(
+        "\x1B[38;2;{};{};{}m",
+        (image_id >> 16) & 0xFF,
+        (image_id >> 8) & 0xFF,
+        image_id & 0xFF
+    ));
+
+    let row_mark = placeholder_diacritic(row);
+    for column in 0..columns {
+        line.push(KITTY_PLACEHOLDER);
+        line.push(row_mark);
+        line.push(placeholder_diacritic(column));
+    }
+
+    line.push_str("\x1B[39m");
+    line
+}
+
+/// Largest row/column count that placeholders can address
+pub fn kitty_placeholder_limit() -> u16 {
+    ROW_COLUMN_DIACRITICS.len() as u16
+}
+
+/// Diacritic encoding a row or column number, clamped to the table size
+fn placeholder_diacritic(index: u16) -> char {
+    let index = (index as usize).min(ROW_COLUMN_DIACRITICS.len() - 1);
+    ROW_COLUMN_DIACRITICS[index]
+}
+
+/// Combining characters used to encode row/column numbers (index = number),
+/// from Kitty's rowcolumn-diacritics.txt
+#[rustfmt::skip]
+const ROW_COLUMN_DIACRITICS: [char; 297] = [
+    '\u{0305}', '\u{030D}', '\u{030E}', '\u{0310}', '\u{0312}', '\u{033D}', '\u{033E}',
+    '\u{033F}', '\u{0346}', '\u{034A}', '\u{034B}', '\u{034C}', '\u{0350}', '\u{0351}',
+    '\u{0352}', '\u{0357}', '\u{035B}', '\u{0363}', '\u{0364}', '\u{0365}', '\u{0366}',
+    '\u{0367}', '\u{0368}', '\u{0369}', '\u{036A}', '\u{036B}', '\u{036C}', '\u{036D}',
+    '\u{036E}', '\u{036F}', '\u{0483}', '\u{0484}', '\u{0485}', '\u{0486}', '\u{0487}',
+    '\u{0592}', '\u{0593}', '\u{0594}', '\u{0595}', '\u{0597}', '\u{0598}', '\u{0599}',
+    '\u{059C}', '\u{059D}', '\u{059E}', '\u{059F}', '\u{05A0}', '\u{05A1}', '\u{05A8}',
+    '\u{05A9}', '\u{05AB}', '\u{05AC}', '\u{05AF}', '\u{05C4}', '\u{0610}', '\u{0611}',
+    '\u{0612}', '\u{0613}', '\u{0614}', '\u{0615}', '\u{0616}', '\u{0617}', '\u{0657}',
+    '\u{0658}', '\u{0659}', '\u{065A}', '\u{065B}', '\u{065D}', '\u{065E}', '\u{06D6}',
+    '\u{06D7}', '\u{06D8}', '\u{06D9}', '\u{06DA}', '\u{06DB}', '\u{06DC}', '\u{06DF}',
+    '\u{06E0}', '\u{06E1}', '\u{06E2}', '\u{06E4}', '\u{06E7}', '\u{06E8}', '\u{06EB}',
+    '\u{06EC}', '\u{0730}', '\u{0732}', '\u{0733}', '\u{0735}', '\u{0736}', '\u{073A}',
+    '\u{073D}', '\u{073F}', '\u{0740}', '\u{0741}', '\u{0743}', '\u{0745}', '\u{0747}',
+    '\u{0749}', '\u{074A}', '\u{07EB}', '\u{07EC}', '\u{07ED}', '\u{07EE}', '\u{07EF}',
+    '\u{07F0}', '\u{07F1}', '\u{07F3}', '\u{0816}', '\u{0817}', '\u{0818}', '\u{0819}',
+    '\u{081B}', '\u{081C}', '\u{081D}', '\u{081E}', '\u{081F}', '\u{0820}', '\u{0821}',
+    '\u{0822}', '\u{0823}', '\u{0825}', '\u{0826}', '\u{0827}', '\u{0829}', '\u{082A}',
+    '\u{082B}', '\u{082C}', '\u{082D}', '\u{0951}', '\u{0953}', '\u{0954}', '\u{0F82}',
+    '\u{0F83}', '\u{0F86}', '\u{0F87}', '\u{135D}', '\u{135E}', '\u{135F}', '\u{17DD}',
+    '\u{193A}', '\u{1A17}', '\u{1A75}', '\u{1A76}', '\u{1A77}', '\u{1A78}', '\u{1A79}',
+    '\u{1A7A}', '\u{1A7B}', '\u{1A7C}', '\u{1B6B}', '\u{1B6D}', '\u{1B6E}', '\u{1B6F}',
+    '\u{1B70}', '\u{1B71}', '\u{1B72}', '\u{1B73}', '\u{1CD0}', '\u{1CD1}', '\u{1CD2}',
+    '\u{1CDA}', '\u{1CDB}', '\u{1CE0}', '\u{1DC0}', '\u{1DC1}', '\u{1DC3}', '\u{1DC4}',
+    '\u{1DC5}', '\u{1DC6}', '\u{1DC7}', '\u{1DC8}', '\u{1DC9}', '\u{1DCB}', '\u{1DCC}',
+    '\u{1DD1}', '\u{1DD2}', '\u{1DD3}', '\u{1DD4}', '\u{1DD5}', '\u{1DD6}', '\u{1DD7}',
+    '\u{1DD8}', '\u{1DD9}', '\u{1DDA}', '\u{1DDB}', '\u{1DDC}', '\u{1DDD}', '\u{1DDE}',
+    '\u{1DDF}', '\u{1DE0}', '\u{1DE1}', '\u{1DE2}', '\u{1DE3}', '\u{1DE4}', '\u{1DE5}',
+    '\u{1DE6}', '\u{1DFE}', '\u{20D0}', '\u{20D1}', '\u{20D4}', '\u{20D5}', '\u{20D6}',
+    '\u{20D7}', '\u{20DB}', '\u{20DC}', '\u{20E1}', '\u{20E7}', '\u{20E9}', '\u{20F0}',
+    '\u{2CEF}', '\u{2CF0}', '\u{2CF1}', '\u{2DE0}', '\u{2DE1}', '\u{2DE2}', '\u{2DE3}',
+    '\u{2DE4}', '\u{2DE5}', '\u{2DE6}', '\u{2DE7}', '\u{2DE8}', '\u{2DE9}', '\u{2DEA}',
+    '\u{2DEB}', '\u{2DEC}', '\u{2DED}', '\u{2DEE}', '\u{2DEF}', '\u{2DF0}', '\u{2DF1}',
+    '\u{2DF2}', '\u{2DF3}', '\u{2DF4}', '\u{2DF5}', '\u{2DF6}', '\u{2DF7}', '\u{2DF8}',
+    '\u{2DF9}', '\u{2DFA}', '\u{2DFB}', '\u{2DFC}', '\u{2DFD}', '\u{2DFE}', '\u{2DFF}',
+    '\u{A66F}', '\u{A67C}', '\u{A67D}', '\u{A6F0}', '\u{A6F1}', '\u{A8E0}', '\u{A8E1}',
+    '\u{A8E2}', '\u{A8E3}', '\u{A8E4}', '\u{A8E5}', '\u{A8E6}', '\u{A8E7}', '\u{A8E8}',
+    '\u{A8E9}', '\u{A8EA}', '\u{A8EB}', '\u{A8EC}', '\u{A8ED}', '\u{A8EE}', '\u{A8EF}',
+    '\u{A8F0}', '\u{A8F1}', '\u{AAB0}', '\u{AAB2}', '\u{AAB3}', '\u{AAB7}', '\u{AAB8}',
+    '\u{AABE}', '\u{AABF}', '\u{AAC1}', '\u{FE20}', '\u{FE21}', '\u{FE22}', '\u{FE23}',
+    '\u{FE24}', '\u{FE25}', '\u{FE26}', '\u{10A0F}', '\u{10A38}', '\u{1D185}', '\u{1D186}',
+    '\u{1D187}', '\u{1D188}', '\u{1D189}', '\u{1D1AA}', '\u{1D1AB}', '\u{1D1AC}', '\u{1D1AD}',
+    '\u{1D242}', '\u{1D243}', '\u{1D244}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KITTY_QUERY: &[u8] = b"\x1B_Gi=31,a=q;AAAA\x1B\\";
+
+    #[test]
+    fn test_detect_from_env() {
+        assert_eq!(Multiplexer::from_env(false, false), Multiplexer::None);
+        assert_eq!(Multiplexer::from_env(true, false), Multiplexer::Tmux);
+        assert_eq!(Multiplexer::from_env(false, true), Multiplexer::Screen);
+        assert_eq!(Multiplexer::from_env(true, true), Multiplexer::Tmux);
+    }
+
+    #[test]
+    fn test_no_multiplexer_is_identity() {
+        assert_eq!(Multiplexer::None.wrap(KITTY_QUERY), KITTY_QUERY);
+    }
+
+    #[test]
+    fn test_tmux_wrap_doubles_escapes() {
+        let wrapped = Multiplexer::Tmux.wrap(KITTY_QUERY);
+        assert_eq!(
+            wrapped,
+            b"\x1BPtmux;\x1B\x1B_Gi=31,a=q;AAAA\x1B\x1B\\\x1B\\".to_vec()
+        );
+
+        // Only the final ESC is followed by a lone backslash
+        let single_terminators = wrapped
+            .windows(3)
+            .filter(|w| w[0] != 0x1B && w[1] == 0x1B && w[2] == b'\\')
+            .count();
+        assert_eq!(single_terminators, 1);
+    }
+
+    #[test]
+    fn test_screen_wrap_short_sequence() {
+        let wrapped = Multiplexer::Screen.wrap(b"\x1B[c");
+        assert_eq!(wrapped, b"\x1BP\x1B[c\x1B\\".to_vec());
+    }
+
+    #[test]
+    fn test_screen_wrap_splits_terminator() {
+        let wrapped = Multiplexer::Screen.wrap(KITTY_QUERY);
+        assert_eq!(
+            wrapped,
+            b"\x1BP\x1B_Gi=31,a=q;AAAA\x1B\x1B\\\x1BP\\\x1B\\".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_screen_chunks_respect_limit() {
+        // Large sixel-like payload with a terminator at the end
+        let mut sequence = b"\x1BPq".to_vec();
+        sequence.extend(std::iter::repeat_n(b'~', 5000));
+        sequence.extend_from_slice(b"\x1B\\");
+
+        let chunks = screen_chunks(&sequence);
+        assert!(chunks.len() > 5000 / SCREEN_CHUNK_SIZE);
+        assert!(chunks.iter().all(|c| c.len() <= SCREEN_CHUNK_SIZE));
+        assert!(chunks.iter().all(|c| !c.windows(2).any(|w| w == b"\x1B\\")));
+        assert_eq!(chunks.concat(), sequence);
+    }
+
+    #[test]
+    fn test_kitty_virtual_placement() {
+        let command = kitty_virtual_placement(42, std::path::Path::new("/tmp/a.png"), 80, 24);
+        assert!(command.starts_with("\x1B_Ga=T,U=1,"));
+        assert!(command.contains("i=42,c=80,r=24"));
+        // base64 of "/tmp/a.png"
+        assert!(command.ends_with(";L3RtcC9hLnBuZw==\x1B\\"));
+    }
+
+    #[test]
+    fn test_kitty_placeholder_row() {
+        let row = kitty_placeholder_row(0x0A0B0C, 1, 3);
+        assert!(row.starts_with("\x1B[38;2;10;11;12m"));
+        assert!(row.ends_with("\x1B[39m"));
+
+        let cells: Vec<char> = row
+            .chars()
+            .skip_while(|&c| c != KITTY_PLACEHOLDER)
+            .take(9)
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                KITTY_PLACEHOLDER,
+                '\u{030D}',
+                '\u{0305}',
+                KITTY_PLACEHOLDER,
+                '\u{030D}',
+                '\u{030D}',
+                KITTY_PLACEHOLDER,
+                '\u{030D}',
+                '\u{030E}',
+            ]
+        );
+    }
+
+    #[test]
+    fn test_placeholder_diacritic_clamps() {
+        assert_eq!(kitty_placeholder_limit(), 297);
+        assert_eq!(placeholder_diacritic(0), '\u{0305}');
+        assert_eq!(placeholder_diacritic(296), '\u{1D244}');
+        assert_eq!(placeholder_diacritic(1000), '\u{1D244}');
+    }
+}
@@ -0,0 +1,54 @@
+// Half-block cells from RGBA pixels, matching TerminalRenderer::build_cell_grid
+//
+// Each cell is the top pixel (fg) and bottom pixel (bg) blended over black and
+// packed as 0x00BBGGRR. Cells where both pixels are fully transparent get
+// TRANSPARENT in fg instead.
+struct Params {
+    columns: u32,
+    rows: u32,
+    height: u32,
+    // Bytes between pixel rows, a multiple of 4
+    stride: u32,
+}
+
+const TRANSPARENT: u32 = 0x01000000u;
+
+@group(0) @binding(0)
+var<storage, read> pixels: array<u32>;
+
+@group(0) @binding(1)
+var<storage, read_write> cells: array<vec2<u32>>;
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+fn pixel(x: u32, y: u32) -> vec4<u32> {
+    if (y >= params.height) {
+        return vec4<u32>(0u, 0u, 0u, 255u);
+    }
+    let packed = pixels[(y * params.stride) / 4u + x];
+    return vec4<u32>(packed & 0xffu, (packed >> 8u) & 0xffu, (packed >> 16u) & 0xffu, packed >> 24u);
+}
+
+fn blend(color: vec4<u32>) -> u32 {
+    let rgb = color.rgb * color.a / 255u;
+    return rgb.r | (rgb.g << 8u) | (rgb.b << 16u);
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    if (x >= params.columns || y >= params.rows) {
+        return;
+    }
+
+    let top = pixel(x, y * 2u);
+    let bottom = pixel(x, y * 2u + 1u);
+    let index = y * params.columns + x;
+    if (top.a == 0u && bottom.a == 0u) {
+        cells[index] = vec2<u32>(TRANSPARENT, 0u);
+        return;
+    }
+    cells[index] = vec2<u32>(blend(top), blend(bottom));
+}
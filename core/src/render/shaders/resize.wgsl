@@ -1,3 +1,19 @@
+// Separable resize with the filters of video/scale.rs
+//
+// `horizontal` filters rows into `intermediate` (output width x input height),
+// `vertical` filters its columns into the output texture. Weights match
+// ScaleFilter::weight, taps outside the frame are dropped and the rest
+// renormalized, like the CPU resampler.
+struct Params {
+    input_size: vec2<f32>,
+    output_size: vec2<f32>,
+    // ScaleFilter: 0 nearest, 1 bilinear, 2 bicubic, 3 lanczos3, 4 area
+    filter_kind: u32,
+    _padding0: u32,
+    _padding1: u32,
+    _padding2: u32,
+}
+
 @group(0) @binding(0)
 var input_texture: texture_2d<f32>;
 
@@ -5,63 +21,117 @@ var input_texture: texture_2d<f32>;
 var output_texture: texture_storage_2d<rgba8unorm, write>;
 
 @group(0) @binding(2)
-var<uniform> params: vec4<f32>; // [input_width, input_height, output_width, output_height]
+var<uniform> params: Params;
 
-// Increased workgroup size for better GPU utilization
-@compute @workgroup_size(16, 16, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let output_size = vec2<u32>(u32(params[2]), u32(params[3]));
-    let input_size = vec2<f32>(params[0], params[1]);
+@group(0) @binding(3)
+var<storage, read_write> intermediate: array<vec4<f32>>;
 
-    // Early return for out-of-bounds
-    if (global_id.x >= output_size.x || global_id.y >= output_size.y) {
-        return;
+const PI: f32 = 3.14159265;
+
+fn support() -> f32 {
+    switch params.filter_kind {
+        case 1u: { return 1.0; }
+        case 2u: { return 2.0; }
+        case 3u: { return 3.0; }
+        default: { return 0.5; }
+    }
+}
+
+fn weight(distance: f32, scale: f32) -> f32 {
+    let t = abs(distance / scale);
+    switch params.filter_kind {
+        case 0u: {
+            return select(0.0, 1.0, distance >= -0.5 && distance < 0.5);
+        }
+        case 1u: {
+            return max(1.0 - t, 0.0);
+        }
+        case 2u: {
+            if (t < 1.0) {
+                return (1.5 * t - 2.5) * t * t + 1.0;
+            }
+            if (t < 2.0) {
+                return ((-0.5 * t + 2.5) * t - 4.0) * t + 2.0;
+            }
+            return 0.0;
+        }
+        case 3u: {
+            if (t < 1e-5) {
+                return 1.0;
+            }
+            if (t < 3.0) {
+                let x = PI * t;
+                return 3.0 * sin(x) * sin(x / 3.0) / (x * x);
+            }
+            return 0.0;
+        }
+        default: {
+            let half = 0.5 / scale;
+            let u = distance / scale;
+            return max(min(u + half, 0.5) - max(u - half, -0.5), 0.0);
+        }
+    }
+}
+
+// First and one-past-last source pixel sampled for output pixel `i`
+fn tap_range(i: u32, input: f32, output: f32) -> vec2<u32> {
+    let ratio = input / output;
+    let center = (f32(i) + 0.5) * ratio;
+    var radius = support();
+    if (params.filter_kind != 0u) {
+        radius = radius * max(ratio, 1.0);
     }
+    let start = u32(max(floor(center - radius), 0.0));
+    let end = min(u32(ceil(center + radius)), u32(input));
+    return vec2<u32>(start, end);
+}
 
-    // Pre-compute scaling factors instead of division per pixel
-    let scale_x = input_size.x / f32(output_size.x);
-    let scale_y = input_size.y / f32(output_size.y);
-    
-    // Calculate source position with pre-computed scales
-    let src_x = f32(global_id.x) * scale_x;
-    let src_y = f32(global_id.y) * scale_y;
-    
-    // For small downscaling, use nearest neighbor for speed
-    if scale_x < 1.2 && scale_y < 1.2 {
-        let src_pixel = vec2<u32>(u32(src_x), u32(src_y));
-        let color = textureLoad(input_texture, src_pixel, 0);
-        textureStore(output_texture, global_id.xy, color);
+@compute @workgroup_size(16, 16, 1)
+fn horizontal(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let width = u32(params.output_size.x);
+    let height = u32(params.input_size.y);
+    if (global_id.x >= width || global_id.y >= height) {
         return;
     }
-    
-    // For larger scaling factors, use a simplified bilinear approach
-    let src_pos_floor = vec2<u32>(u32(src_x), u32(src_y));
-    let fract_x = src_x - f32(src_pos_floor.x);
-    let fract_y = src_y - f32(src_pos_floor.y);
-    
-    // Get source coordinates with bounds checking
-    let x1 = src_pos_floor.x;
-    let y1 = src_pos_floor.y;
-    let x2 = min(x1 + 1, u32(input_size.x) - 1);
-    let y2 = min(y1 + 1, u32(input_size.y) - 1);
-    
-    // Fetch the four pixels
-    let sample00 = textureLoad(input_texture, vec2<u32>(x1, y1), 0);
-    let sample10 = textureLoad(input_texture, vec2<u32>(x2, y1), 0);
-    let sample01 = textureLoad(input_texture, vec2<u32>(x1, y2), 0);
-    let sample11 = textureLoad(input_texture, vec2<u32>(x2, y2), 0);
 
-    // Skip full interpolation if a pixel is completely transparent
-    if (sample00.a < 0.01 && sample10.a < 0.01 && sample01.a < 0.01 && sample11.a < 0.01) {
-        textureStore(output_texture, global_id.xy, vec4<f32>(0.0, 0.0, 0.0, 0.0));
+    let ratio = params.input_size.x / params.output_size.x;
+    let scale = max(ratio, 1.0);
+    let center = (f32(global_id.x) + 0.5) * ratio;
+    let range = tap_range(global_id.x, params.input_size.x, params.output_size.x);
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var j = range.x; j < range.y; j++) {
+        let w = weight(f32(j) + 0.5 - center, scale);
+        sum += textureLoad(input_texture, vec2<u32>(j, global_id.y), 0) * w;
+        total += w;
+    }
+    if (total != 0.0) {
+        sum = sum / total;
+    }
+    intermediate[global_id.y * width + global_id.x] = sum;
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn vertical(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let width = u32(params.output_size.x);
+    let height = u32(params.output_size.y);
+    if (global_id.x >= width || global_id.y >= height) {
         return;
     }
 
-    // Simple bilinear interpolation
-    let top = mix(sample00, sample10, fract_x);
-    let bottom = mix(sample01, sample11, fract_x);
-    let final_color = mix(top, bottom, fract_y);
-    
-    // Write to output
-    textureStore(output_texture, global_id.xy, final_color);
-}
\ No newline at end of file
+    let ratio = params.input_size.y / params.output_size.y;
+    let scale = max(ratio, 1.0);
+    let center = (f32(global_id.y) + 0.5) * ratio;
+    let range = tap_range(global_id.y, params.input_size.y, params.output_size.y);
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var j = range.x; j < range.y; j++) {
+        let w = weight(f32(j) + 0.5 - center, scale);
+        sum += intermediate[j * width + global_id.x] * w;
+        total += w;
+    }
+    if (total != 0.0) {
+        sum = sum / total;
+    }
+    textureStore(output_texture, global_id.xy, clamp(sum, vec4<f32>(0.0), vec4<f32>(1.0)));
+}
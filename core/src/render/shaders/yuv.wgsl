@@ -0,0 +1,53 @@
+// YUV 4:2:0 planes to packed RGBA, matching YuvFrame::to_rgba exactly
+struct Params {
+    width: u32,
+    height: u32,
+    luma_stride: u32,
+    chroma_stride: u32,
+    u_offset: u32,
+    v_offset: u32,
+    // Fixed point factors scaled by 256, see YuvCoefficients
+    y_scale: i32,
+    y_offset: i32,
+    rv: i32,
+    gu: i32,
+    gv: i32,
+    bu: i32,
+}
+
+@group(0) @binding(0)
+var<storage, read> planes: array<u32>;
+
+@group(0) @binding(1)
+var<storage, read_write> pixels: array<u32>;
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+// Planes are uploaded as bytes, read them back one at a time
+fn plane_byte(offset: u32) -> i32 {
+    return i32((planes[offset / 4u] >> ((offset % 4u) * 8u)) & 0xffu);
+}
+
+fn channel(value: i32) -> u32 {
+    return u32(clamp((value + 128) >> 8u, 0, 255));
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    if (x >= params.width || y >= params.height) {
+        return;
+    }
+
+    let chroma = (y / 2u) * params.chroma_stride + x / 2u;
+    let c = (plane_byte(y * params.luma_stride + x) - params.y_offset) * params.y_scale;
+    let d = plane_byte(params.u_offset + chroma) - 128;
+    let e = plane_byte(params.v_offset + chroma) - 128;
+
+    let r = channel(c + params.rv * e);
+    let g = channel(c - params.gu * d - params.gv * e);
+    let b = channel(c + params.bu * d);
+    pixels[y * params.width + x] = r | (g << 8u) | (b << 16u) | 0xff000000u;
+}
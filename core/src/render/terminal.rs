@@ -0,0 +1,484 @@
+// Active terminal capability probing.
+//
+// Instead of guessing from TERM/KITTY_WINDOW_ID/ITERM_SESSION_ID, we send a
+// batch of escape-sequence queries and parse whatever the terminal answers:
+// - Kitty graphics query (a=q) -> "OK" if the graphics protocol is available
+// - XTVERSION (CSI > 0 q) -> terminal name and version
+// - XTGETTCAP TN (DCS + q) -> terminfo name
+// - XTSMGRAPHICS (CSI ? 1 ; 1 ; 0 S) -> number of Sixel color registers
+// - CSI 14 t / CSI 16 t -> text area and cell size in pixels
+// - DA1 (CSI c) -> device attributes, 4 means Sixel
+//
+// DA1 is sent last. Every terminal answers it, so its reply marks the end of
+// the batch and we don't have to wait for the full timeout on terminals that
+// silently ignore the other queries.
+use log::{debug, info, warn};
+use once_cell::sync::OnceCell;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+use super::RenderMethod;
+use super::passthrough::Multiplexer;
+
+/// Image ID used for the Kitty graphics query so the reply can be matched
+const KITTY_QUERY_ID: u32 = 31;
+
+/// Kitty graphics query with a 1x1 RGB pixel
+///
+/// tmux and screen swallow it, so it's the only query sent through passthrough.
+/// The others are answered by the multiplexer itself.
+const KITTY_QUERY: &str = "\x1B_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1B\\";
+
+/// Remaining capability queries, sent in the same write as the Kitty query
+const PROBE_QUERY: &str = concat!(
+    // XTVERSION
+    "\x1B[>0q",
+    // XTGETTCAP for "TN" (hex encoded)
+    "\x1BP+q544e\x1B\\",
+    // XTSMGRAPHICS: read the number of Sixel color registers
+    "\x1B[?1;1;0S",
+    // Text area size in pixels
+    "\x1B[14t",
+    // Cell size in pixels
+    "\x1B[16t",
+    // Primary device attributes - must stay last
+    "\x1B[c",
+);
+
+/// Default time to wait for the terminal to answer the probe
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+static DETECTED: OnceCell<TerminalCapabilities> = OnceCell::new();
+
+/// Capabilities reported by the terminal in response to escape-sequence queries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminalCapabilities {
+    /// Whether the terminal answered any of the queries
+    pub responded: bool,
+    /// Attributes from the primary device attributes (DA1) reply
+    pub device_attributes: Vec<u16>,
+    /// Terminal name and version from XTVERSION (e.g. "kitty(0.35.2)")
+    pub version: Option<String>,
+    /// Terminfo name from XTGETTCAP (e.g. "xterm-kitty")
+    pub terminal_name: Option<String>,
+    /// Whether the terminal acknowledged the Kitty graphics query
+    pub kitty_graphics: bool,
+    /// Number of Sixel color registers reported via XTSMGRAPHICS
+    pub sixel_color_registers: Option<u16>,
+    /// Text area size in pixels (width, height)
+    pub window_pixels: Option<(u16, u16)>,
+    /// Cell size in pixels (width, height)
+    pub cell_pixels: Option<(u16, u16)>,
+}
+
+impl TerminalCapabilities {
+    /// Probe the terminal once per process and cache the result
+    ///
+    /// Returns empty capabilities when stdin/stdout are not a terminal
+    /// (tests, pipes, CI) so callers fall back to environment heuristics.
+    /// The probe reads stdin directly, so call this before anything else
+    /// starts reading terminal input.
+    pub fn detect() -> &'static TerminalCapabilities {
+        DETECTED.get_or_init(|| {
+            let caps = Self::probe(DEFAULT_PROBE_TIMEOUT);
+            info!("Terminal capabilities: {:?}", caps);
+            caps
+        })
+    }
+
+    /// Send the capability queries and parse the replies, waiting at most `timeout`
+    pub fn probe(timeout: Duration) -> TerminalCapabilities {
+        if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+            debug!("Not attached to a terminal, skipping capability probe");
+            return TerminalCapabilities::default();
+        }
+
+        // Replies only arrive unbuffered in raw mode
+        let was_raw = crossterm::terminal::is_raw_mode_enabled().unwrap_or(false);
+        if !was_raw && let Err(e) = crossterm::terminal::enable_raw_mode() {
+            warn!("Failed to enable raw mode for capability probe: {}", e);
+            return TerminalCapabilities::default();
+        }
+
+        let response = {
+            let mut stdout = std::io::stdout();
+            let mut query = Multiplexer::detect().wrap_str(KITTY_QUERY);
+            query.extend_from_slice(PROBE_QUERY.as_bytes());
+            if stdout
+                .write_all(&query)
+                .and_then(|_| stdout.flush())
+                .is_ok()
+            {
+                read_probe_response(timeout)
+            } else {
+                Vec::new()
+            }
+        };
+
+        if !was_raw {
+            let _ = crossterm::terminal::disable_raw_mode();
+        }
+
+        debug!("Capability probe read {} bytes", response.len());
+        parse_responses(&response)
+    }
+
+    /// Whether Sixel graphics are supported (DA1 attribute 4 or XTSMGRAPHICS reply)
+    pub fn supports_sixel(&self) -> bool {
+        self.device_attributes.contains(&4) || self.sixel_color_registers.is_some()
+    }
+
+    /// Whether the iTerm2 inline image protocol is supported
+    pub fn supports_iterm(&self) -> bool {
+        let name = self.version.as_deref().unwrap_or_default();
+        name.starts_with("iTerm2") || name.starts_with("WezTerm")
+    }
+
+    /// Cell size in pixels, derived from the window size if not reported directly
+    pub fn cell_size(&self, term_width: u16, term_height: u16) -> Option<(u16, u16)> {
+        if let Some(size) = self.cell_pixels {
+            return Some(size);
+        }
+        match self.window_pixels {
+            Some((w, h)) if term_width > 0 && term_height > 0 && w > 0 && h > 0 => {
+                Some((w / term_width, h / term_height))
+            }
+            _ => None,
+        }
+    }
+
+    /// Best rendering method according to the probe, None if the terminal didn't answer
+    pub fn preferred_method(&self) -> Option<RenderMethod> {
+        if !self.responded {
+            return None;
+        }
+        Some(if self.kitty_graphics {
+            RenderMethod::Kitty
+        } else if self.supports_iterm() {
+            RenderMethod::ITerm
+        } else if self.supports_sixel() {
+            RenderMethod::Sixel
+        } else {
+            RenderMethod::Blocks
+        })
+    }
+}
+
+/// Read from stdin until the DA1 reply arrives or the timeout expires
+#[cfg(unix)]
+fn read_probe_response(timeout: Duration) -> Vec<u8> {
+    use std::os::fd::AsRawFd;
+
+    let fd = std::io::stdin().as_raw_fd();
+    let deadline = std::time::Instant::now() + timeout;
+    let mut response = Vec::with_capacity(256);
+    let mut buffer = [0u8; 256];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            debug!("Capability probe timed out");
+            break;
+        }
+
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: pollfd points to a single valid pollfd struct for the duration of the call
+        let ready =
+            unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis().max(1) as libc::c_int) };
+        if ready <= 0 {
+            break;
+        }
+
+        // SAFETY: buffer is valid for writes of buffer.len() bytes
+        let n = unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if n <= 0 {
+            break;
+        }
+        response.extend_from_slice(&buffer[..n as usize]);
+
+        if contains_device_attributes(&response) {
+            break;
+        }
+    }
+
+    response
+}
+
+/// Non-unix platforms can't poll stdin without consuming input, so skip probing
+#[cfg(not(unix))]
+fn read_probe_response(_timeout: Duration) -> Vec<u8> {
+    Vec::new()
+}
+
+/// Check whether a DA1 reply (CSI ? ... c) is present
+fn contains_device_attributes(data: &[u8]) -> bool {
+    split_sequences(data).iter().any(|seq| {
+        matches!(
+            seq,
+            Sequence::Csi {
+                private: Some(b'?'),
+                final_byte: b'c',
+                ..
+            }
+        )
+    })
+}
+
+/// A single escape sequence found in the terminal's reply
+#[derive(Debug, PartialEq, Eq)]
+enum Sequence<'a> {
+    /// CSI [private] params final
+    Csi {
+        private: Option<u8>,
+        params: &'a [u8],
+        final_byte: u8,
+    },
+    /// DCS payload ST
+    Dcs(&'a [u8]),
+    /// APC payload ST
+    Apc(&'a [u8]),
+}
+
+/// Split raw bytes into escape sequences, skipping anything else (e.g. user keypresses)
+fn split_sequences(data: &[u8]) -> Vec<Sequence<'_>> {
+    let mut sequences = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        if data[i] != 0x1B || i + 1 >= data.len() {
+            i += 1;
+            continue;
+        }
+
+        match data[i + 1] {
+            b'[' => {
+                let start = i + 2;
+                let private = data
+                    .get(start)
+                    .copied()
+                    .filter(|b| matches!(b, b'?' | b'>' | b'<' | b'='));
+                let params_start = if private.is_some() { start + 1 } else { start };
+                // Parameters and intermediates run until a final byte in 0x40..=0x7E
+                match data[params_start..]
+                    .iter()
+                    .position(|b| (0x40..=0x7E).contains(b))
+                {
+                    Some(offset) => {
+                        let end = params_start + offset;
+                        sequences.push(Sequence::Csi {
+                            private,
+                            params: &data[params_start..end],
+                            final_byte: data[end],
+                        });
+                        i = end + 1;
+                    }
+                    None => break,
+                }
+            }
+            b'P' | b'_' => {
+                let start = i + 2;
+                // String terminator is ESC \
+                match data[start..].windows(2).position(|w| w == b"\x1B\\") {
+                    Some(offset) => {
+                        let payload = &data[start..start + offset];
+                        sequences.push(if data[i + 1] == b'P' {
+                            Sequence::Dcs(payload)
+                        } else {
+                            Sequence::Apc(payload)
+                        });
+                        i = start + offset + 2;
+                    }
+                    None => break,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    sequences
+}
+
+/// Parse semicolon separated numeric parameters
+fn parse_params(params: &[u8]) -> Vec<u16> {
+    String::from_utf8_lossy(params)
+        .split(';')
+        .filter_map(|p| p.parse().ok())
+        .collect()
+}
+
+/// Decode a hex encoded string (XTGETTCAP replies)
+fn decode_hex(hex: &str) -> Option<String> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Parse the terminal's replies to `KITTY_QUERY` and `PROBE_QUERY`
+pub fn parse_responses(data: &[u8]) -> TerminalCapabilities {
+    let mut caps = TerminalCapabilities::default();
+
+    for seq in split_sequences(data) {
+        match seq {
+            Sequence::Csi {
+                private: Some(b'?'),
+                params,
+                final_byte: b'c',
+            } => {
+                caps.responded = true;
+                caps.device_attributes = parse_params(params);
+            }
+            Sequence::Csi {
+                private: Some(b'?'),
+                params,
+                final_byte: b'S',
+            } => {
+                caps.responded = true;
+                // Reply is Pi ; Ps ; Pv where Ps == 0 means success
+                if let [1, 0, registers, ..] = parse_params(params)[..] {
+                    caps.sixel_color_registers = Some(registers);
+                }
+            }
+            Sequence::Csi {
+                private: None,
+                params,
+                final_byte: b't',
+            } => {
+                caps.responded = true;
+                // Replies are "4 ; height ; width" and "6 ; height ; width"
+                match parse_params(params)[..] {
+                    [4, height, width] => caps.window_pixels = Some((width, height)),
+                    [6, height, width] => caps.cell_pixels = Some((width, height)),
+                    _ => {}
+                }
+            }
+            Sequence::Dcs(payload) => {
+                caps.responded = true;
+                let payload = String::from_utf8_lossy(payload);
+                if let Some(version) = payload.strip_prefix(">|") {
+                    caps.version = Some(version.to_string());
+                } else if let Some(reply) = payload.strip_prefix("1+r") {
+                    for capability in reply.split(';') {
+                        if let Some(("544e", value)) = capability.split_once('=') {
+                            caps.terminal_name = decode_hex(value);
+                        }
+                    }
+                }
+            }
+            Sequence::Apc(payload) => {
+                caps.responded = true;
+                let payload = String::from_utf8_lossy(payload);
+                if let Some(reply) = payload.strip_prefix('G') {
+                    let (keys, message) = reply.split_once(';').unwrap_or((reply, ""));
+                    let id_matches = keys
+                        .split(',')
+                        .any(|kv| kv == format!("i={}", KITTY_QUERY_ID));
+                    if id_matches && message == "OK" {
+                        caps.kitty_graphics = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    caps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Replies recorded from real terminals answering the probe
+    const KITTY_REPLY: &[u8] = b"\x1B_Gi=31;OK\x1B\\\x1BP>|kitty(0.35.2)\x1B\\\x1BP1+r544e=787465726d2d6b69747479\x1B\\\x1B[?1;1;0S\x1B[4;1080;1920t\x1B[6;20;10t\x1B[?62;c";
+    const XTERM_SIXEL_REPLY: &[u8] = b"\x1BP>|XTerm(390)\x1B\\\x1BP0+r\x1B\\\x1B[?1;0;1024S\x1B[4;768;1280t\x1B[6;16;8t\x1B[?63;1;2;4;6;9;15;16;22;28c";
+    const WEZTERM_REPLY: &[u8] = b"\x1B_Gi=31;OK\x1B\\\x1BP>|WezTerm 20240203-110809-5046fc22\x1B\\\x1B[?1;0;256S\x1B[4;900;1600t\x1B[6;18;9t\x1B[?65;4;6;18;22c";
+    const VTE_REPLY: &[u8] = b"\x1B[4;800;1200t\x1B[?65;1;9c";
+
+    #[test]
+    fn test_parse_kitty_reply() {
+        let caps = parse_responses(KITTY_REPLY);
+        assert!(caps.responded);
+        assert!(caps.kitty_graphics);
+        assert_eq!(caps.version.as_deref(), Some("kitty(0.35.2)"));
+        assert_eq!(caps.terminal_name.as_deref(), Some("xterm-kitty"));
+        assert_eq!(caps.window_pixels, Some((1920, 1080)));
+        assert_eq!(caps.cell_pixels, Some((10, 20)));
+        // Status 1 means XTSMGRAPHICS failed, so no Sixel registers
+        assert_eq!(caps.sixel_color_registers, None);
+        assert!(!caps.supports_sixel());
+        assert_eq!(caps.preferred_method(), Some(RenderMethod::Kitty));
+    }
+
+    #[test]
+    fn test_parse_xterm_sixel_reply() {
+        let caps = parse_responses(XTERM_SIXEL_REPLY);
+        assert!(!caps.kitty_graphics);
+        assert_eq!(caps.version.as_deref(), Some("XTerm(390)"));
+        assert_eq!(caps.terminal_name, None);
+        assert_eq!(caps.sixel_color_registers, Some(1024));
+        assert!(caps.device_attributes.contains(&4));
+        assert!(caps.supports_sixel());
+        assert_eq!(caps.cell_pixels, Some((8, 16)));
+        assert_eq!(caps.preferred_method(), Some(RenderMethod::Sixel));
+    }
+
+    #[test]
+    fn test_parse_wezterm_reply() {
+        let caps = parse_responses(WEZTERM_REPLY);
+        assert!(caps.kitty_graphics);
+        assert!(caps.supports_iterm());
+        assert!(caps.supports_sixel());
+        // Kitty wins when several protocols are available
+        assert_eq!(caps.preferred_method(), Some(RenderMethod::Kitty));
+    }
+
+    #[test]
+    fn test_parse_reply_without_graphics() {
+        let caps = parse_responses(VTE_REPLY);
+        assert!(caps.responded);
+        assert_eq!(caps.device_attributes, vec![65, 1, 9]);
+        assert_eq!(caps.cell_pixels, None);
+        // Cell size is derived from the window size when not reported directly
+        assert_eq!(caps.cell_size(120, 40), Some((10, 20)));
+        assert_eq!(caps.preferred_method(), Some(RenderMethod::Blocks));
+    }
+
+    #[test]
+    fn test_parse_no_reply() {
+        let caps = parse_responses(b"");
+        assert!(!caps.responded);
+        assert_eq!(caps.preferred_method(), None);
+        assert_eq!(caps.cell_size(80, 24), None);
+    }
+
+    #[test]
+    fn test_parse_ignores_interleaved_input() {
+        // A keypress and an unrelated kitty reply mixed into the response
+        let mut data = b"a".to_vec();
+        data.extend_from_slice(b"\x1B_Gi=7;ENOENT:bad\x1B\\");
+        data.extend_from_slice(XTERM_SIXEL_REPLY);
+        data.extend_from_slice(b"q");
+        let caps = parse_responses(&data);
+        assert!(!caps.kitty_graphics);
+        assert_eq!(caps.sixel_color_registers, Some(1024));
+        assert!(contains_device_attributes(&data));
+    }
+
+    #[test]
+    fn test_truncated_reply() {
+        // The DA1 reply was cut off by the timeout
+        let caps = parse_responses(b"\x1B[6;16;8t\x1B[?63;1;2");
+        assert_eq!(caps.cell_pixels, Some((8, 16)));
+        assert!(caps.device_attributes.is_empty());
+        assert!(!contains_device_attributes(b"\x1B[?63;1;2"));
+    }
+}
@@ -1,14 +1,16 @@
 use std::time::{Duration, Instant};
 use image::{DynamicImage, ImageBuffer, Rgba};
 
-use crate::video::VideoFrame;
-use crate::render::{RenderConfig, RenderMethod, TerminalRenderer};
-use crate::render::gpu::GpuProcessor;
+use crate::video::{ColorMatrix, FrameData, PixelFormat, ScaleFilter, VideoFrame, YuvFrame};
+use crate::render::{
+    BlockCell, CellGrid, ColorDepth, GpuHandle, Multiplexer, OutputTarget, RenderConfig,
+    RenderMethod, RenderedFrame, TerminalRenderer, Viewport,
+};
+use crate::render::gpu::{GpuProcessor, cell_grid_from_packed};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::hash::{Hash, Hasher};
 
     /// Generate a test frame with the given dimensions
     fn create_test_frame(width: u32, height: u32) -> VideoFrame {
@@ -93,7 +95,7 @@ mod tests {
         // Set a timeout for GPU processing (3 seconds)
         const TIMEOUT_SECS: u64 = 3;
         let gpu_data = match std::thread::scope(|s| {
-            let handle = s.spawn(|| processor.process_frame(&frame, target_width, target_height));
+            let handle = s.spawn(|| processor.process_frame(&frame, target_width, target_height, ScaleFilter::default()));
             match handle.join() {
                 Ok(data) => Some(data),
                 Err(_) => None
@@ -139,7 +141,7 @@ mod tests {
         let first_duration = match std::thread::scope(|s| {
             let handle = s.spawn(|| {
                 let start = Instant::now();
-                let _data = processor.process_frame(&frame, 160, 120);
+                let _data = processor.process_frame(&frame, 160, 120, ScaleFilter::Bilinear);
                 start.elapsed()
             });
             
@@ -159,7 +161,7 @@ mod tests {
         let second_duration = match std::thread::scope(|s| {
             let handle = s.spawn(|| {
                 let start = Instant::now();
-                let _data = processor.process_frame(&frame, 160, 120);
+                let _data = processor.process_frame(&frame, 160, 120, ScaleFilter::Bilinear);
                 start.elapsed()
             });
             
@@ -272,7 +274,7 @@ mod tests {
         let config = RenderConfig { method: RenderMethod::Blocks, ..Default::default() };
         let renderer = TerminalRenderer::new(config).unwrap();
         // Internal function: simulate col_transparent logic
-        let img = frame.image.to_rgba8();
+        let img = frame.to_rgba_image();
         let mut col_transparent = vec![true; 4];
         for x in 0..4 {
             for y in 0..4 {
@@ -286,50 +288,416 @@ mod tests {
         assert!(!col_transparent[1], "Column 1 should not be transparent");
     }
 
+    /// Renderer writing blocks to stdout, for encoding cell diffs
+    fn blocks_renderer() -> TerminalRenderer {
+        let config = RenderConfig {
+            method: RenderMethod::Blocks,
+            ..Default::default()
+        };
+        TerminalRenderer::new(config).unwrap()
+    }
+
+    fn block(fg: [u8; 3], bg: [u8; 3]) -> Option<BlockCell> {
+        Some(BlockCell {
+            symbol: '▀',
+            fg,
+            bg,
+        })
+    }
+
+    fn solid_grid(width: u16, height: u16, fg: [u8; 3], bg: [u8; 3]) -> CellGrid {
+        let mut grid = CellGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                grid.set(x, y, block(fg, bg));
+            }
+        }
+        grid
+    }
+
+    // Cursor save/hide before the cells, reset/restore/show after them
+    const FRAME_WRAPPER: &str = "\x1B[s\x1B[?25l\x1B[0m\x1B[u\x1B[?25h";
+
     #[test]
-    fn test_dirty_row_diffing() {
-        // Test that dirty row diffing detects unchanged rows
-        let mut img1 = ImageBuffer::new(2, 4);
-        let mut img2 = ImageBuffer::new(2, 4);
-        // Row 0 and 1 are the same, row 2 and 3 differ
-        for x in 0..2 {
-            img1.put_pixel(x, 0, Rgba([10, 20, 30, 255]));
-            img1.put_pixel(x, 1, Rgba([10, 20, 30, 255]));
-            img1.put_pixel(x, 2, Rgba([40, 50, 60, 255]));
-            img1.put_pixel(x, 3, Rgba([70, 80, 90, 255]));
-            img2.put_pixel(x, 0, Rgba([10, 20, 30, 255]));
-            img2.put_pixel(x, 1, Rgba([10, 20, 30, 255]));
-            img2.put_pixel(x, 2, Rgba([99, 99, 99, 255])); // changed
-            img2.put_pixel(x, 3, Rgba([70, 80, 90, 255]));
+    fn test_static_scene_writes_no_cells() {
+        let mut renderer = blocks_renderer();
+        let frame = create_test_frame(40, 20);
+        let grid = TerminalRenderer::build_cell_grid(&frame.view(), 40, 10, ColorDepth::TrueColor);
+
+        let first = renderer.encode_cell_diff(grid.clone());
+        assert_eq!(first.matches('▀').count(), 400);
+
+        // Nothing changed, only the cursor handling goes out
+        let second = renderer.encode_cell_diff(grid);
+        assert_eq!(second, FRAME_WRAPPER);
+        assert_eq!(second.len(), 22);
+    }
+
+    #[test]
+    fn test_mostly_static_scene_writes_changed_cells() {
+        let mut renderer = blocks_renderer();
+        let grid = solid_grid(80, 24, [10, 20, 30], [40, 50, 60]);
+        let full = renderer.encode_cell_diff(grid.clone());
+        // 80x24 cells: a cursor move and colors per row, three bytes per glyph
+        assert!(full.len() > 80 * 24 * 3);
+
+        // One cell in the middle changes
+        let mut changed = grid.clone();
+        changed.set(40, 12, block([255, 0, 0], [40, 50, 60]));
+        let diff = renderer.encode_cell_diff(changed);
+        let cell = "\x1B[13;41H\x1B[38;2;255;0;0;48;2;40;50;60m▀\x1B[0m";
+        assert_eq!(diff.len(), FRAME_WRAPPER.len() + cell.len());
+        assert!(diff.contains(cell));
+
+        // Changing it back costs the same single cell
+        let diff = renderer.encode_cell_diff(grid);
+        assert_eq!(diff.matches('▀').count(), 1);
+        assert!(diff.len() < 80);
+    }
+
+    #[test]
+    fn test_cell_diff_coalesces_runs() {
+        let mut renderer = blocks_renderer();
+        let grid = solid_grid(20, 2, [0, 0, 0], [0, 0, 0]);
+        renderer.encode_cell_diff(grid.clone());
+
+        // A run of changed cells needs one cursor move and one color change
+        let mut run = grid.clone();
+        for x in 5..10 {
+            run.set(x, 1, block([9, 9, 9], [0, 0, 0]));
         }
-        let frame1 = VideoFrame::new(DynamicImage::ImageRgba8(img1), 0.0, 0.04);
-        let frame2 = VideoFrame::new(DynamicImage::ImageRgba8(img2), 0.0, 0.04);
-        let config = RenderConfig { method: RenderMethod::Blocks, ..Default::default() };
+        let diff = renderer.encode_cell_diff(run.clone());
+        assert_eq!(diff.matches('H').count(), 1);
+        assert_eq!(diff.matches("38;2").count(), 1);
+        assert_eq!(diff.matches('▀').count(), 5);
+
+        // Two changes around an unchanged cell in the same colors: rewriting
+        // the gap is shorter than moving the cursor
+        let mut gap = run.clone();
+        gap.set(4, 1, block([9, 9, 9], [0, 0, 0]));
+        gap.set(6, 1, block([9, 9, 9], [7, 7, 7]));
+        gap.set(7, 1, block([9, 9, 9], [7, 7, 7]));
+        let diff = renderer.encode_cell_diff(gap);
+        assert_eq!(diff.matches('H').count(), 1);
+        // Only the background changes for the second run
+        assert_eq!(diff.matches("48;2;7;7;7m").count(), 1);
+        assert_eq!(diff.matches('▀').count(), 4);
+    }
+
+    #[test]
+    fn test_cell_diff_clears_transparent_cells() {
+        let mut renderer = blocks_renderer();
+        let grid = solid_grid(4, 1, [1, 2, 3], [4, 5, 6]);
+        renderer.encode_cell_diff(grid.clone());
+
+        let mut cleared = grid;
+        cleared.set(3, 0, None);
+        let diff = renderer.encode_cell_diff(cleared);
+        assert!(diff.contains("\x1B[1;4H "));
+        assert_eq!(diff.matches('▀').count(), 0);
+    }
+
+    #[test]
+    fn test_cell_diff_redraws_after_invalidate() {
+        let mut renderer = blocks_renderer();
+        let grid = solid_grid(10, 3, [1, 2, 3], [4, 5, 6]);
+        renderer.encode_cell_diff(grid.clone());
+        renderer.invalidate();
+        assert_eq!(renderer.encode_cell_diff(grid).matches('▀').count(), 30);
+    }
+
+    #[test]
+    fn test_cell_diff_redraws_after_fallback_clear() {
+        let mut renderer = TerminalRenderer::new(RenderConfig {
+            method: RenderMethod::Blocks,
+            output: OutputTarget::Buffered,
+            ..Default::default()
+        })
+        .unwrap();
+        let grid = solid_grid(10, 3, [1, 2, 3], [4, 5, 6]);
+        renderer.encode_cell_diff(grid.clone());
+
+        // A graphics method failed and the screen was cleared for blocks
+        renderer.clear_artifacts();
+        assert_eq!(renderer.encode_cell_diff(grid).matches('▀').count(), 30);
+    }
+
+    #[test]
+    fn test_visible_cells_clip_to_viewport() {
+        let mut renderer = blocks_renderer();
+        let viewport = Viewport {
+            x: 0,
+            y: 3,
+            width: 80,
+            height: 14,
+        };
+        renderer.resize(80, 24, Some(viewport));
+        assert_eq!(renderer.visible_cells(), (80, 14));
+
+        // A viewport from before the terminal shrank is clipped, not underflowed
+        renderer.term_width = 40;
+        renderer.term_height = 2;
+        assert_eq!(renderer.visible_cells(), (40, 0));
+    }
+
+    #[test]
+    fn test_buffered_kitty_delete_waits_for_the_caller() {
+        let config = RenderConfig {
+            method: RenderMethod::Blocks,
+            output: OutputTarget::Buffered,
+            ..Default::default()
+        };
         let mut renderer = TerminalRenderer::new(config).unwrap();
-        // Simulate row hash logic
-        let img1 = frame1.image.to_rgba8();
-        let img2 = frame2.image.to_rgba8();
-        let mut row_hashes1 = vec![0u64; 2];
-        let mut row_hashes2 = vec![0u64; 2];
-        for y in 0..2 {
-            let mut hasher1 = std::collections::hash_map::DefaultHasher::new();
-            let mut hasher2 = std::collections::hash_map::DefaultHasher::new();
-            for x in 0..2 {
-                let y_top = y * 2;
-                let y_bottom = y * 2 + 1;
-                let top1 = img1.get_pixel(x as u32, y_top as u32).0;
-                let bot1 = img1.get_pixel(x as u32, y_bottom as u32).0;
-                let top2 = img2.get_pixel(x as u32, y_top as u32).0;
-                let bot2 = img2.get_pixel(x as u32, y_bottom as u32).0;
-                top1.hash(&mut hasher1);
-                bot1.hash(&mut hasher1);
-                top2.hash(&mut hasher2);
-                bot2.hash(&mut hasher2);
+        renderer.effective_method = RenderMethod::Kitty;
+        renderer.multiplexer = Multiplexer::None;
+
+        // The delete is queued, then the next image goes out after it
+        renderer.invalidate();
+        renderer.emit(b"image".to_vec()).unwrap();
+        let Some(RenderedFrame::Graphics(bytes)) = renderer.take_output() else {
+            panic!("expected graphics output");
+        };
+        assert!(bytes.starts_with(b"\x1B_Ga=d,d=A,q=2\x1B\\"));
+        assert!(bytes.ends_with(b"image"));
+        assert!(renderer.take_output().is_none());
+    }
+
+    #[test]
+    fn test_build_cell_grid() {
+        // 2x3 image: the last cell row only has a top pixel
+        let mut img = ImageBuffer::new(2, 3);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        img.put_pixel(0, 1, Rgba([0, 255, 0, 255]));
+        img.put_pixel(0, 2, Rgba([0, 0, 255, 255]));
+        // Column 1 is fully transparent
+        for y in 0..3 {
+            img.put_pixel(1, y, Rgba([0, 0, 0, 0]));
+        }
+
+        let frame = VideoFrame::new(DynamicImage::ImageRgba8(img), 0.0, 0.04);
+        let grid = TerminalRenderer::build_cell_grid(&frame.view(), 2, 2, ColorDepth::TrueColor);
+        assert_eq!((grid.width, grid.height), (2, 2));
+        assert_eq!(
+            grid.get(0, 0),
+            Some(&BlockCell { symbol: '▀', fg: [255, 0, 0], bg: [0, 255, 0] })
+        );
+        // Missing bottom pixel is drawn black
+        assert_eq!(
+            grid.get(0, 1),
+            Some(&BlockCell { symbol: '▀', fg: [0, 0, 255], bg: [0, 0, 0] })
+        );
+        assert_eq!(grid.get(1, 0), None);
+        assert_eq!(grid.get(1, 1), None);
+    }
+
+    #[test]
+    fn test_output_size_is_stable() {
+        // A frame decoded at the requested size must not be resized again
+        let config = RenderConfig {
+            method: RenderMethod::Blocks,
+            width: Some(80),
+            height: Some(24),
+            quality: 1.0,
+            adaptive_resolution: false,
+            ..Default::default()
+        };
+        let renderer = TerminalRenderer::new(config).unwrap();
+
+        let (width, height) = renderer.output_size(3840, 2160);
+        assert!(width <= 80 && height <= 48);
+        assert_eq!(renderer.output_size(width, height), (width, height));
+    }
+
+    /// YUV fixture with padded planes and values outside the limited range
+    fn create_test_planes(
+        width: u32,
+        height: u32,
+        matrix: ColorMatrix,
+        full_range: bool,
+    ) -> YuvFrame {
+        let (chroma_width, chroma_height) = YuvFrame::chroma_size(width, height);
+        let luma_stride = width as usize + 5;
+        let chroma_stride = chroma_width as usize + 3;
+        let mut data = vec![0u8; YuvFrame::data_len(height, luma_stride, chroma_stride)];
+        let u_offset = luma_stride * height as usize;
+        let v_offset = u_offset + chroma_stride * chroma_height as usize;
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                data[y * luma_stride + x] = (x * 7 + y * 13) as u8;
+            }
+        }
+        for y in 0..chroma_height as usize {
+            for x in 0..chroma_width as usize {
+                data[u_offset + y * chroma_stride + x] = (x * 29 + y * 3) as u8;
+                data[v_offset + y * chroma_stride + x] = !((x * 11 + y * 17) as u8);
+            }
+        }
+        YuvFrame::from_data(
+            FrameData::from_vec(data),
+            width,
+            height,
+            luma_stride,
+            chroma_stride,
+            matrix,
+            full_range,
+            0.0,
+            0.04,
+        )
+    }
+
+    /// RGBA fixture with padded rows, partial alpha and a transparent band
+    fn create_alpha_frame(width: u32, height: u32) -> VideoFrame {
+        let stride = (width as usize * 4).next_multiple_of(32);
+        let mut data = vec![0u8; stride * height as usize];
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let alpha = if (4..8).contains(&x) {
+                    0
+                } else {
+                    (x * 37 + y * 11) as u8
+                };
+                data[y * stride + x * 4..][..4].copy_from_slice(&[
+                    (x * 9) as u8,
+                    (y * 23) as u8,
+                    (x * y) as u8,
+                    alpha,
+                ]);
+            }
+        }
+        VideoFrame::from_data(
+            FrameData::from_vec(data),
+            width,
+            height,
+            stride,
+            PixelFormat::Rgba,
+            0.0,
+            0.04,
+        )
+    }
+
+    #[test]
+    fn test_gpu_yuv_conversion_matches_cpu() {
+        let Ok(mut processor) = pollster::block_on(GpuProcessor::new()) else {
+            println!("Skipping GPU test - GPU not available");
+            return;
+        };
+
+        for (width, height) in [(64, 36), (33, 17), (1, 1)] {
+            for matrix in [ColorMatrix::Bt601, ColorMatrix::Bt709] {
+                for full_range in [false, true] {
+                    let planes = create_test_planes(width, height, matrix, full_range);
+                    let gpu = processor.convert_yuv(&planes).expect("YUV pass should run");
+                    assert!(
+                        *gpu == *planes.to_rgba(),
+                        "{}x{} {:?} full range {} differs from the CPU",
+                        width,
+                        height,
+                        matrix,
+                        full_range
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gpu_block_cells_match_cpu() {
+        let Ok(mut processor) = pollster::block_on(GpuProcessor::new()) else {
+            println!("Skipping GPU test - GPU not available");
+            return;
+        };
+
+        // Full frames, an odd height with a missing bottom row and a cropped grid
+        for (width, height, columns, rows) in [(40, 24, 40, 12), (33, 17, 33, 9), (33, 17, 20, 5)] {
+            let frame = create_alpha_frame(width, height);
+            let packed = processor
+                .block_cells(&frame, columns, rows)
+                .expect("cell pass should run");
+            for depth in [ColorDepth::TrueColor, ColorDepth::Indexed256] {
+                let gpu = cell_grid_from_packed(&packed, columns as u16, rows as u16, depth);
+                let cpu = TerminalRenderer::build_cell_grid(
+                    &frame.view(),
+                    columns as usize,
+                    rows as usize,
+                    depth,
+                );
+                assert_eq!(
+                    gpu, cpu,
+                    "{}x{} as {}x{} cells",
+                    width, height, columns, rows
+                );
+            }
+        }
+
+        // Cells can't come from outside the frame
+        let frame = create_alpha_frame(8, 8);
+        assert!(processor.block_cells(&frame, 9, 4).is_err());
+        assert!(processor.block_cells(&frame, 8, 5).is_err());
+    }
+
+    #[test]
+    fn test_gpu_handle_falls_back_to_cpu() {
+        // A handle without a processor converts on the CPU
+        let handle = GpuHandle::default();
+        let planes = create_test_planes(33, 17, ColorMatrix::Bt709, false);
+        let frame = handle.convert_yuv(&planes);
+        assert_eq!((frame.width, frame.height), (33, 17));
+        assert_eq!(frame.as_rgba_bytes(), planes.to_rgba().as_slice());
+        assert!(
+            handle
+                .block_cells(&frame, 33, 9, ColorDepth::TrueColor)
+                .is_none()
+        );
+    }
+
+    /// Largest per-channel difference between two RGBA buffers
+    fn max_channel_diff(a: &[u8], b: &[u8]) -> u8 {
+        assert_eq!(a.len(), b.len());
+        a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_area_filter_matches_block_average() {
+        // Reference: an exact 4x downscale averages each 4x4 block
+        let frame = create_test_frame(64, 32);
+        let resized = frame.resize_with(16, 8, false, ScaleFilter::Area);
+        let source = frame.as_rgba_bytes();
+        let mut reference = Vec::with_capacity(16 * 8 * 4);
+        for y in 0..8 {
+            for x in 0..16 {
+                for c in 0..4 {
+                    let sum: u32 = (0..16)
+                        .map(|i| source[((y * 4 + i / 4) * 64 + x * 4 + i % 4) * 4 + c] as u32)
+                        .sum();
+                    reference.push(((sum + 8) / 16) as u8);
+                }
+            }
+        }
+        assert!(max_channel_diff(resized.as_rgba_bytes(), &reference) <= 1);
+    }
+
+    #[test]
+    fn test_gpu_resize_filters_match_cpu() {
+        let Ok(mut processor) = pollster::block_on(GpuProcessor::new()) else {
+            println!("Skipping GPU test - GPU not available");
+            return;
+        };
+
+        let frame = create_test_frame(123, 77);
+        for filter in ScaleFilter::ALL {
+            for (width, height) in [(40, 25), (200, 130)] {
+                let gpu = processor.process_frame(&frame, width, height, filter);
+                let cpu = frame.resize_with(width, height, false, filter);
+                let diff = max_channel_diff(&gpu, cpu.as_rgba_bytes());
+                assert!(
+                    diff <= 2,
+                    "{:?} to {}x{} differs by {}",
+                    filter,
+                    width,
+                    height,
+                    diff
+                );
             }
-            row_hashes1[y] = hasher1.finish();
-            row_hashes2[y] = hasher2.finish();
         }
-        assert_eq!(row_hashes1[0], row_hashes2[0], "Row 0 should be unchanged");
-        assert_eq!(row_hashes1[1] != row_hashes2[1], true, "Row 1 should be changed");
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,16 @@
 pub mod decoder;
 pub mod frame;
+pub mod pool;
+pub mod scale;
+pub mod yuv;
 
 use anyhow::{Context, Result};
 use ffmpeg_next as ffmpeg;
-use image::DynamicImage;
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
 pub use frame::FrameBuffer;
+pub use pool::{FrameData, FramePool};
+pub use scale::{Resampler, ScaleFilter};
+pub use yuv::{ColorMatrix, YuvFrame};
 
 // Initialize FFmpeg only once
 pub fn init() -> Result<()> {
@@ -18,11 +24,39 @@ pub fn init() -> Result<()> {
     init_result
 }
 
+/// Pixel layout of frame data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 8-bit RGBA, what `TerminalRenderer` expects
+    Rgba,
+    /// 8-bit RGB without alpha
+    Rgb,
+}
+
+impl PixelFormat {
+    /// Bytes used by one pixel
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba => 4,
+            PixelFormat::Rgb => 3,
+        }
+    }
+}
+
 /// Respresent a decoded video frame with timestamp information
+///
+/// Pixel data is reference counted, so cloning a frame (into the frame buffer,
+/// the renderer, ...) never copies it. Rows are `stride` bytes apart and may
+/// be padded; use `row`, `view` or `to_rgba_image` rather than assuming a
+/// packed layout.
 #[derive(Clone)]
 pub struct VideoFrame {
-    /// The frame data as an RGBA image
-    pub image: DynamicImage,
+    /// Pixel data, shared between clones
+    data: FrameData,
+    /// Bytes between the starts of consecutive rows
+    pub stride: usize,
+    /// Layout of each pixel
+    pub format: PixelFormat,
     /// Presentation timestamp in seconds
     pub timestamp: f64,
     /// Duration of this frame in seconds
@@ -35,13 +69,72 @@ pub struct VideoFrame {
     pub audio_path: Option<std::path::PathBuf>,
 }
 
+/// Borrowed, stride-aware view of a frame's pixels as RGBA
+pub struct FrameView<'a> {
+    data: &'a [u8],
+    stride: usize,
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+}
+
+impl FrameView<'_> {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixel at the given position, RGB frames are reported as opaque
+    #[inline]
+    pub fn get_pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+        let offset = y as usize * self.stride + x as usize * self.format.bytes_per_pixel();
+        let px = &self.data[offset..];
+        match self.format {
+            PixelFormat::Rgba => Rgba([px[0], px[1], px[2], px[3]]),
+            PixelFormat::Rgb => Rgba([px[0], px[1], px[2], 255]),
+        }
+    }
+}
+
 impl VideoFrame {
     /// Create a new VideoFrame from an RGBA image and timing information
     pub fn new(image: DynamicImage, timestamp: f64, duration: f64) -> Self {
-        let width = image.width();
-        let height = image.height();
+        Self::from_rgba_image(image.into_rgba8(), timestamp, duration)
+    }
+
+    /// Create a frame that takes ownership of a packed RGBA image
+    pub fn from_rgba_image(image: RgbaImage, timestamp: f64, duration: f64) -> Self {
+        let (width, height) = image.dimensions();
+        Self::from_data(
+            FrameData::from_vec(image.into_raw()),
+            width,
+            height,
+            width as usize * 4,
+            PixelFormat::Rgba,
+            timestamp,
+            duration,
+        )
+    }
+
+    /// Create a frame over existing pixel data, e.g. a pooled decoder buffer
+    pub fn from_data(
+        data: FrameData,
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: PixelFormat,
+        timestamp: f64,
+        duration: f64,
+    ) -> Self {
+        debug_assert!(stride >= width as usize * format.bytes_per_pixel());
+        debug_assert!(data.len() >= stride * height.saturating_sub(1) as usize);
         Self {
-            image,
+            data,
+            stride,
+            format,
             timestamp,
             duration,
             width,
@@ -50,69 +143,164 @@ impl VideoFrame {
         }
     }
 
-    /// Get frame data as RGBA bytes
+    /// Get frame data as RGBA bytes, rows are `stride` bytes apart
     pub fn as_rgba_bytes(&self) -> &[u8] {
-        self.image.as_rgba8().unwrap().as_raw()
+        debug_assert_eq!(self.format, PixelFormat::Rgba);
+        &self.data
+    }
+
+    /// Whether rows follow each other without padding
+    pub fn is_packed(&self) -> bool {
+        self.stride == self.width as usize * self.format.bytes_per_pixel()
+    }
+
+    /// Pixels of row `y`, without padding
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.stride;
+        &self.data[start..start + self.width as usize * self.format.bytes_per_pixel()]
+    }
+
+    /// Stride-aware pixel access without copying
+    pub fn view(&self) -> FrameView<'_> {
+        FrameView {
+            data: &self.data,
+            stride: self.stride,
+            format: self.format,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Packed RGBA view for the `image` crate, if the layout allows it
+    fn image_view(&self) -> Option<ImageBuffer<Rgba<u8>, &[u8]>> {
+        if self.format != PixelFormat::Rgba || !self.is_packed() {
+            return None;
+        }
+        let len = self.stride * self.height as usize;
+        ImageBuffer::from_raw(self.width, self.height, &self.data[..len])
+    }
+
+    /// Copy the frame into a packed RGBA image
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        if let Some(view) = self.image_view() {
+            return ImageBuffer::from_raw(self.width, self.height, view.into_raw().to_vec())
+                .expect("packed view matches the frame size");
+        }
+        let view = self.view();
+        ImageBuffer::from_fn(self.width, self.height, |x, y| view.get_pixel(x, y))
     }
 
     /// Resize frame to target dimensions, maintaining aspect ratio if needed
-        #[inline]
-        pub fn resize(
-            &self,
-            target_width: u32,
-            target_height: u32,
-            maintain_aspect_ratio: bool,
-        ) -> Self {
-            // If dimensions already match, avoid resizing
-            if self.width == target_width && self.height == target_height {
-                return self.clone();
-            }
-        
-            // Calculate actual dimensions accounting for aspect ratio
-            let (new_width, new_height) = if maintain_aspect_ratio {
-                let ratio = self.width as f32 / self.height as f32;
-
-                // Determine dimensions that fit within target while maintaining aspect ratio
-                if target_width as f32 / target_height as f32 > ratio {
-                    // Height is the limiting factor
-                    let new_height = target_height;
-                    let new_width = (new_height as f32 * ratio) as u32;
-                    (new_width, new_height)
-                } else {
-                    // Width is the limiting factor
-                    let new_width = target_width;
-                    let new_height = (new_width as f32 / ratio) as u32;
-                    (new_width, new_height)
-                }
-            } else {
-                (target_width, target_height)
-            };
-        
-            // Choose filter type based on the scaling factor
-            let scale_factor = (new_width as f32 / self.width as f32)
-                .max(new_height as f32 / self.height as f32);
-            
-            let filter = if scale_factor < 0.7 || scale_factor > 1.5 {
-                // For significant downscaling or upscaling, use higher quality
-                image::imageops::FilterType::Triangle
-            } else {
-                // For minor resizing, use fastest algorithm
-                image::imageops::FilterType::Nearest
-            };
+    ///
+    /// Uses the default `ScaleFilter`, see `resize_with`.
+    #[inline]
+    pub fn resize(
+        &self,
+        target_width: u32,
+        target_height: u32,
+        maintain_aspect_ratio: bool,
+    ) -> Self {
+        self.resize_with(
+            target_width,
+            target_height,
+            maintain_aspect_ratio,
+            ScaleFilter::default(),
+        )
+    }
+
+    /// Resize frame to target dimensions with the given filter
+    pub fn resize_with(
+        &self,
+        target_width: u32,
+        target_height: u32,
+        maintain_aspect_ratio: bool,
+        filter: ScaleFilter,
+    ) -> Self {
+        // If dimensions already match, avoid resizing
+        if self.width == target_width && self.height == target_height {
+            return self.clone();
+        }
+        let (new_width, new_height) =
+            self.fitted_size(target_width, target_height, maintain_aspect_ratio);
 
-            let resized_image = self.image.resize_exact(new_width, new_height, filter);
-            VideoFrame::new(resized_image, self.timestamp, self.duration)
+        // Reads the shared data in place, whatever the stride and format
+        let resized = scale::resize(&self.view(), new_width, new_height, filter);
+        self.resize_from_data(new_width, new_height, resized)
+    }
+
+    /// Like `resize_with`, reusing the taps and buffers kept by `resampler`
+    ///
+    /// Use this when scaling every frame of a video, so that frames of the
+    /// same size don't recompute the filter or allocate.
+    pub fn resize_using(
+        &self,
+        target_width: u32,
+        target_height: u32,
+        maintain_aspect_ratio: bool,
+        filter: ScaleFilter,
+        resampler: &mut Resampler,
+    ) -> Self {
+        if self.width == target_width && self.height == target_height {
+            return self.clone();
         }
+        let (new_width, new_height) =
+            self.fitted_size(target_width, target_height, maintain_aspect_ratio);
+
+        let resized = resampler.resize(&self.view(), new_width, new_height, filter);
+        VideoFrame::from_data(
+            resized,
+            new_width,
+            new_height,
+            new_width as usize * 4,
+            PixelFormat::Rgba,
+            self.timestamp,
+            self.duration,
+        )
+    }
+
+    /// Size to scale to so the frame fits the target, at least 1x1
+    fn fitted_size(
+        &self,
+        target_width: u32,
+        target_height: u32,
+        maintain_aspect_ratio: bool,
+    ) -> (u32, u32) {
+        // Calculate actual dimensions accounting for aspect ratio
+        let (new_width, new_height) = if maintain_aspect_ratio {
+            let ratio = self.width as f32 / self.height as f32;
+
+            // Determine dimensions that fit within target while maintaining aspect ratio
+            if target_width as f32 / target_height as f32 > ratio {
+                // Height is the limiting factor
+                let new_height = target_height;
+                let new_width = (new_height as f32 * ratio) as u32;
+                (new_width, new_height)
+            } else {
+                // Width is the limiting factor
+                let new_width = target_width;
+                let new_height = (new_width as f32 / ratio) as u32;
+                (new_width, new_height)
+            }
+        } else {
+            (target_width, target_height)
+        };
+        (new_width.max(1), new_height.max(1))
+    }
 
     /// Create a new resized VideoFrame from pre-processed RGBA data
     #[inline]
     pub fn resize_from_data(&self, width: u32, height: u32, data: Vec<u8>) -> Self {
-        // Create the image directly from the raw data without additional allocations
-        let rgba_image = image::RgbaImage::from_raw(width, height, data)
-            .expect("Invalid image data dimensions");
-        let dynamic_image = image::DynamicImage::ImageRgba8(rgba_image);
-        
-        VideoFrame::new(dynamic_image, self.timestamp, self.duration)
+        // Wrap the data directly without additional allocations
+        assert!(data.len() >= width as usize * height as usize * 4, "Invalid image data dimensions");
+        VideoFrame::from_data(
+            FrameData::from_vec(data),
+            width,
+            height,
+            width as usize * 4,
+            PixelFormat::Rgba,
+            self.timestamp,
+            self.duration,
+        )
     }
     
     /// Check if the frame needs to be resized
@@ -143,8 +331,11 @@ impl VideoFrame {
         };
         
         // Use nearest-neighbor for maximum speed
-        let thumbnail = self.image.thumbnail(width, height);
-        VideoFrame::new(thumbnail, self.timestamp, self.duration)
+        let thumbnail = match self.image_view() {
+            Some(view) => image::imageops::thumbnail(&view, width, height),
+            None => image::imageops::thumbnail(&self.to_rgba_image(), width, height),
+        };
+        VideoFrame::from_rgba_image(thumbnail, self.timestamp, self.duration)
     }
 }
 
@@ -194,4 +385,41 @@ mod tests {
         assert_eq!(resized.width, 50);
         assert_eq!(resized.height, 50);
     }
+
+    #[test]
+    fn test_video_frame_stride() {
+        // 2x2 RGB frame with two bytes of padding per row
+        let data = vec![
+            1, 2, 3, 4, 5, 6, 0, 0, //
+            7, 8, 9, 10, 11, 12, 0, 0,
+        ];
+        let frame = VideoFrame::from_data(
+            FrameData::from_vec(data),
+            2,
+            2,
+            8,
+            PixelFormat::Rgb,
+            0.,
+            0.04,
+        );
+
+        assert!(!frame.is_packed());
+        assert_eq!(frame.row(1), &[7, 8, 9, 10, 11, 12]);
+        assert_eq!(frame.view().get_pixel(1, 0), Rgba([4, 5, 6, 255]));
+
+        let image = frame.to_rgba_image();
+        assert_eq!(image.get_pixel(0, 1), &Rgba([7, 8, 9, 255]));
+
+        // Resizing goes through the stride-aware path too
+        let resized = frame.resize(1, 1, false);
+        assert!(resized.is_packed());
+        assert_eq!((resized.width, resized.height), (1, 1));
+    }
+
+    #[test]
+    fn test_video_frame_clone_shares_data() {
+        let frame = VideoFrame::new(DynamicImage::ImageRgba8(RgbaImage::new(4, 4)), 0., 0.04);
+        let clone = frame.clone();
+        assert_eq!(frame.as_rgba_bytes().as_ptr(), clone.as_rgba_bytes().as_ptr());
+    }
 }
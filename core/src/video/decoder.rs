@@ -1,25 +1,97 @@
-use super::{MediaInfo, VideoFrame as Frame, init};
+use super::pool::DEFAULT_POOL_SLOTS;
+use super::{
+    ColorMatrix, FramePool, MediaInfo, PixelFormat, ScaleFilter, VideoFrame as Frame, YuvFrame,
+    init,
+};
 use anyhow::{Context, Result, anyhow};
 use ffmpeg_next as ffmpeg;
-use image::{DynamicImage, RgbaImage};
+use std::ffi::c_int;
+use std::ops::{Deref, DerefMut};
 use std::path::Path;
+use std::ptr;
 use std::process::Command;
 use tempfile::NamedTempFile;
 
+/// swscale pixel format producing the given frame layout
+fn sws_format(format: PixelFormat) -> ffmpeg::format::Pixel {
+    match format {
+        PixelFormat::Rgba => ffmpeg::format::Pixel::RGBA,
+        PixelFormat::Rgb => ffmpeg::format::Pixel::RGB24,
+    }
+}
+
+/// Row alignment of decoded frames, lets swscale use its SIMD paths
+const ROW_ALIGN: usize = 32;
+
+/// swscale context owned by a decoder
+///
+/// ffmpeg-next doesn't mark the scaling context `Send`. An SwsContext has no
+/// thread affinity, it just can't be used from two threads at once, which the
+/// `&mut` access through `VideoDecoder` already rules out. This lets the
+/// decoder move to a decode thread.
+struct Scaler(ffmpeg::software::scaling::context::Context);
+
+// SAFETY: see above, the context is only ever used through `&mut Scaler`
+unsafe impl Send for Scaler {}
+
+impl Deref for Scaler {
+    type Target = ffmpeg::software::scaling::context::Context;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Scaler {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 pub struct VideoDecoder {
     format_context: ffmpeg::format::context::Input,
     video_stream_index: usize,
     codec_context: ffmpeg::codec::decoder::Video,
-    scaler: ffmpeg::software::scaling::context::Context,
+    // Scales and converts decoded frames in one pass, rebuilt when the output changes
+    scaler: Scaler,
+    // Scales decoded frames to YUV 4:2:0 for decode_next_planes, built on first use
+    plane_scaler: Option<Scaler>,
+    output_width: u32,
+    output_height: u32,
+    output_format: PixelFormat,
+    scale_filter: ScaleFilter,
+    // Recycled output buffers, shared with the frames handed out
+    pool: FramePool,
+    // Reused for every decoded frame
+    decoded: ffmpeg::util::frame::video::Video,
     frame_rate: f64,
     time_base: f64,
     next_pts: i64,
+    // A packet the decoder refused while its output was full, sent again
+    // once frames have been received
+    pending_packet: Option<ffmpeg::Packet>,
+    // The demuxer ran out and the decoder was told, it still returns the
+    // frames its threads hold
+    draining: bool,
+    // Every frame has been returned
     eof: bool,
+    // Non-reference frames are discarded while playback is far behind
+    skip_non_reference: bool,
 }
 
 impl VideoDecoder {
     /// Create a new video decoder for the specified file path
+    ///
+    /// Decodes with one thread per CPU core, see `with_threads`.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_threads(path, 0)
+    }
+
+    /// Create a video decoder using `threads` decode threads (0 = one per CPU core)
+    ///
+    /// Frame threading decodes several frames in parallel and slice threading
+    /// splits single frames; ffmpeg uses whichever the codec supports.
+    pub fn with_threads<P: AsRef<Path>>(path: P, threads: usize) -> Result<Self> {
         // Init ffmpeg
         init().context("Failed to initialize FFmpeg - check FFmpeg library installation")?;
 
@@ -72,13 +144,16 @@ impl VideoDecoder {
                   stream.parameters().id().name());
 
         // Get codec parameters and stream information with better error handling
-        let context_decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+        let mut context_decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
             .with_context(|| {
                 log::error!("Failed to create decoder context - codec may be unsupported");
                 format!("Failed to create decoder context for codec: {}. This codec may not be supported by your FFmpeg build.", 
                        stream.parameters().id().name())
             })?;
 
+        // Threading has to be set up before the codec is opened
+        Self::configure_threading(&mut context_decoder, threads);
+
         let decoder = context_decoder
             .decoder()
             .video()
@@ -88,6 +163,13 @@ impl VideoDecoder {
                        stream.parameters().id().name())
             })?;
 
+        let threading = decoder.threading();
+        log::debug!(
+            "Decoding with {:?} threading on {} threads",
+            threading.kind,
+            threading.count
+        );
+
         // Validate video dimensions
         if decoder.width() == 0 || decoder.height() == 0 {
             log::error!("Invalid video dimensions: {}x{}", decoder.width(), decoder.height());
@@ -116,23 +198,16 @@ impl VideoDecoder {
         decoder.format()
     );
 
-        // Create a scaler to convert to RGB
-        let scaler = match ffmpeg::software::scaling::context::Context::get(
+        // Convert at native resolution until a consumer asks for a different size
+        let scaler = Scaler(Self::build_scaler(
             decoder.format(),
             decoder.width(),
             decoder.height(),
-            ffmpeg::format::Pixel::RGBA,
             decoder.width(),
             decoder.height(),
-            ffmpeg::software::scaling::flag::Flags::BILINEAR,
-        ) {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("Failed to create video scaler: {}", e);
-                return Err(anyhow!("Failed to create video scaler for pixel format '{:?}': {}",
-                                  decoder.format(), e));
-            }
-        };
+            sws_format(PixelFormat::Rgba),
+            ScaleFilter::default(),
+        )?);
 
         log::info!(
             "Successfully initialized video decoder for {}: {}x{} @ {:.2}fps",
@@ -145,15 +220,382 @@ impl VideoDecoder {
         Ok(Self {
             format_context,
             video_stream_index,
+            output_width: decoder.width(),
+            output_height: decoder.height(),
+            output_format: PixelFormat::Rgba,
+            scale_filter: ScaleFilter::default(),
+            pool: FramePool::new(DEFAULT_POOL_SLOTS),
+            decoded: ffmpeg::util::frame::video::Video::empty(),
             codec_context: decoder,
             scaler,
+            plane_scaler: None,
             frame_rate,
             time_base,
             next_pts: 0,
+            pending_packet: None,
+            draining: false,
             eof: false,
+            skip_non_reference: false,
+        })
+    }
+
+    /// Enable frame and slice threading on a codec context that isn't open yet
+    ///
+    /// ffmpeg-next's `set_threading` only takes a single threading type, so the
+    /// fields are set directly.
+    fn configure_threading(context: &mut ffmpeg::codec::context::Context, threads: usize) {
+        // SAFETY: plain fields of a valid context, read by avcodec_open2
+        unsafe {
+            let context = context.as_mut_ptr();
+            (*context).thread_count = threads as c_int;
+            (*context).thread_type = ffmpeg::ffi::FF_THREAD_FRAME | ffmpeg::ffi::FF_THREAD_SLICE;
+        }
+    }
+
+    /// Create a scaler from the decoder's pixel format to the requested output
+    fn build_scaler(
+        input_format: ffmpeg::format::Pixel,
+        input_width: u32,
+        input_height: u32,
+        output_width: u32,
+        output_height: u32,
+        output_format: ffmpeg::format::Pixel,
+        filter: ScaleFilter,
+    ) -> Result<ffmpeg::software::scaling::context::Context> {
+        use ffmpeg::software::scaling::flag::Flags;
+        let flags = match filter {
+            ScaleFilter::Nearest => Flags::POINT,
+            ScaleFilter::Bilinear => Flags::BILINEAR,
+            ScaleFilter::Bicubic => Flags::BICUBIC,
+            ScaleFilter::Lanczos3 => Flags::LANCZOS,
+            ScaleFilter::Area => Flags::AREA,
+        };
+
+        ffmpeg::software::scaling::context::Context::get(
+            input_format,
+            input_width,
+            input_height,
+            output_format,
+            output_width,
+            output_height,
+            flags,
+        )
+        .map_err(|e| {
+            log::error!("Failed to create video scaler: {}", e);
+            anyhow!(
+                "Failed to create video scaler from '{:?}' {}x{} to {:?} {}x{}: {}",
+                input_format,
+                input_width,
+                input_height,
+                output_format,
+                output_width,
+                output_height,
+                e
+            )
         })
     }
 
+    /// Set the size and pixel format of decoded frames
+    ///
+    /// Scaling and conversion happen in a single swscale pass, so a renderer
+    /// that only needs a small image never pays for full-resolution output.
+    /// The scaler is only rebuilt when the requested output actually changes.
+    pub fn set_output(&mut self, width: u32, height: u32, format: PixelFormat) -> Result<()> {
+        let width = width.max(1);
+        let height = height.max(1);
+        if width == self.output_width
+            && height == self.output_height
+            && format == self.output_format
+        {
+            return Ok(());
+        }
+
+        let input = self.scaler.input();
+        self.scaler = Scaler(Self::build_scaler(
+            input.format,
+            input.width,
+            input.height,
+            width,
+            height,
+            sws_format(format),
+            self.scale_filter,
+        )?);
+        log::debug!(
+            "Decoder output changed from {}x{} {:?} to {}x{} {:?}",
+            self.output_width,
+            self.output_height,
+            self.output_format,
+            width,
+            height,
+            format
+        );
+        self.output_width = width;
+        self.output_height = height;
+        self.output_format = format;
+        Ok(())
+    }
+
+    /// Set the filter swscale uses when scaling decoded frames
+    pub fn set_scale_filter(&mut self, filter: ScaleFilter) -> Result<()> {
+        if filter == self.scale_filter {
+            return Ok(());
+        }
+
+        let input = self.scaler.input();
+        self.scaler = Scaler(Self::build_scaler(
+            input.format,
+            input.width,
+            input.height,
+            self.output_width,
+            self.output_height,
+            sws_format(self.output_format),
+            filter,
+        )?);
+        // Rebuilt with the new filter on next use
+        self.plane_scaler = None;
+        self.scale_filter = filter;
+        log::debug!("Scaling filter set to {}", filter.name());
+        Ok(())
+    }
+
+    /// Size of decoded frames
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.output_width, self.output_height)
+    }
+
+    /// Skip decoding frames no other frame depends on
+    ///
+    /// Used to catch up when playback falls far behind, those frames would be
+    /// dropped anyway.
+    pub fn set_skip_non_reference(&mut self, skip: bool) {
+        if skip == self.skip_non_reference {
+            return;
+        }
+
+        let discard = if skip {
+            ffmpeg::codec::discard::Discard::NonReference
+        } else {
+            ffmpeg::codec::discard::Discard::Default
+        };
+        self.codec_context.skip_frame(discard);
+        self.skip_non_reference = skip;
+        log::debug!("Skipping non-reference frames: {}", skip);
+    }
+
+    /// Rebuild the scaler if the stream changed resolution or pixel format mid-way
+    fn refresh_scaler_input(
+        &mut self,
+        format: ffmpeg::format::Pixel,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        let input = self.scaler.input();
+        if input.format == format && input.width == width && input.height == height {
+            return Ok(());
+        }
+
+        log::debug!(
+            "Stream changed to {:?} {}x{}, rebuilding scaler",
+            format,
+            width,
+            height
+        );
+        self.scaler = Scaler(Self::build_scaler(
+            format,
+            width,
+            height,
+            self.output_width,
+            self.output_height,
+            sws_format(self.output_format),
+            self.scale_filter,
+        )?);
+        Ok(())
+    }
+
+    /// Format and size of the last decoded frame
+    fn decoded_definition(&self) -> Result<(ffmpeg::format::Pixel, u32, u32)> {
+        let (format, width, height) = (
+            self.decoded.format(),
+            self.decoded.width(),
+            self.decoded.height(),
+        );
+        if width == 0 || height == 0 {
+            let error_msg = format!("Invalid frame dimensions: {}x{}", width, height);
+            log::error!("{}", error_msg);
+            return Err(anyhow!(error_msg));
+        }
+        Ok((format, width, height))
+    }
+
+    /// Color matrix and range of the YUV planes swscale produces from the last
+    /// decoded frame
+    ///
+    /// swscale passes YUV values through unless it has to convert, so the
+    /// stream's own tags apply. RGB and JPEG-range (YUVJ) input is converted to
+    /// limited range BT.601.
+    fn plane_color(&self) -> (ColorMatrix, bool) {
+        use ffmpeg::format::Pixel;
+
+        let format = self.decoded.format();
+        // SAFETY: av_pix_fmt_desc_get returns a static descriptor or null
+        let rgb = unsafe {
+            let descriptor = ffmpeg::ffi::av_pix_fmt_desc_get(format.into());
+            !descriptor.is_null()
+                && (*descriptor).flags & ffmpeg::ffi::AV_PIX_FMT_FLAG_RGB as u64 != 0
+        };
+        let yuvj = matches!(
+            format,
+            Pixel::YUVJ411P | Pixel::YUVJ420P | Pixel::YUVJ422P | Pixel::YUVJ440P | Pixel::YUVJ444P
+        );
+        if rgb || yuvj {
+            return (ColorMatrix::Bt601, false);
+        }
+
+        let matrix = match self.decoded.color_space() {
+            ffmpeg::color::Space::BT709 => ColorMatrix::Bt709,
+            _ => ColorMatrix::Bt601,
+        };
+        let full_range = self.decoded.color_range() == ffmpeg::color::Range::JPEG;
+        (matrix, full_range)
+    }
+
+    /// Scale the last decoded frame to YUV 4:2:0 planes at the output size
+    fn scale_planes(&mut self, timestamp: f64, duration: f64) -> Result<YuvFrame> {
+        let (format, width, height) = self.decoded_definition()?;
+        let (out_width, out_height) = (self.output_width, self.output_height);
+        let stale = self.plane_scaler.as_ref().is_none_or(|scaler| {
+            let (input, output) = (scaler.input(), scaler.output());
+            input.format != format
+                || input.width != width
+                || input.height != height
+                || output.width != out_width
+                || output.height != out_height
+        });
+        if stale {
+            self.plane_scaler = Some(Scaler(Self::build_scaler(
+                format,
+                width,
+                height,
+                out_width,
+                out_height,
+                ffmpeg::format::Pixel::YUV420P,
+                self.scale_filter,
+            )?));
+        }
+
+        let (chroma_width, chroma_height) = YuvFrame::chroma_size(out_width, out_height);
+        let luma_stride = (out_width as usize).next_multiple_of(ROW_ALIGN);
+        let chroma_stride = (chroma_width as usize).next_multiple_of(ROW_ALIGN);
+        let u_offset = luma_stride * out_height as usize;
+        let v_offset = u_offset + chroma_stride * chroma_height as usize;
+        let len = YuvFrame::data_len(out_height, luma_stride, chroma_stride);
+        let Some(scaler) = self.plane_scaler.as_mut() else {
+            unreachable!("plane scaler was just built");
+        };
+        let decoded = &self.decoded;
+        let (data, scaled_rows) = self.pool.fill(len, |buffer| {
+            let (luma, chroma) = buffer.split_at_mut(u_offset);
+            let (u, v) = chroma.split_at_mut(v_offset - u_offset);
+            let dst = [
+                luma.as_mut_ptr(),
+                u.as_mut_ptr(),
+                v.as_mut_ptr(),
+                ptr::null_mut(),
+            ];
+            let dst_stride = [
+                luma_stride as c_int,
+                chroma_stride as c_int,
+                chroma_stride as c_int,
+                0,
+            ];
+            // SAFETY: the plane scaler matches the decoded frame's format and
+            // size and outputs out_width x out_height YUV420P, and each plane
+            // in `buffer` holds its rows at the given strides
+            unsafe {
+                ffmpeg::ffi::sws_scale(
+                    scaler.as_mut_ptr(),
+                    (*decoded.as_ptr()).data.as_ptr() as *const *const _,
+                    (*decoded.as_ptr()).linesize.as_ptr() as *const _,
+                    0,
+                    height as c_int,
+                    dst.as_ptr(),
+                    dst_stride.as_ptr(),
+                )
+            }
+        });
+        if scaled_rows < 0 {
+            let e = ffmpeg::Error::from(scaled_rows);
+            log::warn!("Error scaling frame to planes: {}", e);
+            return Err(anyhow!("Failed to scale video frame to planes: {}", e));
+        }
+
+        let (matrix, full_range) = self.plane_color();
+        Ok(YuvFrame::from_data(
+            data,
+            out_width,
+            out_height,
+            luma_stride,
+            chroma_stride,
+            matrix,
+            full_range,
+            timestamp,
+            duration,
+        ))
+    }
+
+    /// Scale and convert the last decoded frame in one pass
+    ///
+    /// swscale writes straight into a pooled buffer that the returned frame
+    /// shares, so there are no further copies on the way to the renderer.
+    fn scale_decoded(&mut self, timestamp: f64, duration: f64) -> Result<Frame> {
+        let (format, width, height) = self.decoded_definition()?;
+        self.refresh_scaler_input(format, width, height)?;
+
+        let (out_width, out_height) = (self.output_width, self.output_height);
+        let stride =
+            (out_width as usize * self.output_format.bytes_per_pixel()).next_multiple_of(ROW_ALIGN);
+        let scaler = &mut self.scaler;
+        let decoded = &self.decoded;
+        let (data, scaled_rows) = self.pool.fill(stride * out_height as usize, |buffer| {
+            let dst = [
+                buffer.as_mut_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            ];
+            let dst_stride = [stride as c_int, 0, 0, 0];
+            // SAFETY: the scaler matches the decoded frame's format and size
+            // (refresh_scaler_input) and outputs out_width x out_height, and
+            // `buffer` holds out_height rows of `stride` bytes
+            unsafe {
+                ffmpeg::ffi::sws_scale(
+                    scaler.as_mut_ptr(),
+                    (*decoded.as_ptr()).data.as_ptr() as *const *const _,
+                    (*decoded.as_ptr()).linesize.as_ptr() as *const _,
+                    0,
+                    height as c_int,
+                    dst.as_ptr(),
+                    dst_stride.as_ptr(),
+                )
+            }
+        });
+        if scaled_rows < 0 {
+            let e = ffmpeg::Error::from(scaled_rows);
+            log::warn!("Error scaling frame: {}", e);
+            return Err(anyhow!("Failed to scale video frame: {}", e));
+        }
+
+        Ok(Frame::from_data(
+            data,
+            out_width,
+            out_height,
+            stride,
+            self.output_format,
+            timestamp,
+            duration,
+        ))
+    }
+
     /// Get information about the media file
     pub fn get_media_info(&self) -> MediaInfo {
         // Safely get stream info, falling back to sensible defaults if needed
@@ -208,15 +650,37 @@ impl VideoDecoder {
 
     /// Decode the next frame from the video
     pub fn decode_next_frame(&mut self) -> Result<Option<Frame>> {
+        match self.receive_next()? {
+            Some((timestamp, duration)) => Ok(Some(self.scale_decoded(timestamp, duration)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Decode the next frame as YUV 4:2:0 planes at the output size
+    ///
+    /// Leaves color conversion to the caller, e.g. a GPU compute pass. The
+    /// output pixel format set with `set_output` is ignored.
+    pub fn decode_next_planes(&mut self) -> Result<Option<YuvFrame>> {
+        match self.receive_next()? {
+            Some((timestamp, duration)) => Ok(Some(self.scale_planes(timestamp, duration)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read packets until the decoder produces a frame in `self.decoded`
+    ///
+    /// Returns the frame's timestamp and duration, or `None` at the end of the
+    /// stream. With frame threading the decoder holds several frames at once,
+    /// so frames are always received before more packets are sent and the
+    /// decoder is drained when the packets run out.
+    fn receive_next(&mut self) -> Result<Option<(f64, f64)>> {
         if self.eof {
             return Ok(None);
         }
 
-        let mut decoded_frame = None;
         let start_time = std::time::Instant::now();
 
-        // Read packets until a video frame is gotten or EOF is reached
-        while decoded_frame.is_none() {
+        loop {
             // Check for timeout to avoid hangs with detailed diagnostics
             if start_time.elapsed() > std::time::Duration::from_secs(5) {
                 log::error!("Decoder timeout detected at position {}s - possible corruption", 
@@ -227,123 +691,96 @@ impl VideoDecoder {
                 ));
             }
 
-            match self.format_context.packets().next() {
-                Some((stream, packet)) if stream.index() == self.video_stream_index => {
-                    // Send packet with improved error context
-                    if let Err(e) = self.codec_context.send_packet(&packet) {
-                        let position = packet.pts().map_or_else(
-                            || self.next_pts as f64 * self.time_base,
-                            |pts| pts as f64 * self.time_base
-                        );
-                        log::warn!("Error sending packet to decoder at position {:.2}s: {}", position, e);
-                        // Try to continue by flushing and skipping this packet
-                        self.codec_context.flush();
-                        continue;
-                    }
+            match self.codec_context.receive_frame(&mut self.decoded) {
+                Ok(_) => {
+                    let pts = self.decoded.pts().unwrap_or(self.next_pts);
+                    self.next_pts = pts + 1;
 
-                    let mut frame = ffmpeg::util::frame::video::Video::empty();
-                    match self.codec_context.receive_frame(&mut frame) {
-                        Ok(_) => {
-                            let pts = frame.pts().unwrap_or(self.next_pts);
-                            self.next_pts = pts + 1;
-
-                            // Calculate timestamp and duration
-                            let timestamp = pts as f64 * self.time_base;
-                            let duration = 1.0 / self.frame_rate;
-
-                            // Convert to RGB using the scaler with error context
-                            let mut rgb_frame = ffmpeg::util::frame::video::Video::empty();
-                            if let Err(e) = self.scaler.run(&frame, &mut rgb_frame) {
-                                log::warn!("Error scaling frame: {}", e);
-                                return Err(anyhow!("Failed to scale video frame: {}", e));
-                            }
-
-                            // Convert to image::DynamicImage
-                            let width = rgb_frame.width();
-                            let height = rgb_frame.height();
-                            let data = rgb_frame.data(0).to_vec();
-
-                            // Validate frame data and dimensions before creating image
-                            if width == 0 || height == 0 {
-                                let error_msg = format!("Invalid frame dimensions: {}x{}", width, height);
-                                log::error!("{}", error_msg);
-                                return Err(anyhow!(error_msg));
-                            }
-                            
-                            let expected_size = width as usize * height as usize * 4;
-                            if data.len() < expected_size {
-                                let error_msg = format!(
-                                    "Corrupted frame data: size {} bytes (expected at least {} bytes for {}x{})",
-                                    data.len(),
-                                    expected_size,
-                                    width,
-                                    height
-                                );
-                                log::error!("{}", error_msg);
-                                // Skip this frame and try the next one
-                                self.next_pts += 1;
-                                continue;
-                            }
-                            
-                            // Create image with better error handling
-                            let image = match RgbaImage::from_raw(width, height, data.clone()) {
-                                Some(img) => img,
-                                None => {
-                                    let error_msg = format!(
-                                        "Failed to create image: {}x{} with {} bytes",
-                                        width,
-                                        height,
-                                        data.len()
-                                    );
-                                    log::error!("{}", error_msg);
-                                    return Err(anyhow!(error_msg));
-                                }
-                            };
-
-                            log::trace!("Decoded frame at timestamp {:.2}s", timestamp);
-                            decoded_frame = Some(Frame::new(
-                                DynamicImage::ImageRgba8(image),
-                                timestamp,
-                                duration,
-                            ));
-                        }
-                        Err(ffmpeg::Error::Other {
-                            errno: ffmpeg::error::EAGAIN,
-                        }) => continue,
-                        Err(e) => {
-                            // For corrupted frame errors, try to recover rather than fail completely
-                            let position = self.next_pts as f64 * self.time_base;
-                            log::warn!("Error receiving frame at position {:.2}s: {}", position, e);
-                            
-                            if e.to_string().contains("corrupted") {
-                                log::warn!("Detected corrupted frame, attempting to skip and continue");
-                                // Skip this frame and try the next one
-                                self.next_pts += 1;
-                                continue;
-                            }
-                            
-                            return Err(anyhow!("Failed to receive frame from decoder at position {:.2}s: {}", 
-                                position, e));
-                        }
+                    // Calculate timestamp and duration
+                    let timestamp = pts as f64 * self.time_base;
+                    let duration = 1.0 / self.frame_rate;
+
+                    log::trace!("Decoded frame at timestamp {:.2}s", timestamp);
+
+                    // Log frame decode time if it's unusually slow
+                    let decode_time = start_time.elapsed();
+                    if decode_time > std::time::Duration::from_millis(50) {
+                        log::warn!("Slow frame decode: {}ms", decode_time.as_millis());
                     }
+                    return Ok(Some((timestamp, duration)));
                 }
-                Some(_) => continue,
-                None => {
-                    // EOF reached
+                Err(ffmpeg::Error::Eof) => {
+                    // Every frame held by the decoder has been returned
                     self.eof = true;
                     log::debug!("End of video file reached");
-                    break;
+                    return Ok(None);
+                }
+                Err(ffmpeg::Error::Other {
+                    errno: ffmpeg::error::EAGAIN,
+                }) => {
+                    // The decoder needs more input
+                    if self.draining {
+                        // Nothing more will come out after EOF was sent
+                        self.eof = true;
+                        return Ok(None);
+                    }
+                }
+                Err(e) => {
+                    // For corrupted frame errors, try to recover rather than fail completely
+                    let position = self.next_pts as f64 * self.time_base;
+                    log::warn!("Error receiving frame at position {:.2}s: {}", position, e);
+                    
+                    if e.to_string().contains("corrupted") {
+                        log::warn!("Detected corrupted frame, attempting to skip and continue");
+                        // Skip this frame and try the next one
+                        self.next_pts += 1;
+                        continue;
+                    }
+                    
+                    return Err(anyhow!("Failed to receive frame from decoder at position {:.2}s: {}", 
+                        position, e));
                 }
             }
-        }
 
-        // Log frame decode time if it's unusually slow
-        let decode_time = start_time.elapsed();
-        if decode_time > std::time::Duration::from_millis(50) {
-            log::warn!("Slow frame decode: {}ms", decode_time.as_millis());
-        }
+            // The decoder's output is empty, feed it the next packet
+            let packet = match self.pending_packet.take() {
+                Some(packet) => packet,
+                None => match self.format_context.packets().next() {
+                    Some((stream, packet)) if stream.index() == self.video_stream_index => packet,
+                    Some(_) => continue,
+                    None => {
+                        // Out of packets, have the decoder return what it still holds
+                        log::debug!("Last packet read, draining the decoder");
+                        self.draining = true;
+                        if let Err(e) = self.codec_context.send_eof() {
+                            log::warn!("Error draining the decoder: {}", e);
+                            self.eof = true;
+                            return Ok(None);
+                        }
+                        continue;
+                    }
+                },
+            };
 
-        Ok(decoded_frame)
+            match self.codec_context.send_packet(&packet) {
+                Ok(_) => {}
+                Err(ffmpeg::Error::Other {
+                    errno: ffmpeg::error::EAGAIN,
+                }) => {
+                    // Output is full: receive frames first, then send it again
+                    self.pending_packet = Some(packet);
+                }
+                Err(e) => {
+                    // Skip the packet. Flushing would drop the reference frames
+                    // the following packets need too
+                    let position = packet.pts().map_or_else(
+                        || self.next_pts as f64 * self.time_base,
+                        |pts| pts as f64 * self.time_base
+                    );
+                    log::warn!("Error sending packet to decoder at position {:.2}s: {}", position, e);
+                }
+            }
+        }
     }
 
     /// Seek to a specific timestamp in seconds
@@ -374,8 +811,10 @@ impl VideoDecoder {
                 Ok(_) => {
                     log::debug!("Seek successful to {:.2}s on attempt {}", timestamp_secs, attempt + 1);
                     
-                    // Flush decoder buffers
+                    // Flush decoder buffers, this also ends draining
                     self.codec_context.flush();
+                    self.pending_packet = None;
+                    self.draining = false;
                     self.eof = false;
                     
                     // Calculate new PTS value in stream timebase
@@ -462,4 +901,92 @@ mod tests {
         let decoder = VideoDecoder::new(&video_path);
         assert!(decoder.is_ok());
     }
+
+    #[test]
+    fn test_scaler_matches_reference_images() {
+        use crate::video::scale::reference;
+
+        init().unwrap();
+        let source = reference::source();
+        let (source_width, source_height) = reference::SOURCE_SIZE;
+        let format = sws_format(PixelFormat::Rgba);
+        let mut input = ffmpeg::frame::Video::new(format, source_width, source_height);
+        let stride = input.stride(0);
+        for y in 0..source_height {
+            let row = &mut input.data_mut(0)[y as usize * stride..];
+            row[..source_width as usize * 4].copy_from_slice(source.row(y));
+        }
+
+        // swscale's kernels differ slightly from ours (its bicubic is not
+        // quite Catmull-Rom and it clamps at the edges), a different filter
+        // is off by several times this
+        for (filter, width, height) in reference::CASES {
+            let mut scaler = VideoDecoder::build_scaler(
+                format,
+                source_width,
+                source_height,
+                width,
+                height,
+                format,
+                filter,
+            )
+            .unwrap();
+            let mut output = ffmpeg::frame::Video::empty();
+            scaler.run(&input, &mut output).unwrap();
+
+            let stride = output.stride(0);
+            let data = output.data(0);
+            let gray = (0..height as usize)
+                .flat_map(|y| (0..width as usize).map(move |x| data[y * stride + x * 4]));
+            let (max, mean) = reference::difference(filter, width, height, gray);
+            assert!(
+                max <= 6 && mean <= 2.0,
+                "{:?} to {}x{} off by up to {} (mean {:.2})",
+                filter,
+                width,
+                height,
+                max,
+                mean
+            );
+        }
+    }
+
+    /// Encode a 2 second, 25fps clip with B-frames using the ffmpeg tool,
+    /// `None` if the tool isn't installed
+    fn generate_clip(dir: &Path) -> Option<std::path::PathBuf> {
+        let path = dir.join("clip.mp4");
+        let status = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "lavfi"])
+            .args(["-i", "testsrc=duration=2:size=160x120:rate=25"])
+            .args(["-c:v", "mpeg4", "-bf", "2"])
+            .arg(&path)
+            .status()
+            .ok()?;
+        assert!(status.success(), "ffmpeg failed to encode the test clip");
+        Some(path)
+    }
+
+    fn count_frames(path: &Path, threads: usize) -> usize {
+        let mut decoder = VideoDecoder::with_threads(path, threads).unwrap();
+        let mut frames = 0;
+        while decoder.decode_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        frames
+    }
+
+    #[test]
+    fn test_threaded_decoding_returns_every_frame() {
+        let temp_dir = tempdir().unwrap();
+        // Only the libraries are needed to build, the tool may be missing
+        let Some(clip) = generate_clip(temp_dir.path()) else {
+            println!("Skipping decoding test - ffmpeg tool not available");
+            return;
+        };
+
+        // Frames still held by the decode threads at the end must come out too
+        let single = count_frames(&clip, 1);
+        assert_eq!(single, 50);
+        assert_eq!(count_frames(&clip, 4), single);
+    }
 }
@@ -1,268 +1,297 @@
-use std::collections::{VecDeque, HashMap};
-use std::sync::{Arc, Mutex};
-use std::thread::{self, JoinHandle};
-use std::time::Duration;
-use log::{debug, trace, warn};
 use super::VideoFrame;
 use anyhow::Result;
+use log::{debug, trace, warn};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Key frames by PTS in milliseconds
+fn pts_key(timestamp: f64) -> i64 {
+    (timestamp * 1000.0).round() as i64
+}
+
+/// Bounded ring of decoded frames, shared with the decode thread
+struct Ring {
+    // Buffered frames ordered by PTS, allocated once at `capacity` so
+    // steady-state playback doesn't allocate
+    frames: VecDeque<(i64, VideoFrame)>,
+    // Maximum number of frames to buffer
+    capacity: usize,
+    // Bumped on every flush, frames decoded for an older generation are dropped
+    generation: u64,
+    // The decode thread reached the end of the stream in this generation
+    eof: bool,
+    // Tells the decode thread to exit
+    stop: bool,
+    // Frames accepted from the decoder, for decode rate stats
+    decoded: u64,
+}
+
+impl Ring {
+    /// Insert a frame decoded in `generation`, returns false if it was dropped
+    fn push(&mut self, frame: VideoFrame, generation: u64) -> bool {
+        if generation != self.generation || self.frames.len() >= self.capacity {
+            return false;
+        }
+        let key = pts_key(frame.timestamp);
+        // Decoders emit in PTS order, so this is almost always the back
+        match self.frames.binary_search_by_key(&key, |(key, _)| *key) {
+            Ok(index) => self.frames[index].1 = frame,
+            Err(index) => self.frames.insert(index, (key, frame)),
+        }
+        self.decoded += 1;
+        true
+    }
+
+    /// The frame with exactly this PTS key
+    fn get(&self, key: i64) -> Option<&VideoFrame> {
+        let index = self.frames.binary_search_by_key(&key, |(key, _)| *key).ok()?;
+        Some(&self.frames[index].1)
+    }
+
+    /// Drop frames before the PTS key
+    fn drop_before(&mut self, key: i64) {
+        let stale = self.frames.partition_point(|(first, _)| *first < key);
+        self.frames.drain(..stale);
+    }
+
+    /// Whether the decode thread should wait before decoding another frame
+    fn is_saturated(&self) -> bool {
+        self.eof || self.frames.len() >= self.capacity
+    }
+}
+
+struct Shared {
+    ring: Mutex<Ring>,
+    // Signalled when space frees up, on flush and on stop
+    changed: Condvar,
+}
 
 /// FrameBuffer for smoother video playback
-/// 
-/// This buffer manages a collection of decoded video frames,
-/// supporting prefetching and smooth frame delivery for better playback
+///
+/// A decode thread started with `start_prefetching` keeps a bounded ring of
+/// frames ordered by PTS topped up, blocking while the ring is full. The
+/// playback side takes frames with `next_frame`, which never waits for the
+/// decoder. Seeking flushes the ring and bumps a generation counter so frames
+/// decoded before the seek are discarded instead of being shown.
 pub struct FrameBuffer {
-    // Buffered frames in playback order
-    frames: VecDeque<VideoFrame>,
-    // Fast lookup by approximate timestamp
-    timestamp_map: HashMap<i64, usize>,
+    shared: Arc<Shared>,
     // Maximum number of frames to buffer
     capacity: usize,
-    // Prefetch flag - if true, background thread is active
-    prefetching: bool,
-    // Position where we're currently playing
+    // Timestamp of the frame currently shown (or the seek target)
     current_position: f64,
+    // Last frame handed out by next_frame, shown while paused
+    current: Option<VideoFrame>,
     // Thread handle for prefetching
     prefetch_thread: Option<JoinHandle<()>>,
-    // Flag to signal prefetch thread to stop
-    stop_prefetch: Arc<Mutex<bool>>,
 }
 
 impl FrameBuffer {
     /// Create a new frame buffer with the specified capacity
     pub fn new(capacity: usize) -> Self {
-        FrameBuffer {
+        let ring = Ring {
             frames: VecDeque::with_capacity(capacity),
-            timestamp_map: HashMap::new(),
             capacity,
-            prefetching: false,
+            generation: 0,
+            eof: false,
+            stop: false,
+            decoded: 0,
+        };
+        FrameBuffer {
+            shared: Arc::new(Shared {
+                ring: Mutex::new(ring),
+                changed: Condvar::new(),
+            }),
+            capacity,
             current_position: 0.0,
+            current: None,
             prefetch_thread: None,
-            stop_prefetch: Arc::new(Mutex::new(false)),
         }
     }
-    
+
     /// Add a frame to the buffer, returns true if added, false if buffer is full
     pub fn add_frame(&mut self, frame: VideoFrame) -> bool {
-        if self.frames.len() >= self.capacity {
-            // Buffer is full, try to remove old frames first
-            self.cleanup_old_frames();
-            
-            // If still full after cleanup, reject the frame
-            if self.frames.len() >= self.capacity {
-                return false;
-            }
-        }
-        
-        // Store timestamp for quick lookup (convert to milliseconds for integer key)
-        let timestamp_key = (frame.timestamp * 1000.0) as i64;
-        let index = self.frames.len();
-        self.timestamp_map.insert(timestamp_key, index);
-        
-        // Add the frame to the buffer
-        self.frames.push_back(frame);
-        true
+        let mut ring = self.shared.ring.lock().unwrap();
+        let generation = ring.generation;
+        ring.push(frame, generation)
     }
-    
+
     /// Get the frame closest to the requested timestamp
     pub fn get_frame_at(&self, timestamp: f64) -> Option<VideoFrame> {
-        if self.frames.is_empty() {
-            return None;
-        }
-        
-        // Convert to milliseconds for lookup
-        let timestamp_key = (timestamp * 1000.0) as i64;
-        
+        let ring = self.shared.ring.lock().unwrap();
+
         // Try exact match first
-        if let Some(&index) = self.timestamp_map.get(&timestamp_key) {
-            if index < self.frames.len() {
-                return Some(self.frames[index].clone());
-            }
+        if let Some(frame) = ring.get(pts_key(timestamp)) {
+            return Some(frame.clone());
         }
-        
+
         // No exact match, find closest frame
-        let mut closest_frame = None;
-        let mut min_diff = f64::MAX;
-        
-        for frame in &self.frames {
-            let diff = (frame.timestamp - timestamp).abs();
-            if diff < min_diff {
-                min_diff = diff;
-                closest_frame = Some(frame.clone());
-            }
+        ring.frames
+            .iter()
+            .map(|(_, frame)| frame)
+            .chain(self.current.as_ref())
+            .min_by(|a, b| {
+                let diff_a = (a.timestamp - timestamp).abs();
+                let diff_b = (b.timestamp - timestamp).abs();
+                diff_a.total_cmp(&diff_b)
+            })
+            .cloned()
+    }
+
+    /// Whether a frame at `timestamp` comes before the current position
+    fn is_stale(&self, timestamp: f64) -> bool {
+        match &self.current {
+            Some(current) => timestamp <= current.timestamp,
+            None => timestamp < self.current_position,
         }
-        
-        closest_frame
     }
-    
-    /// Get the next frame in sequence based on current position
+
+    /// PTS of the frame `next_frame` would return, without taking it
+    pub fn next_timestamp(&self) -> Option<f64> {
+        let ring = self.shared.ring.lock().unwrap();
+        ring.frames
+            .iter()
+            .map(|(_, frame)| frame.timestamp)
+            .find(|&timestamp| !self.is_stale(timestamp))
+    }
+
+    /// Take the next frame after the current position
+    ///
+    /// Never waits for the decoder: returns None if the next frame hasn't been
+    /// decoded yet or the stream has ended (see `is_finished`). Frames from
+    /// before the current position, e.g. the run-up from a keyframe after a
+    /// seek, are skipped.
     pub fn next_frame(&mut self) -> Option<VideoFrame> {
-        if self.frames.is_empty() {
-            return None;
-        }
-        
-        // Find the next frame after current position
-        for (_i, frame) in self.frames.iter().enumerate() {
-            if frame.timestamp > self.current_position {
-                self.current_position = frame.timestamp;
-                return Some(frame.clone());
+        let mut ring = self.shared.ring.lock().unwrap();
+
+        let mut next = None;
+        while let Some((_, frame)) = ring.frames.pop_front() {
+            if self.is_stale(frame.timestamp) {
+                trace!("Skipping frame at {:.3}s before position", frame.timestamp);
+                continue;
             }
+            next = Some(frame);
+            break;
         }
-        
-        // If we're at the end, return the last frame
-        let last_frame = self.frames.back()?;
-        self.current_position = last_frame.timestamp;
-        Some(last_frame.clone())
+        drop(ring);
+        // Popping made room for the decode thread
+        self.shared.changed.notify_all();
+
+        let frame = next?;
+        self.current_position = frame.timestamp;
+        self.current = Some(frame.clone());
+        Some(frame)
     }
-    
+
     /// Get current frame (for paused state)
     pub fn current_frame(&self) -> Option<VideoFrame> {
-        if self.frames.is_empty() {
-            return None;
+        if let Some(current) = &self.current {
+            return Some(current.clone());
         }
-        
+
         self.get_frame_at(self.current_position)
     }
-    
-    /// Seek to a specific timestamp
+
+    /// Move to a timestamp within the buffered frames
+    ///
+    /// Frames before `timestamp` are dropped. Use `flush` when the decoder
+    /// itself was repositioned.
     pub fn seek(&mut self, timestamp: f64) {
         self.current_position = timestamp;
-        
-        // After seeking, clean up frames that are no longer needed
-        self.cleanup_old_frames();
+        self.current = None;
+
+        let mut ring = self.shared.ring.lock().unwrap();
+        let key = pts_key(timestamp);
+        ring.drop_before(key);
+        // A frame exactly at the target becomes the current one
+        if ring.frames.front().is_some_and(|(first, _)| *first == key) {
+            self.current = ring.frames.pop_front().map(|(_, frame)| frame);
+        }
+        drop(ring);
+        self.shared.changed.notify_all();
     }
-    
+
+    /// Discard all buffered frames after the decoder was repositioned
+    ///
+    /// Call this while holding the lock on the prefetch source, right after
+    /// seeking it. The decode thread reads the generation under the same lock,
+    /// so any frame it decoded before the seek is dropped when pushed.
+    pub fn flush(&mut self, position: f64) {
+        let mut ring = self.shared.ring.lock().unwrap();
+        ring.generation += 1;
+        ring.frames.clear();
+        ring.eof = false;
+        drop(ring);
+        self.shared.changed.notify_all();
+
+        self.current_position = position;
+        self.current = None;
+        debug!(
+            "Flushed frame buffer, playback continues at {:.2}s",
+            position
+        );
+    }
+
+    /// Whether the stream has ended and every buffered frame was taken
+    pub fn is_finished(&self) -> bool {
+        let ring = self.shared.ring.lock().unwrap();
+        ring.eof && ring.frames.is_empty()
+    }
+
     /// Get buffer status
     pub fn status(&self) -> (usize, usize, f64) {
-        (self.frames.len(), self.capacity, self.current_position)
+        let frames = self.shared.ring.lock().unwrap().frames.len();
+        (frames, self.capacity, self.current_position)
+    }
+
+    /// Total number of frames added since the buffer was created
+    pub fn frames_decoded(&self) -> u64 {
+        self.shared.ring.lock().unwrap().decoded
     }
-    
+
     /// Clear all frames from the buffer
     pub fn clear(&mut self) {
-        self.frames.clear();
-        self.timestamp_map.clear();
-        self.current_position = 0.0;
-    }
-    
-    /// Clean up frames that are before the current position minus a small buffer
-    fn cleanup_old_frames(&mut self) {
-        // Keep a small buffer of past frames for rewind/seek
-        let buffer_time = 2.0; // seconds
-        let cutoff = self.current_position - buffer_time;
-        
-        // Don't clean up if we're at the beginning
-        if cutoff <= 0.0 {
-            return;
-        }
-        
-        // Remove frames older than the cutoff
-        let mut removed_count = 0;
-        while let Some(frame) = self.frames.front() {
-            if frame.timestamp >= cutoff {
-                break;
-            }
-            
-            self.frames.pop_front();
-            removed_count += 1;
-        }
-        
-        if removed_count > 0 {
-            debug!("Cleaned up {} old frames from buffer", removed_count);
-            
-            // Rebuild timestamp map after removing frames
-            self.rebuild_timestamp_map();
-        }
+        self.flush(0.0);
     }
-    
-    /// Rebuild the timestamp map after changes to the frame collection
-    fn rebuild_timestamp_map(&mut self) {
-        self.timestamp_map.clear();
-        for (i, frame) in self.frames.iter().enumerate() {
-            let timestamp_key = (frame.timestamp * 1000.0) as i64;
-            self.timestamp_map.insert(timestamp_key, i);
-        }
-    }
-    
-    /// Start prefetching frames using a decoder callback
-    pub fn start_prefetching<F>(&mut self, mut decoder_callback: F) 
-    where 
-        F: FnMut() -> Result<Option<VideoFrame>> + Send + 'static 
+
+    /// Start decoding frames into the buffer on a background thread
+    ///
+    /// `decode` is called with `source` locked and should return the next
+    /// frame, or None at the end of the stream. The thread waits while the
+    /// buffer is full and after the end of the stream until the next `flush`.
+    pub fn start_prefetching<D, F>(&mut self, source: Arc<Mutex<D>>, decode: F)
+    where
+        D: Send + 'static,
+        F: FnMut(&mut D) -> Result<Option<VideoFrame>> + Send + 'static,
     {
-        if self.prefetching {
+        if self.prefetch_thread.is_some() {
             debug!("Prefetching already in progress");
             return;
         }
-        
-        // Reset stop flag
-        let stop_flag = self.stop_prefetch.clone();
-        *stop_flag.lock().unwrap() = false;
-        
-        // Create a thread to prefetch frames
-        let buffer_capacity = self.capacity;
-        let thread_handle = thread::spawn(move || {
-            debug!("Frame prefetching thread started");
-            
-            let mut frames_decoded = 0;
-            let max_decode_errors = 5;
-            let mut consecutive_errors = 0;
-            
-            while !*stop_flag.lock().unwrap() {
-                // Check if we should continue prefetching
-                if frames_decoded >= buffer_capacity {
-                    trace!("Prefetch buffer full ({} frames), sleeping", frames_decoded);
-                    thread::sleep(Duration::from_millis(100));
-                    continue;
-                }
-                
-                // Try to decode a frame
-                match decoder_callback() {
-                    Ok(Some(frame)) => {
-                        frames_decoded += 1;
-                        consecutive_errors = 0;
-                        trace!("Prefetched frame at {}s", frame.timestamp);
-                    }
-                    Ok(None) => {
-                        // End of stream reached
-                        debug!("Prefetching complete, reached end of stream");
-                        break;
-                    }
-                    Err(e) => {
-                        consecutive_errors += 1;
-                        warn!("Error during frame prefetching: {}", e);
-                        
-                        if consecutive_errors >= max_decode_errors {
-                            warn!("Too many consecutive decode errors, stopping prefetch");
-                            break;
-                        }
-                        
-                        // Sleep before retrying
-                        thread::sleep(Duration::from_millis(50));
-                    }
-                }
-            }
-            
-            debug!("Frame prefetching thread finished after {} frames", frames_decoded);
-        });
-        
+
+        self.shared.ring.lock().unwrap().stop = false;
+        let shared = self.shared.clone();
+        let thread_handle = thread::Builder::new()
+            .name("frame-prefetch".to_string())
+            .spawn(move || prefetch_loop(&shared, &source, decode))
+            .expect("failed to spawn frame prefetch thread");
+
         self.prefetch_thread = Some(thread_handle);
-        self.prefetching = true;
         debug!("Started frame prefetching");
     }
-    
+
     /// Stop prefetching frames
     pub fn stop_prefetching(&mut self) {
-        if !self.prefetching {
+        let Some(thread) = self.prefetch_thread.take() else {
             return;
-        }
-        
-        // Signal the thread to stop
-        *self.stop_prefetch.lock().unwrap() = true;
-        
-        // Wait for the thread to finish
-        if let Some(thread) = self.prefetch_thread.take() {
-            let _ = thread.join();
-        }
-        
-        self.prefetching = false;
+        };
+
+        // Signal the thread to stop and wait for it to finish
+        self.shared.ring.lock().unwrap().stop = true;
+        self.shared.changed.notify_all();
+        let _ = thread.join();
+
         debug!("Stopped frame prefetching");
     }
 }
@@ -274,77 +303,307 @@ impl Drop for FrameBuffer {
     }
 }
 
+/// Body of the decode thread
+fn prefetch_loop<D, F>(shared: &Shared, source: &Mutex<D>, mut decode: F)
+where
+    F: FnMut(&mut D) -> Result<Option<VideoFrame>>,
+{
+    debug!("Frame prefetching thread started");
+
+    let mut frames_decoded = 0;
+    let max_decode_errors = 5;
+    let mut consecutive_errors = 0;
+
+    loop {
+        // Back-pressure: sleep while the ring is full or the stream has ended
+        {
+            let mut ring = shared.ring.lock().unwrap();
+            while ring.is_saturated() && !ring.stop {
+                ring = shared.changed.wait(ring).unwrap();
+            }
+            if ring.stop {
+                break;
+            }
+        }
+
+        // Seeks flush while holding the source lock, so the generation read
+        // here always matches the position the frame is decoded from
+        let (generation, result) = {
+            let mut source = source.lock().unwrap();
+            let generation = shared.ring.lock().unwrap().generation;
+            (generation, decode(&mut source))
+        };
+
+        let mut ring = shared.ring.lock().unwrap();
+        match result {
+            Ok(Some(frame)) => {
+                consecutive_errors = 0;
+                let timestamp = frame.timestamp;
+                if ring.push(frame, generation) {
+                    frames_decoded += 1;
+                    trace!("Prefetched frame at {}s", timestamp);
+                } else {
+                    trace!("Dropped frame at {}s decoded before a flush", timestamp);
+                }
+            }
+            Ok(None) => {
+                if ring.generation == generation {
+                    debug!("Prefetching reached end of stream");
+                    ring.eof = true;
+                }
+            }
+            Err(e) => {
+                consecutive_errors += 1;
+                warn!("Error during frame prefetching: {}", e);
+
+                if consecutive_errors >= max_decode_errors {
+                    // Treat it as the end of the stream, a seek gets another try
+                    warn!("Too many consecutive decode errors, pausing prefetch");
+                    consecutive_errors = 0;
+                    if ring.generation == generation {
+                        ring.eof = true;
+                    }
+                } else {
+                    // Sleep before retrying
+                    drop(ring);
+                    thread::sleep(Duration::from_millis(50));
+                }
+            }
+        }
+    }
+
+    debug!(
+        "Frame prefetching thread finished after {} frames",
+        frames_decoded
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use image::{DynamicImage, RgbaImage};
-    
+    use std::time::Instant;
+
     // Helper to create a test frame
     fn create_test_frame(timestamp: f64) -> VideoFrame {
         let image = DynamicImage::ImageRgba8(RgbaImage::new(10, 10));
-        VideoFrame::new(image, timestamp, 1.0/30.0)
+        VideoFrame::new(image, timestamp, 1.0 / 30.0)
     }
-    
+
+    // Stand-in for a decoder: yields timestamps in order until `end`
+    struct TestSource {
+        next: f64,
+        end: f64,
+        decoded: usize,
+    }
+
+    impl TestSource {
+        fn shared(end: f64) -> Arc<Mutex<Self>> {
+            Arc::new(Mutex::new(TestSource {
+                next: 0.0,
+                end,
+                decoded: 0,
+            }))
+        }
+    }
+
+    fn decode_test_frame(source: &mut TestSource) -> Result<Option<VideoFrame>> {
+        if source.next >= source.end {
+            return Ok(None);
+        }
+        let frame = create_test_frame(source.next);
+        source.next += 1.0;
+        source.decoded += 1;
+        Ok(Some(frame))
+    }
+
+    // Poll until `condition` holds, the decode thread runs independently
+    fn wait_for(mut condition: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for the decode thread"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
     #[test]
     fn test_frame_buffer_add_get() {
         let mut buffer = FrameBuffer::new(5);
-        
+
         // Add some frames
         for i in 0..5 {
             let frame = create_test_frame(i as f64);
             assert!(buffer.add_frame(frame));
         }
-        
+
         // Buffer should be full now
         let extra_frame = create_test_frame(5.0);
         assert!(!buffer.add_frame(extra_frame));
-        
+
         // Test retrieving frames
         let frame = buffer.get_frame_at(2.0).unwrap();
         assert_eq!(frame.timestamp, 2.0);
-        
+
         // Test approximate match
         let frame = buffer.get_frame_at(2.1).unwrap();
         assert_eq!(frame.timestamp, 2.0);
     }
-    
+
     #[test]
     fn test_frame_buffer_next_frame() {
         let mut buffer = FrameBuffer::new(5);
-        
+
         // Add frames out of order
         buffer.add_frame(create_test_frame(2.0));
         buffer.add_frame(create_test_frame(1.0));
         buffer.add_frame(create_test_frame(4.0));
         buffer.add_frame(create_test_frame(3.0));
-        
+
         // Test sequential access
         buffer.current_position = 0.0;
         let frame1 = buffer.next_frame().unwrap();
         assert_eq!(frame1.timestamp, 1.0);
-        
+
         let frame2 = buffer.next_frame().unwrap();
         assert_eq!(frame2.timestamp, 2.0);
     }
-    
+
     #[test]
     fn test_frame_buffer_seek() {
         let mut buffer = FrameBuffer::new(10);
-        
+
         // Add several frames
         for i in 0..10 {
             buffer.add_frame(create_test_frame(i as f64));
         }
-        
+
         // Seek to middle
         buffer.seek(5.0);
         assert_eq!(buffer.current_position, 5.0);
-        
+
         let frame = buffer.current_frame().unwrap();
         assert_eq!(frame.timestamp, 5.0);
-        
+
         // Next frame should be 6.0
         let next = buffer.next_frame().unwrap();
         assert_eq!(next.timestamp, 6.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_prefetch_fills_buffer_in_order() {
+        let mut buffer = FrameBuffer::new(4);
+        let source = TestSource::shared(100.0);
+        buffer.start_prefetching(source.clone(), decode_test_frame);
+
+        // The decode thread fills the ring and then blocks on back-pressure
+        wait_for(|| buffer.status().0 == 4);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(buffer.status().0, 4);
+        assert_eq!(source.lock().unwrap().decoded, 4);
+
+        // Consuming frees space, frames keep coming in PTS order
+        let mut timestamps = Vec::new();
+        wait_for(|| {
+            if let Some(frame) = buffer.next_frame() {
+                timestamps.push(frame.timestamp);
+            }
+            timestamps.len() == 10
+        });
+        let expected: Vec<f64> = (0..10).map(f64::from).collect();
+        assert_eq!(timestamps, expected);
+    }
+
+    #[test]
+    fn test_next_frame_does_not_wait_for_decoder() {
+        let mut buffer = FrameBuffer::new(4);
+        let source = TestSource::shared(100.0);
+
+        // Hold the decoder so nothing gets decoded
+        let guard = source.lock().unwrap();
+        buffer.start_prefetching(source.clone(), decode_test_frame);
+        assert!(buffer.next_frame().is_none());
+        assert!(!buffer.is_finished());
+        drop(guard);
+
+        wait_for(|| buffer.next_frame().is_some());
+    }
+
+    #[test]
+    fn test_flush_on_seek_drops_stale_frames() {
+        let mut buffer = FrameBuffer::new(4);
+        let source = TestSource::shared(100.0);
+        buffer.start_prefetching(source.clone(), decode_test_frame);
+        wait_for(|| buffer.status().0 == 4);
+
+        // Seek the source and flush under its lock, like a player does
+        {
+            let mut source = source.lock().unwrap();
+            source.next = 50.0;
+            buffer.flush(50.0);
+        }
+
+        wait_for(|| buffer.status().0 == 4);
+        let frame = buffer.next_frame().unwrap();
+        assert_eq!(frame.timestamp, 50.0);
+        assert_eq!(buffer.status().2, 50.0);
+    }
+
+    #[test]
+    fn test_next_timestamp_skips_stale_frames() {
+        let mut buffer = FrameBuffer::new(5);
+        for timestamp in [1.0, 2.0, 3.0] {
+            buffer.add_frame(create_test_frame(timestamp));
+        }
+
+        buffer.current_position = 2.0;
+        assert_eq!(buffer.next_timestamp(), Some(2.0));
+        // Peeking doesn't take the frame
+        assert_eq!(buffer.next_frame().unwrap().timestamp, 2.0);
+        assert_eq!(buffer.next_timestamp(), Some(3.0));
+        buffer.next_frame();
+        assert_eq!(buffer.next_timestamp(), None);
+    }
+
+    #[test]
+    fn test_frames_from_old_generation_are_rejected() {
+        let mut buffer = FrameBuffer::new(4);
+        let generation = buffer.shared.ring.lock().unwrap().generation;
+
+        buffer.flush(10.0);
+        let mut ring = buffer.shared.ring.lock().unwrap();
+        assert!(!ring.push(create_test_frame(3.0), generation));
+        assert!(ring.push(create_test_frame(10.0), generation + 1));
+    }
+
+    #[test]
+    fn test_prefetch_eof() {
+        let mut buffer = FrameBuffer::new(8);
+        let source = TestSource::shared(3.0);
+        buffer.start_prefetching(source.clone(), decode_test_frame);
+
+        let mut frames = 0;
+        wait_for(|| {
+            if buffer.next_frame().is_some() {
+                frames += 1;
+            }
+            buffer.is_finished()
+        });
+        assert_eq!(frames, 3);
+        assert!(buffer.next_frame().is_none());
+
+        // Seeking back after the end restarts decoding
+        {
+            let mut source = source.lock().unwrap();
+            source.next = 1.0;
+            buffer.flush(1.0);
+        }
+        assert!(!buffer.is_finished());
+        wait_for(|| buffer.status().0 == 2);
+        assert_eq!(buffer.next_frame().unwrap().timestamp, 1.0);
+
+        buffer.stop_prefetching();
+    }
+}
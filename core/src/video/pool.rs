@@ -0,0 +1,142 @@
+use std::ops::Deref;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Number of buffers a decoder keeps for reuse
+///
+/// Covers a full `FrameBuffer` at 30fps plus the frames held by the renderer.
+/// Slots are only created while every existing buffer is still in use.
+pub const DEFAULT_POOL_SLOTS: usize = 256;
+
+/// Reference-counted pixel data, shared by every clone of a `VideoFrame`
+///
+/// Buffers from a `FramePool` go back to the pool once the last clone is
+/// dropped, so steady-state playback doesn't allocate per frame.
+#[derive(Clone)]
+pub struct FrameData(Arc<Vec<u8>>);
+
+impl FrameData {
+    /// Wrap bytes that don't come from a pool
+    pub fn from_vec(data: Vec<u8>) -> Self {
+        Self(Arc::new(data))
+    }
+}
+
+impl Deref for FrameData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Pool of frame buffers recycled between decoded frames
+///
+/// The pool keeps a reference to every buffer it hands out; a buffer is free
+/// again once that is the only reference left.
+pub struct FramePool {
+    slots: Mutex<Vec<Arc<Vec<u8>>>>,
+    max_slots: usize,
+}
+
+impl FramePool {
+    /// Create a pool that retains at most `max_slots` buffers
+    pub fn new(max_slots: usize) -> Self {
+        Self {
+            slots: Mutex::new(Vec::with_capacity(max_slots)),
+            max_slots,
+        }
+    }
+
+    /// Get a buffer of `len` bytes, fill it with `fill` and share it
+    ///
+    /// Once the pool is warmed up to the frame size this reuses a free buffer
+    /// without allocating. The contents passed to `fill` are whatever the
+    /// buffer held before.
+    pub fn fill<R>(&self, len: usize, fill: impl FnOnce(&mut [u8]) -> R) -> (FrameData, R) {
+        let mut slots = self.slots.lock();
+
+        // A buffer only referenced by the pool is free
+        let free = slots
+            .iter_mut()
+            .position(|slot| Arc::get_mut(slot).is_some());
+        let mut buffer = match free {
+            Some(index) => slots.swap_remove(index),
+            None => {
+                if slots.len() >= self.max_slots {
+                    log::trace!(
+                        "Frame pool exhausted ({} buffers), allocating",
+                        self.max_slots
+                    );
+                }
+                Arc::new(Vec::with_capacity(len))
+            }
+        };
+
+        let data = Arc::get_mut(&mut buffer).expect("pooled buffer is not shared");
+        data.resize(len, 0);
+        let result = fill(data);
+
+        if slots.len() < self.max_slots {
+            slots.push(buffer.clone());
+        }
+        (FrameData(buffer), result)
+    }
+
+    /// Number of buffers currently retained
+    pub fn len(&self) -> usize {
+        self.slots.lock().len()
+    }
+
+    /// Whether the pool retains no buffers
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let pool = FramePool::new(4);
+
+        let (first, _) = pool.fill(16, |buf| buf.fill(1));
+        let first_ptr = first.as_ptr();
+        drop(first);
+
+        // The freed buffer comes back instead of a new one
+        let (second, _) = pool.fill(16, |buf| buf.fill(2));
+        assert_eq!(second.as_ptr(), first_ptr);
+        assert_eq!(&second[..], &[2; 16]);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_shared_buffers_are_not_reused() {
+        let pool = FramePool::new(4);
+
+        let (first, _) = pool.fill(8, |buf| buf.fill(1));
+        let clone = first.clone();
+        drop(first);
+
+        // Still referenced by `clone`, so a second buffer is created
+        let (second, _) = pool.fill(8, |buf| buf.fill(2));
+        assert_ne!(second.as_ptr(), clone.as_ptr());
+        assert_eq!(&clone[..], &[1; 8]);
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_pool_limit() {
+        let pool = FramePool::new(1);
+
+        let (first, _) = pool.fill(4, |_| ());
+        let (second, _) = pool.fill(4, |_| ());
+        // Only one buffer is retained, the other is freed normally
+        assert_eq!(pool.len(), 1);
+        drop((first, second));
+        assert!(!pool.is_empty());
+    }
+}
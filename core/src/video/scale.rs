@@ -0,0 +1,525 @@
+// Scaling filters shared by every resize path.
+//
+// One `ScaleFilter` setting (see `RenderConfig::scale_filter`) drives swscale
+// in the decoder, the GPU resize shader and `VideoFrame::resize_with`. The
+// resampler here is the reference: a separable convolution whose kernel widens
+// with the downscale factor, so shrinking a frame into a few terminal cells
+// averages the pixels each cell covers instead of skipping most of them.
+// shaders/resize.wgsl evaluates the same weights.
+
+use super::pool::DEFAULT_POOL_SLOTS;
+use super::{FrameData, FramePool, FrameView};
+use rayon::prelude::*;
+use std::ops::Range;
+
+/// Filter used when scaling frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleFilter {
+    /// Nearest source pixel, fastest but aliases badly when shrinking
+    Nearest,
+    /// Linear interpolation
+    Bilinear,
+    /// Catmull-Rom cubic, sharper than bilinear
+    Bicubic,
+    /// Three-lobe windowed sinc, sharpest with slight ringing
+    Lanczos3,
+    /// Average of the covered source pixels, best for heavy downscaling
+    #[default]
+    Area,
+}
+
+impl ScaleFilter {
+    /// Every filter, in the order settings cycle through them
+    pub const ALL: [ScaleFilter; 5] = [
+        ScaleFilter::Nearest,
+        ScaleFilter::Bilinear,
+        ScaleFilter::Bicubic,
+        ScaleFilter::Lanczos3,
+        ScaleFilter::Area,
+    ];
+
+    /// Human readable name
+    pub fn name(self) -> &'static str {
+        match self {
+            ScaleFilter::Nearest => "Nearest",
+            ScaleFilter::Bilinear => "Bilinear",
+            ScaleFilter::Bicubic => "Bicubic",
+            ScaleFilter::Lanczos3 => "Lanczos3",
+            ScaleFilter::Area => "Area",
+        }
+    }
+
+    /// The filter after this one in `ALL`, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&f| f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Kernel radius in source pixels when not downscaling
+    fn support(self) -> f32 {
+        match self {
+            ScaleFilter::Nearest | ScaleFilter::Area => 0.5,
+            ScaleFilter::Bilinear => 1.0,
+            ScaleFilter::Bicubic => 2.0,
+            ScaleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Weight of a source pixel `distance` pixels from the sample point
+    ///
+    /// `scale` is how many source pixels one output pixel covers, at least 1.
+    /// Kernels are stretched by it so downscaling low-passes first.
+    pub fn weight(self, distance: f32, scale: f32) -> f32 {
+        let t = (distance / scale).abs();
+        match self {
+            ScaleFilter::Nearest => {
+                if (-0.5..0.5).contains(&distance) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ScaleFilter::Bilinear => (1.0 - t).max(0.0),
+            ScaleFilter::Bicubic => {
+                // Catmull-Rom, a = -0.5
+                if t < 1.0 {
+                    (1.5 * t - 2.5) * t * t + 1.0
+                } else if t < 2.0 {
+                    ((-0.5 * t + 2.5) * t - 4.0) * t + 2.0
+                } else {
+                    0.0
+                }
+            }
+            ScaleFilter::Lanczos3 => {
+                if t < 1e-5 {
+                    1.0
+                } else if t < 3.0 {
+                    let x = std::f32::consts::PI * t;
+                    3.0 * x.sin() * (x / 3.0).sin() / (x * x)
+                } else {
+                    0.0
+                }
+            }
+            ScaleFilter::Area => {
+                // Overlap of the source pixel with the output pixel's footprint
+                let half = 0.5 / scale;
+                let u = distance / scale;
+                ((u + half).min(0.5) - (u - half).max(-0.5)).max(0.0)
+            }
+        }
+    }
+
+    /// Index of the filter in shaders/resize.wgsl
+    pub(crate) fn shader_index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Source pixels and weights of every output pixel along one axis
+///
+/// Weights are stored back to back so building the taps allocates twice,
+/// not once per output pixel.
+struct AxisTaps {
+    source: u32,
+    target: u32,
+    filter: ScaleFilter,
+    // First source pixel and range in `weights` of each output pixel
+    spans: Vec<(usize, Range<usize>)>,
+    weights: Vec<f32>,
+}
+
+impl AxisTaps {
+    /// Taps of every output pixel when scaling `source` pixels to `target`
+    fn new(source: u32, target: u32, filter: ScaleFilter) -> Self {
+        let ratio = source as f32 / target as f32;
+        let scale = ratio.max(1.0);
+        let radius = if filter == ScaleFilter::Nearest {
+            filter.support()
+        } else {
+            filter.support() * scale
+        };
+        let mut spans = Vec::with_capacity(target as usize);
+        let mut weights = Vec::new();
+        for i in 0..target {
+            let center = (i as f32 + 0.5) * ratio;
+            // Taps outside the frame are dropped and the rest renormalized
+            let start = (center - radius).floor().max(0.0) as usize;
+            let end = ((center + radius).ceil() as usize).min(source as usize);
+            let first = weights.len();
+            weights.extend((start..end).map(|j| filter.weight(j as f32 + 0.5 - center, scale)));
+            let taps = &mut weights[first..];
+            let sum: f32 = taps.iter().sum();
+            if sum != 0.0 {
+                taps.iter_mut().for_each(|w| *w /= sum);
+            }
+            spans.push((start, first..weights.len()));
+        }
+        Self {
+            source,
+            target,
+            filter,
+            spans,
+            weights,
+        }
+    }
+
+    /// First source pixel and weights for output pixel `i`
+    fn get(&self, i: usize) -> (usize, &[f32]) {
+        let (start, range) = &self.spans[i];
+        (*start, &self.weights[range.clone()])
+    }
+
+    /// Reuse `slot` if it holds these taps, otherwise compute them there
+    fn cached(
+        slot: &mut Option<AxisTaps>,
+        source: u32,
+        target: u32,
+        filter: ScaleFilter,
+    ) -> &AxisTaps {
+        let stale = slot
+            .as_ref()
+            .is_none_or(|taps| (taps.source, taps.target, taps.filter) != (source, target, filter));
+        if stale {
+            *slot = Some(Self::new(source, target, filter));
+        }
+        slot.as_ref().expect("taps were just computed")
+    }
+}
+
+/// Filter rows into `horizontal`, then columns into `output`
+///
+/// Values stay floating point between the passes and are rounded once.
+fn resample(
+    view: &FrameView,
+    columns: &AxisTaps,
+    rows: &AxisTaps,
+    horizontal: &mut Vec<f32>,
+    output: &mut [u8],
+) {
+    let row_len = columns.target as usize * 4;
+    horizontal.clear();
+    horizontal.resize(row_len * view.height() as usize, 0.0);
+    horizontal
+        .par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, out)| {
+            for x in 0..columns.target as usize {
+                let (start, weights) = columns.get(x);
+                let mut sum = [0f32; 4];
+                for (k, weight) in weights.iter().enumerate() {
+                    let pixel = view.get_pixel((start + k) as u32, y as u32).0;
+                    for (sum, &channel) in sum.iter_mut().zip(&pixel) {
+                        *sum += channel as f32 * weight;
+                    }
+                }
+                out[x * 4..x * 4 + 4].copy_from_slice(&sum);
+            }
+        });
+
+    let horizontal = &*horizontal;
+    output
+        .par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, out)| {
+            let (start, weights) = rows.get(y);
+            for (x, value) in out.iter_mut().enumerate() {
+                let sum: f32 = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| horizontal[(start + k) * row_len + x] * weight)
+                    .sum();
+                *value = sum.round().clamp(0.0, 255.0) as u8;
+            }
+        });
+}
+
+/// Scale a frame to `width` x `height` packed RGBA
+///
+/// Rows are filtered first into a floating point buffer, then columns, and
+/// the result is rounded once at the end. Use a `Resampler` to scale frame
+/// after frame.
+pub fn resize(view: &FrameView, width: u32, height: u32, filter: ScaleFilter) -> Vec<u8> {
+    let columns = AxisTaps::new(view.width(), width, filter);
+    let rows = AxisTaps::new(view.height(), height, filter);
+    let mut output = vec![0u8; width as usize * height as usize * 4];
+    resample(view, &columns, &rows, &mut Vec::new(), &mut output);
+    output
+}
+
+/// Scales frames like `resize`, keeping taps and buffers between calls
+///
+/// Taps are only recomputed when the source size, output size or filter
+/// changes, and output goes into pooled buffers, so scaling every frame of a
+/// video to the same size doesn't allocate.
+pub struct Resampler {
+    columns: Option<AxisTaps>,
+    rows: Option<AxisTaps>,
+    // Rows filtered by the first pass
+    horizontal: Vec<f32>,
+    pool: FramePool,
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            rows: None,
+            horizontal: Vec::new(),
+            pool: FramePool::new(DEFAULT_POOL_SLOTS),
+        }
+    }
+}
+
+impl Resampler {
+    /// Scale a frame to `width` x `height` packed RGBA
+    pub fn resize(
+        &mut self,
+        view: &FrameView,
+        width: u32,
+        height: u32,
+        filter: ScaleFilter,
+    ) -> FrameData {
+        let columns = AxisTaps::cached(&mut self.columns, view.width(), width, filter);
+        let rows = AxisTaps::cached(&mut self.rows, view.height(), height, filter);
+        let horizontal = &mut self.horizontal;
+        let len = width as usize * height as usize * 4;
+        let (data, _) = self.pool.fill(len, |output| {
+            resample(view, columns, rows, horizontal, output)
+        });
+        data
+    }
+}
+
+/// Reference images from tests/fixtures/scale, see generate.py there
+///
+/// `resize` and the decoder's swscale setup are both checked against these,
+/// so the filters keep looking the same whichever path scales a frame.
+#[cfg(test)]
+pub(crate) mod reference {
+    use super::ScaleFilter;
+    use crate::video::{FrameData, PixelFormat, VideoFrame};
+    use std::path::Path;
+
+    /// Size of the source image
+    pub const SOURCE_SIZE: (u32, u32) = (64, 48);
+
+    /// Filter and output size of each reference image
+    pub const CASES: [(ScaleFilter, u32, u32); 4] = [
+        (ScaleFilter::Bicubic, 24, 18),
+        (ScaleFilter::Bicubic, 100, 75),
+        (ScaleFilter::Lanczos3, 24, 18),
+        (ScaleFilter::Lanczos3, 100, 75),
+    ];
+
+    fn read(name: &str) -> Vec<u8> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/scale")
+            .join(name);
+        std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+    }
+
+    /// The gray source image as RGBA
+    pub fn source() -> VideoFrame {
+        let (width, height) = SOURCE_SIZE;
+        let data = read(&format!("source_{}x{}.gray", width, height))
+            .into_iter()
+            .flat_map(|v| [v, v, v, 255])
+            .collect();
+        VideoFrame::from_data(
+            FrameData::from_vec(data),
+            width,
+            height,
+            width as usize * 4,
+            PixelFormat::Rgba,
+            0.0,
+            0.04,
+        )
+    }
+
+    /// Expected gray values of `source()` scaled with `filter`
+    pub fn expected(filter: ScaleFilter, width: u32, height: u32) -> Vec<u8> {
+        let name = format!("{}_{}x{}.gray", filter.name().to_lowercase(), width, height);
+        read(&name)
+    }
+
+    /// Largest and mean difference between `gray` and the reference image
+    pub fn difference(
+        filter: ScaleFilter,
+        width: u32,
+        height: u32,
+        gray: impl IntoIterator<Item = u8>,
+    ) -> (u8, f32) {
+        let expected = expected(filter, width, height);
+        let mut count = 0;
+        let (max, total) = gray
+            .into_iter()
+            .zip(&expected)
+            .inspect(|_| count += 1)
+            .fold((0, 0u32), |(max, total), (value, &reference)| {
+                let diff = value.abs_diff(reference);
+                (max.max(diff), total + diff as u32)
+            });
+        assert_eq!(
+            count,
+            expected.len(),
+            "image size differs from the reference"
+        );
+        (max, total as f32 / count as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::{PixelFormat, VideoFrame};
+
+    fn frame_from_fn(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> VideoFrame {
+        let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            for x in 0..width {
+                data.extend_from_slice(&pixel(x, y));
+            }
+        }
+        VideoFrame::from_data(
+            FrameData::from_vec(data),
+            width,
+            height,
+            width as usize * 4,
+            PixelFormat::Rgba,
+            0.0,
+            0.04,
+        )
+    }
+
+    #[test]
+    fn weights_are_normalized() {
+        for filter in ScaleFilter::ALL {
+            for (source, target) in [(100, 7), (7, 100), (33, 33), (1, 5)] {
+                let taps = AxisTaps::new(source, target, filter);
+                for i in 0..target as usize {
+                    let sum: f32 = taps.get(i).1.iter().sum();
+                    assert!(
+                        (sum - 1.0).abs() < 1e-4,
+                        "{:?} {}->{}",
+                        filter,
+                        source,
+                        target
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solid_color_is_preserved() {
+        let frame = frame_from_fn(37, 23, |_, _| [200, 100, 50, 255]);
+        for filter in ScaleFilter::ALL {
+            for (width, height) in [(5, 3), (37, 23), (90, 41)] {
+                let resized = resize(&frame.view(), width, height, filter);
+                assert!(
+                    resized.chunks(4).all(|p| p == [200, 100, 50, 255]),
+                    "{:?} to {}x{}",
+                    filter,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn area_averages_a_checkerboard() {
+        // Nearest aliases a fine checkerboard to solid black or white, area
+        // and the stretched kernels average it to gray
+        let frame = frame_from_fn(64, 64, |x, y| {
+            let v = if (x + y) % 2 == 0 { 255 } else { 0 };
+            [v, v, v, 255]
+        });
+        let nearest = resize(&frame.view(), 8, 8, ScaleFilter::Nearest);
+        assert!(nearest.chunks(4).all(|p| p[0] == 0 || p[0] == 255));
+        for filter in [
+            ScaleFilter::Area,
+            ScaleFilter::Bilinear,
+            ScaleFilter::Lanczos3,
+        ] {
+            let resized = resize(&frame.view(), 8, 8, filter);
+            assert!(
+                resized.chunks(4).all(|p| p[0].abs_diff(128) <= 3),
+                "{:?} aliased",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_picks_source_pixels() {
+        let frame = frame_from_fn(4, 1, |x, _| [x as u8 * 10, 0, 0, 255]);
+        let resized = resize(&frame.view(), 2, 1, ScaleFilter::Nearest);
+        assert_eq!(resized, [0, 0, 0, 255, 20, 0, 0, 255]);
+        let resized = resize(&frame.view(), 8, 1, ScaleFilter::Nearest);
+        let reds: Vec<u8> = resized.chunks(4).map(|p| p[0]).collect();
+        assert_eq!(reds, [0, 0, 10, 10, 20, 20, 30, 30]);
+    }
+
+    #[test]
+    fn bilinear_upscale_interpolates() {
+        let frame = frame_from_fn(2, 1, |x, _| [x as u8 * 200, 0, 0, 255]);
+        let resized = resize(&frame.view(), 4, 1, ScaleFilter::Bilinear);
+        let reds: Vec<u8> = resized.chunks(4).map(|p| p[0]).collect();
+        // Sample points at 0.25, 0.75, 1.25 and 1.75 source pixels
+        assert_eq!(reds, [0, 50, 150, 200]);
+    }
+
+    #[test]
+    fn resampler_matches_resize_as_settings_change() {
+        let frame = frame_from_fn(40, 30, |x, y| [x as u8 * 6, y as u8 * 8, 90, 255]);
+        let small = frame_from_fn(20, 30, |x, y| [y as u8 * 8, x as u8 * 12, 30, 255]);
+        let mut resampler = Resampler::default();
+        // Same size twice hits the cached taps, the rest must recompute them
+        for (source, width, height, filter) in [
+            (&frame, 13, 9, ScaleFilter::Bicubic),
+            (&frame, 13, 9, ScaleFilter::Bicubic),
+            (&frame, 13, 9, ScaleFilter::Area),
+            (&frame, 60, 9, ScaleFilter::Area),
+            (&small, 60, 9, ScaleFilter::Area),
+        ] {
+            let expected = resize(&source.view(), width, height, filter);
+            let resized = resampler.resize(&source.view(), width, height, filter);
+            assert_eq!(
+                &resized[..],
+                &expected[..],
+                "{:?} to {}x{}",
+                filter,
+                width,
+                height
+            );
+        }
+    }
+
+    #[test]
+    fn matches_reference_images() {
+        let source = reference::source();
+        for (filter, width, height) in reference::CASES {
+            let resized = resize(&source.view(), width, height, filter);
+            let gray = resized.chunks(4).map(|p| p[0]);
+            let (max, _) = reference::difference(filter, width, height, gray);
+            assert!(
+                max <= 1,
+                "{:?} to {}x{} off by {}",
+                filter,
+                width,
+                height,
+                max
+            );
+        }
+    }
+
+    #[test]
+    fn next_cycles_through_all_filters() {
+        let mut filter = ScaleFilter::default();
+        for _ in 0..ScaleFilter::ALL.len() {
+            filter = filter.next();
+        }
+        assert_eq!(filter, ScaleFilter::default());
+    }
+}
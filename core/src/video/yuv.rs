@@ -0,0 +1,241 @@
+// Planar YUV 4:2:0 frames straight from the decoder.
+//
+// Color conversion is left to the consumer: `GpuHandle::convert_yuv` runs it
+// as a compute pass, and `YuvFrame::to_frame` is both the CPU fallback and the
+// reference the GPU output is tested against. Both use the same 8-bit fixed
+// point BT.601/BT.709 formulas, so they agree exactly.
+
+use super::{FrameData, PixelFormat, VideoFrame};
+
+/// YUV to RGB conversion matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMatrix {
+    /// SD video and the ffmpeg default when a stream doesn't say
+    #[default]
+    Bt601,
+    /// HD video
+    Bt709,
+}
+
+/// Fixed point conversion factors, scaled by 256
+///
+/// `c = (y - y_offset) * y_scale`, then
+/// `r = c + rv * (v - 128)`, `g = c - gu * (u - 128) - gv * (v - 128)` and
+/// `b = c + bu * (u - 128)`, each rounded and shifted right by 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YuvCoefficients {
+    pub y_scale: i32,
+    pub y_offset: i32,
+    pub rv: i32,
+    pub gu: i32,
+    pub gv: i32,
+    pub bu: i32,
+}
+
+impl ColorMatrix {
+    /// Conversion factors for limited (16-235) or full (0-255) range input
+    pub fn coefficients(self, full_range: bool) -> YuvCoefficients {
+        let (y_scale, y_offset) = if full_range { (256, 0) } else { (298, 16) };
+        let [rv, gu, gv, bu] = match (self, full_range) {
+            (ColorMatrix::Bt601, false) => [409, 100, 208, 516],
+            (ColorMatrix::Bt601, true) => [359, 88, 183, 454],
+            (ColorMatrix::Bt709, false) => [459, 55, 136, 541],
+            (ColorMatrix::Bt709, true) => [403, 48, 120, 475],
+        };
+        YuvCoefficients {
+            y_scale,
+            y_offset,
+            rv,
+            gu,
+            gv,
+            bu,
+        }
+    }
+}
+
+impl YuvCoefficients {
+    /// Convert one pixel
+    pub fn to_rgb(&self, y: u8, u: u8, v: u8) -> [u8; 3] {
+        let c = (y as i32 - self.y_offset) * self.y_scale;
+        let d = u as i32 - 128;
+        let e = v as i32 - 128;
+        let channel = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+        [
+            channel(c + self.rv * e),
+            channel(c - self.gu * d - self.gv * e),
+            channel(c + self.bu * d),
+        ]
+    }
+}
+
+/// Decoded frame as YUV 4:2:0 planes
+///
+/// The Y, U and V planes follow each other in one shared buffer. Chroma planes
+/// have half the width and height, rounded up.
+#[derive(Clone)]
+pub struct YuvFrame {
+    data: FrameData,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes between rows of the Y plane
+    pub luma_stride: usize,
+    /// Bytes between rows of the U and V planes
+    pub chroma_stride: usize,
+    pub matrix: ColorMatrix,
+    /// Full (0-255) rather than limited (16-235) range
+    pub full_range: bool,
+    /// Timestamp in seconds
+    pub timestamp: f64,
+    /// Frame duration in seconds
+    pub duration: f64,
+}
+
+impl YuvFrame {
+    /// Size of the U and V planes for a frame of the given size
+    pub fn chroma_size(width: u32, height: u32) -> (u32, u32) {
+        (width.div_ceil(2), height.div_ceil(2))
+    }
+
+    /// Bytes needed for all three planes
+    pub fn data_len(height: u32, luma_stride: usize, chroma_stride: usize) -> usize {
+        let (_, chroma_height) = Self::chroma_size(1, height);
+        luma_stride * height as usize + 2 * chroma_stride * chroma_height as usize
+    }
+
+    /// Create a frame over existing plane data, e.g. a pooled decoder buffer
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_data(
+        data: FrameData,
+        width: u32,
+        height: u32,
+        luma_stride: usize,
+        chroma_stride: usize,
+        matrix: ColorMatrix,
+        full_range: bool,
+        timestamp: f64,
+        duration: f64,
+    ) -> Self {
+        debug_assert!(luma_stride >= width as usize);
+        debug_assert!(chroma_stride >= Self::chroma_size(width, height).0 as usize);
+        debug_assert!(data.len() >= Self::data_len(height, luma_stride, chroma_stride));
+        Self {
+            data,
+            width,
+            height,
+            luma_stride,
+            chroma_stride,
+            matrix,
+            full_range,
+            timestamp,
+            duration,
+        }
+    }
+
+    /// Offsets of the U and V planes in `data`
+    pub fn chroma_offsets(&self) -> (usize, usize) {
+        let (_, chroma_height) = Self::chroma_size(self.width, self.height);
+        let u = self.luma_stride * self.height as usize;
+        (u, u + self.chroma_stride * chroma_height as usize)
+    }
+
+    /// All three planes, Y then U then V
+    pub fn data(&self) -> &[u8] {
+        &self.data[..Self::data_len(self.height, self.luma_stride, self.chroma_stride)]
+    }
+
+    /// Convert to packed RGBA on the CPU
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let coefficients = self.matrix.coefficients(self.full_range);
+        let (u_offset, v_offset) = self.chroma_offsets();
+        let mut rgba = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for y in 0..self.height as usize {
+            let luma = &self.data[y * self.luma_stride..][..self.width as usize];
+            let chroma = (y / 2) * self.chroma_stride;
+            for (x, &luma) in luma.iter().enumerate() {
+                let [r, g, b] = coefficients.to_rgb(
+                    luma,
+                    self.data[u_offset + chroma + x / 2],
+                    self.data[v_offset + chroma + x / 2],
+                );
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        rgba
+    }
+
+    /// Convert to an RGBA frame on the CPU
+    pub fn to_frame(&self) -> VideoFrame {
+        self.frame_from_rgba(FrameData::from_vec(self.to_rgba()))
+    }
+
+    /// Wrap packed RGBA converted from this frame, keeping its timing
+    pub fn frame_from_rgba(&self, rgba: FrameData) -> VideoFrame {
+        VideoFrame::from_data(
+            rgba,
+            self.width,
+            self.height,
+            self.width as usize * 4,
+            PixelFormat::Rgba,
+            self.timestamp,
+            self.duration,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, yuv: [u8; 3], stride_pad: usize) -> YuvFrame {
+        let (chroma_width, chroma_height) = YuvFrame::chroma_size(width, height);
+        let luma_stride = width as usize + stride_pad;
+        let chroma_stride = chroma_width as usize + stride_pad;
+        let u = luma_stride * height as usize;
+        let v = u + chroma_stride * chroma_height as usize;
+        let mut data = vec![yuv[0]; YuvFrame::data_len(height, luma_stride, chroma_stride)];
+        data[u..v].fill(yuv[1]);
+        data[v..].fill(yuv[2]);
+        YuvFrame::from_data(
+            FrameData::from_vec(data),
+            width,
+            height,
+            luma_stride,
+            chroma_stride,
+            ColorMatrix::Bt601,
+            false,
+            0.0,
+            0.04,
+        )
+    }
+
+    #[test]
+    fn limited_range_extremes() {
+        let coefficients = ColorMatrix::Bt601.coefficients(false);
+        assert_eq!(coefficients.to_rgb(16, 128, 128), [0, 0, 0]);
+        assert_eq!(coefficients.to_rgb(235, 128, 128), [255, 255, 255]);
+        // Pure red in BT.601 limited range
+        assert_eq!(coefficients.to_rgb(81, 90, 240), [255, 0, 0]);
+    }
+
+    #[test]
+    fn full_range_gray_is_unchanged() {
+        for matrix in [ColorMatrix::Bt601, ColorMatrix::Bt709] {
+            let coefficients = matrix.coefficients(true);
+            for y in [0, 64, 128, 255] {
+                assert_eq!(coefficients.to_rgb(y, 128, 128), [y, y, y]);
+            }
+        }
+    }
+
+    #[test]
+    fn converts_padded_planes_with_odd_size() {
+        let frame = solid(5, 3, [235, 128, 128], 3);
+        let rgba = frame.to_rgba();
+        assert_eq!(rgba.len(), 5 * 3 * 4);
+        assert!(rgba.iter().all(|&c| c == 255));
+
+        let frame = frame.to_frame();
+        assert_eq!((frame.width, frame.height), (5, 3));
+        assert!(frame.is_packed());
+    }
+}
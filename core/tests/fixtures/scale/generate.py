@@ -0,0 +1,82 @@
+#!/usr/bin/env python3
+"""Regenerate the scaling reference images in this directory.
+
+Written independently of the Rust resampler, in double precision: each output
+pixel samples at its center, kernels are stretched by the downscale factor and
+taps falling outside the image are dropped and the rest renormalized. Images
+are raw 8-bit gray, row after row.
+"""
+
+import math
+from pathlib import Path
+
+SOURCE = (64, 48)
+TARGETS = [(24, 18), (100, 75)]
+
+
+def source_pixel(x, y):
+    wave = 60 * math.sin(2 * math.pi * x / 16) * math.cos(2 * math.pi * y / 12)
+    ramp = 40 * (x / (SOURCE[0] - 1) - 0.5)
+    return round(128 + wave + ramp)
+
+
+def catmull_rom(t):
+    t = abs(t)
+    if t < 1:
+        return (1.5 * t - 2.5) * t * t + 1
+    if t < 2:
+        return ((-0.5 * t + 2.5) * t - 4) * t + 2
+    return 0.0
+
+
+def lanczos3(t):
+    t = abs(t)
+    if t < 1e-9:
+        return 1.0
+    if t < 3:
+        x = math.pi * t
+        return 3 * math.sin(x) * math.sin(x / 3) / (x * x)
+    return 0.0
+
+
+def taps(source, target, kernel, support):
+    ratio = source / target
+    scale = max(ratio, 1.0)
+    result = []
+    for i in range(target):
+        center = (i + 0.5) * ratio
+        lo = max(0, math.floor(center - support * scale))
+        hi = min(source, math.ceil(center + support * scale))
+        weights = [kernel((j + 0.5 - center) / scale) for j in range(lo, hi)]
+        total = sum(weights)
+        result.append([(j, w / total) for j, w in zip(range(lo, hi), weights)])
+    return result
+
+
+def resize(pixels, size, target, kernel, support):
+    (width, height), (out_w, out_h) = size, target
+    columns = taps(width, out_w, kernel, support)
+    rows = taps(height, out_h, kernel, support)
+    horizontal = [[sum(pixels[y][j] * w for j, w in columns[x]) for x in range(out_w)] for y in range(height)]
+    return [
+        [min(255, max(0, round(sum(horizontal[j][x] * w for j, w in rows[y])))) for x in range(out_w)]
+        for y in range(out_h)
+    ]
+
+
+def write(path, rows):
+    path.write_bytes(bytes(v for row in rows for v in row))
+
+
+def main():
+    here = Path(__file__).parent
+    pixels = [[source_pixel(x, y) for x in range(SOURCE[0])] for y in range(SOURCE[1])]
+    write(here / "source_64x48.gray", pixels)
+    for name, kernel, support in [("bicubic", catmull_rom, 2), ("lanczos3", lanczos3, 3)]:
+        for target in TARGETS:
+            out = resize(pixels, SOURCE, target, kernel, support)
+            write(here / f"{name}_{target[0]}x{target[1]}.gray", out)
+
+
+if __name__ == "__main__":
+    main()
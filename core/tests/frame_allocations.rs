@@ -0,0 +1,158 @@
+// Counts heap allocations made while frames move through the pipeline.
+//
+// Lives in its own test binary because it installs a global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use core::FrameBuffer;
+use core::video::{FramePool, PixelFormat, Resampler, ScaleFilter, VideoFrame};
+
+struct CountingAllocator;
+
+thread_local! {
+    // Per thread so allocations made by the test harness don't count
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 90;
+
+/// Produce a frame the way the decoder does: fill a pooled buffer and share it
+fn decode(pool: &FramePool, index: usize) -> VideoFrame {
+    let stride = (WIDTH as usize * 4).next_multiple_of(32);
+    let (data, _) = pool.fill(stride * HEIGHT as usize, |buffer| buffer.fill(index as u8));
+    VideoFrame::from_data(
+        data,
+        WIDTH,
+        HEIGHT,
+        stride,
+        PixelFormat::Rgba,
+        index as f64 / 30.0,
+        1.0 / 30.0,
+    )
+}
+
+/// How many frames playback runs behind the decoder, a second at 30fps
+const LEAD: usize = 30;
+
+#[test]
+fn steady_state_playback_does_not_allocate() {
+    let pool = FramePool::new(64);
+    // Five seconds, as `LocalMediaPlayer` sizes it
+    let mut buffer = FrameBuffer::new(150);
+
+    let mut play = |index: usize| {
+        // The decode thread's side
+        assert!(buffer.add_frame(decode(&pool, index)));
+        if index < LEAD {
+            return;
+        }
+
+        // The player's side: take the next frame, poll the status for the UI
+        let frame = buffer.next_frame().expect("frame was buffered");
+        let shown = index - LEAD;
+        assert_eq!(buffer.status().0, LEAD);
+        assert!(buffer.current_frame().is_some());
+
+        // What the renderer reads
+        let view = frame.view();
+        let checksum = (0..HEIGHT).fold(0u32, |sum, y| {
+            sum + frame.row(y)[0] as u32 + view.get_pixel(WIDTH - 1, y).0[3] as u32
+        });
+        assert_eq!(checksum, HEIGHT * (shown as u8 as u32 * 2));
+    };
+
+    // Warm up the pool to its working size
+    for index in 0..64 {
+        play(index);
+    }
+
+    let before = allocations();
+    for index in 64..400 {
+        play(index);
+    }
+    assert_eq!(
+        allocations() - before,
+        0,
+        "frames allocated in steady state"
+    );
+}
+
+#[test]
+fn cloning_frames_does_not_allocate() {
+    let pool = FramePool::new(2);
+    let frame = decode(&pool, 1);
+
+    let before = allocations();
+    let clones: [VideoFrame; 4] = std::array::from_fn(|_| frame.clone());
+    assert_eq!(allocations() - before, 0);
+    assert!(
+        clones
+            .iter()
+            .all(|clone| clone.as_rgba_bytes().as_ptr() == frame.as_rgba_bytes().as_ptr())
+    );
+}
+
+#[test]
+fn scaling_to_a_fixed_size_does_not_allocate() {
+    // Jobs sent to rayon from outside its threads go through a global queue
+    // that allocates a block every few dozen jobs, so scale on a pool thread
+    let threads = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .expect("thread pool");
+    let allocated = threads.install(|| {
+        let pool = FramePool::new(4);
+        let mut resampler = Resampler::default();
+        let mut scale = |index: usize| {
+            let frame = decode(&pool, index);
+            let resized = frame.resize_using(64, 36, true, ScaleFilter::Lanczos3, &mut resampler);
+            assert_eq!((resized.width, resized.height), (64, 36));
+        };
+
+        // The first frames compute the taps and fill the resampler's pool
+        for index in 0..4 {
+            scale(index);
+        }
+
+        let before = allocations();
+        for index in 4..400 {
+            scale(index);
+        }
+        allocations() - before
+    });
+    assert_eq!(allocated, 0, "scaling allocated per frame");
+}
@@ -1,19 +1,43 @@
-use std::{path::PathBuf, time::Instant};
+use std::{collections::VecDeque, path::PathBuf, time::Instant};
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::commands;
+use crate::commands::{self, line::CommandLine, user::UserCommands};
 use anyhow::{Context, Result};
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::Color,
-};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
 use core::{
     MediaInfo, MediaPlayer, YouTubeConfig, YouTubePlayer, YouTubeVideoInfo, FrameBuffer,
-    create_media_player, detect_media_type, MediaSourceType, render::RenderConfig,
-    render::RenderMethod,
+    LocalMediaPlayer, create_media_player, detect_media_type, MediaSourceType,
+    render::GpuHandle, render::OutputTarget, render::RenderConfig, render::RenderMethod, render::Viewport,
+    media::{PlaybackStats, RateMeter},
+};
+use crate::keymap::{Action, KeyContext, Keymap, Resolution};
+use crate::events::PlayerEvent;
+use crate::script::{self, PlayerState, Request, Scripts};
+use crate::ui::app::{
+    YOUTUBE_RESULT_HEIGHT, list_index_at, list_view_layout, player_controls_layout, player_layout,
 };
+use crate::ui::components::{PlaybackControls, ProgressBar};
+use crate::ui::theme::{StatusKind, Theme};
+use crate::ui::video::VideoState;
+
+/// How long the controls stay up after input in fullscreen
+const CONTROLS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Longest gap between the clicks of a double-click
+const DOUBLE_CLICK: std::time::Duration = std::time::Duration::from_millis(400);
+
+/// Actions of the playback control buttons, left to right
+const CONTROL_ACTIONS: [Action; 5] = [
+    Action::Seek(-30),
+    Action::Seek(-5),
+    Action::TogglePause,
+    Action::Seek(5),
+    Action::Seek(30),
+];
 
 // App state
 pub struct App {
@@ -31,9 +55,9 @@ pub struct App {
     pub input: String,
     /// Cursor position in the input field
     pub input_cursor: usize,
-    /// Whether the player UI is shown (always true in this implementation)
+    /// Whether the player controls are shown, they only hide in fullscreen
     pub show_ui: bool,
-    /// Last time the UI was shown
+    /// Last keyboard or mouse input, the controls hide a while after it
     pub last_ui_interaction: Instant,
     /// Whether YouTube URLs should be automatically detected
     pub auto_detect_youtube: bool,
@@ -42,7 +66,7 @@ pub struct App {
     /// Render configuration
     pub render_config: RenderConfig,
     /// Status message to display
-    pub status_message: Option<(String, Instant, Color)>,
+    pub status_message: Option<(String, Instant, StatusKind)>,
     /// Whether the app should exit
     pub should_quit: bool,
     /// Help dialog visibility
@@ -51,10 +75,60 @@ pub struct App {
     pub show_settings: bool,
     /// Whether command mode is active
     pub command_mode: bool,
-    /// Command buffer for command mode
-    pub command_buffer: String,
+    /// Text, history and completions of the command line
+    pub command_line: CommandLine,
+    /// Aliases and macros defined with `:alias` and `:macro`
+    pub user_commands: UserCommands,
+    /// Playback speed, kept for every player opened
+    pub speed: f64,
     /// Buffer status (buffered frames, capacity, buffer position)
     pub buffer_status: Option<(usize, usize, f64)>,
+    /// Highlighted playback control button, if any
+    pub player_control_selected: Option<usize>,
+    /// Latest video output, drawn by the player view
+    pub video_state: VideoState,
+    /// Stats overlay visibility
+    pub show_stats: bool,
+    /// Latest diagnostics from the player, refreshed every update
+    pub playback_stats: Option<PlaybackStats>,
+    /// Bytes written to the terminal so far, counted by the backend writer
+    pub terminal_bytes: Arc<AtomicU64>,
+    /// Terminal output rate for the stats overlay
+    pub output_rate: RateMeter,
+    /// Key bindings, see keymap.rs
+    pub keymap: Keymap,
+    /// Whether the video fills the terminal, with the controls as an overlay
+    pub fullscreen: bool,
+    /// Time and cell of the last left click, for double-clicks
+    pub last_click: Option<(Instant, Position)>,
+    /// Where a drag that started on the progress bar will seek to
+    ///
+    /// The bar follows the mouse while the button is held, the seek happens
+    /// once it is released.
+    pub seek_drag: Option<f64>,
+    /// Colors the UI is drawn with
+    pub theme: Theme,
+    /// Name of the theme, for `:theme`
+    pub theme_name: String,
+    /// Rhai scripts and their key bindings, see script.rs
+    pub scripts: Scripts,
+    /// Path or URL of the media being played
+    pub media_path: Option<String>,
+    /// Files and URLs to play after the current one, see `:queue`
+    pub queue: VecDeque<String>,
+    /// Whether scripts were told playback reached the end
+    pub eof_reported: bool,
+    /// When `PlayerEvent::Tick` was last sent
+    pub last_tick_event: Instant,
+    /// JSON IPC socket, when started with `--ipc`
+    #[cfg(unix)]
+    pub ipc: Option<crate::ipc::IpcServer>,
+    /// MPRIS export on the session bus
+    #[cfg(feature = "mpris")]
+    pub mpris: Option<crate::mpris::Mpris>,
+    /// HTTP/WebSocket remote, when started with `--remote`
+    #[cfg(feature = "remote")]
+    pub remote: Option<crate::remote::Remote>,
 }
 
 /// Application views
@@ -87,6 +161,8 @@ pub struct FileBrowser {
     pub future: Vec<PathBuf>,
     /// Filter for file list
     pub filter: String,
+    /// First visible row, kept from the last draw for mouse hit-testing
+    pub offset: usize,
 }
 
 /// File entry in file browser
@@ -115,6 +191,8 @@ pub struct YoutubeSearch {
     pub selected: Option<usize>,
     /// Whether a search is in progress
     pub searching: bool,
+    /// First visible result, kept from the last draw for mouse hit-testing
+    pub offset: usize,
 }
 
 /// YouTube search result
@@ -130,6 +208,15 @@ pub struct YoutubeResult {
     pub thumbnail: Option<String>,
     /// Channel name
     pub channel: String,
+    /// Local path of the downloaded thumbnail
+    pub downloaded_thumbnail: Option<PathBuf>,
+}
+
+impl YoutubeResult {
+    /// Whether the thumbnail has been downloaded
+    pub fn has_downloaded_thumbnail(&self) -> bool {
+        self.downloaded_thumbnail.as_ref().is_some_and(|path| path.exists())
+    }
 }
 
 impl Default for App {
@@ -148,12 +235,14 @@ impl Default for App {
                 history: vec![home_dir],
                 future: Vec::new(),
                 filter: String::new(),
+                offset: 0,
             },
             youtube_search: YoutubeSearch {
                 query: String::new(),
                 results: Vec::new(),
                 selected: None,
                 searching: false,
+                offset: 0,
             },
             input: String::new(),
             input_cursor: 0,
@@ -164,16 +253,44 @@ impl Default for App {
             render_config: RenderConfig {
                 enable_gpu: false,
                 method: RenderMethod::Auto,
-                target_fps: 30,
+                target_fps: 30.0,
                 quality: 1.0,
+                // The player view draws frames itself, see `ui::video`
+                output: OutputTarget::Buffered,
+                ..Default::default()
             },
             status_message: None,
             should_quit: false,
             show_help: false,
             show_settings: false,
             command_mode: false,
-            command_buffer: String::new(),
+            command_line: CommandLine::default(),
+            user_commands: UserCommands::default(),
+            speed: 1.0,
             buffer_status: None,
+            player_control_selected: None,
+            video_state: VideoState::default(),
+            show_stats: false,
+            playback_stats: None,
+            terminal_bytes: Arc::new(AtomicU64::new(0)),
+            output_rate: RateMeter::new(),
+            keymap: Keymap::default(),
+            fullscreen: false,
+            last_click: None,
+            seek_drag: None,
+            theme: Theme::default(),
+            theme_name: "dark".to_string(),
+            scripts: Scripts::default(),
+            media_path: None,
+            queue: VecDeque::new(),
+            eof_reported: false,
+            last_tick_event: Instant::now(),
+            #[cfg(unix)]
+            ipc: None,
+            #[cfg(feature = "mpris")]
+            mpris: None,
+            #[cfg(feature = "remote")]
+            remote: None,
         }
     }
 }
@@ -181,14 +298,350 @@ impl Default for App {
 impl App {
     /// Create a new application
     pub fn new() -> Self {
-        Self::default()
+        let mut app = Self::default();
+        app.load_config();
+        app.load_keymap();
+        app.command_line = CommandLine::load();
+        app.load_rc();
+        app.load_scripts();
+        app
+    }
+
+    /// Load the scripts directory, reporting problems in the log and the status bar
+    pub fn load_scripts(&mut self) {
+        let problems = self.scripts.load();
+        self.report_problems("Script", &problems);
+        self.run_script_requests();
+    }
+
+    /// Log every problem and show the first in the status bar
+    pub fn report_problems(&mut self, prefix: &str, problems: &[String]) {
+        if let Some(summary) = Self::summarize_problems(prefix, problems) {
+            self.set_status(summary, StatusKind::Error);
+        }
+    }
+
+    /// Log every problem, returning `prefix: first (and N more, see log)`
+    pub fn summarize_problems(prefix: &str, problems: &[String]) -> Option<String> {
+        for problem in problems {
+            log::warn!("{}: {}", prefix, problem);
+        }
+        let first = problems.first()?;
+        let more = match problems.len() {
+            1 => String::new(),
+            n => format!(" (and {} more, see log)", n - 1),
+        };
+        Some(format!("{}: {}{}", prefix, first, more))
+    }
+
+    /// Run a script file with `:script`
+    pub fn run_script(&mut self, path: &std::path::Path) -> Result<()> {
+        self.scripts.run_file(path)?;
+        self.set_status(format!("Ran script {}", path.display()), StatusKind::Success);
+        self.run_script_requests();
+        Ok(())
+    }
+
+    /// Tell IPC clients and scripts about an event, carrying out what
+    /// scripts ask for
+    fn emit(&mut self, event: PlayerEvent) {
+        self.broadcast(&event);
+        if self.scripts.is_empty() {
+            return;
+        }
+        let state = self.script_state();
+        self.scripts.set_state(state);
+        let problems = self.scripts.hook(&event);
+        self.report_problems("Script", &problems);
+        self.run_script_requests();
+    }
+
+    /// Send an event to IPC, D-Bus and remote clients
+    fn broadcast(&mut self, event: &PlayerEvent) {
+        #[cfg(unix)]
+        if let Some(ipc) = &mut self.ipc {
+            ipc.broadcast(event);
+        }
+        #[cfg(feature = "mpris")]
+        if let Some(mpris) = &mut self.mpris {
+            mpris.event(event);
+        }
+        #[cfg(feature = "remote")]
+        if let Some(remote) = &self.remote {
+            remote.event(event);
+        }
+        #[cfg(not(any(unix, feature = "mpris", feature = "remote")))]
+        let _ = event;
+    }
+
+    /// Export the player over MPRIS, see mpris.rs
+    ///
+    /// Without a session bus this only logs, the player works the same.
+    #[cfg(feature = "mpris")]
+    pub fn start_mpris(&mut self) {
+        match crate::mpris::Mpris::start() {
+            Ok(mpris) => self.mpris = Some(mpris),
+            Err(e) => log::warn!("MPRIS not available: {:#}", e),
+        }
+    }
+
+    /// Carry out what D-Bus clients asked for, called every pass of the main loop
+    #[cfg(feature = "mpris")]
+    pub fn handle_mpris(&mut self) {
+        use crate::mpris::Request;
+        while let Some(request) = self.mpris.as_ref().and_then(|m| m.take_request()) {
+            log::debug!("MPRIS request {:?}", request);
+            let result = match request {
+                Request::Play => commands::CommandHandler::execute(self, "play"),
+                Request::Pause => commands::CommandHandler::execute(self, "pause"),
+                Request::PlayPause => commands::CommandHandler::execute(self, "toggle"),
+                // Action::Stop would only close a dialog if one is open
+                Request::Stop => {
+                    self.stop_playback();
+                    Ok(())
+                }
+                Request::Next => self.play_next(),
+                Request::Seek(seconds) => {
+                    self.seek_by(seconds);
+                    Ok(())
+                }
+                Request::SetPosition(position) => {
+                    self.seek_to(position);
+                    Ok(())
+                }
+                Request::Open(path) => self.open_media(&path),
+                Request::SetVolume(volume) => {
+                    commands::CommandHandler::execute(self, &format!("volume {}", volume))
+                }
+                Request::SetRate(rate) => {
+                    commands::CommandHandler::execute(self, &format!("speed {}", rate))
+                }
+                Request::Quit => {
+                    self.should_quit = true;
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                self.set_status(format!("MPRIS: {}", e), StatusKind::Error);
+            }
+        }
+    }
+
+    /// What D-Bus clients see of the player
+    #[cfg(feature = "mpris")]
+    fn mpris_status(&self) -> crate::mpris::Status {
+        use crate::mpris::{Playback, Status, Track};
+        let Some(player) = &self.player else {
+            return Status {
+                rate: self.speed,
+                has_next: !self.queue.is_empty(),
+                ..Status::default()
+            };
+        };
+        let youtube = player
+            .as_any()
+            .downcast_ref::<YouTubePlayer>()
+            .and_then(|p| p.get_youtube_info());
+        Status {
+            playback: if player.is_paused() { Playback::Paused } else { Playback::Playing },
+            position: player.get_position(),
+            volume: player.get_volume(),
+            rate: self.speed,
+            track: Some(Track {
+                number: 0,
+                url: self.media_path.clone().unwrap_or_default(),
+                title: self.media_title(),
+                duration: self.media_info.as_ref().map_or(0.0, |info| info.duration),
+                artist: youtube.as_ref().and_then(|info| info.uploader.clone()),
+                art_url: youtube.and_then(|info| info.thumbnail),
+            }),
+            has_next: !self.queue.is_empty(),
+        }
+    }
+
+    /// Name of what's playing, the video title for YouTube or the file name
+    #[cfg(any(feature = "mpris", feature = "remote"))]
+    fn media_title(&self) -> String {
+        let youtube = self
+            .player
+            .as_ref()
+            .and_then(|p| p.as_any().downcast_ref::<YouTubePlayer>())
+            .and_then(|p| p.get_youtube_info());
+        let path = self.media_path.clone().unwrap_or_default();
+        match youtube {
+            Some(info) => info.title,
+            None => std::path::Path::new(&path)
+                .file_name()
+                .map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// Serve the web remote, see remote.rs
+    #[cfg(feature = "remote")]
+    pub fn start_remote(&mut self, address: std::net::SocketAddr, token: Option<String>) -> Result<()> {
+        let remote = crate::remote::Remote::start(address, token)?;
+        // Not through `set_status`, which would log the token
+        self.status_message = Some((
+            format!("Remote: {}", remote.url()),
+            Instant::now(),
+            StatusKind::Success,
+        ));
+        self.remote = Some(remote);
+        Ok(())
+    }
+
+    /// Run what remote clients asked for, called every pass of the main loop
+    #[cfg(feature = "remote")]
+    pub fn handle_remote(&mut self) {
+        while let Some(request) = self.remote.as_ref().and_then(|r| r.take_request()) {
+            log::debug!("Remote request {}", request.line);
+            let result = commands::CommandHandler::execute(self, &request.line);
+            // The client may have given up waiting
+            let _ = request.reply.send(result.map_err(|e| e.to_string()));
+        }
+    }
+
+    /// What remote clients see of the player
+    #[cfg(feature = "remote")]
+    fn remote_status(&self) -> serde_json::Value {
+        let player = self.player.as_ref();
+        serde_json::json!({
+            "playing": player.is_some(),
+            "paused": player.is_some_and(|p| p.is_paused()),
+            "position": player.map_or(0.0, |p| p.get_position()),
+            "duration": self.media_info.as_ref().map_or(0.0, |info| info.duration),
+            "path": self.media_path,
+            "title": self.media_path.as_ref().map(|_| self.media_title()),
+            "volume": player.map(|p| p.get_volume()),
+            "speed": self.speed,
+            "queue": self.queue,
+        })
+    }
+
+    /// Listen for IPC clients on a socket, see ipc.rs
+    #[cfg(unix)]
+    pub fn start_ipc(&mut self, path: &std::path::Path) -> Result<()> {
+        self.ipc = Some(crate::ipc::IpcServer::bind(path)?);
+        Ok(())
+    }
+
+    /// Answer what IPC clients sent, called every pass of the main loop
+    #[cfg(unix)]
+    pub fn handle_ipc(&mut self) {
+        let Some(ipc) = &mut self.ipc else {
+            return;
+        };
+        for (client, line) in ipc.poll() {
+            log::debug!("IPC client {}: {}", client, line);
+            let reply = crate::ipc::handle(self, &line);
+            if let Some(ipc) = &mut self.ipc {
+                ipc.reply(client, &reply);
+            }
+        }
+    }
+
+    /// What scripts see of the player
+    fn script_state(&self) -> PlayerState {
+        PlayerState {
+            path: self.media_path.clone(),
+            position: self.player.as_ref().map_or(0.0, |p| p.get_position()),
+            paused: self.player.as_ref().is_some_and(|p| p.is_paused()),
+            media_info: self.media_info.clone(),
+        }
+    }
+
+    /// Carry out script requests, including those of hooks they trigger
+    pub fn run_script_requests(&mut self) {
+        // Hooks fired while carrying out a request queue more for this loop
+        if self.scripts.draining {
+            return;
+        }
+        self.scripts.draining = true;
+        let mut count = 0;
+        while let Some(request) = self.scripts.take_request() {
+            count += 1;
+            if count > script::MAX_REQUESTS {
+                let dropped = self.scripts.clear_requests() + 1;
+                log::warn!("Script: dropped {} requests, do hooks trigger each other?", dropped);
+                self.set_status(
+                    format!("Script: stopped after {} requests, see log", script::MAX_REQUESTS),
+                    StatusKind::Error,
+                );
+                break;
+            }
+            log::debug!("Script request {:?}", request);
+            if let Err(e) = self.run_script_request(request) {
+                log::warn!("Script: {}", e);
+                self.set_status(format!("Script: {}", e), StatusKind::Error);
+            }
+        }
+        self.scripts.draining = false;
+    }
+
+    fn run_script_request(&mut self, request: Request) -> Result<()> {
+        match request {
+            Request::Open(path) => self.open_media(&path)?,
+            Request::Seek(position) => self.seek_to(position),
+            Request::SeekBy(seconds) => self.seek_by(seconds),
+            Request::Pause => self.set_paused(true)?,
+            Request::Resume => self.set_paused(false)?,
+            Request::TogglePause => self.run_action(Action::TogglePause)?,
+            Request::Status(text) => self.set_status(text, StatusKind::Info),
+            Request::Command(line) => commands::CommandHandler::execute(self, &line)?,
+            Request::BindKey { script, context, keys, function } => {
+                let context = KeyContext::parse(&context)?;
+                let keys = crate::keymap::parse_chord(&keys)?;
+                let index = self.scripts.add_binding(script, &function)?;
+                self.keymap.bind(context, keys, Action::Script(index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pause or resume playback, telling clients and scripts if it changed
+    pub fn set_paused(&mut self, paused: bool) -> Result<()> {
+        if self.player.as_ref().is_some_and(|p| p.is_paused() != paused) {
+            self.run_action(Action::TogglePause)?;
+        }
+        Ok(())
+    }
+
+    /// Run the rc file, reporting problems in the log and the status bar
+    pub fn load_rc(&mut self) {
+        // Settings changed at startup need no status message
+        let status = self.status_message.take();
+        let problems = commands::rc::load(self);
+        self.status_message = status;
+        self.report_problems("rc", &problems);
+    }
+
+    /// Load config.json, reporting problems in the log and the status bar
+    pub fn load_config(&mut self) {
+        let (config, mut problems) = crate::config::load();
+        let (theme_name, theme, theme_problems) = config.theme();
+        problems.extend(theme_problems);
+        self.report_problems("Config", &problems);
+        self.theme = theme;
+        self.theme_name = theme_name;
+    }
+
+    /// Load keymap.json, reporting problems in the log and the status bar
+    pub fn load_keymap(&mut self) {
+        let (keymap, problems) = crate::keymap::config::load();
+        self.report_problems("Keymap", &problems);
+        self.keymap = keymap;
     }
 
-    /// Set a status message with a color
-    pub fn set_status(&mut self, message: impl Into<String>, color: Color) {
+    /// Set a status message, `kind` picks its theme color
+    ///
+    /// Errors are also sent to IPC clients as `PlayerEvent::Error`.
+    pub fn set_status(&mut self, message: impl Into<String>, kind: StatusKind) {
         let message_string = message.into();
-        log::debug!("Status message: {} ({})", message_string, color);
-        self.status_message = Some((message_string, Instant::now(), color));
+        log::debug!("Status message: {} ({:?})", message_string, kind);
+        if kind == StatusKind::Error {
+            self.broadcast(&PlayerEvent::Error(message_string.clone()));
+        }
+        self.status_message = Some((message_string, Instant::now(), kind));
     }
 
     /// Open a media file or URL
@@ -217,25 +670,167 @@ impl App {
 
                 // Create a new player based on the media type
                 self.last_ui_interaction = Instant::now();
+                // Keep one GPU processor for every player we open
+                if self.render_config.enable_gpu && self.render_config.gpu.is_none() {
+                    self.render_config.gpu = Some(GpuHandle::spawn());
+                }
                 self.player = Some(create_media_player(path_or_url, Some(self.render_config.clone()))?);
+                self.video_state.clear();
                 self.view = AppView::Player;
 
+                // Place the video between the menu bar and the controls
+                self.relayout();
+
+                // Keep the speed set before opening
+                let speed = self.speed;
+                let player = self.player.as_mut().filter(|_| speed != 1.0);
+                if let Some(Err(e)) = player.map(|player| player.set_speed(speed)) {
+                    log::warn!("Failed to set speed {}: {}", speed, e);
+                }
+
                 // Try to get media info
                 if let Some(player) = &self.player {
                     if let Some(info) = player.get_media_info() {
                         self.media_info = Some(info);
                     } else {
-                        self.set_status("Warning: Could not get media information".to_string(), Color::Yellow);
+                        self.set_status("Warning: Could not get media information".to_string(), StatusKind::Warning);
                     }
                 }
 
-                self.set_status(format!("Playing: {}", path_or_url), Color::Green);
+                self.set_status(format!("Playing: {}", path_or_url), StatusKind::Success);
+                self.media_path = Some(path_or_url.to_string());
+                self.eof_reported = false;
+                self.emit(PlayerEvent::FileLoaded(path_or_url.to_string()));
             }
         }
 
         Ok(())
     }
 
+    /// Open the next file or URL in the queue
+    pub fn play_next(&mut self) -> Result<()> {
+        let next = self
+            .queue
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("The queue is empty"))?;
+        self.open_media(&next)
+    }
+
+    /// Stop playback, if any, and return to the main menu
+    pub fn stop_playback(&mut self) {
+        let Some(mut player) = self.player.take() else {
+            return;
+        };
+        let _ = player.stop();
+        self.media_info = None;
+        self.media_path = None;
+        self.view = AppView::MainMenu;
+        self.set_status("Playback stopped", StatusKind::Info);
+    }
+
+    /// Whether the player controls are drawn
+    pub fn controls_visible(&self) -> bool {
+        !self.fullscreen || self.show_ui
+    }
+
+    /// What the player view's layout depends on
+    ///
+    /// The terminal is cleared when this changes so graphics protocol frames
+    /// don't linger where the controls are drawn.
+    pub fn layout_state(&self) -> (AppView, bool, bool) {
+        (self.view, self.fullscreen, self.controls_visible())
+    }
+
+    /// Show the controls after input, re-laying out the video if they were hidden
+    fn show_controls(&mut self) {
+        self.last_ui_interaction = Instant::now();
+        if !self.show_ui {
+            self.show_ui = true;
+            self.relayout();
+        }
+    }
+
+    /// Give the renderer the area the controls leave free at the current size
+    fn relayout(&mut self) {
+        if let Ok((width, height)) = crossterm::terminal::size() {
+            self.handle_resize(width, height);
+        }
+    }
+
+    /// Propagate a terminal resize to the active player
+    pub fn handle_resize(&mut self, width: u16, height: u16) {
+        let area = Rect::new(0, 0, width, height);
+        let video_area = player_layout(area, self.fullscreen, self.controls_visible())[1];
+        let viewport = Viewport {
+            x: video_area.x,
+            y: video_area.y,
+            width: video_area.width,
+            height: video_area.height,
+        };
+        log::debug!("Terminal resized to {}x{}, video viewport {:?}", width, height, viewport);
+        // The last frame was laid out for the old size
+        self.video_state.clear();
+
+        if let Some(player) = &mut self.player {
+            player.resize(width, height, Some(viewport));
+        }
+    }
+
+    /// Whether command mode is active
+    pub fn is_command_mode(&self) -> bool {
+        self.command_mode
+    }
+
+    /// Enter command mode with an empty line
+    pub fn enter_command_mode(&mut self) {
+        self.command_mode = true;
+        self.command_line.clear();
+    }
+
+    /// Leave command mode
+    pub fn exit_command_mode(&mut self) {
+        self.command_mode = false;
+    }
+
+    /// Run the command line, keeping it open with the error if it fails
+    pub fn submit_command(&mut self) {
+        let line = self.command_line.submit();
+        log::debug!("Executing command: {}", line);
+        self.exit_command_mode();
+
+        if let Err(e) = commands::handle_command(self, &line) {
+            log::warn!("Command failed: {}: {:#}", line, e);
+            self.command_mode = true;
+            self.command_line.fail(line, e.to_string());
+        }
+    }
+
+    /// Edit the command line
+    fn handle_command_line_key(&mut self, key: KeyEvent) {
+        let line = &mut self.command_line;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => self.submit_command(),
+            KeyCode::Esc => self.exit_command_mode(),
+            KeyCode::Tab => line.complete(true),
+            KeyCode::BackTab => line.complete(false),
+            KeyCode::Up => line.history_back(),
+            KeyCode::Down => line.history_forward(),
+            KeyCode::Left => line.left(),
+            KeyCode::Right => line.right(),
+            KeyCode::Home => line.home(),
+            KeyCode::End => line.end(),
+            KeyCode::Char('a') if ctrl => line.home(),
+            KeyCode::Char('e') if ctrl => line.end(),
+            KeyCode::Char('w') if ctrl => line.delete_word(),
+            KeyCode::Char('u') if ctrl => line.delete_to_start(),
+            KeyCode::Char(c) if !ctrl => line.insert(c),
+            KeyCode::Backspace => line.backspace(),
+            KeyCode::Delete => line.delete(),
+            _ => {}
+        }
+    }
+
     /// Refresh file list in file browser
     pub fn refresh_file_list(&mut self) -> Result<()> {
         let path = &self.file_browser.current_dir;
@@ -286,6 +881,7 @@ impl App {
 
         // Update file browser state
         self.file_browser.files = entries;
+        self.file_browser.offset = 0;
         self.file_browser.selected = if self.file_browser.files.is_empty() {
             None
         } else {
@@ -330,376 +926,407 @@ impl App {
 
     /// Handle key event
     pub fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
-        self.last_ui_interaction = Instant::now();
-        self.show_ui = true;
+        self.show_controls();
 
         // Check if we're in command mode
         if self.is_command_mode() {
-            match key.code {
-                KeyCode::Char(c) => {
-                    self.add_to_command_buffer(c);
-                }
-                KeyCode::Backspace => {
-                    self.remove_from_command_buffer();
-                }
-                KeyCode::Esc => {
-                    self.exit_command_mode();
-                }
-                _ => {}
-            }
+            self.handle_command_line_key(key);
             return Ok(());
         }
 
-        // Handle function keys globally
-        match key.code {
-            KeyCode::F(1) => {
-                self.show_help = !self.show_help;
-                return Ok(());
-            }
-            _ => {}
+        if self.view == AppView::Player && self.player.is_none() {
+            // No player active, return to main menu
+            self.view = AppView::MainMenu;
+            self.set_status("No active media playback".to_string(), StatusKind::Warning);
+            return Ok(());
         }
 
-        // Handle view-specific keys
+        // Configurable bindings first, see keymap.rs
+        match self.keymap.resolve(KeyContext::for_view(self.view), key) {
+            Resolution::Action(action) => return self.run_action(action),
+            Resolution::Pending => return Ok(()),
+            Resolution::Unbound => {}
+        }
+
+        // Views taking text or numbers keep their fixed keys
         match self.view {
-            AppView::MainMenu => self.handle_main_menu_key(key)?,
-            AppView::Player => self.handle_player_key(key)?,
-            AppView::FileBrowser => self.handle_file_browser_key(key)?,
             AppView::YoutubeSearch => self.handle_youtube_search_key(key)?,
             AppView::Settings => self.handle_settings_key(key)?,
+            AppView::MainMenu | AppView::Player | AppView::FileBrowser => {}
         }
 
         Ok(())
     }
 
-    // Mouse events have been removed for consistency across all app views
-
-    /// Handle key events in player view
-    fn handle_player_key(&mut self, key: KeyEvent) -> Result<()> {
-        // First check if we have a valid player
-        if self.player.is_none() {
-            // No player active, return to main menu
-            self.view = AppView::MainMenu;
-            self.set_status("No active media playback".to_string(), Color::Yellow);
-            return Ok(());
-        }
-
-        // Check buffer status to show debugging info
-        if let Some((buffer_size, capacity, _)) = self.buffer_status {
-            log::debug!("Buffer status: {}/{} frames", buffer_size, capacity);
-        }
-
-        // IMPORTANT: Add aggressive debug logging to track control flow
-        log::warn!("PLAYER CONTROL: Key press detected: {:?}", key.code);
-
-        // Define an enum for the actions we might take
-        enum PlayerAction {
-            Seek(f64, String),
-            TogglePause(bool),
-        }
-
-        // First determine what action to take without calling set_status
-        let action = match key.code {
-            KeyCode::Char(' ') => {
-                if let Some(player) = &mut self.player {
-                    // DIRECT CONTROL: Immediately toggle pause state to improve responsiveness
-                    let was_paused = player.is_paused();
-                    player.toggle_pause();
-                    let now_paused = player.is_paused();
-                    
-                    log::warn!("PLAYER CONTROL: Toggle pause - Was: {}, Now: {}", was_paused, now_paused);
-                    
-                    self.last_ui_interaction = Instant::now();
-                    Some(PlayerAction::TogglePause(now_paused))
-                } else {
-                    None
-                }
+    /// Run a key binding's action in the current view
+    pub fn run_action(&mut self, action: Action) -> Result<()> {
+        log::debug!("Action {} in {:?}", action, self.view);
+        match action {
+            Action::Quit => {
+                self.should_quit = true;
             }
-            KeyCode::Left => {
-                if let Some(player) = &mut self.player {
-                    let old_pos = player.get_position();
-                    let new_pos = (old_pos - 5.0).max(0.0);
-                    
-                    log::warn!("PLAYER CONTROL: Seeking from {:.2}s to {:.2}s", old_pos, new_pos);
-                    
-                    // More robust seek with error handling
-                    match player.seek(new_pos) {
-                        Ok(_) => {
-                            log::warn!("PLAYER CONTROL: Seek successful");
-                            self.last_ui_interaction = Instant::now();
-                            Some(PlayerAction::Seek(
-                                new_pos,
-                                format!("Seek to {:.1}s", new_pos),
-                            ))
-                        },
-                        Err(e) => {
-                            log::warn!("PLAYER CONTROL: Seek failed: {}", e);
-                            self.set_status(format!("Seek failed: {}", e), Color::Red);
-                            None
-                        }
-                    }
-                } else {
-                    None
+            Action::CommandLine => {
+                self.enter_command_mode();
+            }
+            Action::ToggleHelp => {
+                self.show_help = !self.show_help;
+                if self.view == AppView::Player {
+                    self.set_status(if self.show_help { "Help opened" } else { "Help closed" }, StatusKind::Info);
                 }
             }
-            KeyCode::Right => {
-                if let (Some(player), Some(media_info)) = (&mut self.player, &self.media_info) {
-                    let old_pos = player.get_position();
-                    let new_pos = (old_pos + 5.0).min(media_info.duration);
-                    
-                    log::warn!("PLAYER CONTROL: Seeking from {:.2}s to {:.2}s", old_pos, new_pos);
-                    
-                    // More robust seek with error handling
-                    match player.seek(new_pos) {
-                        Ok(_) => {
-                            log::warn!("PLAYER CONTROL: Seek successful");
-                            self.last_ui_interaction = Instant::now();
-                            Some(PlayerAction::Seek(
-                                new_pos,
-                                format!("Seek to {:.1}s", new_pos),
-                            ))
-                        },
-                        Err(e) => {
-                            log::warn!("PLAYER CONTROL: Seek failed: {}", e);
-                            self.set_status(format!("Seek failed: {}", e), Color::Red);
-                            None
-                        }
-                    }
-                } else {
-                    None
+            Action::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                // Set view to Settings if dialog is shown
+                if self.show_settings {
+                    self.view = AppView::Settings;
                 }
+                self.set_status(if self.show_settings { "Settings opened" } else { "Settings closed" }, StatusKind::Info);
             }
-            KeyCode::Char('f') => {
-                if let (Some(player), Some(media_info)) = (&mut self.player, &self.media_info) {
-                    let old_pos = player.get_position();
-                    let new_pos = (old_pos + 30.0).min(media_info.duration);
-                    
-                    log::warn!("PLAYER CONTROL: Seeking forward 30s from {:.2}s to {:.2}s", old_pos, new_pos);
-                    
-                    match player.seek(new_pos) {
-                        Ok(_) => {
-                            log::warn!("PLAYER CONTROL: Forward seek successful");
-                            self.last_ui_interaction = Instant::now();
-                            Some(PlayerAction::Seek(
-                                new_pos,
-                                format!("Forward 30s to {:.1}s", new_pos),
-                            ))
-                        },
-                        Err(e) => {
-                            log::warn!("PLAYER CONTROL: Forward seek failed: {}", e);
-                            self.set_status(format!("Seek failed: {}", e), Color::Red);
-                            None
-                        }
-                    }
-                } else {
-                    None
+            Action::ToggleStats => {
+                self.show_stats = !self.show_stats;
+                self.set_status(if self.show_stats { "Stats shown" } else { "Stats hidden" }, StatusKind::Info);
+            }
+            Action::TogglePause => {
+                if let Some(player) = &mut self.player {
+                    player.toggle_pause();
+                    let paused = player.is_paused();
+                    self.set_status(if paused { "Paused" } else { "Playing" }, StatusKind::Success);
+                    self.emit(PlayerEvent::Pause(paused));
                 }
             }
-            KeyCode::Char('b') => {
+            Action::Seek(seconds) => self.seek_by(seconds as f64),
+            Action::ToggleFullscreen => {
+                self.fullscreen = !self.fullscreen;
+                // Controls stay up for a moment before hiding
+                self.show_ui = true;
+                self.last_ui_interaction = Instant::now();
+                self.relayout();
+                let status = if self.fullscreen { "Fullscreen" } else { "Fullscreen off" };
+                self.set_status(status, StatusKind::Info);
+            }
+            Action::Restart => {
                 if let Some(player) = &mut self.player {
-                    let old_pos = player.get_position();
-                    let new_pos = (old_pos - 30.0).max(0.0);
-                    
-                    log::warn!("PLAYER CONTROL: Seeking back 30s from {:.2}s to {:.2}s", old_pos, new_pos);
-                    
-                    match player.seek(new_pos) {
+                    match player.seek(0.0) {
                         Ok(_) => {
-                            log::warn!("PLAYER CONTROL: Backward seek successful");
-                            self.last_ui_interaction = Instant::now();
-                            Some(PlayerAction::Seek(
-                                new_pos,
-                                format!("Back 30s to {:.1}s", new_pos),
-                            ))
-                        },
-                        Err(e) => {
-                            log::warn!("PLAYER CONTROL: Backward seek failed: {}", e);
-                            self.set_status(format!("Seek failed: {}", e), Color::Red);
-                            None
+                            self.set_status("Restarted", StatusKind::Info);
+                            self.emit(PlayerEvent::Seek(0.0));
                         }
+                        Err(e) => self.set_status(format!("Seek failed: {}", e), StatusKind::Error),
                     }
-                } else {
-                    None
                 }
             }
-            KeyCode::Esc => {
+            Action::Stop => {
                 if self.show_help || self.show_settings {
                     self.show_help = false;
                     self.show_settings = false;
-                    None
                 } else {
-                    // Stop playback and return to main menu
-                    if let Some(player) = &mut self.player {
-                        let _ = player.stop();
-                    }
-                    self.player = None;
-                    self.media_info = None;
-                    self.view = AppView::MainMenu;
-                    Some(PlayerAction::Seek(0.0, "Playback stopped".to_string()))
+                    self.stop_playback();
                 }
             }
-            KeyCode::Char('o') => {
+            Action::Back => match self.view {
+                AppView::MainMenu => {
+                    // Close help or settings if open
+                    self.show_help = false;
+                    self.show_settings = false;
+                }
+                AppView::Player => return self.run_action(Action::Stop),
+                _ => {
+                    self.view = AppView::MainMenu;
+                    self.set_status("Main Menu".to_string(), StatusKind::Info);
+                }
+            },
+            Action::FileBrowser => {
                 self.view = AppView::FileBrowser;
                 self.refresh_file_list()?;
-                Some(PlayerAction::Seek(0.0, "File Browser".to_string()))
+                self.set_status("File Browser".to_string(), StatusKind::Info);
             }
-            KeyCode::Char('y') => {
+            Action::YoutubeSearch => {
                 self.view = AppView::YoutubeSearch;
                 self.input = String::new();
                 self.input_cursor = 0;
-                Some(PlayerAction::Seek(0.0, "YouTube Search".to_string()))
+                self.set_status("YouTube Search".to_string(), StatusKind::Info);
             }
-            KeyCode::Char('s') => {
-                // Toggle settings dialog
-                self.show_settings = !self.show_settings;
-                // Set view to Settings if dialog is shown
-                if self.show_settings {
-                    self.view = AppView::Settings;
+            Action::Up => {
+                if let Some(selected) = self.file_browser.selected {
+                    if selected > 0 {
+                        self.file_browser.selected = Some(selected - 1);
+                    }
                 }
-                Some(PlayerAction::Seek(
-                    0.0,
-                    if self.show_settings {
-                        "Settings opened".to_string()
-                    } else {
-                        "Settings closed".to_string()
-                    },
-                ))
             }
-            KeyCode::Char('h') => {
-                // Toggle help dialog
-                self.show_help = !self.show_help;
-                Some(PlayerAction::Seek(
-                    0.0,
-                    if self.show_help {
-                        "Help opened".to_string()
+            Action::Down => {
+                if let Some(selected) = self.file_browser.selected {
+                    if selected < self.file_browser.files.len().saturating_sub(1) {
+                        self.file_browser.selected = Some(selected + 1);
+                    }
+                }
+            }
+            Action::Select => {
+                let entry = self
+                    .file_browser
+                    .selected
+                    .and_then(|selected| self.file_browser.files.get(selected))
+                    .map(|entry| (entry.path.clone(), entry.is_dir));
+                if let Some((path, is_dir)) = entry {
+                    if is_dir {
+                        // Navigate to directory
+                        self.navigate_to(&path)?;
                     } else {
-                        "Help closed".to_string()
-                    },
-                ))
+                        // Open file
+                        self.open_media(path.to_str().unwrap_or_default())?;
+                    }
+                }
             }
-            _ => None,
-        };
-
-        // Now take action based on what we determined above
-        if let Some(action) = action {
-            match action {
-                PlayerAction::Seek(_, message) => {
-                    self.set_status(message, Color::Blue);
+            Action::ParentDir => {
+                // Navigate up a directory
+                if let Some(parent) = self.file_browser.current_dir.parent() {
+                    self.navigate_to(&parent.to_path_buf())?;
                 }
-                PlayerAction::TogglePause(is_paused) => {
-                    self.set_status(
-                        (if is_paused { "Paused" } else { "Playing" }).to_string(),
-                        Color::Green,
-                    );
+            }
+            Action::HomeDir => {
+                // Navigate to home directory
+                if let Some(home) = dirs::home_dir() {
+                    self.navigate_to(&home)?;
                 }
             }
+            Action::Filter => {
+                // Filter files
+                self.input = String::new();
+                self.input_cursor = 0;
+                // TODO: Enter filter mode
+            }
+            Action::Script(index) => {
+                let state = self.script_state();
+                self.scripts.set_state(state);
+                if let Err(e) = self.scripts.call_binding(index) {
+                    self.report_problems("Script", &[e.to_string()]);
+                }
+                self.run_script_requests();
+            }
         }
 
         Ok(())
     }
 
-    /// Handle key events in main menu view
-    fn handle_main_menu_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('1') | KeyCode::Char('f') => {
-                self.view = AppView::FileBrowser;
-                self.refresh_file_list()?;
-                self.set_status("File Browser".to_string(), Color::Blue);
+    /// Seek to an absolute position, from a click on the progress bar or `:seek`
+    pub fn seek_to(&mut self, position: f64) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        log::debug!("Seeking to {:.2}s", position);
+
+        match player.seek(position) {
+            Ok(_) => {
+                self.set_status(format!("Seek to {:.1}s", position), StatusKind::Info);
+                self.emit(PlayerEvent::Seek(position));
             }
-            KeyCode::Char('2') | KeyCode::Char('y') => {
-                self.view = AppView::YoutubeSearch;
-                self.input = String::new();
-                self.input_cursor = 0;
-                self.set_status("YouTube Search".to_string(), Color::Blue);
+            Err(e) => {
+                log::warn!("Seek failed: {}", e);
+                self.set_status(format!("Seek failed: {}", e), StatusKind::Error);
             }
-            KeyCode::Char('3') | KeyCode::Char('s') => {
-                self.view = AppView::Settings;
-                self.show_settings = true;
-                self.set_status("Settings".to_string(), Color::Blue);
+        }
+    }
+
+    /// Change the volume by `delta` percent
+    fn change_volume(&mut self, delta: i32) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        let volume = (player.get_volume() + delta).clamp(0, 100);
+        match player.set_volume(volume) {
+            Ok(_) => self.set_status(format!("Volume {}%", volume), StatusKind::Info),
+            Err(e) => self.set_status(format!("Volume failed: {}", e), StatusKind::Error),
+        }
+    }
+
+    /// Seek relative to the current position
+    pub fn seek_by(&mut self, seconds: f64) {
+        let duration = self.media_info.as_ref().map_or(f64::MAX, |info| info.duration);
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        let old_pos = player.get_position();
+        let new_pos = (old_pos + seconds).clamp(0.0, duration);
+        log::debug!("Seeking from {:.2}s to {:.2}s", old_pos, new_pos);
+
+        match player.seek(new_pos) {
+            Ok(_) => {
+                let direction = if seconds < 0.0 { "Back" } else { "Forward" };
+                self.set_status(
+                    format!("{} {}s to {:.1}s", direction, seconds.abs(), new_pos),
+                    StatusKind::Info,
+                );
+                self.emit(PlayerEvent::Seek(new_pos));
             }
-            KeyCode::Char('4') | KeyCode::Char('h') => {
-                self.show_help = true;
-                self.set_status("Help".to_string(), Color::Blue);
+            Err(e) => {
+                log::warn!("Seek failed: {}", e);
+                self.set_status(format!("Seek failed: {}", e), StatusKind::Error);
             }
-            KeyCode::Char('5') | KeyCode::Char('q') => {
-                self.should_quit = true;
+        }
+    }
+
+    /// Handle mouse event
+    ///
+    /// Hit-testing uses the same layout functions the views are drawn with,
+    /// for the full terminal area.
+    pub fn handle_mouse_event(&mut self, mouse: MouseEvent) -> Result<()> {
+        // Dialogs and the command line take the keyboard only
+        if self.is_command_mode() || self.show_help || self.show_settings {
+            return Ok(());
+        }
+
+        let (width, height) = crossterm::terminal::size()?;
+        let area = Rect::new(0, 0, width, height);
+        let position = Position::new(mouse.column, mouse.row);
+
+        // Hit-test against what is on screen, then bring up hidden controls,
+        // moving the mouse is enough for that
+        let result = match self.view {
+            AppView::Player => self.handle_player_mouse(mouse.kind, area, position),
+            AppView::FileBrowser => self.handle_file_browser_mouse(mouse.kind, area, position),
+            AppView::YoutubeSearch => self.handle_youtube_search_mouse(mouse.kind, area, position),
+            AppView::MainMenu | AppView::Settings => Ok(()),
+        };
+        self.show_controls();
+        result
+    }
+
+    /// Record a left click, returning whether it completes a double-click
+    fn is_double_click(&mut self, position: Position) -> bool {
+        let now = Instant::now();
+        let double = self.last_click.is_some_and(|(time, last)| {
+            last == position && now.duration_since(time) <= DOUBLE_CLICK
+        });
+        // A third click starts a new double-click
+        self.last_click = if double { None } else { Some((now, position)) };
+        double
+    }
+
+    /// Handle mouse events in the player view
+    fn handle_player_mouse(
+        &mut self,
+        kind: MouseEventKind,
+        area: Rect,
+        position: Position,
+    ) -> Result<()> {
+        let layout = player_layout(area, self.fullscreen, self.controls_visible());
+        let controls = player_controls_layout(layout[2]);
+        let duration = self.media_info.as_ref().map(|info| info.duration);
+
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double = self.is_double_click(position);
+                // Controls are only drawn once the media is loaded
+                if let Some(duration) = duration.filter(|_| self.controls_visible()) {
+                    if controls[0].contains(position) {
+                        self.seek_drag =
+                            Some(ProgressBar::position_at(controls[0], position.x, duration));
+                        return Ok(());
+                    }
+                    if let Some(button) = PlaybackControls::button_at(controls[2], position) {
+                        // Buttons drawn disabled don't respond
+                        let enabled = self.player.as_ref().is_some_and(|player| {
+                            PlaybackControls::new(player.is_paused())
+                                .position(player.get_position(), duration)
+                                .is_enabled(button)
+                        });
+                        if !enabled {
+                            return Ok(());
+                        }
+                        self.player_control_selected = Some(button);
+                        return self.run_action(CONTROL_ACTIONS[button]);
+                    }
+                }
+                if double && layout[1].contains(position) {
+                    return self.run_action(Action::ToggleFullscreen);
+                }
             }
-            KeyCode::Esc => {
-                // Close help or settings if open
-                if self.show_help {
-                    self.show_help = false;
+            MouseEventKind::Drag(MouseButton::Left) if self.seek_drag.is_some() => {
+                if let Some(duration) = duration {
+                    self.seek_drag =
+                        Some(ProgressBar::position_at(controls[0], position.x, duration));
                 }
-                if self.show_settings {
-                    self.show_settings = false;
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some(target) = self.seek_drag.take() {
+                    self.seek_to(target);
                 }
             }
+            MouseEventKind::ScrollUp => self.change_volume(5),
+            MouseEventKind::ScrollDown => self.change_volume(-5),
             _ => {}
         }
 
         Ok(())
     }
 
-    // Mouse events have been removed for consistency across all app views
-
-    /// Handle key events in file browser view
-    fn handle_file_browser_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Up => {
-                if let Some(selected) = self.file_browser.selected {
-                    if selected > 0 {
-                        self.file_browser.selected = Some(selected - 1);
+    /// Handle mouse events in the file browser, double-click opens
+    fn handle_file_browser_mouse(
+        &mut self,
+        kind: MouseEventKind,
+        area: Rect,
+        position: Position,
+    ) -> Result<()> {
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let list_area = list_view_layout(area)[2];
+                let index = list_index_at(list_area, self.file_browser.offset, 1, position)
+                    .filter(|&index| index < self.file_browser.files.len());
+                if let Some(index) = index {
+                    let double = self.is_double_click(position);
+                    self.file_browser.selected = Some(index);
+                    if double {
+                        return self.run_action(Action::Select);
                     }
                 }
             }
-            KeyCode::Down => {
-                if let Some(selected) = self.file_browser.selected {
-                    if selected < self.file_browser.files.len().saturating_sub(1) {
-                        self.file_browser.selected = Some(selected + 1);
-                    }
-                }
-            }
-            KeyCode::Enter => {
-                if let Some(selected) = self.file_browser.selected {
-                    if let Some(entry) = self.file_browser.files.get(selected) {
-                        if entry.is_dir {
-                            // Navigate to directory
-                            self.navigate_to(&entry.path)?;
-                        } else {
-                            // Open file
-                            self.open_media(entry.path.to_str().unwrap_or_default())?;
+            MouseEventKind::ScrollUp => return self.run_action(Action::Up),
+            MouseEventKind::ScrollDown => return self.run_action(Action::Down),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Handle mouse events in the YouTube search results, double-click plays
+    fn handle_youtube_search_mouse(
+        &mut self,
+        kind: MouseEventKind,
+        area: Rect,
+        position: Position,
+    ) -> Result<()> {
+        let last = self.youtube_search.results.len().saturating_sub(1);
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let list_area = list_view_layout(area)[2];
+                let offset = self.youtube_search.offset;
+                let index = list_index_at(list_area, offset, YOUTUBE_RESULT_HEIGHT, position)
+                    .filter(|&index| index < self.youtube_search.results.len());
+                if let Some(index) = index {
+                    let double = self.is_double_click(position);
+                    self.youtube_search.selected = Some(index);
+                    if double {
+                        if let Some(result) = self.youtube_search.results.get(index).cloned() {
+                            self.open_media(&result.id)?;
                         }
                     }
                 }
             }
-            KeyCode::Backspace => {
-                // Navigate up a directory
-                let current_dir = &self.file_browser.current_dir;
-                if let Some(parent) = current_dir.parent() {
-                    self.navigate_to(&parent.to_path_buf())?;
+            MouseEventKind::ScrollUp => {
+                if let Some(selected) = self.youtube_search.selected {
+                    self.youtube_search.selected = Some(selected.saturating_sub(1));
                 }
             }
-            KeyCode::Char('~') => {
-                // Navigate to home directory
-                if let Some(home) = dirs::home_dir() {
-                    self.navigate_to(&home)?;
+            MouseEventKind::ScrollDown => {
+                if let Some(selected) = self.youtube_search.selected {
+                    self.youtube_search.selected = Some((selected + 1).min(last));
                 }
             }
-            KeyCode::Char('/') => {
-                // Filter files
-                self.input = String::new();
-                self.input_cursor = 0;
-                // TODO: Enter filter mode
-            }
-            KeyCode::Esc => {
-                self.view = AppView::MainMenu;
-                self.set_status("Main Menu".to_string(), Color::Blue);
-            }
             _ => {}
         }
 
         Ok(())
     }
 
-    // Mouse events have been removed for consistency across all app views
-
     /// Handle key events in YouTube search view
     fn handle_youtube_search_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
@@ -716,7 +1343,7 @@ impl App {
                     let query = self.input.clone();
                     self.youtube_search.query = query.clone();
                     self.youtube_search.searching = true;
-                    self.set_status(format!("Searching YouTube for '{}'...", query), Color::Yellow);
+                    self.set_status(format!("Searching YouTube for '{}'...", query), StatusKind::Warning);
                     
                     // Create search results (would connect to API in production)
                     self.youtube_search.results.clear();
@@ -727,6 +1354,7 @@ impl App {
                         title: format!("{} - Never Gonna Give You Up", query),
                         duration: "3:32".to_string(),
                         thumbnail: None,
+                        downloaded_thumbnail: None,
                         channel: "Rick Astley".to_string(),
                     });
                     
@@ -735,6 +1363,7 @@ impl App {
                         title: format!("{} - Popular Video", query),
                         duration: "4:20".to_string(),
                         thumbnail: None,
+                        downloaded_thumbnail: None,
                         channel: "YouTube Channel".to_string(),
                     });
                     
@@ -743,6 +1372,7 @@ impl App {
                         title: format!("{} - Me at the zoo", query),
                         duration: "0:19".to_string(),
                         thumbnail: None,
+                        downloaded_thumbnail: None,
                         channel: "jawed".to_string(),
                     });
                     
@@ -750,7 +1380,7 @@ impl App {
                     self.youtube_search.selected = Some(0);
                     self.youtube_search.searching = false;
                     self.set_status(format!("Found {} results for '{}'", 
-                        self.youtube_search.results.len(), query), Color::Green);
+                        self.youtube_search.results.len(), query), StatusKind::Success);
                     
                     self.input = String::new();
                     self.input_cursor = 0;
@@ -797,7 +1427,7 @@ impl App {
             KeyCode::Esc => {
                 // Return to main menu
                 self.view = AppView::MainMenu;
-                self.set_status("Main Menu".to_string(), Color::Blue);
+                self.set_status("Main Menu".to_string(), StatusKind::Info);
             }
             _ => {}
         }
@@ -805,8 +1435,6 @@ impl App {
         Ok(())
     }
 
-    // Mouse events have been removed for consistency across all app views
-
     /// Handle key events in settings view
     fn handle_settings_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
@@ -828,6 +1456,20 @@ impl App {
                     RenderMethod::ITerm => RenderMethod::Auto,
                 };
             }
+            KeyCode::Char('8') => {
+                // Cycle through output byte budgets, low ones suit slow SSH links
+                self.render_config.max_bytes_per_second =
+                    match self.render_config.max_bytes_per_second {
+                        None => Some(256 * 1024),
+                        Some(budget) if budget < 1024 * 1024 => Some(1024 * 1024),
+                        Some(budget) if budget < 4 * 1024 * 1024 => Some(4 * 1024 * 1024),
+                        Some(_) => None,
+                    };
+            }
+            KeyCode::Char('9') => {
+                // Cycle through scaling filters
+                self.render_config.scale_filter = self.render_config.scale_filter.next();
+            }
             _ => {}
         }
 
@@ -836,8 +1478,15 @@ impl App {
 
     /// Update application state
     pub fn update(&mut self) -> Result<()> {
-        // Always keep UI visible - this prevents flickering issues
-        self.show_ui = true;
+        // Hide the controls once input stops in fullscreen, unless paused or
+        // a dialog is open
+        let paused = self.player.as_ref().is_some_and(|p| p.is_paused());
+        let busy = paused || self.show_help || self.show_settings || self.command_mode;
+        let idle = self.last_ui_interaction.elapsed() > CONTROLS_TIMEOUT;
+        if self.fullscreen && self.show_ui && idle && !busy {
+            self.show_ui = false;
+            self.relayout();
+        }
 
         // Clear status message after timeout
         if let Some((_, time, _)) = &self.status_message {
@@ -852,4 +1501,65 @@ impl App {
         }
 
         // Make sure YouTube search view is properly set up
-        if self.view
\ No newline at end of file
+        if self.view == AppView::YoutubeSearch
+            && self.youtube_search.selected.is_none()
+            && !self.youtube_search.results.is_empty()
+        {
+            self.youtube_search.selected = Some(0);
+        }
+
+        // Refresh buffer status for the progress bar
+        self.buffer_status = self
+            .player
+            .as_ref()
+            .and_then(|p| p.as_any().downcast_ref::<LocalMediaPlayer>())
+            .and_then(|p| p.get_buffer_status());
+
+        // Refresh diagnostics for the stats overlay
+        self.output_rate
+            .update(self.terminal_bytes.load(Ordering::Relaxed));
+        self.playback_stats = self.player.as_ref().and_then(|p| p.playback_stats());
+
+        // Refresh what D-Bus and remote clients see
+        #[cfg(feature = "mpris")]
+        if self.mpris.is_some() {
+            let status = self.mpris_status();
+            if let Some(mpris) = &mut self.mpris {
+                mpris.update(status);
+            }
+        }
+        #[cfg(feature = "remote")]
+        if self.remote.is_some() {
+            let status = self.remote_status();
+            if let Some(remote) = &mut self.remote {
+                remote.update(status);
+            }
+        }
+
+        // Scripts and IPC clients hear about the end once, and get ticks while playing
+        let playback = self
+            .player
+            .as_ref()
+            .map(|p| (p.is_finished(), p.is_paused(), p.get_position()));
+        if let Some((finished, paused, position)) = playback {
+            if finished && !self.eof_reported {
+                self.eof_reported = true;
+                self.emit(PlayerEvent::Eof);
+
+                // Go on with the queue
+                let next = self.queue.pop_front();
+                if let Some(Err(e)) = next.map(|path| self.open_media(&path)) {
+                    self.set_status(format!("Queue: {}", e), StatusKind::Error);
+                }
+            } else if !finished {
+                self.eof_reported = false;
+            }
+            if !finished && !paused && self.last_tick_event.elapsed() >= script::TICK_INTERVAL {
+                self.last_tick_event = Instant::now();
+                self.emit(PlayerEvent::Tick(position));
+            }
+        }
+
+        Ok(())
+    }
+}
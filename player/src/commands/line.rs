@@ -0,0 +1,569 @@
+// The text being typed after `:`, with its history and completion. Editing
+// only, running the line is up to mod.rs.
+
+use super::parse::{self, Word};
+use super::spec::{self, Arg, Command};
+use std::path::{Path, PathBuf};
+
+/// Entries kept in the history file
+const HISTORY_LIMIT: usize = 500;
+
+/// Where command history is kept between runs
+pub fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("tui_player").join("history"))
+}
+
+/// Completions offered for the word before the cursor
+#[derive(Debug, Default)]
+struct Completion {
+    /// Byte offset of the word being completed
+    start: usize,
+    candidates: Vec<String>,
+    /// The candidate in the line, None until the first Tab cycles
+    selected: Option<usize>,
+}
+
+/// The command being typed and where to show its hint
+#[derive(Debug, Clone, Copy)]
+pub struct Hint {
+    pub command: &'static Command,
+    /// The parameter the cursor is on, if any
+    pub param: Option<usize>,
+    /// What the command, or the setting being set, does
+    pub help: &'static str,
+}
+
+/// The command line's text, cursor, history and completions
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    input: String,
+    /// Byte offset into `input`, always on a character boundary
+    cursor: usize,
+    /// Oldest first
+    history: Vec<String>,
+    /// Entry shown while walking the history
+    history_index: Option<usize>,
+    /// What was typed before walking the history, also the prefix entries
+    /// must start with
+    draft: String,
+    completion: Option<Completion>,
+    /// Why the last line failed, shown until the next edit
+    error: Option<String>,
+    history_file: Option<PathBuf>,
+    /// Aliases and macros, completed along with the built-in commands
+    user_commands: Vec<String>,
+}
+
+impl CommandLine {
+    /// A command line with the history from the last run
+    pub fn load() -> Self {
+        let mut line = Self::default();
+        if let Some(path) = history_path() {
+            line.load_history(&path);
+        }
+        line
+    }
+
+    /// Read the history from a file and keep adding to it
+    pub fn load_history(&mut self, path: &Path) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => self.history = text.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Failed to read {}: {}", path.display(), e),
+        }
+        self.history_file = Some(path.to_path_buf());
+    }
+
+    /// Start over with an empty line
+    pub fn clear(&mut self) {
+        self.set_input(String::new());
+        self.error = None;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Completions on offer and which one is in the line
+    pub fn completions(&self) -> (&[String], Option<usize>) {
+        match &self.completion {
+            Some(completion) => (&completion.candidates, completion.selected),
+            None => (&[], None),
+        }
+    }
+
+    /// Offer these alias and macro names in completions
+    pub fn set_user_commands(&mut self, names: Vec<String>) {
+        self.user_commands = names;
+    }
+
+    /// Take the line to run it, adding it to the history
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.clear();
+        if !line.is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            self.save_history();
+        }
+        line
+    }
+
+    /// Put a line back after it failed, with the reason
+    pub fn fail(&mut self, line: String, error: String) {
+        self.set_input(line);
+        self.error = Some(error);
+    }
+
+    fn save_history(&mut self) {
+        let Some(path) = &self.history_file else {
+            return;
+        };
+        let skip = self.history.len().saturating_sub(HISTORY_LIMIT);
+        self.history.drain(..skip);
+        let mut text = self.history.join("\n");
+        text.push('\n');
+        let saved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, text));
+        if let Err(e) = saved {
+            log::warn!("Failed to save history to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Replace the whole line, the cursor going to the end
+    fn set_input(&mut self, input: String) {
+        self.cursor = input.len();
+        self.input = input;
+        self.edited();
+    }
+
+    /// Forget state that the last edit made stale
+    fn edited(&mut self) {
+        self.completion = None;
+        self.history_index = None;
+        self.error = None;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.input.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        self.edited();
+    }
+
+    /// Delete the character before the cursor
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.input[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.input.remove(self.cursor);
+            self.edited();
+        }
+    }
+
+    /// Delete the character under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.input.len() {
+            self.input.remove(self.cursor);
+            self.edited();
+        }
+    }
+
+    /// Delete the word before the cursor
+    pub fn delete_word(&mut self) {
+        let before = self.input[..self.cursor].trim_end();
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        self.input.replace_range(start..self.cursor, "");
+        self.cursor = start;
+        self.edited();
+    }
+
+    /// Delete everything before the cursor
+    pub fn delete_to_start(&mut self) {
+        self.input.replace_range(..self.cursor, "");
+        self.cursor = 0;
+        self.edited();
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.input[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.input[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.input.len();
+    }
+
+    /// Show the previous history entry starting with what was typed
+    pub fn history_back(&mut self) {
+        if self.history_index.is_none() {
+            self.draft = self.input.clone();
+        }
+        let end = self.history_index.unwrap_or(self.history.len());
+        let found = self.history[..end]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.draft));
+        if let Some(index) = found {
+            self.show_history(Some(index));
+        }
+    }
+
+    /// Show the next history entry, or what was typed after the newest
+    pub fn history_forward(&mut self) {
+        let Some(current) = self.history_index else {
+            return;
+        };
+        let found = self.history[current + 1..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.draft))
+            .map(|offset| current + 1 + offset);
+        self.show_history(found);
+    }
+
+    fn show_history(&mut self, index: Option<usize>) {
+        let input = match index {
+            Some(index) => self.history[index].clone(),
+            None => self.draft.clone(),
+        };
+        self.set_input(input);
+        self.history_index = index;
+    }
+
+    /// Complete the word before the cursor, going through the candidates on
+    /// each call, backwards if `forward` is false
+    ///
+    /// A single candidate is taken right away and the first call with
+    /// several only extends the word as far as they agree.
+    pub fn complete(&mut self, forward: bool) {
+        if let Some(mut completion) = self.completion.take() {
+            let count = completion.candidates.len();
+            let next = match (completion.selected, forward) {
+                (None, true) => 0,
+                (None, false) => count - 1,
+                (Some(i), true) => (i + 1) % count,
+                (Some(i), false) => (i + count - 1) % count,
+            };
+            let replacement = quote_candidate(&completion.candidates[next]);
+            self.input
+                .replace_range(completion.start..self.cursor, &replacement);
+            self.cursor = completion.start + replacement.len();
+            completion.selected = Some(next);
+            self.completion = Some(completion);
+            return;
+        }
+
+        let (start, candidates) = completions(&self.input[..self.cursor], &self.user_commands);
+        let replacement = match candidates.as_slice() {
+            [] => return,
+            [only] if only.ends_with('/') => quote_candidate(only),
+            [only] => quote_candidate(only) + " ",
+            several => parse::quote_partial(&common_prefix(several)),
+        };
+        // Keep what was typed when the candidates only differ in case
+        if replacement.len() > self.cursor - start || candidates.len() == 1 {
+            self.input.replace_range(start..self.cursor, &replacement);
+            self.cursor = start + replacement.len();
+        }
+        self.history_index = None;
+        self.error = None;
+        if candidates.len() > 1 {
+            self.completion = Some(Completion {
+                start,
+                candidates,
+                selected: None,
+            });
+        }
+    }
+
+    /// The command under the cursor and which of its arguments is being typed
+    pub fn hint(&self) -> Option<Hint> {
+        let (_, words, index) = current_word(&self.input[..self.cursor]);
+        let command = spec::find(&words.first()?.text)?;
+        // Text takes the rest of the line, whatever its word count
+        let last = command.params.len().checked_sub(1);
+        let takes_rest = |i: usize| matches!(command.params[i].arg, Arg::Text | Arg::Rest);
+        let param = match index.checked_sub(1) {
+            Some(i) if i < command.params.len() => Some(i),
+            Some(_) if last.is_some_and(takes_rest) => last,
+            _ => None,
+        };
+        // Say what a setting is once it is named
+        let help = match param.map(|i| command.params[i].arg) {
+            Some(Arg::SettingValue) => words.get(1).and_then(|word| spec::setting(&word.text)),
+            _ => None,
+        }
+        .map_or(command.help, |setting| setting.help);
+        Some(Hint {
+            command,
+            param,
+            help,
+        })
+    }
+}
+
+/// Split the text before the cursor into the words of its command, and find
+/// the word the cursor is in
+///
+/// Returns the byte offset where that word starts, the words, and the
+/// word's index, which is one past the last word after a space.
+fn current_word(before_cursor: &str) -> (usize, Vec<Word>, usize) {
+    let &(offset, command) = parse::split_commands(before_cursor)
+        .last()
+        .expect("split_commands always returns a command");
+    let words = parse::words(command);
+    if ends_in_word(command) {
+        let index = words.len() - 1;
+        (offset + words[index].start, words, index)
+    } else {
+        let index = words.len();
+        (before_cursor.len(), words, index)
+    }
+}
+
+/// Whether the last character of a command belongs to a word, that is it
+/// is not a space outside quotes
+fn ends_in_word(command: &str) -> bool {
+    let mut in_word = false;
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in command.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                in_word = false;
+                continue;
+            }
+            _ => {}
+        }
+        in_word = true;
+    }
+    in_word
+}
+
+/// Where the word before the cursor starts and what it could become
+fn completions(before_cursor: &str, user_commands: &[String]) -> (usize, Vec<String>) {
+    let (start, words, index) = current_word(before_cursor);
+    let partial = words.get(index).map_or("", |word| word.text.as_str());
+    let (arg, names) = spec::candidates(&words, index);
+    let user_commands = user_commands.iter().map(String::as_str);
+    let names: Vec<&str> = match arg {
+        Some(Arg::UserCommand) => user_commands.collect(),
+        None if index == 0 => names.into_iter().chain(user_commands).collect(),
+        _ => names,
+    };
+    let candidates = match arg {
+        Some(Arg::Path) => paths(partial),
+        _ => {
+            let lower = partial.to_lowercase();
+            names
+                .into_iter()
+                .filter(|name| name.starts_with(&lower))
+                .map(str::to_string)
+                .collect()
+        }
+    };
+    (start, candidates)
+}
+
+/// Files and directories whose path starts with `partial`, directories
+/// ending in `/`
+fn paths(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+    let search = match dir {
+        "" => ".".to_string(),
+        dir => parse::expand_home(dir),
+    };
+    let Ok(entries) = std::fs::read_dir(&search) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Quote a candidate for the line, directories staying open for the next
+/// completion
+fn quote_candidate(candidate: &str) -> String {
+    if candidate.ends_with('/') {
+        parse::quote_partial(candidate)
+    } else {
+        parse::quote(candidate)
+    }
+}
+
+/// The longest start all candidates share
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = candidates[0].as_str();
+    for candidate in &candidates[1..] {
+        let end = prefix
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(candidate.len()), |((i, _), _)| i);
+        prefix = &prefix[..end];
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> CommandLine {
+        let mut line = CommandLine::default();
+        text.chars().for_each(|c| line.insert(c));
+        line
+    }
+
+    #[test]
+    fn edits_at_the_cursor() {
+        let mut line = typed("sek 10");
+        (0..4).for_each(|_| line.left());
+        line.insert('e');
+        assert_eq!(line.input(), "seek 10");
+        line.end();
+        line.delete_word();
+        assert_eq!(line.input(), "seek ");
+        line.backspace();
+        line.home();
+        line.delete();
+        assert_eq!(line.input(), "eek");
+
+        let mut line = typed("voilà");
+        line.backspace();
+        line.left();
+        line.insert('ü');
+        assert_eq!(line.input(), "voiül");
+    }
+
+    #[test]
+    fn walks_history_by_prefix() {
+        let mut line = CommandLine::default();
+        for entry in ["seek 10", "gpu off", "seek 20"] {
+            entry.chars().for_each(|c| line.insert(c));
+            line.submit();
+        }
+        line.submit();
+        assert_eq!(line.history.len(), 3);
+
+        "se".chars().for_each(|c| line.insert(c));
+        line.history_back();
+        assert_eq!(line.input(), "seek 20");
+        line.history_back();
+        assert_eq!(line.input(), "seek 10");
+        line.history_back();
+        assert_eq!(line.input(), "seek 10");
+        line.history_forward();
+        assert_eq!(line.input(), "seek 20");
+        line.history_forward();
+        assert_eq!(line.input(), "se");
+    }
+
+    #[test]
+    fn completes_commands_and_arguments() {
+        let mut line = typed("rend");
+        line.complete(true);
+        assert_eq!(line.input(), "renderer ");
+        "k".chars().for_each(|c| line.insert(c));
+        line.complete(true);
+        assert_eq!(line.input(), "renderer kitty ");
+
+        let mut line = typed("gpu on; set f");
+        line.complete(true);
+        assert_eq!(line.input(), "gpu on; set f");
+        assert_eq!(line.completions().0, ["fps", "filter", "fullscreen"]);
+        line.complete(true);
+        assert_eq!(line.input(), "gpu on; set fps");
+        line.complete(false);
+        assert_eq!(line.input(), "gpu on; set fullscreen");
+        line.insert(' ');
+        assert!(line.completions().0.is_empty());
+        line.complete(true);
+        assert_eq!(line.completions().0, ["on", "off"]);
+        assert_eq!(line.input(), "gpu on; set fullscreen o");
+    }
+
+    #[test]
+    fn completes_aliases() {
+        let mut line = typed("d");
+        line.set_user_commands(vec!["demo".to_string()]);
+        line.complete(true);
+        assert_eq!(line.input(), "demo ");
+
+        let mut line = typed("unalias ");
+        line.set_user_commands(vec!["demo".to_string(), "k".to_string()]);
+        line.complete(true);
+        assert_eq!(line.completions().0, ["demo", "k"]);
+    }
+
+    #[test]
+    fn completes_paths() {
+        let dir = std::env::temp_dir().join(format!("tui_player_complete_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("My Videos")).unwrap();
+        std::fs::write(dir.join("movie.mp4"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+        let dir = dir.to_string_lossy().into_owned();
+
+        let mut line = typed(&format!("open {}/", dir));
+        line.complete(true);
+        assert_eq!(line.completions().0.len(), 2);
+        line.complete(true);
+        assert_eq!(line.input(), format!("open \"{}/My Videos/", dir));
+        line.end();
+        line.completion = None;
+        line.complete(true);
+        assert!(line.completions().0.is_empty());
+
+        let mut line = typed(&format!("open {}/m", dir));
+        line.complete(true);
+        assert_eq!(line.input(), format!("open {}/movie.mp4 ", dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn hints_the_argument_being_typed() {
+        let hint = typed("vol").hint().unwrap();
+        assert_eq!((hint.command.name, hint.param), ("volume", None));
+        assert_eq!(typed("set gpu").hint().unwrap().param, Some(0));
+        let hint = typed("set gpu ").hint().unwrap();
+        assert_eq!((hint.param, hint.help), (Some(1), "GPU scaling"));
+        assert_eq!(typed("pause; yt rust talks").hint().unwrap().param, Some(0));
+        assert!(typed("nope ").hint().is_none());
+        assert!(typed("seek 10;").hint().is_none());
+    }
+}
@@ -1,72 +1,176 @@
+pub mod line;
+pub mod parse;
+pub mod rc;
+pub mod spec;
+pub mod user;
+
 use anyhow::{Result, anyhow};
-use core::{render::RenderMethod};
+use core::{render::RenderMethod, video::scale::ScaleFilter};
 
 use crate::app::App;
+use crate::keymap::Action;
+use crate::ui::theme::StatusKind;
+use parse::Time;
+use spec::{COMMANDS, Value};
+use user::UserCommand;
 
 /// Command handler for the application
 pub struct CommandHandler;
 
 impl CommandHandler {
-    /// Parse and execute a command
-    pub fn execute(app: &mut App, command_str: &str) -> Result<()> {
-        let parts: Vec<&str> = command_str.trim().splitn(2, ' ').collect();
-        let cmd = parts[0].to_lowercase();
-        let args = parts.get(1).map(|s| s.trim());
-        
-        match cmd.as_str() {
-            "seek" | "s" => {
-                if let Some(args) = args {
-                    if let Ok(position) = args.parse::<f64>() {
-                        if let Some(player) = &mut app.player {
-                            player.seek(position)?;
-                        }
-                    } else {
-                        return Err(anyhow!("Invalid position: {}", args));
-                    }
-                } else {
-                    return Err(anyhow!("Seek command requires a position argument"));
+    /// Parse and execute a line of `;` separated commands
+    ///
+    /// Stops at the first command that fails, the ones before it stay done.
+    pub fn execute(app: &mut App, line: &str) -> Result<()> {
+        for (_, command) in parse::split_commands(line) {
+            Self::run(app, command)?;
+        }
+        Ok(())
+    }
+
+    /// Parse and execute a single command
+    fn run(app: &mut App, command_str: &str) -> Result<()> {
+        let words = parse::words(command_str);
+        let Some(name) = words.first() else {
+            // Empty command, do nothing
+            return Ok(());
+        };
+        let Some(command) = spec::find(&name.text) else {
+            return Self::run_user(app, &name.text, &words[1..]);
+        };
+        let args = command
+            .parse(&words[1..])
+            .map_err(|e| anyhow!("{}: {}", command.name, e))?;
+        log::debug!("Command {} {:?}", command.name, args);
+
+        let mut args = args.into_iter();
+        let mut arg = || args.next().flatten();
+        match command.name {
+            "seek" => {
+                match arg() {
+                    Some(Value::Time(Time::At(position))) => app.seek_to(position),
+                    Some(Value::Time(Time::By(offset))) => app.seek_by(offset),
+                    _ => unreachable!("seek takes a time"),
                 }
             },
-            "play" | "p" => {
-                if let Some(player) = &mut app.player {
-                    if player.is_paused() {
-                        player.toggle_pause();
-                    }
+            // Through the app's actions, which send the events
+            "play" => app.set_paused(false)?,
+            "pause" => app.set_paused(true)?,
+            "toggle" => app.run_action(Action::TogglePause)?,
+            "restart" => app.run_action(Action::Restart)?,
+            "volume" => {
+                let volume = number(arg()) as i32;
+                let player = app.player.as_mut().ok_or_else(|| anyhow!("volume: nothing is playing"))?;
+                player.set_volume(volume)?;
+                app.set_status(format!("Volume {}%", volume), StatusKind::Info);
+            },
+            "open" => {
+                if let Some(path) = text(arg()) {
+                    app.open_media(&path)?;
+                } else {
+                    app.view = crate::app::AppView::FileBrowser;
+                    app.refresh_file_list()?;
                 }
             },
-            "pause" => {
-                if let Some(player) = &mut app.player {
-                    if !player.is_paused() {
-                        player.toggle_pause();
-                    }
+            "queue" => {
+                if let Some(path) = text(arg()) {
+                    app.queue.push_back(path.clone());
+                    app.set_status(
+                        format!("Queued {} ({} waiting)", path, app.queue.len()),
+                        StatusKind::Info
+                    );
+                } else {
+                    let queue: Vec<&str> = app.queue.iter().map(String::as_str).collect();
+                    let list = if queue.is_empty() { "empty".to_string() } else { queue.join(", ") };
+                    app.set_status(format!("Queue: {}", list), StatusKind::Info);
                 }
             },
-            "toggle" | "t" => {
-                if let Some(player) = &mut app.player {
-                    player.toggle_pause();
+            "next" => {
+                app.play_next()?;
+            },
+            "unqueue" => {
+                app.queue.clear();
+                app.set_status("Queue emptied", StatusKind::Info);
+            },
+            "youtube" => {
+                youtube(app, text(arg()))?;
+            },
+            "quality" => {
+                set(app, "youtube-quality", arg())?;
+            },
+            "renderer" => {
+                set(app, "renderer", arg())?;
+            },
+            "gpu" => {
+                let enable = switch(arg()).unwrap_or(!app.render_config.enable_gpu);
+                set(app, "gpu", Some(Value::Switch(enable)))?;
+            },
+            "fullscreen" => {
+                let enable = switch(arg()).unwrap_or(!app.fullscreen);
+                set(app, "fullscreen", Some(Value::Switch(enable)))?;
+            },
+            "theme" => {
+                if let Some(name) = arg() {
+                    set(app, "theme", Some(name))?;
+                } else {
+                    let names: Vec<&str> = spec::theme_names().collect();
+                    app.set_status(
+                        format!("Theme: {} (available: {})", app.theme_name, names.join(", ")),
+                        StatusKind::Info
+                    );
                 }
             },
-            "volume" | "vol" | "v" => {
-                if let Some(args) = args {
-                    if let Ok(volume) = args.parse::<u8>() {
-                        // TODO: Implement volume control in the MediaPlayer trait
-                        app.set_status(format!("Volume set to {}", volume), ratatui::style::Color::Yellow);
-                    } else {
-                        return Err(anyhow!("Invalid volume: {}", args));
+            "set" => {
+                let name = text(arg()).unwrap_or_default();
+                set(app, &name, arg())?;
+            },
+            "speed" => {
+                set(app, "speed", arg())?;
+            },
+            "source" => {
+                let path = text(arg()).unwrap_or_default();
+                source(app, std::path::Path::new(&path))?;
+            },
+            "script" => {
+                let path = text(arg()).unwrap_or_default();
+                app.run_script(std::path::Path::new(&path))?;
+            },
+            "alias" | "macro" => {
+                let is_macro = command.name == "macro";
+                match (text(arg()), text(arg())) {
+                    (None, _) => {
+                        let defined = app.user_commands.describe(is_macro);
+                        let list = if defined.is_empty() { "none".to_string() } else { defined.join(", ") };
+                        app.set_status(
+                            format!("{}: {}", if is_macro { "Macros" } else { "Aliases" }, list),
+                            StatusKind::Info
+                        );
+                    }
+                    (Some(name), None) => {
+                        let body = match app.user_commands.get(&name) {
+                            Some(UserCommand::Alias(body) | UserCommand::Macro(body)) => body.clone(),
+                            None => return Err(anyhow!("{}: {} is not defined", command.name, name)),
+                        };
+                        app.set_status(format!("{} = {}", name, body), StatusKind::Info);
+                    }
+                    (Some(name), Some(body)) => {
+                        if spec::find(&name).is_some() {
+                            return Err(anyhow!("{}: {} is a built-in command", command.name, name));
+                        }
+                        let user_command = if is_macro { UserCommand::Macro(body) } else { UserCommand::Alias(body) };
+                        app.user_commands.define(&name, user_command);
+                        app.command_line.set_user_commands(app.user_commands.names());
                     }
-                } else {
-                    return Err(anyhow!("Volume command requires a level argument (0-100)"));
                 }
             },
-            "open" | "o" => {
-                if let Some(path) = args {
-                    app.open_media(path)?;
-                } else {
-                    app.view = crate::app::AppView::FileBrowser;
-                    app.refresh_file_list()?;
+            "unalias" => {
+                let name = text(arg()).unwrap_or_default();
+                if !app.user_commands.remove(&name) {
+                    return Err(anyhow!("unalias: {} is not defined", name));
                 }
+                app.command_line.set_user_commands(app.user_commands.names());
             },
-            "menu" | "home" | "main" => {
+            "menu" => {
                 // Return to main menu from any view
                 if app.view == crate::app::AppView::Player {
                     // Stop playback if in player view
@@ -75,118 +179,18 @@ impl CommandHandler {
                     }
                     app.player = None;
                     app.media_info = None;
+                    app.media_path = None;
                 }
                 app.view = crate::app::AppView::MainMenu;
-                app.set_status("Main Menu", ratatui::style::Color::Blue);
-            },
-            "youtube" | "yt" | "y" => {
-                if let Some(query) = args {
-                    // If it looks like a YouTube URL or ID, open it directly
-                    if query.contains("youtube.com") || query.contains("youtu.be") || query.len() == 11 {
-                        app.open_media(query)?;
-                    } else {
-                        // Otherwise, set it as a search query and perform a search
-                        app.view = crate::app::AppView::YoutubeSearch;
-                        app.input = query.to_string();
-                        app.input_cursor = query.len();
-                        
-                        // Generate some mock search results
-                        app.youtube_search.results.clear();
-                        app.youtube_search.searching = true;
-                        app.set_status(format!("Searching for '{}'...", query), ratatui::style::Color::Yellow);
-                        
-                        // Use the app's internal search mechanism (real YouTube search)
-                        app.youtube_search.query = query.to_string();
-                        app.youtube_search.searching = true;
-                        
-                        // This will trigger the actual search in the background
-                        // Results will be populated by the app's update method
-                        app.set_status(format!("Searching YouTube for '{}'...", query), 
-                                     ratatui::style::Color::Yellow);
-                        
-                        // Add a loading placeholder
-                        app.youtube_search.results.push(crate::app::YoutubeResult {
-                            id: "loading".to_string(),
-                            title: "Searching YouTube...".to_string(),
-                            duration: "".to_string(),
-                            thumbnail: None,
-                            downloaded_thumbnail: None,
-                            channel: "Please wait".to_string(),
-                        });
-                        
-                        app.youtube_search.selected = Some(0);
-                    }
-                } else {
-                    app.view = crate::app::AppView::YoutubeSearch;
-                }
-            },
-            "quality" | "q" => {
-                if let Some(args) = args {
-                    if let Ok(quality) = args.parse::<u8>() {
-                        if quality <= 5 {
-                            app.youtube_config.quality = quality;
-                            app.set_status(format!("YouTube quality set to {}", quality), ratatui::style::Color::Green);
-                        } else {
-                            return Err(anyhow!("Quality must be between 0 and 5"));
-                        }
-                    } else {
-                        return Err(anyhow!("Invalid quality: {}", args));
-                    }
-                } else {
-                    return Err(anyhow!("Quality command requires a value argument (0-5)"));
-                }
-            },
-            "renderer" | "render" | "r" => {
-                if let Some(args) = args {
-                    let render_method = match args {
-                        "auto" => RenderMethod::Auto,
-                        "blocks" => RenderMethod::Blocks,
-                        "kitty" => RenderMethod::Kitty,
-                        "sixel" => RenderMethod::Sixel,
-                        "iterm" => RenderMethod::ITerm,
-                        _ => return Err(anyhow!("Unknown render method: {}", args)),
-                    };
-                    
-                    app.render_config.method = render_method;
-                    app.set_status(format!("Renderer set to {:?}", render_method), ratatui::style::Color::Green);
-                } else {
-                    return Err(anyhow!("Renderer command requires a method argument"));
-                }
-            },
-            "restart" => {
-                if let Some(player) = &mut app.player {
-                    player.seek(0.0)?;
-                }
+                app.set_status("Main Menu", StatusKind::Info);
             },
-            "gpu" => {
-                if let Some(args) = args {
-                    let enable = match args {
-                        "on" | "true" | "1" | "enable" | "enabled" => true,
-                        "off" | "false" | "0" | "disable" | "disabled" => false,
-                        _ => return Err(anyhow!("Invalid GPU setting: {}", args)),
-                    };
-                    
-                    app.render_config.enable_gpu = enable;
-                    app.set_status(
-                        format!("GPU acceleration {}", if enable { "enabled" } else { "disabled" }), 
-                        ratatui::style::Color::Green
-                    );
-                } else {
-                    // Toggle current setting
-                    app.render_config.enable_gpu = !app.render_config.enable_gpu;
-                    app.set_status(
-                        format!("GPU acceleration {}", if app.render_config.enable_gpu { "enabled" } else { "disabled" }), 
-                        ratatui::style::Color::Green
-                    );
-                }
-            },
-            "settings" | "config" => {
+            "settings" => {
                 app.show_settings = true;
             },
-            "help" | "h" | "?" => {
+            "help" => {
                 app.show_help = true;
             },
-            "quit" | "exit" => {
+            "quit" => {
                 if app.view == crate::app::AppView::Player {
                     // Stop playback and return to main menu
                     if let Some(player) = &mut app.player {
@@ -194,44 +198,200 @@ impl CommandHandler {
                     }
                     app.player = None;
                     app.media_info = None;
+                    app.media_path = None;
                     app.view = crate::app::AppView::MainMenu;
-                    app.set_status("Returned to main menu", ratatui::style::Color::Blue);
+                    app.set_status("Returned to main menu", StatusKind::Info);
                 } else {
                     // Exit application
                     app.should_quit = true;
                 }
             },
-            "" => {
-                // Empty command, do nothing
-            },
-            _ => {
-                return Err(anyhow!("Unknown command: {}", cmd));
-            }
+            name => unreachable!("{} is in COMMANDS but not handled", name),
         }
-        
+
         Ok(())
     }
+
+    /// Run an alias or macro
+    fn run_user(app: &mut App, name: &str, args: &[parse::Word]) -> Result<()> {
+        let Some(user_command) = app.user_commands.get(name).cloned() else {
+            let user_names = app.user_commands.names();
+            let names = COMMANDS
+                .iter()
+                .map(|command| command.name)
+                .chain(user_names.iter().map(String::as_str));
+            return Err(match spec::suggest(name, names) {
+                Some(suggestion) => anyhow!("Unknown command: {} (did you mean {}?)", name, suggestion),
+                None => anyhow!("Unknown command: {}", name),
+            });
+        };
+        let line = user_command.expand(name, args)?;
+        log::debug!("{} runs {}", name, line);
+
+        app.user_commands.enter()?;
+        let result = Self::execute(app, &line);
+        app.user_commands.leave();
+        result
+    }
 }
 
-/// Handle a command string entered by the user
-pub fn handle_command(app: &mut App, command: &str) -> Result<()> {
-    let result = CommandHandler::execute(app, command);
-    
-    // If command was successful and it's a search, ensure we have the right view
-    if result.is_ok() {
-        if command.starts_with("youtube") || command.starts_with("yt") || command.starts_with("y") {
-            // Make sure we're in the right view
-            if app.view == crate::app::AppView::YoutubeSearch {
-                // Ensure the YouTube search view is properly set up
-                if app.youtube_search.results.is_empty() && !app.youtube_search.searching {
-                    app.set_status("Enter search term or YouTube URL", ratatui::style::Color::Yellow);
-                }
+/// Run a file of commands for `:source`, failing with the first problem
+pub fn source(app: &mut App, path: &std::path::Path) -> Result<()> {
+    app.user_commands.enter()?;
+    let result = rc::run_file(app, path);
+    app.user_commands.leave();
+
+    let problems = result?;
+    match App::summarize_problems(&path.display().to_string(), &problems) {
+        Some(summary) => Err(anyhow!(summary)),
+        None => {
+            app.set_status(format!("Ran {}", path.display()), StatusKind::Success);
+            Ok(())
+        }
+    }
+}
+
+// Values the parser has already checked against the command's parameters
+fn number(value: Option<Value>) -> f64 {
+    match value {
+        Some(Value::Number(number)) => number,
+        other => unreachable!("expected a number, got {:?}", other),
+    }
+}
+
+fn switch(value: Option<Value>) -> Option<bool> {
+    match value {
+        Some(Value::Switch(on)) => Some(on),
+        None => None,
+        other => unreachable!("expected a switch, got {:?}", other),
+    }
+}
+
+fn text(value: Option<Value>) -> Option<String> {
+    match value {
+        Some(Value::Text(text)) => Some(text),
+        None => None,
+        other => unreachable!("expected text, got {:?}", other),
+    }
+}
+
+/// Change a setting from `spec::SETTINGS`
+fn set(app: &mut App, name: &str, value: Option<Value>) -> Result<()> {
+    let on_off = |on: bool| if on { "enabled" } else { "disabled" };
+    match name {
+        "gpu" => {
+            let enable = switch(value).unwrap_or_default();
+            app.render_config.enable_gpu = enable;
+            app.set_status(format!("GPU acceleration {}", on_off(enable)), StatusKind::Success);
+        }
+        "renderer" => {
+            let render_method = match text(value).as_deref() {
+                Some("blocks") => RenderMethod::Blocks,
+                Some("kitty") => RenderMethod::Kitty,
+                Some("sixel") => RenderMethod::Sixel,
+                Some("iterm") => RenderMethod::ITerm,
+                _ => RenderMethod::Auto,
+            };
+            app.render_config.method = render_method;
+            app.set_status(format!("Renderer set to {:?}", render_method), StatusKind::Success);
+        }
+        "fps" => {
+            app.render_config.target_fps = number(value) as f32;
+            app.set_status(format!("Target FPS set to {}", app.render_config.target_fps), StatusKind::Success);
+        }
+        "quality" => {
+            app.render_config.quality = number(value) as f32;
+            app.set_status(format!("Quality set to {:.1}", app.render_config.quality), StatusKind::Success);
+        }
+        "budget" => {
+            let Some(Value::Bytes(budget)) = value else {
+                unreachable!("budget takes a size");
+            };
+            app.render_config.max_bytes_per_second = budget;
+            app.set_status(
+                format!("Bandwidth budget set to {}", crate::ui::stats::format_budget(budget)),
+                StatusKind::Success,
+            );
+        }
+        "speed" => {
+            let speed = number(value);
+            app.speed = speed;
+            if let Some(player) = &mut app.player {
+                player.set_speed(speed)?;
+            }
+            app.set_status(format!("Speed {}x", speed), StatusKind::Success);
+        }
+        "filter" => {
+            let name = text(value).unwrap_or_default();
+            let filter = ScaleFilter::ALL
+                .into_iter()
+                .find(|filter| filter.name().eq_ignore_ascii_case(&name))
+                .unwrap_or_default();
+            app.render_config.scale_filter = filter;
+            app.set_status(format!("Scaling filter set to {}", filter.name()), StatusKind::Success);
+        }
+        "theme" => {
+            let name = text(value).unwrap_or_default();
+            app.theme = crate::ui::theme::Theme::builtin(&name)?;
+            app.set_status(format!("Theme set to {}", name), StatusKind::Success);
+            app.theme_name = name;
+        }
+        "fullscreen" => {
+            if switch(value).unwrap_or_default() != app.fullscreen {
+                app.run_action(Action::ToggleFullscreen)?;
             }
-        } else if command == "help" || command == "h" || command == "?" {
-            // Make the help overlay visible regardless of view
-            app.show_help = true;
         }
+        "youtube-quality" => {
+            let quality = number(value) as u8;
+            app.youtube_config.quality = quality;
+            app.set_status(format!("YouTube quality set to {}", quality), StatusKind::Success);
+        }
+        name => return Err(anyhow!("Unknown setting: {}", name)),
     }
-    
-    result
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Search YouTube, or open a video given its URL or ID
+fn youtube(app: &mut App, query: Option<String>) -> Result<()> {
+    let Some(query) = query else {
+        app.view = crate::app::AppView::YoutubeSearch;
+        if app.youtube_search.results.is_empty() && !app.youtube_search.searching {
+            app.set_status("Enter search term or YouTube URL", StatusKind::Warning);
+        }
+        return Ok(());
+    };
+
+    // If it looks like a YouTube URL or ID, open it directly
+    if query.contains("youtube.com") || query.contains("youtu.be") || query.len() == 11 {
+        return app.open_media(&query);
+    }
+
+    // Otherwise, set it as a search query and perform a search
+    app.view = crate::app::AppView::YoutubeSearch;
+    app.input = query.clone();
+    app.input_cursor = query.len();
+
+    // This will trigger the actual search in the background
+    // Results will be populated by the app's update method
+    app.youtube_search.results.clear();
+    app.youtube_search.query = query.clone();
+    app.youtube_search.searching = true;
+    app.set_status(format!("Searching YouTube for '{}'...", query), StatusKind::Warning);
+
+    // Add a loading placeholder
+    app.youtube_search.results.push(crate::app::YoutubeResult {
+        id: "loading".to_string(),
+        title: "Searching YouTube...".to_string(),
+        duration: "".to_string(),
+        thumbnail: None,
+        downloaded_thumbnail: None,
+        channel: "Please wait".to_string(),
+    });
+    app.youtube_search.selected = Some(0);
+    Ok(())
+}
+
+/// Handle a line of commands entered by the user
+pub fn handle_command(app: &mut App, command: &str) -> Result<()> {
+    CommandHandler::execute(app, command)
+}
@@ -0,0 +1,266 @@
+// Splitting a command line into commands and words, and reading typed
+// values out of the words. Nothing here knows about the commands themselves,
+// see spec.rs for those.
+
+use anyhow::{Result, anyhow};
+
+/// A word of a command and where it starts, quotes included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    /// The word with quotes and escapes removed
+    pub text: String,
+    /// Byte offset of the word in the command
+    pub start: usize,
+}
+
+/// Split a line into commands at `;` outside quotes
+///
+/// Returns each command with its byte offset in the line.
+pub fn split_commands(line: &str) -> Vec<(usize, &str)> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                commands.push((start, &line[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    commands.push((start, &line[start..]));
+    commands
+}
+
+/// Split a command into words
+///
+/// Double quotes keep spaces in a word and a backslash escapes the next
+/// character. An unclosed quote runs to the end, so a command that is still
+/// being typed splits the way it will once finished.
+pub fn words(command: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in command.char_indices() {
+        if !quoted && !escaped && c.is_whitespace() {
+            words.extend(current.take());
+            continue;
+        }
+        let word = current.get_or_insert_with(|| Word {
+            text: String::new(),
+            start: i,
+        });
+        match c {
+            _ if escaped => {
+                word.text.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            _ => word.text.push(c),
+        }
+    }
+    words.extend(current);
+    words
+}
+
+/// Quote a word if it would not survive `words` as it is
+pub fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "\"\\;".contains(c)) {
+        return word.to_string();
+    }
+    let escaped = word.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+/// Quote a word being completed, leaving a quote open so the completion
+/// can go on into a directory
+pub fn quote_partial(word: &str) -> String {
+    let mut quoted = quote(word);
+    if quoted.len() > word.len() {
+        quoted.pop();
+    }
+    quoted
+}
+
+/// Where a seek goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Time {
+    /// Seconds from the start
+    At(f64),
+    /// Seconds from the current position, negative to go back
+    By(f64),
+}
+
+/// Read a time: seconds ("90", "12.5"), minutes and seconds ("1:30") or
+/// hours too ("1:02:03"), with `+` or `-` in front to seek relative
+pub fn time(text: &str) -> Result<Time> {
+    let invalid = || anyhow!("expected a time like 90, 1:30 or +10, got '{}'", text);
+    let (sign, clock) = match text.as_bytes().first() {
+        Some(b'+') => (Some(1.0), &text[1..]),
+        Some(b'-') => (Some(-1.0), &text[1..]),
+        _ => (None, text),
+    };
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: f64 = part.parse().map_err(|_| invalid())?;
+        // Only the seconds may have a fraction, and only a leading part may
+        // go past 59
+        let last = i == parts.len() - 1;
+        let whole = value.fract() == 0.0;
+        if !value.is_finite() || value < 0.0 || (!last && !whole) || (i > 0 && value >= 60.0) {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    Ok(match sign {
+        Some(sign) => Time::By(sign * seconds),
+        None => Time::At(seconds),
+    })
+}
+
+/// Read a switch: on/off, true/false, yes/no or 1/0
+pub fn switch(text: &str) -> Result<bool> {
+    match text.to_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" | "enable" | "enabled" => Ok(true),
+        "off" | "false" | "no" | "0" | "disable" | "disabled" => Ok(false),
+        _ => Err(anyhow!("expected on or off, got '{}'", text)),
+    }
+}
+
+/// Read a number between `min` and `max`
+pub fn number(text: &str, min: f64, max: f64) -> Result<f64> {
+    let value: f64 = text
+        .parse()
+        .map_err(|_| anyhow!("expected a number, got '{}'", text))?;
+    if !(min..=max).contains(&value) {
+        return Err(anyhow!("{} is out of range ({} to {})", text, min, max));
+    }
+    Ok(value)
+}
+
+/// Read a whole number between `min` and `max`
+pub fn integer(text: &str, min: i64, max: i64) -> Result<i64> {
+    let value: i64 = text
+        .parse()
+        .map_err(|_| anyhow!("expected a whole number, got '{}'", text))?;
+    if !(min..=max).contains(&value) {
+        return Err(anyhow!("{} is out of range ({} to {})", text, min, max));
+    }
+    Ok(value)
+}
+
+/// Read a byte count like "256k" or "4m", "off" meaning no limit
+pub fn bytes(text: &str) -> Result<Option<u64>> {
+    let lower = text.to_lowercase();
+    if lower == "off" || lower == "0" {
+        return Ok(None);
+    }
+    let (digits, scale) = match lower.as_bytes().last() {
+        Some(b'k') => (&lower[..lower.len() - 1], 1024),
+        Some(b'm') => (&lower[..lower.len() - 1], 1024 * 1024),
+        _ => (lower.as_str(), 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .map(Some)
+        .ok_or_else(|| anyhow!("expected a size like 256k, 4m or off, got '{}'", text))
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(command: &str) -> Vec<String> {
+        words(command).into_iter().map(|word| word.text).collect()
+    }
+
+    #[test]
+    fn splits_commands_and_words() {
+        let line = r#"renderer kitty; open "my video.mp4";gpu off"#;
+        let commands: Vec<&str> = split_commands(line).iter().map(|&(_, c)| c).collect();
+        assert_eq!(
+            commands,
+            ["renderer kitty", r#" open "my video.mp4""#, "gpu off"]
+        );
+        assert_eq!(texts(commands[1]), ["open", "my video.mp4"]);
+
+        assert_eq!(split_commands(r#"youtube "a;b""#).len(), 1);
+        assert_eq!(texts(r#"open a\ b "c\"d"#), ["open", "a b", "c\"d"]);
+        assert_eq!(
+            words("  seek  10")
+                .iter()
+                .map(|w| w.start)
+                .collect::<Vec<_>>(),
+            [2, 8]
+        );
+    }
+
+    #[test]
+    fn quotes_words_that_need_it() {
+        for word in [
+            "plain",
+            "two words",
+            r#"say "hi""#,
+            "a;b",
+            r"back\slash",
+            "",
+        ] {
+            assert_eq!(texts(&quote(word)), [word]);
+        }
+        assert_eq!(quote("plain"), "plain");
+    }
+
+    #[test]
+    fn reads_times() {
+        assert_eq!(time("90").unwrap(), Time::At(90.0));
+        assert_eq!(time("12.5").unwrap(), Time::At(12.5));
+        assert_eq!(time("1:30").unwrap(), Time::At(90.0));
+        assert_eq!(time("1:02:03").unwrap(), Time::At(3723.0));
+        assert_eq!(time("+10").unwrap(), Time::By(10.0));
+        assert_eq!(time("-1:00").unwrap(), Time::By(-60.0));
+        for bad in ["", "abc", "1:60", "1.5:00", "1:2:3:4", "--5", "inf"] {
+            assert!(time(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn reads_other_values() {
+        assert!(switch("On").unwrap());
+        assert!(!switch("0").unwrap());
+        assert!(switch("maybe").is_err());
+
+        assert_eq!(integer("42", 0, 100).unwrap(), 42);
+        assert!(integer("101", 0, 100).is_err());
+        assert!(integer("4.5", 0, 100).is_err());
+        assert_eq!(number("0.5", 0.1, 1.0).unwrap(), 0.5);
+        assert!(number("x", 0.1, 1.0).is_err());
+
+        assert_eq!(bytes("256k").unwrap(), Some(256 * 1024));
+        assert_eq!(bytes("4M").unwrap(), Some(4 * 1024 * 1024));
+        assert_eq!(bytes("off").unwrap(), None);
+        assert!(bytes("lots").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+// The rc file: commands run at startup, as if typed after `:`.
+//
+//     # ~/.config/tui_player/rc
+//     renderer kitty
+//     gpu off; speed 1.25
+//     alias k renderer kitty
+//     macro demo "fullscreen on; open $1"
+//
+// One command line per line. Blank lines and lines starting with # are
+// skipped, and a failing line is reported without stopping the rest.
+// `:source` runs any other file the same way.
+
+use super::CommandHandler;
+use crate::app::App;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Where the rc file is looked for
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tui_player").join("rc"))
+}
+
+/// Run the user's rc file if there is one
+///
+/// Returns any problem running it.
+pub fn load(app: &mut App) -> Vec<String> {
+    match path() {
+        Some(path) if path.exists() => match run_file(app, &path) {
+            Ok(problems) => problems,
+            Err(e) => vec![format!("{:#}", e)],
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Run every command line of a file
+///
+/// Fails if the file can't be read, otherwise returns what went wrong by
+/// line number.
+pub fn run_file(app: &mut App, path: &Path) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    log::info!("Running commands from {}", path.display());
+
+    let mut problems = Vec::new();
+    for (number, line) in lines(&text) {
+        if let Err(e) = CommandHandler::execute(app, line) {
+            problems.push(format!("line {}: {}", number, e));
+        }
+    }
+    Ok(problems)
+}
+
+/// Command lines of a file with their line numbers, counting from 1
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# demo profile\nrenderer kitty\n\n  gpu off; speed 1.25  \n\t# done\n";
+        let lines: Vec<(usize, &str)> = lines(text).collect();
+        assert_eq!(lines, [(2, "renderer kitty"), (4, "gpu off; speed 1.25")]);
+    }
+}
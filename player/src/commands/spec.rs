@@ -0,0 +1,587 @@
+// What each command is called and what it takes. The command line uses this
+// for parsing, completion and hints, mod.rs runs the parsed commands.
+
+use super::parse::{self, Time, Word};
+use crate::ui::theme::Theme;
+use anyhow::{Result, anyhow};
+
+/// The kind of value an argument takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
+    /// A time, see `parse::time`
+    Time,
+    /// A number in a range
+    Number(f64, f64),
+    /// A whole number in a range
+    Integer(i64, i64),
+    /// on or off
+    Switch,
+    /// One of a fixed set of words
+    Choice(&'static [&'static str]),
+    /// A byte count, see `parse::bytes`
+    Bytes,
+    /// A built-in theme name
+    Theme,
+    /// A file or directory
+    Path,
+    /// Everything up to the end of the command
+    Text,
+    /// Everything up to the end of the command, quoted where needed to run
+    /// it again
+    Rest,
+    /// Any single word
+    Word,
+    /// The name of an alias or macro
+    UserCommand,
+    /// A name from `SETTINGS`
+    Setting,
+    /// A value for the setting named by the previous argument
+    SettingValue,
+}
+
+/// An argument of a command
+#[derive(Debug)]
+pub struct Param {
+    /// Shown in hints
+    pub name: &'static str,
+    pub arg: Arg,
+    /// Whether the command works without it
+    pub optional: bool,
+}
+
+/// A command of the command line
+#[derive(Debug)]
+pub struct Command {
+    pub name: &'static str,
+    /// Shorter names for the same command
+    pub aliases: &'static [&'static str],
+    pub params: &'static [Param],
+    /// One line shown next to the usage
+    pub help: &'static str,
+}
+
+/// A setting `:set` can change
+#[derive(Debug)]
+pub struct Setting {
+    pub name: &'static str,
+    pub arg: Arg,
+    pub help: &'static str,
+}
+
+/// An argument after parsing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Time(Time),
+    Number(f64),
+    Switch(bool),
+    Bytes(Option<u64>),
+    /// Choices, themes, setting names, paths, words and text
+    Text(String),
+}
+
+/// Names of the render methods
+pub const RENDERERS: &[&str] = &["auto", "blocks", "kitty", "sixel", "iterm"];
+
+/// Names of the scaling filters, as `ScaleFilter::name` in lower case
+pub const FILTERS: &[&str] = &["nearest", "bilinear", "bicubic", "lanczos3", "area"];
+
+/// Slowest and fastest playback speed, for `:speed` and MPRIS clients
+pub const MIN_SPEED: f64 = 0.25;
+pub const MAX_SPEED: f64 = 4.0;
+
+const fn required(name: &'static str, arg: Arg) -> Param {
+    Param {
+        name,
+        arg,
+        optional: false,
+    }
+}
+
+const fn optional(name: &'static str, arg: Arg) -> Param {
+    Param {
+        name,
+        arg,
+        optional: true,
+    }
+}
+
+/// Every command, in the order completion offers them
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "seek",
+        aliases: &["s"],
+        params: &[required("time", Arg::Time)],
+        help: "Seek to a time (90, 1:30) or by an offset (+10, -5)",
+    },
+    Command {
+        name: "play",
+        aliases: &["p"],
+        params: &[],
+        help: "Resume playback",
+    },
+    Command {
+        name: "pause",
+        aliases: &[],
+        params: &[],
+        help: "Pause playback",
+    },
+    Command {
+        name: "toggle",
+        aliases: &["t"],
+        params: &[],
+        help: "Pause or resume playback",
+    },
+    Command {
+        name: "restart",
+        aliases: &[],
+        params: &[],
+        help: "Seek to the start",
+    },
+    Command {
+        name: "speed",
+        aliases: &[],
+        params: &[required("rate", Arg::Number(MIN_SPEED, MAX_SPEED))],
+        help: "Set the playback speed, 1 is normal",
+    },
+    Command {
+        name: "volume",
+        aliases: &["vol", "v"],
+        params: &[required("level", Arg::Integer(0, 100))],
+        help: "Set the volume in percent",
+    },
+    Command {
+        name: "open",
+        aliases: &["o"],
+        params: &[optional("path", Arg::Path)],
+        help: "Open a file or URL, or the file browser without one",
+    },
+    Command {
+        name: "queue",
+        aliases: &[],
+        params: &[optional("path", Arg::Path)],
+        help: "Play a file or URL after the current one, or list the queue",
+    },
+    Command {
+        name: "next",
+        aliases: &[],
+        params: &[],
+        help: "Play the next file in the queue",
+    },
+    Command {
+        name: "unqueue",
+        aliases: &[],
+        params: &[],
+        help: "Empty the queue",
+    },
+    Command {
+        name: "youtube",
+        aliases: &["yt", "y"],
+        params: &[optional("query", Arg::Text)],
+        help: "Search YouTube or open a video URL or ID",
+    },
+    Command {
+        name: "quality",
+        aliases: &["q"],
+        params: &[required("level", Arg::Integer(0, 5))],
+        help: "Set the YouTube quality, 0 is best",
+    },
+    Command {
+        name: "renderer",
+        aliases: &["render", "r"],
+        params: &[required("method", Arg::Choice(RENDERERS))],
+        help: "Set how video is drawn",
+    },
+    Command {
+        name: "gpu",
+        aliases: &[],
+        params: &[optional("on|off", Arg::Switch)],
+        help: "Turn GPU scaling on or off, or toggle it",
+    },
+    Command {
+        name: "fullscreen",
+        aliases: &["fs"],
+        params: &[optional("on|off", Arg::Switch)],
+        help: "Turn fullscreen on or off, or toggle it",
+    },
+    Command {
+        name: "theme",
+        aliases: &[],
+        params: &[optional("name", Arg::Theme)],
+        help: "Switch theme, or show the current one",
+    },
+    Command {
+        name: "set",
+        aliases: &[],
+        params: &[
+            required("setting", Arg::Setting),
+            required("value", Arg::SettingValue),
+        ],
+        help: "Change a setting",
+    },
+    Command {
+        name: "source",
+        aliases: &[],
+        params: &[required("file", Arg::Path)],
+        help: "Run the commands in a file",
+    },
+    Command {
+        name: "script",
+        aliases: &[],
+        params: &[required("file", Arg::Path)],
+        help: "Run a Rhai script",
+    },
+    Command {
+        name: "alias",
+        aliases: &[],
+        params: &[optional("name", Arg::Word), optional("command", Arg::Rest)],
+        help: "Name a command, or list aliases",
+    },
+    Command {
+        name: "macro",
+        aliases: &[],
+        params: &[optional("name", Arg::Word), optional("commands", Arg::Text)],
+        help: "Name commands using $1..$9 and $*, or list macros",
+    },
+    Command {
+        name: "unalias",
+        aliases: &[],
+        params: &[required("name", Arg::UserCommand)],
+        help: "Remove an alias or macro",
+    },
+    Command {
+        name: "menu",
+        aliases: &["home", "main"],
+        params: &[],
+        help: "Stop playback and go to the main menu",
+    },
+    Command {
+        name: "settings",
+        aliases: &["config"],
+        params: &[],
+        help: "Show the settings",
+    },
+    Command {
+        name: "help",
+        aliases: &["h", "?"],
+        params: &[],
+        help: "Show the key bindings",
+    },
+    Command {
+        name: "quit",
+        aliases: &["exit"],
+        params: &[],
+        help: "Stop playback, or quit from the menus",
+    },
+];
+
+/// Settings of the settings dialog, by the names `:set` takes
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        name: "gpu",
+        arg: Arg::Switch,
+        help: "GPU scaling",
+    },
+    Setting {
+        name: "renderer",
+        arg: Arg::Choice(RENDERERS),
+        help: "How video is drawn",
+    },
+    Setting {
+        name: "fps",
+        arg: Arg::Number(1.0, 240.0),
+        help: "Target frame rate",
+    },
+    Setting {
+        name: "quality",
+        arg: Arg::Number(0.1, 1.0),
+        help: "Render quality, lower is faster",
+    },
+    Setting {
+        name: "budget",
+        arg: Arg::Bytes,
+        help: "Output bytes per second, e.g. 256k, 4m or off",
+    },
+    Setting {
+        name: "speed",
+        arg: Arg::Number(MIN_SPEED, MAX_SPEED),
+        help: "Playback speed, 1 is normal",
+    },
+    Setting {
+        name: "filter",
+        arg: Arg::Choice(FILTERS),
+        help: "Scaling filter",
+    },
+    Setting {
+        name: "theme",
+        arg: Arg::Theme,
+        help: "Color theme",
+    },
+    Setting {
+        name: "fullscreen",
+        arg: Arg::Switch,
+        help: "Fullscreen player",
+    },
+    Setting {
+        name: "youtube-quality",
+        arg: Arg::Integer(0, 5),
+        help: "YouTube quality, 0 is best",
+    },
+];
+
+/// Look up a command by name or alias
+pub fn find(name: &str) -> Option<&'static Command> {
+    let name = name.to_lowercase();
+    COMMANDS
+        .iter()
+        .find(|command| command.name == name || command.aliases.contains(&name.as_str()))
+}
+
+/// Look up a setting by name
+pub fn setting(name: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.name == name)
+}
+
+/// The closest name to a mistyped one, for "did you mean"
+pub fn suggest<'a>(name: &str, names: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    names
+        .into_iter()
+        .map(|candidate| (distance(&name, candidate), candidate))
+        .filter(|&(distance, candidate)| distance <= 2 && distance < candidate.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between two words
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != cb))
+                .min(row[j] + 1)
+                .min(above + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Names of the built-in themes
+pub fn theme_names() -> impl Iterator<Item = &'static str> + Clone {
+    Theme::BUILTIN.iter().map(|&(name, _)| name)
+}
+
+impl Command {
+    /// The command with its arguments, e.g. "seek <time>"
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.to_string();
+        for param in self.params {
+            usage += &if param.optional {
+                format!(" [{}]", param.name)
+            } else {
+                format!(" <{}>", param.name)
+            };
+        }
+        usage
+    }
+
+    /// Parse the words after the command name, one value per parameter
+    pub fn parse(&self, words: &[Word]) -> Result<Vec<Option<Value>>> {
+        let takes_rest = self
+            .params
+            .last()
+            .is_some_and(|param| matches!(param.arg, Arg::Text | Arg::Rest));
+        if words.len() > self.params.len() && !takes_rest {
+            return Err(anyhow!("too many arguments, usage: {}", self.usage()));
+        }
+
+        let mut values = Vec::new();
+        for (i, param) in self.params.iter().enumerate() {
+            let rest = words.get(i..).filter(|rest| !rest.is_empty());
+            let text = match (param.arg, rest) {
+                (Arg::Text, Some(rest)) => {
+                    let rest: Vec<&str> = rest.iter().map(|word| word.text.as_str()).collect();
+                    Some(rest.join(" "))
+                }
+                (Arg::Rest, Some(rest)) => {
+                    let rest: Vec<String> =
+                        rest.iter().map(|word| parse::quote(&word.text)).collect();
+                    Some(rest.join(" "))
+                }
+                _ => words.get(i).map(|word| word.text.clone()),
+            };
+            let Some(text) = text else {
+                if param.optional {
+                    values.push(None);
+                    continue;
+                }
+                return Err(anyhow!("missing <{}>, usage: {}", param.name, self.usage()));
+            };
+
+            let arg = match param.arg {
+                Arg::SettingValue => match &values.last() {
+                    Some(Some(Value::Text(name))) => setting(name).map_or(Arg::Text, |s| s.arg),
+                    _ => Arg::Text,
+                },
+                arg => arg,
+            };
+            let value = value(arg, &text).map_err(|e| anyhow!("<{}>: {}", param.name, e))?;
+            values.push(Some(value));
+        }
+        Ok(values)
+    }
+}
+
+/// Parse one argument
+pub fn value(arg: Arg, text: &str) -> Result<Value> {
+    Ok(match arg {
+        Arg::Time => Value::Time(parse::time(text)?),
+        Arg::Number(min, max) => Value::Number(parse::number(text, min, max)?),
+        Arg::Integer(min, max) => Value::Number(parse::integer(text, min, max)? as f64),
+        Arg::Switch => Value::Switch(parse::switch(text)?),
+        Arg::Bytes => Value::Bytes(parse::bytes(text)?),
+        Arg::Choice(choices) => Value::Text(one_of(text, choices.iter().copied())?),
+        Arg::Theme => Value::Text(one_of(text, theme_names())?),
+        Arg::Setting => Value::Text(one_of(text, SETTINGS.iter().map(|s| s.name))?),
+        Arg::Path => Value::Text(parse::expand_home(text)),
+        Arg::Text | Arg::Rest | Arg::Word | Arg::UserCommand | Arg::SettingValue => {
+            Value::Text(text.to_string())
+        }
+    })
+}
+
+/// Match a word against a set of names, suggesting the closest on a typo
+fn one_of<'a>(text: &str, names: impl Iterator<Item = &'a str> + Clone) -> Result<String> {
+    let lower = text.to_lowercase();
+    if let Some(name) = names.clone().find(|&name| name == lower) {
+        return Ok(name.to_string());
+    }
+    let expected: Vec<&str> = names.clone().collect();
+    Err(match suggest(text, names) {
+        Some(name) => anyhow!("unknown '{}', did you mean {}?", text, name),
+        None => anyhow!(
+            "unknown '{}', expected one of {}",
+            text,
+            expected.join(", ")
+        ),
+    })
+}
+
+/// Candidates for completing a word, given the words before it
+///
+/// `index` is the position of the word, 0 being the command name. Paths and
+/// aliases are left to the caller.
+pub fn candidates(words: &[Word], index: usize) -> (Option<Arg>, Vec<&'static str>) {
+    if index == 0 {
+        return (None, COMMANDS.iter().map(|command| command.name).collect());
+    }
+    let Some(command) = words.first().and_then(|word| find(&word.text)) else {
+        return (None, Vec::new());
+    };
+    let param = match command.params.get(index - 1) {
+        Some(param) => param,
+        None => return (None, Vec::new()),
+    };
+    let arg = match param.arg {
+        Arg::SettingValue => match words.get(index - 1).and_then(|word| setting(&word.text)) {
+            Some(setting) => setting.arg,
+            None => return (None, Vec::new()),
+        },
+        arg => arg,
+    };
+    let names = match arg {
+        Arg::Switch => vec!["on", "off"],
+        Arg::Choice(choices) => choices.to_vec(),
+        Arg::Theme => theme_names().collect(),
+        Arg::Setting => SETTINGS.iter().map(|setting| setting.name).collect(),
+        _ => Vec::new(),
+    };
+    (Some(arg), names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Result<Vec<Option<Value>>> {
+        let words = parse::words(command);
+        find(&words[0].text).unwrap().parse(&words[1..])
+    }
+
+    #[test]
+    fn parses_typed_arguments() {
+        assert_eq!(
+            parse("seek 1:30").unwrap(),
+            [Some(Value::Time(Time::At(90.0)))]
+        );
+        assert_eq!(parse("V 40").unwrap(), [Some(Value::Number(40.0))]);
+        assert_eq!(parse("gpu").unwrap(), [None]);
+        assert_eq!(
+            parse("youtube rust  talks").unwrap(),
+            [Some(Value::Text("rust talks".to_string()))]
+        );
+        assert_eq!(
+            parse("set budget 1m").unwrap(),
+            [
+                Some(Value::Text("budget".to_string())),
+                Some(Value::Bytes(Some(1024 * 1024)))
+            ]
+        );
+        assert_eq!(
+            parse("renderer Kitty").unwrap(),
+            [Some(Value::Text("kitty".to_string()))]
+        );
+    }
+
+    #[test]
+    fn explains_bad_arguments() {
+        let error = |command: &str| parse(command).unwrap_err().to_string();
+        assert_eq!(error("seek"), "missing <time>, usage: seek <time>");
+        assert_eq!(error("pause now"), "too many arguments, usage: pause");
+        assert_eq!(
+            error("volume 200"),
+            "<level>: 200 is out of range (0 to 100)"
+        );
+        assert_eq!(
+            error("renderer kity"),
+            "<method>: unknown 'kity', did you mean kitty?"
+        );
+        assert_eq!(
+            error("set gpu maybe"),
+            "<value>: expected on or off, got 'maybe'"
+        );
+        assert!(error("set zoom 2").starts_with("<setting>: unknown 'zoom'"));
+    }
+
+    #[test]
+    fn suggests_close_names() {
+        let names = || COMMANDS.iter().map(|command| command.name);
+        assert_eq!(suggest("sek", names()), Some("seek"));
+        assert_eq!(suggest("theem", names()), Some("theme"));
+        assert_eq!(suggest("xyzzy", names()), None);
+    }
+
+    #[test]
+    fn names_are_unique_and_match_the_core() {
+        let mut names: Vec<&str> = COMMANDS
+            .iter()
+            .flat_map(|command| {
+                std::iter::once(command.name).chain(command.aliases.iter().copied())
+            })
+            .collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+
+        let filters: Vec<String> = core::video::scale::ScaleFilter::ALL
+            .iter()
+            .map(|filter| filter.name().to_lowercase())
+            .collect();
+        assert_eq!(filters, FILTERS);
+    }
+}
@@ -0,0 +1,170 @@
+// Commands users define with `:alias` and `:macro`, usually in the rc file.
+//
+//     alias k renderer kitty            :k runs "renderer kitty"
+//     alias v volume                    :v 40 runs "volume 40"
+//     macro demo "gpu off; speed $1"    :demo 1.5 runs both with $1 = 1.5
+//
+// An alias gets the words after it appended, a macro gets them put in for
+// $1 to $9, with $* for all of them and $$ for a dollar sign.
+
+use super::parse::{self, Word};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
+/// How deep aliases, macros and sourced files may call each other
+const MAX_DEPTH: usize = 16;
+
+/// What a user command runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserCommand {
+    /// A command the arguments are appended to
+    Alias(String),
+    /// Commands with `$1` style placeholders
+    Macro(String),
+}
+
+/// Aliases and macros by name
+#[derive(Debug, Default)]
+pub struct UserCommands {
+    commands: BTreeMap<String, UserCommand>,
+    /// Aliases, macros and files currently running, to stop loops
+    depth: usize,
+}
+
+impl UserCommands {
+    /// Define or replace a user command
+    pub fn define(&mut self, name: &str, command: UserCommand) {
+        self.commands.insert(name.to_lowercase(), command);
+    }
+
+    /// Forget a user command, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.commands.remove(&name.to_lowercase()).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&UserCommand> {
+        self.commands.get(&name.to_lowercase())
+    }
+
+    /// Names of every user command, sorted
+    pub fn names(&self) -> Vec<String> {
+        self.commands.keys().cloned().collect()
+    }
+
+    /// User commands of one kind, as "name = body"
+    pub fn describe(&self, macros: bool) -> Vec<String> {
+        self.commands
+            .iter()
+            .filter_map(|(name, command)| match command {
+                UserCommand::Alias(body) if !macros => Some(format!("{} = {}", name, body)),
+                UserCommand::Macro(body) if macros => Some(format!("{} = {}", name, body)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Count one more level of nesting, failing past `MAX_DEPTH`
+    ///
+    /// Every call must be matched by `leave`.
+    pub fn enter(&mut self) -> Result<()> {
+        if self.depth >= MAX_DEPTH {
+            return Err(anyhow!(
+                "Commands nested more than {} deep, does an alias, macro or file run itself?",
+                MAX_DEPTH
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+impl UserCommand {
+    /// The command line to run for `name` called with `args`
+    pub fn expand(&self, name: &str, args: &[Word]) -> Result<String> {
+        let quoted: Vec<String> = args.iter().map(|arg| parse::quote(&arg.text)).collect();
+        match self {
+            UserCommand::Alias(body) if quoted.is_empty() => Ok(body.clone()),
+            UserCommand::Alias(body) => Ok(format!("{} {}", body, quoted.join(" "))),
+            UserCommand::Macro(body) => substitute(name, body, &quoted),
+        }
+    }
+}
+
+/// Put arguments in for a macro's placeholders
+fn substitute(name: &str, body: &str, args: &[String]) -> Result<String> {
+    let mut line = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            line.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('$') => line.push('$'),
+            Some('*') => line += &args.join(" "),
+            Some(digit @ '1'..='9') => {
+                let index = digit as usize - '1' as usize;
+                let arg = args
+                    .get(index)
+                    .ok_or_else(|| anyhow!("{}: missing argument ${}", name, digit))?;
+                line += arg;
+            }
+            Some(other) => {
+                line.push('$');
+                line.push(other);
+            }
+            None => line.push('$'),
+        }
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(command: UserCommand, args: &str) -> Result<String> {
+        command.expand("test", &parse::words(args))
+    }
+
+    #[test]
+    fn aliases_append_arguments() {
+        let alias = UserCommand::Alias("volume".to_string());
+        assert_eq!(expand(alias.clone(), "").unwrap(), "volume");
+        assert_eq!(expand(alias, "40").unwrap(), "volume 40");
+
+        let alias = UserCommand::Alias("open".to_string());
+        assert_eq!(
+            expand(alias, r#""my video.mp4""#).unwrap(),
+            r#"open "my video.mp4""#
+        );
+    }
+
+    #[test]
+    fn macros_fill_in_placeholders() {
+        let demo = UserCommand::Macro("gpu off; speed $1; youtube $*; open $$HOME".to_string());
+        assert_eq!(
+            expand(demo.clone(), "1.5 rust").unwrap(),
+            "gpu off; speed 1.5; youtube 1.5 rust; open $HOME"
+        );
+        assert_eq!(
+            expand(demo, "").unwrap_err().to_string(),
+            "test: missing argument $1"
+        );
+    }
+
+    #[test]
+    fn stops_runaway_nesting() {
+        let mut commands = UserCommands::default();
+        for _ in 0..MAX_DEPTH {
+            commands.enter().unwrap();
+        }
+        assert!(commands.enter().is_err());
+        commands.leave();
+        assert!(commands.enter().is_ok());
+    }
+}
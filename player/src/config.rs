@@ -0,0 +1,120 @@
+// config.json, the player's settings other than key bindings.
+//
+//     {
+//         "theme": "solarized",
+//         "colors": { "accent": "#d33682", "status_bar": "black" }
+//     }
+//
+// Colors override single slots of the theme, see ui/theme.rs for the names.
+// Mistakes are reported and skipped rather than failing the whole file.
+
+use crate::ui::theme::Theme;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Contents of config.json
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Built-in theme name, "dark" when missing
+    pub theme: Option<String>,
+    /// Theme slots to colors
+    pub colors: BTreeMap<String, String>,
+}
+
+/// Where config.json is looked for
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tui_player").join("config.json"))
+}
+
+/// Load the user's config, falling back to the defaults
+///
+/// Returns the config and any problem reading it.
+pub fn load() -> (Config, Vec<String>) {
+    match path() {
+        Some(path) if path.exists() => load_from(&path),
+        _ => (Config::default(), Vec::new()),
+    }
+}
+
+/// Load a config from a file
+pub fn load_from(path: &Path) -> (Config, Vec<String>) {
+    let config = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .and_then(|text| {
+            serde_json::from_str::<Config>(&text)
+                .with_context(|| format!("Invalid config {}", path.display()))
+        });
+    match config {
+        Ok(config) => (config, Vec::new()),
+        Err(e) => (Config::default(), vec![format!("{:#}", e)]),
+    }
+}
+
+impl Config {
+    /// The configured theme with its overrides, and its name
+    pub fn theme(&self) -> (String, Theme, Vec<String>) {
+        let mut problems = Vec::new();
+        let name = self.theme.as_deref().unwrap_or("dark");
+        let (name, mut theme) = match Theme::builtin(name) {
+            Ok(theme) => (name, theme),
+            Err(e) => {
+                problems.push(e.to_string());
+                ("dark", Theme::DARK)
+            }
+        };
+        for (slot, color) in &self.colors {
+            if let Err(e) = theme.set(slot, color) {
+                problems.push(e.to_string());
+            }
+        }
+
+        // Overrides make it a different theme
+        let name = if self.colors.is_empty() {
+            name.to_string()
+        } else {
+            format!("{} (custom)", name)
+        };
+        (name, theme, problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    fn theme(json: &str) -> (String, Theme, Vec<String>) {
+        serde_json::from_str::<Config>(json).unwrap().theme()
+    }
+
+    #[test]
+    fn overrides_builtin_colors() {
+        let (name, theme, problems) =
+            theme(r##"{ "theme": "light", "colors": { "accent": "#d33682", "error": "red" } }"##);
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(name, "light (custom)");
+        assert_eq!(theme.accent, Color::Rgb(0xd3, 0x36, 0x82));
+        assert_eq!(theme.error, Color::Red);
+        assert_eq!(theme.background, Theme::LIGHT.background);
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        let (name, theme, problems) =
+            theme(r#"{ "theme": "neon", "colors": { "glow": "red", "muted": "grey-ish" } }"#);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert_eq!(name, "dark (custom)");
+        assert_eq!(theme, Theme::DARK);
+    }
+
+    #[test]
+    fn missing_file_is_the_default() {
+        let (name, theme, problems) = Config::default().theme();
+        assert!(problems.is_empty());
+        assert_eq!(name, "dark");
+        assert_eq!(theme, Theme::DARK);
+    }
+}
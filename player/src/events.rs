@@ -1,33 +1,49 @@
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+use crossterm::event::Event;
+use crate::keymap::{Action, KeyContext, Keymap};
+use serde_json::{Value, json};
+
+/// Something that happened to playback, for scripts and IPC clients
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerEvent {
+    /// A file or URL opened
+    FileLoaded(String),
+    /// Playback reached the end
+    Eof,
+    /// A seek finished, at this position in seconds
+    Seek(f64),
+    /// The position while playing, every `script::TICK_INTERVAL`
+    Tick(f64),
+    /// Playback paused or resumed
+    Pause(bool),
+    /// An error shown in the status bar
+    Error(String),
+}
+
+impl PlayerEvent {
+    /// The message sent to IPC and remote clients
+    pub fn to_json(&self) -> Value {
+        match self {
+            PlayerEvent::FileLoaded(path) => json!({ "event": "file-loaded", "path": path }),
+            PlayerEvent::Eof => json!({ "event": "eof" }),
+            PlayerEvent::Seek(position) => json!({ "event": "seek", "position": position }),
+            PlayerEvent::Tick(position) => json!({ "event": "position", "position": position }),
+            PlayerEvent::Pause(paused) => json!({ "event": "pause", "paused": paused }),
+            PlayerEvent::Error(message) => json!({ "event": "error", "message": message }),
+        }
+    }
+}
 
 /// Event utility functions
 pub mod event_utils {
     use super::*;
     
-    /// Check if a key event matches Ctrl+C or Ctrl+Q (terminate)
-    pub fn is_terminate_event(event: &Event) -> bool {
-        matches!(
-            event,
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('c'),
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            }) | Event::Key(KeyEvent {
-                code: KeyCode::Char('q'),
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            })
-        )
-    }
-    
-    /// Check if a key event is a navigation event
-    pub fn is_navigation_event(event: &Event) -> bool {
-        matches!(
-            event,
-            Event::Key(KeyEvent {
-                code: KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End,
-                ..
-            }) | Event::Mouse(MouseEvent { .. })
-        )
+    /// Check if a key event is bound to quit in the global keymap (Ctrl+C or Ctrl+Q by default)
+    ///
+    /// Only single keys count, so this works in command mode too.
+    pub fn is_terminate_event(keymap: &Keymap, event: &Event) -> bool {
+        match event {
+            Event::Key(key) => keymap.is_bound(KeyContext::Global, Action::Quit, *key),
+            _ => false,
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,438 @@
+// JSON IPC over a Unix socket, for driving the player from other programs.
+//
+//     tui_player --ipc /tmp/tui_player.sock video.mp4
+//     echo '{"command": ["seek", 30]}' | socat - /tmp/tui_player.sock
+//
+// Like mpv, every line a client sends is a JSON object with a `command`
+// array and an optional `request_id`, answered on one line:
+//
+//     {"command": ["get_property", "position"], "request_id": 1}
+//     {"request_id": 1, "error": "success", "data": 12.5}
+//
+// Commands:
+//
+//     ["loadfile", path]                  open a file or URL
+//     ["seek", seconds, "absolute"]       seek, relative unless "absolute"
+//     ["pause"], ["play"], ["toggle"]
+//     ["set_property", name, value]       pause, volume, or any :set setting
+//     ["get_property", name]              see `get_property`
+//     ["command", line]                   run a command line, as after `:`
+//     ["quit"]                            exit the player
+//
+// Every client also gets the player's events, see `PlayerEvent::to_json`:
+//
+//     {"event": "seek", "position": 30.0}
+//
+// Most commands are run through `CommandHandler`, so they behave exactly as
+// when typed. `quit` exits the player like mpv's does, where `:quit` would
+// only go back to the menu.
+
+use crate::app::App;
+use crate::commands::{CommandHandler, parse, spec};
+use crate::events::PlayerEvent;
+use anyhow::{Context, Result, anyhow};
+use core::MediaInfo;
+use serde_json::{Value, json};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+/// Longest line a client may send
+const MAX_LINE: usize = 64 * 1024;
+
+/// Most output kept for a client that doesn't read it
+const MAX_PENDING: usize = 1024 * 1024;
+
+/// A connected client
+struct Client {
+    id: u64,
+    stream: UnixStream,
+    /// Bytes read after the last complete line
+    input: Vec<u8>,
+    /// Bytes not written yet
+    output: Vec<u8>,
+    /// Whether the client is done sending, it still gets its replies
+    eof: bool,
+    closed: bool,
+}
+
+impl Client {
+    /// Whether the client can be dropped
+    fn is_done(&self) -> bool {
+        self.closed || (self.eof && self.output.is_empty())
+    }
+
+    /// Read what the client sent, returning complete lines
+    fn read_lines(&mut self) -> Vec<String> {
+        let mut buffer = [0; 4096];
+        while !self.eof {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.input.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    log::debug!("IPC client {}: {}", self.id, e);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.input.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        if self.input.len() > MAX_LINE {
+            log::warn!("IPC client {}: line too long, disconnecting", self.id);
+            self.closed = true;
+        }
+        lines
+    }
+
+    /// Queue a message and write as much as the socket takes
+    fn send(&mut self, message: &Value) {
+        self.output
+            .extend_from_slice(message.to_string().as_bytes());
+        self.output.push(b'\n');
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while !self.output.is_empty() && !self.closed {
+            match self.stream.write(&self.output) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    log::debug!("IPC client {}: {}", self.id, e);
+                    self.closed = true;
+                }
+            }
+        }
+        if self.output.len() > MAX_PENDING {
+            log::warn!("IPC client {}: not reading, disconnecting", self.id);
+            self.closed = true;
+        }
+    }
+}
+
+/// The listening socket and its clients, polled from the main loop
+pub struct IpcServer {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+    next_id: u64,
+}
+
+impl IpcServer {
+    /// Listen on a socket path, replacing a stale socket left there
+    pub fn bind(path: &Path) -> Result<Self> {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) => {
+                // Never remove anything but a socket, the path may be a typo
+                if !metadata.file_type().is_socket() {
+                    return Err(anyhow!(
+                        "{} already exists and is not a socket",
+                        path.display()
+                    ));
+                }
+                if UnixStream::connect(path).is_ok() {
+                    return Err(anyhow!("{} is in use by another player", path.display()));
+                }
+                std::fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to check {}", path.display()));
+            }
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        listener.set_nonblocking(true)?;
+        log::info!("IPC listening on {}", path.display());
+
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+            clients: Vec::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Accept new clients and collect the lines every client sent
+    pub fn poll(&mut self) -> Vec<(u64, String)> {
+        // Clients that hung up are kept until their replies are out
+        self.clients.retain(|client| !client.is_done());
+
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        log::warn!("IPC: {}", e);
+                        continue;
+                    }
+                    self.next_id += 1;
+                    log::debug!("IPC client {} connected", self.next_id);
+                    self.clients.push(Client {
+                        id: self.next_id,
+                        stream,
+                        input: Vec::new(),
+                        output: Vec::new(),
+                        eof: false,
+                        closed: false,
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("IPC: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        for client in &mut self.clients {
+            client.flush();
+            lines.extend(
+                client
+                    .read_lines()
+                    .into_iter()
+                    .map(|line| (client.id, line)),
+            );
+        }
+        lines
+    }
+
+    /// Answer one client
+    pub fn reply(&mut self, client: u64, message: &Value) {
+        if let Some(client) = self.clients.iter_mut().find(|c| c.id == client) {
+            client.send(message);
+        }
+    }
+
+    /// Tell every client about an event
+    pub fn broadcast(&mut self, event: &PlayerEvent) {
+        let message = event.to_json();
+        for client in &mut self.clients {
+            client.send(&message);
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Answer a line from a client
+pub fn handle(app: &mut App, line: &str) -> Value {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return response(Value::Null, Err(anyhow!("invalid JSON: {}", e))),
+    };
+    let request_id = message.get("request_id").cloned().unwrap_or(Value::Null);
+    let result = match message.get("command").and_then(Value::as_array) {
+        Some(command) => run(app, command),
+        None => Err(anyhow!("expected {{\"command\": [...]}}")),
+    };
+    response(request_id, result)
+}
+
+/// The reply to a command, mpv style
+fn response(request_id: Value, result: Result<Value>) -> Value {
+    let (error, data) = match result {
+        Ok(data) => ("success".to_string(), data),
+        Err(e) => (e.to_string(), Value::Null),
+    };
+    let mut response = json!({ "error": error, "data": data });
+    if !request_id.is_null() {
+        response["request_id"] = request_id;
+    }
+    response
+}
+
+/// Run a command array
+fn run(app: &mut App, command: &[Value]) -> Result<Value> {
+    let (name, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("empty command"))?;
+    let name = name
+        .as_str()
+        .ok_or_else(|| anyhow!("command name must be a string"))?;
+    let arg = |index: usize| {
+        args.get(index)
+            .ok_or_else(|| anyhow!("{}: missing argument {}", name, index + 1))
+    };
+
+    let line = match name {
+        "loadfile" => format!("open {}", parse::quote(&text(arg(0)?))),
+        "seek" => {
+            let seconds = arg(0)?
+                .as_f64()
+                .ok_or_else(|| anyhow!("seek: expected seconds"))?;
+            match args.get(1).and_then(Value::as_str).unwrap_or("relative") {
+                "relative" => format!("seek {:+}", seconds),
+                "absolute" => format!("seek {}", seconds.max(0.0)),
+                mode => return Err(anyhow!("seek: unknown mode '{}'", mode)),
+            }
+        }
+        "pause" | "play" | "toggle" => name.to_string(),
+        "quit" => {
+            app.should_quit = true;
+            return Ok(Value::Null);
+        }
+        "set_property" => set_property(&text(arg(0)?), arg(1)?)?,
+        "get_property" => return get_property(app, &text(arg(0)?)),
+        "command" => text(arg(0)?),
+        _ => return Err(anyhow!("unknown command '{}'", name)),
+    };
+    CommandHandler::execute(app, &line)?;
+    Ok(Value::Null)
+}
+
+/// The command line setting a property
+fn set_property(name: &str, value: &Value) -> Result<String> {
+    Ok(match (name, value) {
+        ("pause", Value::Bool(true)) => "pause".to_string(),
+        ("pause", Value::Bool(false)) => "play".to_string(),
+        ("pause", _) => return Err(anyhow!("pause: expected true or false")),
+        ("volume", value) => format!("volume {}", text(value)),
+        (name, value) if spec::setting(name).is_some() => {
+            format!("set {} {}", name, parse::quote(&text(value)))
+        }
+        _ => return Err(anyhow!("unknown property '{}'", name)),
+    })
+}
+
+/// A property of the player
+fn get_property(app: &App, name: &str) -> Result<Value> {
+    let player = app.player.as_ref();
+    Ok(match name {
+        "position" | "time-pos" => json!(player.map_or(0.0, |p| p.get_position())),
+        "duration" => json!(app.media_info.as_ref().map_or(0.0, |info| info.duration)),
+        "pause" => json!(player.is_some_and(|p| p.is_paused())),
+        "path" => json!(app.media_path),
+        "volume" => json!(player.map(|p| p.get_volume())),
+        "speed" => json!(app.speed),
+        "fullscreen" => json!(app.fullscreen),
+        "theme" => json!(app.theme_name),
+        "media-info" => app.media_info.as_ref().map_or(Value::Null, media_info),
+        _ => return Err(anyhow!("unknown property '{}'", name)),
+    })
+}
+
+fn media_info(info: &MediaInfo) -> Value {
+    json!({
+        "duration": info.duration,
+        "width": info.width,
+        "height": info.height,
+        "fps": info.frame_rate,
+        "format": info.format_name,
+        "video_codec": info.video_codec,
+        "audio_codec": info.audio_codec,
+    })
+}
+
+/// An argument as command line text, `true` and `false` as on and off
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Bool(true) => "on".to_string(),
+        Value::Bool(false) => "off".to_string(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn translates_commands_to_command_lines() {
+        assert_eq!(set_property("pause", &json!(true)).unwrap(), "pause");
+        assert_eq!(set_property("volume", &json!(40)).unwrap(), "volume 40");
+        assert_eq!(set_property("gpu", &json!(false)).unwrap(), "set gpu off");
+        assert_eq!(
+            set_property("renderer", &json!("kitty")).unwrap(),
+            "set renderer kitty"
+        );
+        assert!(set_property("zoom", &json!(2)).is_err());
+
+        let reply = response(json!(7), Err(anyhow!("seek: expected seconds")));
+        assert_eq!(
+            reply,
+            json!({ "request_id": 7, "error": "seek: expected seconds", "data": null })
+        );
+        assert_eq!(
+            PlayerEvent::Seek(30.0).to_json(),
+            json!({ "event": "seek", "position": 30.0 })
+        );
+    }
+
+    #[test]
+    fn serves_clients_over_the_socket() {
+        let path = std::env::temp_dir().join(format!("tui_player_ipc_{}.sock", std::process::id()));
+        let mut server = IpcServer::bind(&path).unwrap();
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client
+            .write_all(b"{\"command\": [\"pause\"]}\n{\"comm")
+            .unwrap();
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            lines.extend(server.poll());
+            if !lines.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(lines, [(1, "{\"command\": [\"pause\"]}".to_string())]);
+
+        server.reply(1, &json!({ "error": "success" }));
+        server.broadcast(&PlayerEvent::Eof);
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"error\":\"success\"}\n");
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"event\":\"eof\"}\n");
+
+        assert!(IpcServer::bind(&path).is_err());
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn only_replaces_stale_sockets() {
+        let dir = std::env::temp_dir().join(format!("tui_player_ipc_stale_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // A file at the path, e.g. from a typo, is left alone
+        let file = dir.join("movie.mp4");
+        std::fs::write(&file, b"not a socket").unwrap();
+        let error = IpcServer::bind(&file).err().unwrap();
+        assert!(error.to_string().contains("is not a socket"));
+        assert_eq!(std::fs::read(&file).unwrap(), b"not a socket");
+
+        // A socket nobody listens on is replaced
+        let socket = dir.join("stale.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        drop(IpcServer::bind(&socket).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+// keymap.json, the user's changes to a preset.
+//
+//     {
+//         "preset": "vim",
+//         "bindings": {
+//             "player": { "ctrl+l": "toggle-stats", "right": "seek +10", "i": "none" },
+//             "global": { "F2": "toggle-help" }
+//         }
+//     }
+//
+// Contexts are "global", "menu", "player" and "browser". Binding a key to
+// "none" removes it from the preset. Mistakes are reported and skipped rather
+// than failing the whole file.
+
+use super::{Action, KeyContext, Keymap, Preset, chord_to_string, parse_chord};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Contents of keymap.json
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeymapConfig {
+    /// Preset name, "default" when missing
+    pub preset: Option<String>,
+    /// Keys to actions, per context
+    pub bindings: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Where keymap.json is looked for
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tui_player").join("keymap.json"))
+}
+
+/// Load the user's keymap, falling back to the default preset
+///
+/// Returns the keymap and every problem found in the file, including
+/// conflicting bindings.
+pub fn load() -> (Keymap, Vec<String>) {
+    match path() {
+        Some(path) if path.exists() => load_from(&path),
+        _ => (Keymap::default(), Vec::new()),
+    }
+}
+
+/// Load a keymap from a file
+pub fn load_from(path: &Path) -> (Keymap, Vec<String>) {
+    let config = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .and_then(|text| {
+            serde_json::from_str::<KeymapConfig>(&text)
+                .with_context(|| format!("Invalid keymap {}", path.display()))
+        });
+    match config {
+        Ok(config) => config.build(),
+        Err(e) => (Keymap::default(), vec![format!("{:#}", e)]),
+    }
+}
+
+impl KeymapConfig {
+    /// Apply the bindings to the preset
+    pub fn build(&self) -> (Keymap, Vec<String>) {
+        let mut problems = Vec::new();
+        let preset = match self.preset.as_deref().map(Preset::parse) {
+            None => Preset::default(),
+            Some(Ok(preset)) => preset,
+            Some(Err(e)) => {
+                problems.push(e.to_string());
+                Preset::default()
+            }
+        };
+        let mut keymap = preset.keymap();
+
+        for (context_name, bindings) in &self.bindings {
+            let context = match KeyContext::parse(context_name) {
+                Ok(context) => context,
+                Err(e) => {
+                    problems.push(e.to_string());
+                    continue;
+                }
+            };
+
+            // "ctrl+q" and "control+q" are the same key, only one can win
+            let mut seen = HashMap::new();
+            for (keys_text, action_text) in bindings {
+                let keys = match parse_chord(keys_text) {
+                    Ok(keys) => keys,
+                    Err(e) => {
+                        problems.push(format!("{}: {}", context_name, e));
+                        continue;
+                    }
+                };
+                if let Some(previous) = seen.insert(keys.clone(), keys_text) {
+                    problems.push(format!(
+                        "{}: '{}' and '{}' are both '{}'",
+                        context_name,
+                        previous,
+                        keys_text,
+                        chord_to_string(&keys)
+                    ));
+                }
+
+                if action_text.trim() == "none" {
+                    keymap.unbind(context, &keys);
+                    continue;
+                }
+                match Action::parse(action_text) {
+                    Ok(action) => {
+                        keymap.bind(context, keys, action);
+                    }
+                    Err(e) => problems.push(format!("{}: '{}': {}", context_name, keys_text, e)),
+                }
+            }
+        }
+
+        problems.extend(keymap.conflicts());
+        (keymap, problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{KeyPress, Resolution};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn build(json: &str) -> (Keymap, Vec<String>) {
+        serde_json::from_str::<KeymapConfig>(json).unwrap().build()
+    }
+
+    #[test]
+    fn overrides_preset_bindings() {
+        let (mut keymap, problems) = build(
+            r#"{ "preset": "mpv", "bindings": { "player": { "right": "seek +10", "i": "none" } } }"#,
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        let player = Some(KeyContext::Player);
+        let right = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
+        assert_eq!(
+            keymap.resolve(player, right),
+            Resolution::Action(Action::Seek(10))
+        );
+        let i = KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE);
+        assert_eq!(keymap.resolve(player, i), Resolution::Unbound);
+        // Still the mpv preset underneath
+        let p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE);
+        assert_eq!(
+            keymap.resolve(player, p),
+            Resolution::Action(Action::TogglePause)
+        );
+    }
+
+    #[test]
+    fn reports_mistakes_and_conflicts() {
+        let (keymap, problems) = build(
+            r#"{
+                "preset": "emacs",
+                "bindings": {
+                    "player": {
+                        "ctrl+x": "quit",
+                        "control+x": "stop",
+                        "hyper+k": "stop",
+                        "k": "dance",
+                        "ctrl+c": "stop"
+                    },
+                    "sidebar": { "k": "up" }
+                }
+            }"#,
+        );
+        let expected = [
+            "Unknown keymap preset: emacs",
+            "are both 'ctrl+x'",
+            "Unknown modifier 'hyper'",
+            "Unknown action: dance",
+            "Unknown key context: sidebar",
+            "hides global 'ctrl+c' (quit)",
+        ];
+        assert_eq!(problems.len(), expected.len(), "{:?}", problems);
+        for text in expected {
+            assert!(
+                problems.iter().any(|problem| problem.contains(text)),
+                "missing '{}' in {:?}",
+                text,
+                problems
+            );
+        }
+        // Valid bindings are still applied
+        assert!(
+            keymap
+                .bindings(KeyContext::Player)
+                .iter()
+                .any(|binding| binding.keys == [KeyPress::parse("ctrl+c").unwrap()])
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(serde_json::from_str::<KeymapConfig>(r#"{ "binds": {} }"#).is_err());
+    }
+}
@@ -0,0 +1,717 @@
+// Configurable keybindings.
+//
+// Every key press outside of text entry resolves to an `Action` through the
+// active `Keymap`. Bindings live in contexts: the current view's context is
+// searched first, then `KeyContext::Global`. A binding can be a chord of
+// several keys ("g g"), pending keys are kept until the chord completes, a
+// key breaks it or `CHORD_TIMEOUT` passes.
+//
+// The keymap starts from a preset (see presets.rs) and is adjusted by the
+// user's keymap.json (see config.rs). The YouTube search box and the settings
+// view keep their fixed keys since they take text and numbers directly.
+
+pub mod config;
+pub mod presets;
+
+pub use presets::Preset;
+
+use crate::app::AppView;
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long the keys of an unfinished chord are kept
+pub const CHORD_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Something a key binding does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Exit the application
+    Quit,
+    /// Open the command line
+    CommandLine,
+    ToggleHelp,
+    ToggleSettings,
+    ToggleStats,
+    TogglePause,
+    /// Give the video the whole terminal
+    ToggleFullscreen,
+    /// Seek by a number of seconds, negative seeks back
+    Seek(i32),
+    /// Seek to the start
+    Restart,
+    /// Close overlays, or stop playback and return to the menu
+    Stop,
+    /// Leave the current view
+    Back,
+    FileBrowser,
+    YoutubeSearch,
+    /// Move the selection up in lists
+    Up,
+    /// Move the selection down in lists
+    Down,
+    /// Open the selected entry
+    Select,
+    ParentDir,
+    HomeDir,
+    /// Filter the file list
+    Filter,
+    /// Call a script function bound with `bind_key`, see script.rs
+    Script(u16),
+}
+
+impl Action {
+    /// Actions without arguments, with their config names
+    const NAMED: [(&'static str, Action); 18] = [
+        ("quit", Action::Quit),
+        ("command-line", Action::CommandLine),
+        ("toggle-help", Action::ToggleHelp),
+        ("toggle-settings", Action::ToggleSettings),
+        ("toggle-stats", Action::ToggleStats),
+        ("toggle-pause", Action::TogglePause),
+        ("toggle-fullscreen", Action::ToggleFullscreen),
+        ("restart", Action::Restart),
+        ("stop", Action::Stop),
+        ("back", Action::Back),
+        ("file-browser", Action::FileBrowser),
+        ("youtube-search", Action::YoutubeSearch),
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("select", Action::Select),
+        ("parent-dir", Action::ParentDir),
+        ("home-dir", Action::HomeDir),
+        ("filter", Action::Filter),
+    ];
+
+    /// Parse an action as written in keymap.json, e.g. "toggle-pause" or "seek -5"
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if let Some(seconds) = text.strip_prefix("seek ") {
+            let seconds = seconds.trim();
+            return seconds
+                .strip_prefix('+')
+                .unwrap_or(seconds)
+                .parse()
+                .map(Action::Seek)
+                .map_err(|_| anyhow!("Invalid seek offset: {}", seconds));
+        }
+        Self::NAMED
+            .iter()
+            .find(|(name, _)| *name == text)
+            .map(|&(_, action)| action)
+            .ok_or_else(|| anyhow!("Unknown action: {}", text))
+    }
+
+    /// What the action does, for the help overlay
+    pub fn description(self) -> String {
+        match self {
+            Action::Quit => "Quit".to_string(),
+            Action::CommandLine => "Command line".to_string(),
+            Action::ToggleHelp => "Toggle help".to_string(),
+            Action::ToggleSettings => "Settings".to_string(),
+            Action::ToggleStats => "Toggle stats overlay".to_string(),
+            Action::TogglePause => "Play/Pause".to_string(),
+            Action::ToggleFullscreen => "Toggle fullscreen".to_string(),
+            Action::Seek(seconds) if seconds < 0 => format!("Seek back {} seconds", -seconds),
+            Action::Seek(seconds) => format!("Seek forward {} seconds", seconds),
+            Action::Restart => "Restart from the beginning".to_string(),
+            Action::Stop => "Stop playback/Return to menu".to_string(),
+            Action::Back => "Back".to_string(),
+            Action::FileBrowser => "Open file browser".to_string(),
+            Action::YoutubeSearch => "YouTube search".to_string(),
+            Action::Up => "Move up".to_string(),
+            Action::Down => "Move down".to_string(),
+            Action::Select => "Open selection".to_string(),
+            Action::ParentDir => "Parent directory".to_string(),
+            Action::HomeDir => "Home directory".to_string(),
+            Action::Filter => "Filter files".to_string(),
+            Action::Script(_) => "Script function".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Seek(seconds) => write!(f, "seek {:+}", seconds),
+            Action::Script(index) => write!(f, "script {}", index),
+            action => {
+                let (name, _) = Self::NAMED
+                    .iter()
+                    .find(|(_, named)| named == action)
+                    .expect("every action without arguments is named");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// Where a binding applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyContext {
+    /// Every view, after the view's own bindings
+    Global,
+    MainMenu,
+    Player,
+    FileBrowser,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 4] = [
+        KeyContext::Global,
+        KeyContext::MainMenu,
+        KeyContext::Player,
+        KeyContext::FileBrowser,
+    ];
+
+    /// Context of a view, None for views with fixed keys
+    pub fn for_view(view: AppView) -> Option<Self> {
+        match view {
+            AppView::MainMenu => Some(KeyContext::MainMenu),
+            AppView::Player => Some(KeyContext::Player),
+            AppView::FileBrowser => Some(KeyContext::FileBrowser),
+            AppView::YoutubeSearch | AppView::Settings => None,
+        }
+    }
+
+    /// Name used in keymap.json
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyContext::Global => "global",
+            KeyContext::MainMenu => "menu",
+            KeyContext::Player => "player",
+            KeyContext::FileBrowser => "browser",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|context| context.name() == text)
+            .ok_or_else(|| anyhow!("Unknown key context: {}", text))
+    }
+}
+
+/// A single key with its modifiers
+///
+/// Shift is folded into the character for printable keys, so `G` and
+/// `shift+g` are the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parse a key such as "q", "ctrl+q", "space" or "F1"
+    pub fn parse(text: &str) -> Result<Self> {
+        // A lone character is always that key, so "+" works
+        let (modifier_part, key) = match text.rfind('+') {
+            Some(index) if text.chars().count() > 1 && index + 1 < text.len() => {
+                (&text[..index], &text[index + 1..])
+            }
+            Some(_) if text.chars().count() > 1 => {
+                // The key is the trailing "+", drop the separator before it
+                let rest = &text[..text.len() - '+'.len_utf8()];
+                (rest.strip_suffix('+').unwrap_or(rest), "+")
+            }
+            _ => ("", text),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_part.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(anyhow!("Unknown modifier '{}' in key '{}'", modifier, text)),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => return Err(anyhow!("Unknown key: {}", text)),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("backtab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Insert => f.write_str("insert"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Parse a chord of space separated keys, e.g. "g g"
+pub fn parse_chord(text: &str) -> Result<Vec<KeyPress>> {
+    let keys = text
+        .split_whitespace()
+        .map(KeyPress::parse)
+        .collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        return Err(anyhow!("Empty key binding"));
+    }
+    Ok(keys)
+}
+
+/// Format a chord the way `parse_chord` reads it
+pub fn chord_to_string(keys: &[KeyPress]) -> String {
+    keys.iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Keys bound to an action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub keys: Vec<KeyPress>,
+    pub action: Action,
+}
+
+/// Result of feeding a key press to the keymap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// A binding completed
+    Action(Action),
+    /// The keys so far start a chord
+    Pending,
+    /// Nothing is bound to the keys
+    Unbound,
+}
+
+/// Keys and descriptions for one action, as shown in the help overlay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpEntry {
+    pub keys: String,
+    pub description: String,
+}
+
+/// Bindings per context plus the keys of an unfinished chord
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyContext, Vec<Binding>>,
+    pending: Vec<KeyPress>,
+    pending_since: Option<Instant>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Preset::default().keymap()
+    }
+}
+
+impl Keymap {
+    /// A keymap without bindings
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            pending: Vec::new(),
+            pending_since: None,
+        }
+    }
+
+    /// Bind keys in a context, returning the action they were bound to before
+    pub fn bind(
+        &mut self,
+        context: KeyContext,
+        keys: Vec<KeyPress>,
+        action: Action,
+    ) -> Option<Action> {
+        let bindings = self.bindings.entry(context).or_default();
+        match bindings.iter_mut().find(|binding| binding.keys == keys) {
+            Some(binding) => Some(std::mem::replace(&mut binding.action, action)),
+            None => {
+                bindings.push(Binding { keys, action });
+                None
+            }
+        }
+    }
+
+    /// Remove the binding for keys in a context
+    pub fn unbind(&mut self, context: KeyContext, keys: &[KeyPress]) -> Option<Action> {
+        let bindings = self.bindings.get_mut(&context)?;
+        let index = bindings.iter().position(|binding| binding.keys == keys)?;
+        Some(bindings.remove(index).action)
+    }
+
+    /// Bindings of a context, in the order they were added
+    pub fn bindings(&self, context: KeyContext) -> &[Binding] {
+        self.bindings.get(&context).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether a single key is bound to an action in a context
+    pub fn is_bound(&self, context: KeyContext, action: Action, key: KeyEvent) -> bool {
+        let key = KeyPress::from(key);
+        self.bindings(context)
+            .iter()
+            .any(|binding| binding.action == action && binding.keys == [key])
+    }
+
+    /// Keys of the chord being typed
+    pub fn pending(&self) -> &[KeyPress] {
+        &self.pending
+    }
+
+    /// Forget a partly typed chord
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+        self.pending_since = None;
+    }
+
+    /// Feed a key press, looking in the view's context and then the global one
+    pub fn resolve(&mut self, context: Option<KeyContext>, key: KeyEvent) -> Resolution {
+        if self
+            .pending_since
+            .is_some_and(|since| since.elapsed() > CHORD_TIMEOUT)
+        {
+            self.clear_pending();
+        }
+
+        self.pending.push(KeyPress::from(key));
+        let resolution = self.lookup(context);
+        if resolution == Resolution::Unbound && self.pending.len() > 1 {
+            // The chord is broken, the last key may still work on its own
+            let last = self.pending.pop();
+            self.pending.clear();
+            self.pending.extend(last);
+            let resolution = self.lookup(context);
+            return self.settle(resolution);
+        }
+        self.settle(resolution)
+    }
+
+    fn lookup(&self, context: Option<KeyContext>) -> Resolution {
+        for context in context.into_iter().chain([KeyContext::Global]) {
+            let bindings = self.bindings(context);
+            if let Some(binding) = bindings.iter().find(|binding| binding.keys == self.pending) {
+                return Resolution::Action(binding.action);
+            }
+            if bindings
+                .iter()
+                .any(|binding| binding.keys.starts_with(&self.pending))
+            {
+                return Resolution::Pending;
+            }
+        }
+        Resolution::Unbound
+    }
+
+    fn settle(&mut self, resolution: Resolution) -> Resolution {
+        if resolution == Resolution::Pending {
+            self.pending_since = Some(Instant::now());
+        } else {
+            self.clear_pending();
+        }
+        resolution
+    }
+
+    /// Help entries for a view: its own bindings, then the global ones
+    ///
+    /// Keys bound to the same action are listed together.
+    pub fn help(&self, context: Option<KeyContext>) -> Vec<(KeyContext, Vec<HelpEntry>)> {
+        context
+            .into_iter()
+            .chain([KeyContext::Global])
+            .map(|context| {
+                let mut actions: Vec<(Action, Vec<String>)> = Vec::new();
+                for binding in self.bindings(context) {
+                    let keys = chord_to_string(&binding.keys);
+                    match actions
+                        .iter_mut()
+                        .find(|(action, _)| *action == binding.action)
+                    {
+                        Some((_, all_keys)) => all_keys.push(keys),
+                        None => actions.push((binding.action, vec![keys])),
+                    }
+                }
+                let entries = actions
+                    .into_iter()
+                    .map(|(action, keys)| HelpEntry {
+                        keys: keys.join(", "),
+                        description: action.description(),
+                    })
+                    .collect();
+                (context, entries)
+            })
+            .collect()
+    }
+
+    /// Bindings that can't all be reached
+    ///
+    /// A binding that is a prefix of a longer chord in the same context makes
+    /// the chord unreachable, and a view binding hides global bindings
+    /// starting with the same keys.
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for context in KeyContext::ALL {
+            let bindings = self.bindings(context);
+            for (i, short) in bindings.iter().enumerate() {
+                for long in bindings.iter().skip(i + 1) {
+                    let (short, long) = if short.keys.len() <= long.keys.len() {
+                        (short, long)
+                    } else {
+                        (long, short)
+                    };
+                    if long.keys.starts_with(&short.keys) {
+                        conflicts.push(format!(
+                            "{}: '{}' ({}) makes '{}' ({}) unreachable",
+                            context.name(),
+                            chord_to_string(&short.keys),
+                            short.action,
+                            chord_to_string(&long.keys),
+                            long.action
+                        ));
+                    }
+                }
+            }
+
+            if context == KeyContext::Global {
+                continue;
+            }
+            for binding in bindings {
+                for global in self.bindings(KeyContext::Global) {
+                    if global.keys.starts_with(&binding.keys)
+                        || binding.keys.starts_with(&global.keys)
+                    {
+                        conflicts.push(format!(
+                            "{}: '{}' ({}) hides global '{}' ({})",
+                            context.name(),
+                            chord_to_string(&binding.keys),
+                            binding.action,
+                            chord_to_string(&global.keys),
+                            global.action
+                        ));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parses_and_formats_keys() {
+        for text in [
+            "q",
+            "ctrl+q",
+            "space",
+            "F1",
+            "left",
+            "+",
+            "ctrl++",
+            "alt+enter",
+            "G",
+            "alt+é",
+        ] {
+            let parsed = KeyPress::parse(text).unwrap();
+            assert_eq!(parsed.to_string(), text);
+        }
+        assert_eq!(
+            KeyPress::parse("shift+g").unwrap(),
+            KeyPress::parse("G").unwrap()
+        );
+        assert_eq!(
+            KeyPress::from(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            KeyPress::parse("G").unwrap()
+        );
+        assert!(KeyPress::parse("hyper+x").is_err());
+        // Not a modifier, and must not be split inside the character
+        assert!(KeyPress::parse("é+").is_err());
+        assert!(KeyPress::parse("ctrl+é+").is_err());
+        assert!(KeyPress::parse("nope").is_err());
+    }
+
+    #[test]
+    fn parses_actions() {
+        assert_eq!(Action::parse("toggle-pause").unwrap(), Action::TogglePause);
+        assert_eq!(Action::parse("seek +30").unwrap(), Action::Seek(30));
+        assert_eq!(Action::parse("seek -5").unwrap(), Action::Seek(-5));
+        assert_eq!(Action::Seek(-5).to_string(), "seek -5");
+        for (name, action) in Action::NAMED {
+            assert_eq!(action.to_string(), name);
+        }
+        assert!(Action::parse("dance").is_err());
+    }
+
+    #[test]
+    fn resolves_view_before_global() {
+        let mut keymap = Keymap::empty();
+        keymap.bind(KeyContext::Global, parse_chord("q").unwrap(), Action::Quit);
+        keymap.bind(KeyContext::Player, parse_chord("q").unwrap(), Action::Stop);
+
+        let q = key(KeyCode::Char('q'));
+        assert_eq!(
+            keymap.resolve(Some(KeyContext::Player), q),
+            Resolution::Action(Action::Stop)
+        );
+        assert_eq!(
+            keymap.resolve(Some(KeyContext::MainMenu), q),
+            Resolution::Action(Action::Quit)
+        );
+        assert_eq!(
+            keymap.resolve(None, key(KeyCode::Char('x'))),
+            Resolution::Unbound
+        );
+    }
+
+    #[test]
+    fn resolves_chords() {
+        let mut keymap = Keymap::empty();
+        keymap.bind(
+            KeyContext::Player,
+            parse_chord("g g").unwrap(),
+            Action::Restart,
+        );
+        keymap.bind(KeyContext::Player, parse_chord("x").unwrap(), Action::Stop);
+
+        let player = Some(KeyContext::Player);
+        let g = key(KeyCode::Char('g'));
+        assert_eq!(keymap.resolve(player, g), Resolution::Pending);
+        assert_eq!(
+            keymap.resolve(player, g),
+            Resolution::Action(Action::Restart)
+        );
+        assert!(keymap.pending().is_empty());
+
+        // A key that breaks the chord still runs its own binding
+        assert_eq!(keymap.resolve(player, g), Resolution::Pending);
+        assert_eq!(
+            keymap.resolve(player, key(KeyCode::Char('x'))),
+            Resolution::Action(Action::Stop)
+        );
+        assert!(keymap.pending().is_empty());
+    }
+
+    #[test]
+    fn presets_have_no_conflicts() {
+        for preset in Preset::ALL {
+            assert_eq!(
+                preset.keymap().conflicts(),
+                Vec::<String>::new(),
+                "{:?}",
+                preset
+            );
+        }
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let mut keymap = Keymap::empty();
+        keymap.bind(
+            KeyContext::Global,
+            parse_chord("ctrl+q").unwrap(),
+            Action::Quit,
+        );
+        keymap.bind(KeyContext::Player, parse_chord("g").unwrap(), Action::Stop);
+        keymap.bind(
+            KeyContext::Player,
+            parse_chord("g g").unwrap(),
+            Action::Restart,
+        );
+        keymap.bind(
+            KeyContext::Player,
+            parse_chord("ctrl+q").unwrap(),
+            Action::Stop,
+        );
+
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 2, "{:?}", conflicts);
+        assert!(conflicts[0].contains("'g g' (restart) unreachable"));
+        assert!(conflicts[1].contains("hides global 'ctrl+q' (quit)"));
+    }
+
+    #[test]
+    fn help_groups_keys_by_action() {
+        let keymap = Preset::Default.keymap();
+        let help = keymap.help(Some(KeyContext::Player));
+        assert_eq!(help[0].0, KeyContext::Player);
+        assert_eq!(help[1].0, KeyContext::Global);
+        let quit = help[1]
+            .1
+            .iter()
+            .find(|entry| entry.description == "Quit")
+            .unwrap();
+        assert_eq!(quit.keys, "ctrl+c, ctrl+q");
+    }
+}
@@ -0,0 +1,122 @@
+// Built-in keymaps that keymap.json starts from.
+
+use super::{Action, KeyContext, Keymap, parse_chord};
+use anyhow::{Result, anyhow};
+
+/// Starting point for the keymap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    /// The player's classic keys
+    #[default]
+    Default,
+    /// hjkl movement, `g g` to restart and `Z Z` to quit
+    Vim,
+    /// mpv's playback keys
+    Mpv,
+}
+
+/// Bindings every preset shares
+const COMMON: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::Global, "ctrl+c", Action::Quit),
+    (KeyContext::Global, "ctrl+q", Action::Quit),
+    (KeyContext::Global, ":", Action::CommandLine),
+    (KeyContext::Global, "F1", Action::ToggleHelp),
+    (KeyContext::MainMenu, "1", Action::FileBrowser),
+    (KeyContext::MainMenu, "2", Action::YoutubeSearch),
+    (KeyContext::MainMenu, "3", Action::ToggleSettings),
+    (KeyContext::MainMenu, "4", Action::ToggleHelp),
+    (KeyContext::MainMenu, "5", Action::Quit),
+    (KeyContext::MainMenu, "esc", Action::Back),
+    (KeyContext::Player, "space", Action::TogglePause),
+    (KeyContext::Player, "left", Action::Seek(-5)),
+    (KeyContext::Player, "right", Action::Seek(5)),
+    (KeyContext::Player, "esc", Action::Stop),
+    (KeyContext::Player, "o", Action::FileBrowser),
+    (KeyContext::Player, "y", Action::YoutubeSearch),
+    (KeyContext::Player, "s", Action::ToggleSettings),
+    (KeyContext::Player, "i", Action::ToggleStats),
+    (KeyContext::FileBrowser, "up", Action::Up),
+    (KeyContext::FileBrowser, "down", Action::Down),
+    (KeyContext::FileBrowser, "enter", Action::Select),
+    (KeyContext::FileBrowser, "backspace", Action::ParentDir),
+    (KeyContext::FileBrowser, "~", Action::HomeDir),
+    (KeyContext::FileBrowser, "/", Action::Filter),
+    (KeyContext::FileBrowser, "esc", Action::Back),
+];
+
+const DEFAULT: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::MainMenu, "f", Action::FileBrowser),
+    (KeyContext::MainMenu, "y", Action::YoutubeSearch),
+    (KeyContext::MainMenu, "s", Action::ToggleSettings),
+    (KeyContext::MainMenu, "h", Action::ToggleHelp),
+    (KeyContext::MainMenu, "q", Action::Quit),
+    (KeyContext::Player, "f", Action::Seek(30)),
+    (KeyContext::Player, "b", Action::Seek(-30)),
+    (KeyContext::Player, "h", Action::ToggleHelp),
+    (KeyContext::Player, "F", Action::ToggleFullscreen),
+];
+
+const VIM: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::MainMenu, "?", Action::ToggleHelp),
+    (KeyContext::MainMenu, "Z Z", Action::Quit),
+    (KeyContext::Player, "h", Action::Seek(-5)),
+    (KeyContext::Player, "l", Action::Seek(5)),
+    (KeyContext::Player, "H", Action::Seek(-30)),
+    (KeyContext::Player, "L", Action::Seek(30)),
+    (KeyContext::Player, "g g", Action::Restart),
+    (KeyContext::Player, "?", Action::ToggleHelp),
+    (KeyContext::Player, "q", Action::Stop),
+    (KeyContext::Player, "Z Z", Action::Quit),
+    (KeyContext::Player, "F", Action::ToggleFullscreen),
+    (KeyContext::FileBrowser, "k", Action::Up),
+    (KeyContext::FileBrowser, "j", Action::Down),
+    (KeyContext::FileBrowser, "l", Action::Select),
+    (KeyContext::FileBrowser, "h", Action::ParentDir),
+];
+
+const MPV: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::MainMenu, "h", Action::ToggleHelp),
+    (KeyContext::MainMenu, "q", Action::Quit),
+    (KeyContext::Player, "p", Action::TogglePause),
+    (KeyContext::Player, "up", Action::Seek(60)),
+    (KeyContext::Player, "down", Action::Seek(-60)),
+    (KeyContext::Player, "q", Action::Stop),
+    (KeyContext::Player, "Q", Action::Quit),
+    (KeyContext::Player, "h", Action::ToggleHelp),
+    (KeyContext::Player, "f", Action::ToggleFullscreen),
+];
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Default, Preset::Vim, Preset::Mpv];
+
+    /// Name used in keymap.json
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Vim => "vim",
+            Preset::Mpv => "mpv",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name() == text)
+            .ok_or_else(|| anyhow!("Unknown keymap preset: {}", text))
+    }
+
+    /// The preset's bindings
+    pub fn keymap(self) -> Keymap {
+        let extra = match self {
+            Preset::Default => DEFAULT,
+            Preset::Vim => VIM,
+            Preset::Mpv => MPV,
+        };
+        let mut keymap = Keymap::empty();
+        for &(context, keys, action) in COMMON.iter().chain(extra) {
+            let keys = parse_chord(keys).expect("preset keys parse");
+            keymap.bind(context, keys, action);
+        }
+        keymap
+    }
+}
@@ -1,9 +1,9 @@
 use std::{io, time::{Duration, Instant}, fs::OpenOptions, io::Write};
 use anyhow::{Result, Context};
+use clap::Parser;
 use ratatui::{
     backend::CrosstermBackend, 
     Terminal,
-    style::Color,
 };
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event},
@@ -34,12 +34,48 @@ fn debug_log(message: &str) {
 
 mod app;
 mod commands;
+mod config;
 mod events; // Contains event utility functions
+#[cfg(unix)]
+mod ipc;
+mod keymap;
+#[cfg(feature = "mpris")]
+mod mpris;
+#[cfg(feature = "remote")]
+mod remote;
+mod script;
 mod ui;
 
 use app::App;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use ui::stats::CountingWriter;
+use ui::theme::StatusKind;
+
+/// Play videos in the terminal
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// File, URL or YouTube video ID to open
+    media: Option<String>,
+    /// Take JSON commands on a Unix socket, see ipc.rs
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH")]
+    ipc: Option<std::path::PathBuf>,
+    /// Serve the web remote, on 127.0.0.1:8080 without an address, see remote.rs
+    #[cfg(feature = "remote")]
+    #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = remote::DEFAULT_ADDRESS)]
+    remote: Option<std::net::SocketAddr>,
+    /// Token the web remote asks for, a new one each run without it
+    #[cfg(feature = "remote")]
+    #[arg(long, value_name = "TOKEN", requires = "remote")]
+    remote_token: Option<String>,
+}
 
 fn main() -> Result<()> {
+    // Parse arguments before the terminal is taken over, so --help prints normally
+    let cli = Cli::parse();
+
     // Setup logger
     env_logger::init();
     
@@ -54,6 +90,7 @@ fn main() -> Result<()> {
         let mut stdout = io::stdout();
         let _ = execute!(
             stdout,
+            DisableMouseCapture,
             LeaveAlternateScreen
         );
         
@@ -90,9 +127,13 @@ fn main() -> Result<()> {
     }
     
     debug_log("Terminal setup complete");
-    
-    // Create backend and terminal
-    let backend = CrosstermBackend::new(stdout);
+
+    // Probe now, while nothing else reads stdin, so keypresses aren't taken for replies
+    core::render::TerminalCapabilities::detect();
+
+    // Create backend and terminal, counting the bytes sent for the stats overlay
+    let terminal_bytes = Arc::new(AtomicU64::new(0));
+    let backend = CrosstermBackend::new(CountingWriter::new(stdout, terminal_bytes.clone()));
     let mut terminal = match Terminal::new(backend) {
         Ok(term) => term,
         Err(e) => {
@@ -104,37 +145,53 @@ fn main() -> Result<()> {
     
     // Create app state and initialize
     let mut app = App::new();
+    app.terminal_bytes = terminal_bytes;
     
-    // Start with mouse capture disabled to prevent escape sequences
+    // Every view handles the mouse, see App::handle_mouse_event
     let _ = execute!(
         terminal.backend_mut(),
-        DisableMouseCapture
+        EnableMouseCapture
     );
-    debug_log("App initialized with mouse capture disabled");
+    debug_log("App initialized with mouse capture enabled");
     
+    // Listen before opening media so clients see it load
+    #[cfg(unix)]
+    if let Some(Err(e)) = cli.ipc.as_deref().map(|path| app.start_ipc(path)) {
+        debug_log(&format!("Failed to start IPC: {:#}", e));
+        app.set_status(format!("IPC: {:#}", e), StatusKind::Error);
+    }
+
+    // Media keys and desktop applets, see mpris.rs
+    #[cfg(feature = "mpris")]
+    app.start_mpris();
+
+    // Phones and browsers, see remote.rs
+    #[cfg(feature = "remote")]
+    if let Some(Err(e)) = cli.remote.map(|address| app.start_remote(address, cli.remote_token.clone())) {
+        debug_log(&format!("Failed to start the remote: {:#}", e));
+        app.set_status(format!("Remote: {:#}", e), StatusKind::Error);
+    }
+
     // If a command line argument is provided, try to open it
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        debug_log(&format!("Attempting to open media: {}", &args[1]));
+    if let Some(media) = &cli.media {
+        debug_log(&format!("Attempting to open media: {}", media));
         // Try to open the specified file or URL
-        if let Err(e) = app.open_media(&args[1]) {
+        if let Err(e) = app.open_media(media) {
             let error_msg = format!("Error opening media: {}", e);
             debug_log(&error_msg);
             eprintln!("{}", error_msg);
-            app.set_status(format!("Error: {}", e), Color::Red);
+            app.set_status(format!("Error: {}", e), StatusKind::Error);
         } else {
             debug_log("Media opened successfully");
-            app.set_status("Media loaded successfully", Color::Green);
+            app.set_status("Media loaded successfully", StatusKind::Success);
                         
-            // If we're loading directly into player view, ensure mouse capture is disabled
-            // and terminal is cleared
+            // If we're loading directly into player view, ensure terminal is cleared
             if app.view == app::AppView::Player {
                 let _ = execute!(
                     terminal.backend_mut(),
-                    DisableMouseCapture,
                     crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
                 );
-                debug_log("Terminal cleared and mouse capture disabled for direct video playback");
+                debug_log("Terminal cleared for direct video playback");
             }
         }
     }
@@ -174,6 +231,14 @@ fn main() -> Result<()> {
                 // Don't propagate terminal draw errors to avoid crashing
                 // on non-critical display issues
             }
+            // Graphics protocol frames go out after ratatui has flushed so the
+            // UI never paints over them
+            if let Some(graphics) = app.video_state.take_graphics() {
+                let backend = terminal.backend_mut();
+                if let Err(e) = backend.write_all(&graphics).and_then(|_| backend.flush()) {
+                    debug_log(&format!("ERROR: Failed to write video frame: {}", e));
+                }
+            }
             let draw_time = draw_start.elapsed();
             if draw_time > Duration::from_millis(50) {
                 debug_log(&format!("SLOW RENDER: UI draw took {}ms", draw_time.as_millis()));
@@ -209,81 +274,50 @@ fn main() -> Result<()> {
                     debug_log(&format!("EVENT: Key {:?} with modifiers {:?}", key.code, key.modifiers));
                             
                     // Check for global quit key using utility function
-                    if events::event_utils::is_terminate_event(&Event::Key(key)) {
+                    if events::event_utils::is_terminate_event(&app.keymap, &Event::Key(key)) {
                         debug_log("ACTION: Quit key pressed, exiting application");
                         app.should_quit = true;
                         break;
                     }
                             
-                    // Check for command mode (press ':')
-                    if key.code == event::KeyCode::Char(':') && !app.is_command_mode() {
-                        debug_log("Entering command mode");
-                        app.enter_command_mode();
-                    } else if app.is_command_mode() && key.code == event::KeyCode::Enter {
-                        // Execute command when Enter is pressed in command mode
-                        let cmd = app.get_command_buffer().to_string();
-                        debug_log(&format!("Executing command: {}", cmd));
-                        app.exit_command_mode();
-                                
-                        if let Err(e) = commands::handle_command(&mut app, &cmd) {
-                            debug_log(&format!("Command error: {}", e));
-                            app.set_status(format!("Error: {}", e), Color::Red);
-                        } else {
-                            debug_log("Command executed successfully");
-                        }
-                    } else {
-                        debug_log(&format!("Handling key event: {:?}", key));
-                        if let Err(e) = app.handle_key_event(key) {
-                            debug_log(&format!("Key handler error: {}", e));
-                            app.set_status(format!("Key error: {}", e), Color::Red);
-                        }
-                                
-                        // Ensure mouse state is always updated after key events that might change app view
-                        if key.code == event::KeyCode::Esc || 
-                           key.code == event::KeyCode::Char(' ') ||
-                           key.code == event::KeyCode::Char('o') ||
-                           key.code == event::KeyCode::Char('y') {
-                            // Clear the terminal to prevent artifacts during view transitions
-                            let _ = execute!(
-                                terminal.backend_mut(),
-                                crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
-                            );
+                    // The command line runs on Enter, see App::handle_command_line_key
+                    debug_log(&format!("Handling key event: {:?}", key));
+                    let layout_before = app.layout_state();
+                    if let Err(e) = app.handle_key_event(key) {
+                        debug_log(&format!("Key handler error: {}", e));
+                        app.set_status(format!("Key error: {}", e), StatusKind::Error);
+                    }
                             
-                            // Update mouse state based on current view
-                            if app.view == app::AppView::Player {
-                                // Always disable mouse capture during video playback
-                                let _ = execute!(
-                                    terminal.backend_mut(),
-                                    DisableMouseCapture
-                                );
-                                debug_log("Cleared terminal and disabled mouse capture for playback");
-                            } else {
-                                // Re-enable mouse capture for UI interaction
-                                let _ = execute!(
-                                    terminal.backend_mut(),
-                                    EnableMouseCapture
-                                );
-                                debug_log("Cleared terminal and enabled mouse capture for UI");
-                            }
-                        }
+                    // Clear the terminal to prevent artifacts when the view or the
+                    // player layout changes, whichever keys they are bound to
+                    if app.layout_state() != layout_before {
+                        // Also makes the next draw repaint every cell
+                        let _ = terminal.clear();
+                        debug_log("Cleared terminal for layout change");
                     }
                 }
                 Ok(Event::Mouse(mouse)) => {
-                    // Handle navigation-related mouse events
-                    let is_nav_event = events::event_utils::is_navigation_event(&Event::Mouse(mouse));
-                    // For playback view, disable mouse and clear artifacts
-                    if app.view == app::AppView::Player {
-                        // Make sure mouse remains disabled for player view and clear any artifacts
-                        let _ = execute!(
-                            terminal.backend_mut(),
-                            DisableMouseCapture,
-                            crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
-                        );
-                        debug_log(&format!("Mouse event received (navigation: {}) - cleared terminal and disabled mouse", is_nav_event));
+                    let layout_before = app.layout_state();
+                    if let Err(e) = app.handle_mouse_event(mouse) {
+                        debug_log(&format!("Mouse handler error: {}", e));
+                        app.set_status(format!("Mouse error: {}", e), StatusKind::Error);
+                    }
+                    
+                    // Double-clicks open files and toggle fullscreen, moving shows the controls
+                    if app.layout_state() != layout_before {
+                        let _ = terminal.clear();
+                        debug_log("Cleared terminal for layout change");
                     }
                 }
                 Ok(Event::Resize(w, h)) => {
                     debug_log(&format!("Resize event: {}x{}", w, h));
+                    // Re-lay out the video so it doesn't overlap the controls
+                    app.handle_resize(w, h);
+                    // Old frames may be left outside the new video area
+                    let _ = execute!(
+                        terminal.backend_mut(),
+                        crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+                    );
                     // Force a redraw on resize
                     last_tick = Instant::now() - tick_rate;
                 }
@@ -296,6 +330,14 @@ fn main() -> Result<()> {
             }
         }
         
+        // Commands from IPC clients, see ipc.rs
+        #[cfg(unix)]
+        app.handle_ipc();
+        #[cfg(feature = "mpris")]
+        app.handle_mpris();
+        #[cfg(feature = "remote")]
+        app.handle_remote();
+        
         // Update video state more frequently than UI for smooth playback
         let video_elapsed = last_video_update.elapsed();
         if video_elapsed >= video_update_rate {
@@ -305,6 +347,9 @@ fn main() -> Result<()> {
                     match player.update() {
                         Ok(_) => {
                             // Video frame updated successfully
+                            if let Some(output) = player.take_output() {
+                                app.video_state.update(output);
+                            }
                             let actual_update_time = video_update_start.elapsed();
                             if actual_update_time > Duration::from_millis(33) {
                                 // Log if frame decode/render took longer than ~30fps would allow
@@ -317,7 +362,7 @@ fn main() -> Result<()> {
                         Err(e) => {
                             debug_log(&format!("ERROR: Playback error: {} at position {:.2}s", 
                                              e, player.get_position()));
-                            app.set_status(format!("Playback error: {}", e), Color::Red);
+                            app.set_status(format!("Playback error: {}", e), StatusKind::Error);
                         }
                     }
                 }
@@ -333,15 +378,16 @@ fn main() -> Result<()> {
         
         // Update app state at a controlled rate
         if now.duration_since(last_tick) >= tick_rate {
-            // Track view changes to toggle mouse capture
+            // Track view and layout changes to clear the terminal
             let old_view = app.view;
+            let layout_before = app.layout_state();
             
             if let Err(e) = app.update() {
                 debug_log(&format!("App update error: {}", e));
-                app.set_status(format!("Error: {}", e), Color::Red);
+                app.set_status(format!("Error: {}", e), StatusKind::Error);
             }
             
-            // Log view changes and update mouse capture state
+            // Log view changes
             if old_view != app.view {
                 debug_log(&format!("View changed from {:?} to {:?}", old_view, app.view));
                 
@@ -351,23 +397,10 @@ fn main() -> Result<()> {
                     crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
                 );
                 debug_log("Cleared terminal for view transition");
-            
-                // Whenever view changes, ensure mouse capture state is correct
-                if app.view == app::AppView::Player {
-                    // Disable mouse capture when entering player view
-                    let _ = execute!(
-                        terminal.backend_mut(),
-                        DisableMouseCapture
-                    );
-                    debug_log("Disabled mouse capture for player view");
-                } else if old_view == app::AppView::Player {
-                    // Re-enable mouse capture when leaving player view
-                    let _ = execute!(
-                        terminal.backend_mut(),
-                        EnableMouseCapture
-                    );
-                    debug_log("Re-enabled mouse capture for UI view");
-                }
+            } else if app.layout_state() != layout_before {
+                // Fullscreen controls hid after the idle timeout, repaint every cell
+                let _ = terminal.clear();
+                debug_log("Cleared terminal for layout change");
             }
         }
         
@@ -411,6 +444,7 @@ fn main() -> Result<()> {
         debug_log("CLEANUP: Leaving alternate screen");
         execute!(
             terminal.backend_mut(),
+            DisableMouseCapture,
             LeaveAlternateScreen
         ).context("Failed to leave alternate screen")?;
         
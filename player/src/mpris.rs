@@ -0,0 +1,612 @@
+// MPRIS over D-Bus, so media keys, playerctl and desktop applets control the
+// player. Built with `--features mpris`.
+//
+// The interfaces are served from zbus's own thread. They answer property
+// reads from a `Status` the app refreshes every update, and pass method
+// calls back as `Request`s for the main loop, see `App::handle_mpris`.
+//
+//     playerctl --player=tui_player play-pause
+//     playerctl --player=tui_player metadata
+
+use crate::commands::spec;
+use crate::events::PlayerEvent;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use url::Url;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, Value};
+use zbus::{blocking, fdo, interface};
+
+const PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Slowest and fastest speed, as `:speed` allows
+const MIN_RATE: f64 = spec::MIN_SPEED;
+const MAX_RATE: f64 = spec::MAX_SPEED;
+
+/// Something a D-Bus client asked for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    /// Play the next file in the queue
+    Next,
+    /// Seek by seconds
+    Seek(f64),
+    /// Seek to seconds
+    SetPosition(f64),
+    Open(String),
+    /// Volume in percent
+    SetVolume(i32),
+    SetRate(f64),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Playback {
+    #[default]
+    Stopped,
+    Playing,
+    Paused,
+}
+
+/// The media being played, as shown in Metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Track {
+    /// Counts the files opened, for the track ID, set by `Mpris::update`
+    pub number: u64,
+    pub url: String,
+    pub title: String,
+    /// Seconds
+    pub duration: f64,
+    pub artist: Option<String>,
+    pub art_url: Option<String>,
+}
+
+/// What D-Bus clients see of the player
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub playback: Playback,
+    /// Seconds
+    pub position: f64,
+    /// Percent
+    pub volume: i32,
+    pub rate: f64,
+    pub track: Option<Track>,
+    /// Whether the queue has something to play next
+    pub has_next: bool,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            playback: Playback::Stopped,
+            position: 0.0,
+            volume: 100,
+            rate: 1.0,
+            track: None,
+            has_next: false,
+        }
+    }
+}
+
+/// State shared with the interfaces
+#[derive(Clone)]
+struct Shared {
+    status: Arc<Mutex<Status>>,
+    requests: Sender<Request>,
+}
+
+impl Shared {
+    fn status(&self) -> Status {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn request(&self, request: Request) {
+        // The receiver only goes away with the app
+        let _ = self.requests.send(request);
+    }
+}
+
+/// org.mpris.MediaPlayer2
+struct Root {
+    shared: Shared,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        self.shared.request(Request::Quit);
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "TUI Player"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<&str> {
+        vec!["file", "http", "https"]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<&str> {
+        vec![
+            "video/mp4",
+            "video/x-matroska",
+            "video/webm",
+            "video/quicktime",
+        ]
+    }
+}
+
+/// What to open for an `OpenUri` call: the path of a file URI, or the URL
+fn open_target(uri: &str) -> fdo::Result<String> {
+    if uri.starts_with("http://") || uri.starts_with("https://") {
+        return Ok(uri.to_string());
+    }
+    if !uri.starts_with("file://") {
+        return Err(fdo::Error::NotSupported(format!(
+            "Unsupported URI: {}",
+            uri
+        )));
+    }
+    // File URIs are percent-encoded, `file:///a%20b.mkv` is "/a b.mkv"
+    Url::parse(uri)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .and_then(|path| path.into_os_string().into_string().ok())
+        .ok_or_else(|| fdo::Error::InvalidArgs(format!("Invalid file URI: {}", uri)))
+}
+
+/// org.mpris.MediaPlayer2.Player
+struct Player {
+    shared: Shared,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        // Clients shouldn't call this when CanGoNext is false, but may
+        if self.shared.status().has_next {
+            self.shared.request(Request::Next);
+        }
+    }
+
+    fn previous(&self) {}
+
+    fn pause(&self) {
+        self.shared.request(Request::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.shared.request(Request::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.shared.request(Request::Stop);
+    }
+
+    fn play(&self) {
+        self.shared.request(Request::Play);
+    }
+
+    /// Seek by `offset` microseconds
+    fn seek(&self, offset: i64) {
+        self.shared.request(Request::Seek(offset as f64 / 1e6));
+    }
+
+    /// Seek to `position` microseconds, if the track is still the current one
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let status = self.shared.status();
+        let current = status.track.as_ref().map(track_id_of);
+        if current.as_deref() == Some(track_id.as_str()) && position >= 0 {
+            self.shared
+                .request(Request::SetPosition(position as f64 / 1e6));
+        }
+    }
+
+    fn open_uri(&self, uri: &str) -> fdo::Result<()> {
+        self.shared.request(Request::Open(open_target(uri)?));
+        Ok(())
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        match self.shared.status().playback {
+            Playback::Stopped => "Stopped",
+            Playback::Playing => "Playing",
+            Playback::Paused => "Paused",
+        }
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.shared.status().rate
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, rate: f64) {
+        // A rate of 0 means pause, out of range rates are ignored
+        if rate == 0.0 {
+            self.shared.request(Request::Pause);
+        } else if (MIN_RATE..=MAX_RATE).contains(&rate) {
+            self.shared.request(Request::SetRate(rate));
+        }
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        self.shared
+            .status()
+            .track
+            .as_ref()
+            .map(metadata)
+            .unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.shared.status().volume as f64 / 100.0
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        let percent = (volume.clamp(0.0, 1.0) * 100.0).round() as i32;
+        self.shared.request(Request::SetVolume(percent));
+    }
+
+    /// Read on demand, clients follow it with the rate and `Seeked`
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        (self.shared.status().position * 1e6) as i64
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        MIN_RATE
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        MAX_RATE
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.shared.status().has_next
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.shared.status().track.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.shared.status().track.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.shared.status().track.is_some()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// D-Bus object path identifying a track
+fn track_id_of(track: &Track) -> String {
+    format!("/org/tui_player/track/{}", track.number)
+}
+
+/// The Metadata property for a track
+fn metadata(track: &Track) -> HashMap<String, Value<'static>> {
+    let mut metadata = HashMap::new();
+    if let Ok(id) = ObjectPath::try_from(track_id_of(track)) {
+        metadata.insert("mpris:trackid".to_string(), Value::from(id));
+    }
+    metadata.insert(
+        "mpris:length".to_string(),
+        Value::from((track.duration * 1e6) as i64),
+    );
+    metadata.insert("xesam:title".to_string(), Value::from(track.title.clone()));
+    metadata.insert("xesam:url".to_string(), Value::from(track.url.clone()));
+    if let Some(artist) = &track.artist {
+        metadata.insert(
+            "xesam:artist".to_string(),
+            Value::from(vec![artist.clone()]),
+        );
+    }
+    if let Some(art_url) = &track.art_url {
+        metadata.insert("mpris:artUrl".to_string(), Value::from(art_url.clone()));
+    }
+    metadata
+}
+
+/// The exported player, owned by the app
+pub struct Mpris {
+    connection: blocking::Connection,
+    status: Arc<Mutex<Status>>,
+    requests: Receiver<Request>,
+    /// Last status clients were told about
+    announced: Status,
+    /// Files opened so far
+    tracks: u64,
+}
+
+impl Mpris {
+    /// Export the player on the session bus
+    pub fn start() -> Result<Self> {
+        Self::start_with(blocking::connection::Builder::session()?)
+    }
+
+    /// Export the player on a bus given by address, such as a private test bus
+    pub fn start_at(address: &str) -> Result<Self> {
+        Self::start_with(blocking::connection::Builder::address(address)?)
+    }
+
+    fn start_with(builder: blocking::connection::Builder<'_>) -> Result<Self> {
+        let (sender, requests) = channel();
+        let status = Arc::new(Mutex::new(Status::default()));
+        let shared = Shared {
+            status: status.clone(),
+            requests: sender,
+        };
+        // One name per process, so several players can run at once
+        let name = format!(
+            "org.mpris.MediaPlayer2.tui_player.instance{}",
+            std::process::id()
+        );
+        let connection = builder
+            .name(name.as_str())?
+            .serve_at(
+                PATH,
+                Root {
+                    shared: shared.clone(),
+                },
+            )?
+            .serve_at(PATH, Player { shared })?
+            .build()
+            .context("Failed to export MPRIS on D-Bus")?;
+        log::info!("MPRIS exported as {}", name);
+
+        Ok(Self {
+            connection,
+            status,
+            requests,
+            announced: Status::default(),
+            tracks: 0,
+        })
+    }
+
+    /// Next request from a client
+    pub fn take_request(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+
+    /// Refresh what clients see, signalling the properties that changed
+    pub fn update(&mut self, mut status: Status) {
+        if let Some(track) = &mut status.track {
+            track.number = self.tracks;
+        }
+        *self.status.lock().unwrap() = status.clone();
+        if status == self.announced {
+            return;
+        }
+        let old = std::mem::replace(&mut self.announced, status);
+        let new = &self.announced;
+        if let Err(e) = self.announce(&old, new) {
+            log::warn!("MPRIS: {}", e);
+        }
+    }
+
+    fn announce(&self, old: &Status, new: &Status) -> Result<()> {
+        let player = self
+            .connection
+            .object_server()
+            .interface::<_, Player>(PATH)?;
+        let emitter = player.signal_emitter();
+        let player = player.get();
+        zbus::block_on(async {
+            if old.playback != new.playback {
+                player.playback_status_changed(emitter).await?;
+            }
+            if old.rate != new.rate {
+                player.rate_changed(emitter).await?;
+            }
+            if old.volume != new.volume {
+                player.volume_changed(emitter).await?;
+            }
+            if old.has_next != new.has_next {
+                player.can_go_next_changed(emitter).await?;
+            }
+            if old.track != new.track {
+                player.metadata_changed(emitter).await?;
+                player.can_play_changed(emitter).await?;
+                player.can_pause_changed(emitter).await?;
+                player.can_seek_changed(emitter).await?;
+            }
+            Ok::<_, zbus::Error>(())
+        })?;
+        Ok(())
+    }
+
+    /// Follow the player's events
+    pub fn event(&mut self, event: &PlayerEvent) {
+        match event {
+            PlayerEvent::FileLoaded(_) => self.tracks += 1,
+            PlayerEvent::Seek(position) => self.seeked(*position),
+            _ => {}
+        }
+    }
+
+    /// Tell clients playback jumped, to `position` seconds
+    fn seeked(&self, position: f64) {
+        let result = self
+            .connection
+            .object_server()
+            .interface::<_, Player>(PATH)
+            .and_then(|player| {
+                zbus::block_on(Player::seeked(
+                    player.signal_emitter(),
+                    (position * 1e6) as i64,
+                ))
+            });
+        if let Err(e) = result {
+            log::warn!("MPRIS: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    fn track() -> Track {
+        Track {
+            number: 3,
+            url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            title: "Never Gonna Give You Up".to_string(),
+            duration: 212.0,
+            artist: Some("Rick Astley".to_string()),
+            art_url: Some("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg".to_string()),
+        }
+    }
+
+    #[test]
+    fn builds_metadata() {
+        let metadata = metadata(&track());
+        assert_eq!(
+            metadata["mpris:trackid"],
+            Value::from(ObjectPath::try_from("/org/tui_player/track/3").unwrap())
+        );
+        assert_eq!(metadata["mpris:length"], Value::from(212_000_000i64));
+        assert_eq!(
+            metadata["xesam:title"],
+            Value::from("Never Gonna Give You Up")
+        );
+        assert_eq!(
+            metadata["mpris:artUrl"],
+            Value::from("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg")
+        );
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(
+            open_target("file:///home/me/Videos/My%20Clip%20%231.mkv").unwrap(),
+            "/home/me/Videos/My Clip #1.mkv"
+        );
+        assert_eq!(
+            open_target("file://localhost/tmp/caf%C3%A9.mp4").unwrap(),
+            "/tmp/café.mp4"
+        );
+        assert_eq!(
+            open_target("https://example.com/a%20b.mp4").unwrap(),
+            "https://example.com/a%20b.mp4"
+        );
+        assert!(open_target("file://server/share/clip.mkv").is_err());
+        assert!(open_target("ftp://example.com/clip.mkv").is_err());
+    }
+
+    /// Runs against a private bus when dbus-daemon is installed
+    #[test]
+    fn serves_a_private_bus() {
+        let Ok(mut daemon) = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+        else {
+            eprintln!("dbus-daemon not found, skipping");
+            return;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+
+        let mut mpris = Mpris::start_at(address.trim()).unwrap();
+        mpris.update(Status {
+            playback: Playback::Playing,
+            position: 12.5,
+            track: Some(track()),
+            has_next: true,
+            ..Status::default()
+        });
+
+        let client = blocking::connection::Builder::address(address.trim())
+            .unwrap()
+            .build()
+            .unwrap();
+        let name = format!(
+            "org.mpris.MediaPlayer2.tui_player.instance{}",
+            std::process::id()
+        );
+        let player = blocking::Proxy::new(
+            &client,
+            name.as_str(),
+            PATH,
+            "org.mpris.MediaPlayer2.Player",
+        )
+        .unwrap();
+
+        let status: String = player.get_property("PlaybackStatus").unwrap();
+        assert_eq!(status, "Playing");
+        let position: i64 = player.get_property("Position").unwrap();
+        assert_eq!(position, 12_500_000);
+        let can_go_next: bool = player.get_property("CanGoNext").unwrap();
+        assert!(can_go_next);
+
+        player.call_method("PlayPause", &()).unwrap();
+        player.call_method("Next", &()).unwrap();
+        player.call_method("Seek", &(-5_000_000i64)).unwrap();
+        player.set_property("Volume", 0.4).unwrap();
+        let requests: Vec<Request> = std::iter::from_fn(|| mpris.take_request()).collect();
+        assert_eq!(
+            requests,
+            [
+                Request::PlayPause,
+                Request::Next,
+                Request::Seek(-5.0),
+                Request::SetVolume(40)
+            ]
+        );
+
+        let _ = daemon.kill();
+        let _ = daemon.wait();
+    }
+}
@@ -0,0 +1,402 @@
+// HTTP and WebSocket remote control, with a small web page for phones.
+// Built with `--features remote`.
+//
+//     tui_player --remote                    on 127.0.0.1:8080
+//     tui_player --remote 0.0.0.0:8080       reachable from other machines
+//
+// The page and its token are shown in the status bar as
+// http://127.0.0.1:8080/?token=..., the log only gets the address. Every
+// other route needs the token, as
+// `Authorization: Bearer <token>` or `?token=<token>`:
+//
+//     GET    /api/status                     what's playing, see `App::remote_status`
+//     POST   /api/play, pause, toggle, next, restart
+//     POST   /api/seek    {"position": 90} or {"offset": -10}
+//     POST   /api/volume  {"volume": 40}
+//     POST   /api/open    {"path": "..."}
+//     GET    /api/queue
+//     POST   /api/queue   {"path": "..."}
+//     DELETE /api/queue
+//     GET    /ws                             events, as `PlayerEvent::to_json`
+//
+// The server runs on its own thread. Requests become command lines that the
+// main loop runs through `CommandHandler`, see `App::handle_remote`, and
+// WebSocket clients get the same events as scripts and IPC clients plus a
+// "status" message whenever more than the position changes.
+
+use crate::commands::parse;
+use crate::events::PlayerEvent;
+use anyhow::{Context, Result, anyhow};
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::{Path, Request as HttpRequest, State, WebSocketUpgrade};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+
+/// Where the server listens without an address
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// How long a request waits for the main loop, opening a YouTube video
+/// takes a while
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The web remote
+const PAGE: &str = include_str!("remote.html");
+
+/// A command line to run for a client, and where its outcome goes
+pub struct Request {
+    pub line: String,
+    pub reply: oneshot::Sender<Result<(), String>>,
+}
+
+/// State shared with the request handlers
+#[derive(Clone)]
+struct Shared {
+    token: Arc<str>,
+    status: Arc<Mutex<Value>>,
+    requests: mpsc::Sender<Request>,
+    events: broadcast::Sender<String>,
+}
+
+/// The running server, owned by the app
+pub struct Remote {
+    address: SocketAddr,
+    token: String,
+    status: Arc<Mutex<Value>>,
+    requests: mpsc::Receiver<Request>,
+    events: broadcast::Sender<String>,
+    /// Last status sent to WebSocket clients, without the position
+    announced: Value,
+}
+
+impl Remote {
+    /// Listen on an address, with a new token unless one is given
+    pub fn start(address: SocketAddr, token: Option<String>) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(address)
+            .with_context(|| format!("Failed to listen on {}", address))?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let token = match token {
+            Some(token) => token,
+            None => new_token()?,
+        };
+
+        let (sender, requests) = mpsc::channel();
+        let (events, _) = broadcast::channel(256);
+        let status = Arc::new(Mutex::new(json!({})));
+        let app = router(Shared {
+            token: token.as_str().into(),
+            status: status.clone(),
+            requests: sender,
+            events: events.clone(),
+        });
+
+        std::thread::Builder::new()
+            .name("remote".to_string())
+            .spawn(move || {
+                let served = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .and_then(|runtime| {
+                        runtime.block_on(async {
+                            let listener = tokio::net::TcpListener::from_std(listener)?;
+                            axum::serve(listener, app).await
+                        })
+                    });
+                if let Err(e) = served {
+                    log::error!("Remote server stopped: {}", e);
+                }
+            })
+            .context("Failed to start the remote server")?;
+        log::info!("Remote listening on {}", address);
+
+        Ok(Self {
+            address,
+            token,
+            status,
+            requests,
+            events,
+            announced: Value::Null,
+        })
+    }
+
+    /// The web remote's address with the token
+    pub fn url(&self) -> String {
+        format!("http://{}/?token={}", self.address, self.token)
+    }
+
+    /// Next command line a client sent
+    pub fn take_request(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+
+    /// Send an event to WebSocket clients
+    pub fn event(&self, event: &PlayerEvent) {
+        // Nobody listening is fine
+        let _ = self.events.send(event.to_json().to_string());
+    }
+
+    /// Refresh the status, pushing it when more than the position changed
+    pub fn update(&mut self, status: Value) {
+        *self.status.lock().unwrap() = status.clone();
+
+        let mut without_position = status.clone();
+        if let Some(fields) = without_position.as_object_mut() {
+            fields.remove("position");
+        }
+        if without_position != self.announced {
+            self.announced = without_position;
+            let _ = self.events.send(status_message(status).to_string());
+        }
+    }
+}
+
+/// A status as sent over the WebSocket
+fn status_message(mut status: Value) -> Value {
+    status["event"] = json!("status");
+    status
+}
+
+/// A token for a session, 128 bits from the OS random number generator
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("Failed to generate a token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Compare tokens in time independent of where they differ
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn router(shared: Shared) -> Router {
+    let api = Router::new()
+        .route("/api/status", get(status))
+        .route("/api/seek", post(seek))
+        .route("/api/volume", post(volume))
+        .route("/api/open", post(open))
+        .route("/api/queue", get(queue).post(enqueue).delete(unqueue))
+        .route("/api/{action}", post(action))
+        .route("/ws", get(websocket))
+        .route_layer(middleware::from_fn_with_state(shared.clone(), authorize));
+
+    Router::new()
+        .route("/", get(|| async { Html(PAGE) }))
+        .merge(api)
+        .with_state(shared)
+}
+
+/// Turn away requests without the token
+async fn authorize(State(shared): State<Shared>, request: HttpRequest, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = request.uri().query().unwrap_or_default();
+    let param = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="));
+
+    match bearer.or(param) {
+        Some(given) if same_token(given, &shared.token) => next.run(request).await,
+        _ => error(StatusCode::UNAUTHORIZED, "Missing or wrong token"),
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// Have the main loop run a command line and wait for the outcome
+async fn run(shared: &Shared, line: String) -> Response {
+    let (reply, outcome) = oneshot::channel();
+    if shared.requests.send(Request { line, reply }).is_err() {
+        return error(StatusCode::SERVICE_UNAVAILABLE, "The player has stopped");
+    }
+    match tokio::time::timeout(REPLY_TIMEOUT, outcome).await {
+        Ok(Ok(Ok(()))) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Ok(Err(message))) => error(StatusCode::BAD_REQUEST, &message),
+        _ => error(StatusCode::GATEWAY_TIMEOUT, "The player did not answer"),
+    }
+}
+
+async fn status(State(shared): State<Shared>) -> Json<Value> {
+    Json(shared.status.lock().unwrap().clone())
+}
+
+async fn queue(State(shared): State<Shared>) -> Json<Value> {
+    Json(shared.status.lock().unwrap()["queue"].clone())
+}
+
+async fn action(State(shared): State<Shared>, Path(action): Path<String>) -> Response {
+    match action.as_str() {
+        "play" | "pause" | "toggle" | "next" | "restart" => run(&shared, action).await,
+        _ => error(StatusCode::NOT_FOUND, "Unknown action"),
+    }
+}
+
+#[derive(Deserialize)]
+struct Seek {
+    position: Option<f64>,
+    offset: Option<f64>,
+}
+
+async fn seek(State(shared): State<Shared>, Json(seek): Json<Seek>) -> Response {
+    let line = match (seek.position, seek.offset) {
+        (Some(position), None) => format!("seek {}", position.max(0.0)),
+        (None, Some(offset)) => format!("seek {:+}", offset),
+        _ => return error(StatusCode::BAD_REQUEST, "Give either position or offset"),
+    };
+    run(&shared, line).await
+}
+
+#[derive(Deserialize)]
+struct Volume {
+    volume: i32,
+}
+
+async fn volume(State(shared): State<Shared>, Json(volume): Json<Volume>) -> Response {
+    run(&shared, format!("volume {}", volume.volume)).await
+}
+
+#[derive(Deserialize)]
+struct Media {
+    path: String,
+}
+
+async fn open(State(shared): State<Shared>, Json(media): Json<Media>) -> Response {
+    run(&shared, format!("open {}", parse::quote(&media.path))).await
+}
+
+async fn enqueue(State(shared): State<Shared>, Json(media): Json<Media>) -> Response {
+    run(&shared, format!("queue {}", parse::quote(&media.path))).await
+}
+
+async fn unqueue(State(shared): State<Shared>) -> Response {
+    run(&shared, "unqueue".to_string()).await
+}
+
+async fn websocket(State(shared): State<Shared>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| push(socket, shared))
+}
+
+/// Send a client the status, then every event until it goes away
+async fn push(mut socket: WebSocket, shared: Shared) {
+    let mut events = shared.events.subscribe();
+    let status = status_message(shared.status.lock().unwrap().clone());
+    if socket
+        .send(Message::Text(status.to_string().into()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(text) => {
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                // A slow client misses some events, the next status catches it up
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                // Clients only listen
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+
+    /// Send a request, returning the status line and body
+    fn http(address: SocketAddr, request: &str, body: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let request = format!(
+            "{}\r\nHost: test\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            request,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status).unwrap();
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        let body = rest.split("\r\n\r\n").nth(1).unwrap_or_default();
+        (status.trim().to_string(), body.to_string())
+    }
+
+    #[test]
+    fn checks_tokens() {
+        let token = new_token().unwrap();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, new_token().unwrap());
+        assert!(same_token("abc", "abc"));
+        assert!(!same_token("abd", "abc"));
+        assert!(!same_token("ab", "abc"));
+    }
+
+    #[test]
+    fn serves_the_api() {
+        let address = "127.0.0.1:0".parse().unwrap();
+        let mut remote = Remote::start(address, Some("secret".to_string())).unwrap();
+        let address = remote.address;
+        remote.update(json!({ "paused": false, "position": 1.5, "queue": ["b.mp4"] }));
+
+        let (status, body) = http(address, "GET / HTTP/1.1", "");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.contains("<html"));
+
+        let (status, _) = http(address, "GET /api/status HTTP/1.1", "");
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+
+        let (status, body) = http(address, "GET /api/queue?token=secret HTTP/1.1", "");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, r#"["b.mp4"]"#);
+
+        // Stand in for the main loop, failing the command it gets
+        let main_loop = std::thread::spawn(move || {
+            loop {
+                if let Some(request) = remote.take_request() {
+                    let _ = request.reply.send(Err(format!("ran {}", request.line)));
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let (status, body) = http(
+            address,
+            "POST /api/seek HTTP/1.1\r\nAuthorization: Bearer secret",
+            r#"{"offset": -10}"#,
+        );
+        main_loop.join().unwrap();
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        assert_eq!(body, r#"{"error":"ran seek -10"}"#);
+    }
+}
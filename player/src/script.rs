@@ -0,0 +1,502 @@
+// Rhai scripts, for behavior that doesn't belong in the player itself.
+//
+//     // ~/.config/tui_player/scripts/skip-intro.rhai
+//     bind_key("player", "i", "skip_intro");
+//
+//     fn skip_intro() { seek_by(85); }
+//
+//     fn on_file_loaded(path) {
+//         if path.contains("/Shows/") { seek(90); set_status("Skipped intro"); }
+//     }
+//
+// Every *.rhai file in the scripts directory is run at startup, sorted by
+// name, and `:script` runs another. A script's top level runs once, the
+// functions it defines are called on events:
+//
+//     on_file_loaded(path)    after a file or URL opens
+//     on_eof()                when playback reaches the end
+//     on_seek(position)       after a seek, in seconds
+//     on_tick(position)       every TICK_INTERVAL while playing
+//
+// Inside them `this` is a map the script keeps between calls.
+//
+// Scripts only see the API registered in `Scripts::new`. Calls that change
+// something are queued as `Request`s and carried out by the app once the
+// script returns, so queries like `position()` describe the player as it
+// was when the call started. The engine's limits stop runaway scripts from
+// hanging the UI.
+
+use crate::events::PlayerEvent;
+use anyhow::{Result, anyhow};
+use core::MediaInfo;
+use rhai::{AST, CallFnOptions, Dynamic, Engine, FLOAT, INT, Map, Scope};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// How often `on_tick` is called during playback
+pub const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Most requests carried out for one event, so hooks can't trigger each
+/// other forever
+pub const MAX_REQUESTS: usize = 100;
+
+/// Where scripts are looked for
+pub fn dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tui_player").join("scripts"))
+}
+
+/// Script functions handling events, with their number of parameters
+const HOOKS: [(&str, usize); 4] = [
+    ("on_file_loaded", 1),
+    ("on_eof", 0),
+    ("on_seek", 1),
+    ("on_tick", 1),
+];
+
+/// The script function handling an event and its arguments
+fn handler(event: &PlayerEvent) -> Option<(&'static str, Vec<Dynamic>)> {
+    match event {
+        PlayerEvent::FileLoaded(path) => Some(("on_file_loaded", vec![path.clone().into()])),
+        PlayerEvent::Eof => Some(("on_eof", Vec::new())),
+        PlayerEvent::Seek(position) => Some(("on_seek", vec![(*position as FLOAT).into()])),
+        PlayerEvent::Tick(position) => Some(("on_tick", vec![(*position as FLOAT).into()])),
+        PlayerEvent::Pause(_) | PlayerEvent::Error(_) => None,
+    }
+}
+
+/// Something a script asked the app to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    Open(String),
+    Seek(f64),
+    SeekBy(f64),
+    Pause,
+    Resume,
+    TogglePause,
+    Status(String),
+    /// A command line, as typed after `:`
+    Command(String),
+    /// Bind keys in a context to a function of the script
+    BindKey {
+        script: usize,
+        context: String,
+        keys: String,
+        function: String,
+    },
+}
+
+/// What scripts can ask about the player
+#[derive(Clone, Default)]
+pub struct PlayerState {
+    pub path: Option<String>,
+    pub position: f64,
+    pub paused: bool,
+    pub media_info: Option<MediaInfo>,
+}
+
+/// State shared with the functions registered in the engine
+#[derive(Default)]
+struct Shared {
+    state: PlayerState,
+    requests: VecDeque<Request>,
+    /// Script being run, for `bind_key`
+    script: usize,
+}
+
+/// A loaded script
+struct Script {
+    /// File name, for messages
+    name: String,
+    ast: AST,
+    /// Variables left by the top level
+    scope: Scope<'static>,
+    /// `this` in the script's functions
+    this: Dynamic,
+    /// Hooks the script defines and hasn't failed in
+    hooks: Vec<&'static str>,
+}
+
+/// The scripting engine with every script loaded
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    shared: Rc<RefCell<Shared>>,
+    /// Functions bound to keys, by `Action::Script` index
+    bindings: Vec<(usize, String)>,
+    /// Whether the app is carrying out requests, see `App::run_script_requests`
+    pub draining: bool,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scripts {
+    /// An engine with the player API and no scripts
+    pub fn new() -> Self {
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(1_000_000);
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(1 << 20);
+        engine.set_max_array_size(100_000);
+        engine.set_max_map_size(100_000);
+        engine.on_print(|text| log::info!("Script: {}", text));
+        engine.on_debug(|text, _, position| log::debug!("Script at {}: {}", position, text));
+
+        let request = |shared: &Rc<RefCell<Shared>>| {
+            let shared = shared.clone();
+            move |request: Request| shared.borrow_mut().requests.push_back(request)
+        };
+
+        let push = request(&shared);
+        engine.register_fn("open", move |path: &str| {
+            push(Request::Open(path.to_string()))
+        });
+        let push = request(&shared);
+        engine.register_fn("seek", move |position: FLOAT| push(Request::Seek(position)));
+        let push = request(&shared);
+        engine.register_fn("seek", move |position: INT| {
+            push(Request::Seek(position as f64))
+        });
+        let push = request(&shared);
+        engine.register_fn("seek_by", move |seconds: FLOAT| {
+            push(Request::SeekBy(seconds))
+        });
+        let push = request(&shared);
+        engine.register_fn("seek_by", move |seconds: INT| {
+            push(Request::SeekBy(seconds as f64))
+        });
+        let push = request(&shared);
+        engine.register_fn("pause", move || push(Request::Pause));
+        let push = request(&shared);
+        engine.register_fn("resume", move || push(Request::Resume));
+        let push = request(&shared);
+        engine.register_fn("toggle_pause", move || push(Request::TogglePause));
+        let push = request(&shared);
+        engine.register_fn("set_status", move |text: &str| {
+            push(Request::Status(text.to_string()))
+        });
+        let push = request(&shared);
+        engine.register_fn("command", move |line: &str| {
+            push(Request::Command(line.to_string()))
+        });
+
+        let bind = shared.clone();
+        engine.register_fn(
+            "bind_key",
+            move |context: &str, keys: &str, function: &str| {
+                let mut shared = bind.borrow_mut();
+                let script = shared.script;
+                shared.requests.push_back(Request::BindKey {
+                    script,
+                    context: context.to_string(),
+                    keys: keys.to_string(),
+                    function: function.to_string(),
+                });
+            },
+        );
+
+        let state = shared.clone();
+        engine.register_fn("position", move || state.borrow().state.position as FLOAT);
+        let state = shared.clone();
+        engine.register_fn("duration", move || {
+            let shared = state.borrow();
+            shared
+                .state
+                .media_info
+                .as_ref()
+                .map_or(0.0, |info| info.duration) as FLOAT
+        });
+        let state = shared.clone();
+        engine.register_fn("is_paused", move || state.borrow().state.paused);
+        let state = shared.clone();
+        engine.register_fn("path", move || -> Dynamic {
+            match &state.borrow().state.path {
+                Some(path) => path.clone().into(),
+                None => Dynamic::UNIT,
+            }
+        });
+        let state = shared.clone();
+        engine.register_fn("media", move || -> Dynamic {
+            match &state.borrow().state.media_info {
+                Some(info) => media_map(info).into(),
+                None => Dynamic::UNIT,
+            }
+        });
+
+        Self {
+            engine,
+            scripts: Vec::new(),
+            shared,
+            bindings: Vec::new(),
+            draining: false,
+        }
+    }
+
+    /// Run every script in the scripts directory
+    ///
+    /// Returns any problem loading them, see `take_request` for what their
+    /// top levels asked for.
+    pub fn load(&mut self) -> Vec<String> {
+        let Some(dir) = dir().filter(|dir| dir.is_dir()) else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect(),
+            Err(e) => return vec![format!("Failed to read {}: {}", dir.display(), e)],
+        };
+        paths.sort();
+
+        paths
+            .iter()
+            .filter_map(|path| self.run_file(path).err())
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    /// Compile a script and run its top level
+    pub fn run_file(&mut self, path: &Path) -> Result<()> {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let ast = self
+            .engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("{}: {}", name, e))?;
+        log::info!("Running script {}", path.display());
+        self.add(name, ast)
+    }
+
+    /// Run a compiled script's top level and keep it for events
+    fn add(&mut self, name: String, ast: AST) -> Result<()> {
+        self.shared.borrow_mut().script = self.scripts.len();
+        let mut scope = Scope::new();
+        self.engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("{}: {}", name, e))?;
+
+        let hooks = HOOKS
+            .into_iter()
+            .filter(|&(hook, params)| {
+                ast.iter_functions()
+                    .any(|f| f.name == hook && f.params.len() == params)
+            })
+            .map(|(hook, _)| hook)
+            .collect();
+
+        self.scripts.push(Script {
+            name,
+            ast,
+            scope,
+            this: Map::new().into(),
+            hooks,
+        });
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Update what scripts see of the player, before calling them
+    pub fn set_state(&mut self, state: PlayerState) {
+        self.shared.borrow_mut().state = state;
+    }
+
+    /// Call every script's handler for an event
+    ///
+    /// A handler that fails is reported and not called again.
+    pub fn hook(&mut self, event: &PlayerEvent) -> Vec<String> {
+        let Some((name, args)) = handler(event) else {
+            return Vec::new();
+        };
+        let mut problems = Vec::new();
+        for index in 0..self.scripts.len() {
+            if !self.scripts[index].hooks.contains(&name) {
+                continue;
+            }
+            if let Err(e) = self.call(index, name, args.clone()) {
+                self.scripts[index].hooks.retain(|hook| *hook != name);
+                problems.push(format!("{} ({} disabled)", e, name));
+            }
+        }
+        problems
+    }
+
+    /// Remember a function to call for keys, returning its `Action::Script` index
+    pub fn add_binding(&mut self, script: usize, function: &str) -> Result<u16> {
+        let defined = self.scripts.get(script).is_some_and(|script| {
+            script
+                .ast
+                .iter_functions()
+                .any(|f| f.name == function && f.params.is_empty())
+        });
+        if !defined {
+            return Err(anyhow!("bind_key: no function {}() to bind", function));
+        }
+
+        let binding = (script, function.to_string());
+        let index = match self.bindings.iter().position(|bound| *bound == binding) {
+            Some(index) => index,
+            None => {
+                self.bindings.push(binding);
+                self.bindings.len() - 1
+            }
+        };
+        u16::try_from(index).map_err(|_| anyhow!("bind_key: too many bindings"))
+    }
+
+    /// Call the function bound with `add_binding`
+    pub fn call_binding(&mut self, index: u16) -> Result<()> {
+        let (script, function) = self
+            .bindings
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("No script binding {}", index))?;
+        self.call(script, &function, Vec::new())
+    }
+
+    /// Next thing a script asked for
+    pub fn take_request(&mut self) -> Option<Request> {
+        self.shared.borrow_mut().requests.pop_front()
+    }
+
+    /// Drop requests that were not carried out
+    pub fn clear_requests(&mut self) -> usize {
+        let mut shared = self.shared.borrow_mut();
+        let dropped = shared.requests.len();
+        shared.requests.clear();
+        dropped
+    }
+
+    fn call(&mut self, index: usize, function: &str, args: Vec<Dynamic>) -> Result<()> {
+        self.shared.borrow_mut().script = index;
+        let script = &mut self.scripts[index];
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .rewind_scope(true)
+            .bind_this_ptr(&mut script.this);
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut script.scope,
+                &script.ast,
+                function,
+                args,
+            )
+            .map(|_| ())
+            .map_err(|e| anyhow!("{}: {}", script.name, e))
+    }
+}
+
+/// What `media()` returns
+fn media_map(info: &MediaInfo) -> Map {
+    let mut map = Map::new();
+    map.insert("duration".into(), (info.duration as FLOAT).into());
+    map.insert("width".into(), (info.width as INT).into());
+    map.insert("height".into(), (info.height as INT).into());
+    map.insert("fps".into(), (info.frame_rate as FLOAT).into());
+    map.insert("format".into(), info.format_name.clone().into());
+    map.insert("video_codec".into(), info.video_codec.clone().into());
+    let audio = match &info.audio_codec {
+        Some(codec) => codec.clone().into(),
+        None => Dynamic::UNIT,
+    };
+    map.insert("audio_codec".into(), audio);
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Scripts {
+        let mut scripts = Scripts::new();
+        let ast = scripts.engine.compile(source).unwrap();
+        scripts.add("test.rhai".to_string(), ast).unwrap();
+        scripts
+    }
+
+    fn requests(scripts: &mut Scripts) -> Vec<Request> {
+        std::iter::from_fn(|| scripts.take_request()).collect()
+    }
+
+    #[test]
+    fn queues_requests_for_the_app() {
+        let mut scripts = run(r#"seek(90); seek_by(-5.5); pause(); command("speed 2");"#);
+        assert_eq!(
+            requests(&mut scripts),
+            [
+                Request::Seek(90.0),
+                Request::SeekBy(-5.5),
+                Request::Pause,
+                Request::Command("speed 2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn hooks_see_the_player_and_keep_state() {
+        let mut scripts = run(r#"
+            fn on_tick(position) {
+                if position > 10.0 && !("skipped" in this) {
+                    this.skipped = true;
+                    seek(position + duration());
+                }
+            }
+            "#);
+        scripts.set_state(PlayerState {
+            position: 12.0,
+            media_info: Some(MediaInfo {
+                duration: 60.0,
+                width: 1920,
+                height: 1080,
+                frame_rate: 24.0,
+                format_name: "mp4".to_string(),
+                video_codec: "h264".to_string(),
+                audio_codec: None,
+            }),
+            ..Default::default()
+        });
+        for _ in 0..3 {
+            assert!(scripts.hook(&PlayerEvent::Tick(12.0)).is_empty());
+        }
+        assert_eq!(requests(&mut scripts), [Request::Seek(72.0)]);
+    }
+
+    #[test]
+    fn disables_failing_hooks() {
+        let mut scripts = run("fn on_eof() { throw \"broken\"; }");
+        let problems = scripts.hook(&PlayerEvent::Eof);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("on_eof disabled"));
+        assert!(scripts.hook(&PlayerEvent::Eof).is_empty());
+    }
+
+    #[test]
+    fn binds_only_defined_functions() {
+        let mut scripts = run(r#"bind_key("player", "i", "skip"); fn skip() { seek_by(85); }"#);
+        assert!(matches!(
+            &requests(&mut scripts)[..],
+            [Request::BindKey { function, .. }] if function == "skip"
+        ));
+        assert_eq!(scripts.add_binding(0, "skip").unwrap(), 0);
+        assert_eq!(scripts.add_binding(0, "skip").unwrap(), 0);
+        assert!(scripts.add_binding(0, "missing").is_err());
+
+        scripts.call_binding(0).unwrap();
+        assert_eq!(requests(&mut scripts), [Request::SeekBy(85.0)]);
+    }
+}
@@ -1,20 +1,28 @@
 use crate::app::{App, AppView};
+use crate::commands::line::{CommandLine, Hint};
+use crate::keymap::KeyContext;
 use crate::ui::components::{*, VolumeIndicator};
+use crate::ui::video::VideoWidget;
+use crate::ui::stats::{StatsOverlay, format_budget};
+use crate::ui::theme::{StatusKind, Theme};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
+    style::{Modifier, Style},
     text::{Line, Span, Text},
     widgets::{Block, Borders, Paragraph, List, ListItem, ListState, Wrap, Clear, Gauge},
     Frame,
 };
 use std::path::Path;
+use std::rc::Rc;
 use std::time::Duration;
 use unicode_width::UnicodeWidthStr;
 
 /// Draw the main menu view
-pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
+pub fn draw_main_menu_view(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme;
+    
     // Clear the area first
-    f.render_widget(Block::default().style(Style::default().bg(Color::Black)), area);
+    f.render_widget(Block::default().style(Style::default().bg(theme.background)), area);
     
     // Create layout
     let chunks = Layout::default()
@@ -29,15 +37,15 @@ pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
     // Draw title with decorative border
     let title_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan))
+        .border_style(Style::default().fg(theme.accent))
         .title(" TUI Video Player ")
         .title_alignment(Alignment::Center)
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(theme.background));
     
     let title_text = Paragraph::new(Text::from("Welcome to TUI Video Player"))
         .block(title_block)
         .style(Style::default()
-            .fg(Color::Cyan)
+            .fg(theme.accent)
             .add_modifier(Modifier::BOLD))
         .alignment(Alignment::Center);
     
@@ -76,13 +84,13 @@ pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
     let files_button = Paragraph::new(Text::from(" 1 - BROWSE LOCAL FILES "))
         .alignment(Alignment::Center)
         .style(Style::default()
-            .fg(Color::White)
-            .bg(Color::Blue)
+            .fg(theme.highlight_text)
+            .bg(theme.highlight)
             .add_modifier(Modifier::BOLD))
         .block(Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::LightBlue))
-            .style(Style::default().bg(Color::Blue)));
+            .border_style(Style::default().fg(theme.border))
+            .style(Style::default().bg(theme.highlight)));
     
     f.render_widget(files_button, files_button_area);
     
@@ -91,13 +99,13 @@ pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
     let youtube_button = Paragraph::new(Text::from(" 2 - SEARCH YOUTUBE "))
         .alignment(Alignment::Center)
         .style(Style::default()
-            .fg(Color::White)
-            .bg(Color::Red)
+            .fg(theme.highlight_text)
+            .bg(theme.highlight)
             .add_modifier(Modifier::BOLD))
         .block(Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::LightRed))
-            .style(Style::default().bg(Color::Red)));
+            .border_style(Style::default().fg(theme.border))
+            .style(Style::default().bg(theme.highlight)));
     
     f.render_widget(youtube_button, youtube_button_area);
     
@@ -106,13 +114,13 @@ pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
     let settings_button = Paragraph::new(Text::from(" 3 - SETTINGS "))
         .alignment(Alignment::Center)
         .style(Style::default()
-            .fg(Color::White)
-            .bg(Color::Magenta)
+            .fg(theme.highlight_text)
+            .bg(theme.highlight)
             .add_modifier(Modifier::BOLD))
         .block(Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::LightMagenta))
-            .style(Style::default().bg(Color::Magenta)));
+            .border_style(Style::default().fg(theme.border))
+            .style(Style::default().bg(theme.highlight)));
     
     f.render_widget(settings_button, settings_button_area);
     
@@ -121,13 +129,13 @@ pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
     let help_button = Paragraph::new(Text::from(" 4 - HELP "))
         .alignment(Alignment::Center)
         .style(Style::default()
-            .fg(Color::White)
-            .bg(Color::Cyan)
+            .fg(theme.highlight_text)
+            .bg(theme.highlight)
             .add_modifier(Modifier::BOLD))
         .block(Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::LightCyan))
-            .style(Style::default().bg(Color::Cyan)));
+            .border_style(Style::default().fg(theme.border))
+            .style(Style::default().bg(theme.highlight)));
     
     f.render_widget(help_button, help_button_area);
     
@@ -136,35 +144,109 @@ pub fn draw_main_menu_view(f: &mut Frame, _app: &App, area: Rect) {
     let exit_button = Paragraph::new(Text::from(" 5 - EXIT "))
         .alignment(Alignment::Center)
         .style(Style::default()
-            .fg(Color::White)
-            .bg(Color::Gray)
+            .fg(theme.highlight_text)
+            .bg(theme.highlight)
             .add_modifier(Modifier::BOLD))
         .block(Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::White))
-            .style(Style::default().bg(Color::Gray)));
+            .border_style(Style::default().fg(theme.border))
+            .style(Style::default().bg(theme.highlight)));
     
     f.render_widget(exit_button, exit_button_area);
     
     // Draw status bar with help text
     let status_text = "F1: Help | Arrow Keys: Navigate | Enter: Select | Esc: Back | Ctrl+Q: Quit";
     let status = Paragraph::new(Text::from(status_text))
-        .style(Style::default().fg(Color::White).bg(Color::DarkGray));
+        .style(Style::default().fg(theme.foreground).bg(theme.status_bar));
     
     f.render_widget(status, chunks[2]);
 }
 
-/// Draw the player view with improved controls
-pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
-    // Create layout - video is drawn by the renderer, we just need to draw controls on top
-    let vertical = Layout::default()
+/// Split the player view into menu bar, video area and controls
+///
+/// Shared with `App::handle_resize` so the renderer draws into the same
+/// region the controls leave free, and with `App::handle_mouse_event`.
+/// Fullscreen drops the menu bar, and the video gets the whole area while
+/// the controls are hidden.
+pub fn player_layout(area: Rect, fullscreen: bool, controls_visible: bool) -> Rc<[Rect]> {
+    let menu = if fullscreen { 0 } else { 3 };
+    let controls = if controls_visible { 7 } else { 0 };
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3),         // Top menu bar
+            Constraint::Length(menu),      // Top menu bar
             Constraint::Min(1),            // Video area (video rendering happens outside TUI)
-            Constraint::Length(7),         // Controls area (increased for better controls)
+            Constraint::Length(controls),  // Controls area (increased for better controls)
         ])
-        .split(area);
+        .split(area)
+}
+
+/// Split the player controls into progress bar, spacing, buttons and info
+pub fn player_controls_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),  // Progress bar
+            Constraint::Length(1),  // Space
+            Constraint::Length(3),  // Playback controls
+            Constraint::Length(1),  // Status info
+        ])
+        .split(area)
+}
+
+/// Split the file browser and YouTube search views into menu bar, location
+/// or search input, list and status bar
+pub fn list_view_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),   // Menu bar
+            Constraint::Length(3),   // Current directory or search input
+            Constraint::Min(2),      // File list or search results
+            Constraint::Length(1),   // Status bar
+        ])
+        .split(area)
+}
+
+/// Rows each YouTube result takes, five for the thumbnail and one blank
+pub const YOUTUBE_RESULT_HEIGHT: usize = 6;
+
+/// Index of the list item at a cell of a bordered list drawn in `area`
+///
+/// `offset` is the first visible item, as left in the list's state by the
+/// last draw. The index may be past the end of a short list.
+pub fn list_index_at(
+    area: Rect,
+    offset: usize,
+    item_height: usize,
+    position: Position,
+) -> Option<usize> {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    if !inner.contains(position) {
+        return None;
+    }
+    Some(offset + (position.y - inner.y) as usize / item_height)
+}
+
+/// Draw the player view with improved controls
+pub fn draw_player_view(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme;
+    
+    // Create layout - the video gets the middle area like any other widget
+    let vertical = player_layout(area, app.fullscreen, app.controls_visible());
+    
+    // Nothing but the video while fullscreen controls are hidden
+    if !app.controls_visible() {
+        if app.player.is_some() {
+            f.render_stateful_widget(VideoWidget, vertical[1], &mut app.video_state);
+            if app.show_stats {
+                let overlay = StatsOverlay::new(app.playback_stats.as_ref(), app.output_rate.rate())
+                    .theme(theme);
+                f.render_widget(overlay, vertical[1]);
+            }
+        }
+        return;
+    }
     
     // Draw top menu bar with title if available
     let title = if let Some(media_info) = &app.media_info {
@@ -180,22 +262,33 @@ pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
     // Create a visually distinct menu with high contrast
     let menu_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::White).bg(Color::Black))
+        .border_style(Style::default().fg(theme.border).bg(theme.background))
         .title(title)
         .title_alignment(Alignment::Center)
-        .style(Style::default().bg(Color::Black)); // Ensure background is solid
+        .style(Style::default().bg(theme.background)); // Ensure background is solid
         
     // First clear the area with black to prevent any transparency or artifacts
-    f.render_widget(Block::default().style(Style::default().bg(Color::Black)), vertical[0]);
+    f.render_widget(Block::default().style(Style::default().bg(theme.background)), vertical[0]);
     f.render_widget(menu_block, vertical[0]);
     draw_menu_bar(f, app, vertical[0]);
     
     // Draw a strong background for controls to ensure visibility over video
     let controls_bg = Block::default()
-        .style(Style::default().bg(Color::Black))
+        .style(Style::default().bg(theme.background))
         .borders(Borders::TOP)
-        .border_style(Style::default().fg(Color::White));
+        .border_style(Style::default().fg(theme.border));
     f.render_widget(controls_bg, vertical[2]);
+
+    // Draw the latest video frame into its area
+    if app.player.is_some() {
+        f.render_stateful_widget(VideoWidget, vertical[1], &mut app.video_state);
+
+        if app.show_stats {
+            let overlay = StatsOverlay::new(app.playback_stats.as_ref(), app.output_rate.rate())
+                .theme(theme);
+            f.render_widget(overlay, vertical[1]);
+        }
+    }
     
     // If we're still loading or don't have a valid player, show a loading message
     if app.player.is_none() || app.media_info.is_none() {
@@ -206,10 +299,10 @@ pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
         // Show loading message in the video area with animated spinner
         let loading_message = Paragraph::new(Text::from(format!("{} Loading media, please wait... {}", spinner, spinner)))
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .style(Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))
             .block(Block::default()
                 .borders(Borders::NONE)
-                .style(Style::default().bg(Color::Black)));
+                .style(Style::default().bg(theme.background)));
         
         f.render_widget(loading_message, vertical[1]);
         
@@ -217,7 +310,7 @@ pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
         let progress_pct = (elapsed_ms % 3000) as f64 / 3000.0;
         let progress = Gauge::default()
             .block(Block::default().borders(Borders::NONE))
-            .gauge_style(Style::default().fg(Color::Yellow).bg(Color::Black))
+            .gauge_style(Style::default().fg(theme.warning).bg(theme.background))
             .ratio(progress_pct)
             .label(format!("{} Loading... {}", spinner, spinner));
             
@@ -236,10 +329,10 @@ pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
         // Show placeholder controls
         let controls_block = Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan).bg(Color::Black))
+            .border_style(Style::default().fg(theme.accent).bg(theme.background))
             .title(format!(" {} LOADING MEDIA... {} ", spinner, spinner))
             .title_alignment(Alignment::Center)
-            .style(Style::default().bg(Color::Black));
+            .style(Style::default().bg(theme.background));
             
         f.render_widget(controls_block, vertical[2]);
         
@@ -256,13 +349,13 @@ pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
         let exit_button = Paragraph::new(Text::from("CANCEL [ESC]"))
             .alignment(Alignment::Center)
             .style(Style::default()
-                .fg(Color::White)
-                .bg(Color::Red)
+                .fg(theme.highlight_text)
+                .bg(theme.error)
                 .add_modifier(Modifier::BOLD))
             .block(Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::LightRed))
-                .style(Style::default().bg(Color::Red)));
+                .border_style(Style::default().fg(theme.error))
+                .style(Style::default().bg(theme.error)));
                 
         f.render_widget(exit_button, exit_layout[1]);
     } else if let Some(_player) = &app.player {
@@ -275,24 +368,26 @@ pub fn draw_player_view(f: &mut Frame, app: &App, area: Rect) {
 
 /// Draw the menu bar
 pub fn draw_menu_bar(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme;
+    
     // Get inner area inside block borders
     let inner_area = Block::default().inner(area);
     
     // Ensure the area has a solid background
     f.render_widget(
-        Block::default().style(Style::default().bg(Color::Black)), 
+        Block::default().style(Style::default().bg(theme.background)), 
         inner_area
     );
     
     // Create menu tabs
     let menu_tabs = Paragraph::new(Line::from(vec![
-        Span::styled("[F]ile", Style::default().fg(if app.view == AppView::FileBrowser { Color::Yellow } else { Color::White })),
+        Span::styled("[F]ile", Style::default().fg(if app.view == AppView::FileBrowser { theme.accent } else { theme.foreground })),
         Span::raw(" | "),
-        Span::styled("[Y]ouTube", Style::default().fg(if app.view == AppView::YoutubeSearch { Color::Yellow } else { Color::White })),
+        Span::styled("[Y]ouTube", Style::default().fg(if app.view == AppView::YoutubeSearch { theme.accent } else { theme.foreground })),
         Span::raw(" | "),
-        Span::styled("[S]ettings", Style::default().fg(if app.view == AppView::Settings { Color::Yellow } else { Color::White })),
+        Span::styled("[S]ettings", Style::default().fg(if app.view == AppView::Settings { theme.accent } else { theme.foreground })),
         Span::raw(" | "),
-        Span::styled("[H]elp", Style::default().fg(if app.show_help { Color::Yellow } else { Color::White })),
+        Span::styled("[H]elp", Style::default().fg(if app.show_help { theme.accent } else { theme.foreground })),
     ]))
     .alignment(Alignment::Center);
     
@@ -301,6 +396,8 @@ pub fn draw_menu_bar(f: &mut Frame, app: &App, area: Rect) {
 
 /// Draw enhanced player controls
 pub fn draw_player_controls(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme;
+    
     // Get player state
     let player = match &app.player {
         Some(p) => p,
@@ -312,8 +409,8 @@ pub fn draw_player_controls(f: &mut Frame, app: &App, area: Rect) {
         None => return,
     };
     
-    // Get current playback state
-    let position = player.get_position();
+    // Get current playback state, the bar follows the mouse while dragging
+    let position = app.seek_drag.unwrap_or_else(|| player.get_position());
     let duration = media_info.duration;
     let is_paused = player.is_paused();
     
@@ -321,41 +418,30 @@ pub fn draw_player_controls(f: &mut Frame, app: &App, area: Rect) {
     let buffered_position = app.buffer_status.map(|(_, _, pos)| pos);
     
     // Create control layout
-    let controls = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(2),  // Progress bar
-            Constraint::Length(1),  // Space
-            Constraint::Length(3),  // Playback controls
-            Constraint::Length(1),  // Status info
-        ])
-        .split(area);
+    let controls = player_controls_layout(area);
     
     // Create enhanced progress bar with buffer info
     let progress_bar = ProgressBar::new(position, duration)
         .paused(is_paused)
         .buffered_to(buffered_position)
-        .title(Some(&media_info.format_name));
+        .title(Some(&media_info.format_name))
+        .theme(theme);
     
     f.render_widget(progress_bar, controls[0]);
     
-    // Create playback controls
-    let at_start = position < 5.0;
-    let at_end = position > duration - 5.0;
-    
     // Use the player_control_selected field to determine which button is selected
     let playback_controls = PlaybackControls::new(is_paused)
-        .can_rewind(!at_start)
-        .can_fast_forward(!at_end)
+        .position(position, duration)
         .selected(app.player_control_selected)
-        .show_keyboard_hints(true);
+        .show_keyboard_hints(true)
+        .theme(theme);
     
     f.render_widget(playback_controls, controls[2]);
     
     // Add volume indicator with actual volume data from player
     let volume = app.player.as_ref().map(|p| p.get_volume()).unwrap_or(50) as u8;
     let muted = app.player.as_ref().map(|p| p.is_muted()).unwrap_or(false);
-    let volume_indicator = VolumeIndicator::new(volume, muted);
+    let volume_indicator = VolumeIndicator::new(volume, muted).theme(theme);
     
     // Render in top-right corner
     let volume_area = Rect::new(
@@ -377,31 +463,26 @@ pub fn draw_player_controls(f: &mut Frame, app: &App, area: Rect) {
     
     let info = Paragraph::new(Text::from(info_text))
         .alignment(Alignment::Center)
-        .style(Style::default().fg(Color::Gray));
+        .style(Style::default().fg(theme.muted));
     
     f.render_widget(info, controls[3]);
 }
 
 /// Draw the file browser view
-pub fn draw_file_browser_view(f: &mut Frame, app: &App, area: Rect) {
+pub fn draw_file_browser_view(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme;
+    
     // Clear the entire area first
     f.render_widget(Clear, area);
+    f.render_widget(Block::default().style(Style::default().bg(theme.background)), area);
     
     // Create layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),   // Menu bar
-            Constraint::Length(3),   // Current directory
-            Constraint::Min(2),      // File list
-            Constraint::Length(1),   // Status bar
-        ])
-        .split(area);
+    let chunks = list_view_layout(area);
     
     // Draw menu bar
     let menu_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::White))
+        .border_style(Style::default().fg(theme.border))
         .title(" File Browser ")
         .title_alignment(Alignment::Center);
     
@@ -412,13 +493,13 @@ pub fn draw_file_browser_view(f: &mut Frame, app: &App, area: Rect) {
     let current_dir = app.file_browser.current_dir.to_string_lossy();
     let dir_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Blue))
+        .border_style(Style::default().fg(theme.info))
         .title(" Location ")
         .title_alignment(Alignment::Left);
     
     let dir_text = Paragraph::new(Text::from(current_dir.to_string()))
         .block(dir_block)
-        .style(Style::default().fg(Color::Blue).bg(Color::Black));
+        .style(Style::default().fg(theme.info).bg(theme.background));
     
     f.render_widget(dir_text, chunks[1]);
     
@@ -427,13 +508,13 @@ pub fn draw_file_browser_view(f: &mut Frame, app: &App, area: Rect) {
         .iter()
         .map(|file| {
             let (icon, color) = if file.is_dir {
-                ("📁 ", Color::Blue)
+                ("📁 ", theme.info)
             } else if is_video_file(&file.path) {
-                ("🎬 ", Color::Green)
+                ("🎬 ", theme.success)
             } else if is_audio_file(&file.path) {
-                ("🎵 ", Color::Yellow)
+                ("🎵 ", theme.warning)
             } else {
-                ("📄 ", Color::White)
+                ("📄 ", theme.foreground)
             };
             
             let name_len = file.name.len();
@@ -447,52 +528,51 @@ pub fn draw_file_browser_view(f: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
     
-    // Create file list
-    let mut list_state = ListState::default();
-    list_state.select(app.file_browser.selected);
+    // Create file list, scrolled where the last draw left it
+    let mut list_state = ListState::default()
+        .with_offset(app.file_browser.offset)
+        .with_selected(app.file_browser.selected);
     
     let file_list = List::new(files)
-        .block(Block::default().borders(Borders::ALL).title(" Files "))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border))
+            .title(" Files "))
         .highlight_style(
             Style::default()
-                .bg(Color::Blue)
-                .fg(Color::White)
+                .bg(theme.highlight)
+                .fg(theme.highlight_text)
                 .add_modifier(Modifier::BOLD),
         );
     
     f.render_stateful_widget(file_list, chunks[2], &mut list_state);
+    app.file_browser.offset = list_state.offset();
     
     // Draw status bar with help text
     let status_text = "↑/↓: Navigate | Enter: Open | Backspace: Back | ESC: Main Menu | F1: Help | Ctrl+Q: Quit";
     let status = Paragraph::new(Text::from(status_text))
-        .style(Style::default().fg(Color::White).bg(Color::DarkGray));
+        .style(Style::default().fg(theme.foreground).bg(theme.status_bar));
     
     f.render_widget(status, chunks[3]);
 }
 
 /// Draw the YouTube search view
-pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
+pub fn draw_youtube_search_view(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme;
+    
     // Clear the area first
-    f.render_widget(Block::default().style(Style::default().bg(Color::Black)), area);
+    f.render_widget(Block::default().style(Style::default().bg(theme.background)), area);
     
     // Create layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),   // Menu bar
-            Constraint::Length(3),   // Search input
-            Constraint::Min(2),      // Search results
-            Constraint::Length(1),   // Status bar
-        ])
-        .split(area);
+    let chunks = list_view_layout(area);
     
     // Draw title block with strong styling
     let title_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::White))
+        .border_style(Style::default().fg(theme.border))
         .title(" YouTube Search ")
         .title_alignment(Alignment::Center)
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(theme.background));
     
     f.render_widget(title_block, chunks[0]);
     draw_menu_bar(f, app, chunks[0]);
@@ -503,9 +583,9 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
     
     // Change border color based on search state
     let border_color = if app.youtube_search.searching {
-        Color::LightYellow  // Bright yellow during search
+        theme.warning  // Stands out during search
     } else {
-        Color::Yellow
+        theme.accent
     };
     
     let title = if app.youtube_search.searching {
@@ -519,7 +599,7 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
             .borders(Borders::ALL)
             .border_style(Style::default().fg(border_color))
             .title(title)
-            .style(Style::default().bg(Color::Black)));
+            .style(Style::default().bg(theme.background)));
     
     f.render_widget(input, chunks[1]);
     
@@ -566,11 +646,11 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
             let thumbnail_text_lines: Vec<Line> = thumbnail_lines.iter()
                 .map(|line| {
                     let color = if result.has_downloaded_thumbnail() {
-                        Color::LightGreen  // Use green for downloaded thumbnails
+                        theme.success  // Downloaded thumbnails
                     } else if result.thumbnail.is_some() {
-                        Color::Yellow      // Use yellow for pending downloads
+                        theme.warning  // Pending downloads
                     } else {
-                        Color::Gray        // Use gray for missing thumbnails
+                        theme.muted    // Missing thumbnails
                     };
                     Line::from(vec![Span::styled(*line, Style::default().fg(color))])
                 })
@@ -579,18 +659,18 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
             // Create a multi-line item with title, duration, and channel
             let content_lines = vec![
                 Line::from(vec![
-                    Span::styled("▶ ", Style::default().fg(Color::Red)),
-                    Span::styled(&result.title, Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                    Span::styled("▶ ", Style::default().fg(theme.error)),
+                    Span::styled(&result.title, Style::default().fg(theme.foreground).add_modifier(Modifier::BOLD)),
                 ]),
                 Line::from(vec![
                     Span::raw("  "),
-                    Span::styled("Duration: ", Style::default().fg(Color::Gray)),
-                    Span::styled(&result.duration, Style::default().fg(Color::Yellow)),
+                    Span::styled("Duration: ", Style::default().fg(theme.muted)),
+                    Span::styled(&result.duration, Style::default().fg(theme.accent)),
                 ]),
                 Line::from(vec![
                     Span::raw("  "),
-                    Span::styled("Channel: ", Style::default().fg(Color::Gray)),
-                    Span::styled(&result.channel, Style::default().fg(Color::Blue)),
+                    Span::styled("Channel: ", Style::default().fg(theme.muted)),
+                    Span::styled(&result.channel, Style::default().fg(theme.info)),
                 ]),
             ];
             
@@ -619,25 +699,27 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
     
-    // Create result list with better styling
-    let mut list_state = ListState::default();
-    list_state.select(app.youtube_search.selected);
+    // Create result list with better styling, scrolled where the last draw left it
+    let mut list_state = ListState::default()
+        .with_offset(app.youtube_search.offset)
+        .with_selected(app.youtube_search.selected);
     
     let result_list = List::new(results)
         .block(Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Gray))
+            .border_style(Style::default().fg(theme.border))
             .title(" Search Results ")
-            .style(Style::default().bg(Color::Black)))
+            .style(Style::default().bg(theme.background)))
         .highlight_style(
             Style::default()
-                .bg(Color::DarkGray)
-                .fg(Color::White)
+                .bg(theme.highlight)
+                .fg(theme.highlight_text)
                 .add_modifier(Modifier::BOLD),
         )
         .highlight_symbol("➤➤ ");
     
     f.render_stateful_widget(result_list, chunks[2], &mut list_state);
+    app.youtube_search.offset = list_state.offset();
     
     // Show appropriate message based on search status
     if app.youtube_search.results.is_empty() || app.youtube_search.searching {
@@ -649,7 +731,7 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
         
         let placeholder = Paragraph::new(Text::from(message))
             .alignment(Alignment::Center)
-            .style(Style::default().fg(if app.youtube_search.searching { Color::Yellow } else { Color::DarkGray }));
+            .style(Style::default().fg(if app.youtube_search.searching { theme.warning } else { theme.muted }));
         
         f.render_widget(placeholder, chunks[2]);
     }
@@ -657,7 +739,7 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
     // Draw status bar with keyboard shortcuts
     let status_text = "Enter: Search/Play | ↑/↓: Navigate | Tab: Focus | ESC: Main Menu | F1: Help | Ctrl+Q: Quit";
     let status = Paragraph::new(Text::from(status_text))
-        .style(Style::default().fg(Color::White).bg(Color::DarkGray));
+        .style(Style::default().fg(theme.foreground).bg(theme.status_bar));
     
     f.render_widget(status, chunks[3]);
 }
@@ -666,8 +748,10 @@ pub fn draw_youtube_search_view(f: &mut Frame, app: &App, area: Rect) {
 
 /// Draw the settings view
 pub fn draw_settings_view(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme;
+    
     // Clear the area with a solid background
-    f.render_widget(Block::default().style(Style::default().bg(Color::Black)), area);
+    f.render_widget(Block::default().style(Style::default().bg(theme.background)), area);
     
     // Create layout
     let chunks = Layout::default()
@@ -682,10 +766,10 @@ pub fn draw_settings_view(f: &mut Frame, app: &App, area: Rect) {
     // Draw title block with strong styling
     let title_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::White))
+        .border_style(Style::default().fg(theme.border))
         .title(" Settings ")
         .title_alignment(Alignment::Center)
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(theme.background));
     
     f.render_widget(title_block, chunks[0]);
     draw_menu_bar(f, app, chunks[0]);
@@ -693,77 +777,86 @@ pub fn draw_settings_view(f: &mut Frame, app: &App, area: Rect) {
     // Draw settings with better styling
     let settings_text = Text::from(vec![
         Line::from(vec![
-            Span::styled("Video Settings:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            Span::styled("Video Settings:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("• GPU Acceleration: ", Style::default().fg(Color::Green)),
+            Span::styled("• GPU Acceleration: ", Style::default().fg(theme.label)),
             Span::styled(
                 if app.render_config.enable_gpu { "Enabled" } else { "Disabled" },
-                Style::default().fg(if app.render_config.enable_gpu { Color::LightGreen } else { Color::LightRed })
+                Style::default().fg(if app.render_config.enable_gpu { theme.success } else { theme.error })
             )
         ]),
         Line::from(vec![
-            Span::styled("• Renderer: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{:?}", app.render_config.method), Style::default().fg(Color::White)),
-            Span::styled(" (Auto = default, best for your terminal)", Style::default().fg(Color::Gray))
+            Span::styled("• Renderer: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{:?}", app.render_config.method), Style::default().fg(theme.foreground)),
+            Span::styled(" (Auto = default, best for your terminal)", Style::default().fg(theme.muted))
         ]),
         Line::from(vec![
-            Span::styled("• Target FPS: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{}", app.render_config.target_fps), Style::default().fg(Color::White))
+            Span::styled("• Target FPS: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{}", app.render_config.target_fps), Style::default().fg(theme.foreground))
         ]),
         Line::from(vec![
-            Span::styled("• Quality: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{:.1}", app.render_config.quality), Style::default().fg(Color::White))
+            Span::styled("• Quality: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{:.1}", app.render_config.quality), Style::default().fg(theme.foreground))
+        ]),
+        Line::from(vec![
+            Span::styled("• Bandwidth Budget: ", Style::default().fg(theme.label)),
+            Span::styled(format_budget(app.render_config.max_bytes_per_second), Style::default().fg(theme.foreground))
+        ]),
+        Line::from(vec![
+            Span::styled("• Scaling Filter: ", Style::default().fg(theme.label)),
+            Span::styled(app.render_config.scale_filter.name(), Style::default().fg(theme.foreground))
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("YouTube Settings:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            Span::styled("YouTube Settings:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("• Quality: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{}", app.youtube_config.quality), Style::default().fg(Color::White))
+            Span::styled("• Quality: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{}", app.youtube_config.quality), Style::default().fg(theme.foreground))
         ]),
         Line::from(vec![
-            Span::styled("• Format: ", Style::default().fg(Color::Green)),
+            Span::styled("• Format: ", Style::default().fg(theme.label)),
             Span::styled(
                 app.youtube_config.format.as_ref().unwrap_or(&"Auto".to_string()).to_string(),
-                Style::default().fg(Color::White)
+                Style::default().fg(theme.foreground)
             )
         ]),
         Line::from(vec![
-            Span::styled("• Max Resolution: ", Style::default().fg(Color::Green)),
+            Span::styled("• Max Resolution: ", Style::default().fg(theme.label)),
             Span::styled(
                 app.youtube_config.max_resolution.as_ref().unwrap_or(&"Auto".to_string()).to_string(),
-                Style::default().fg(Color::White)
+                Style::default().fg(theme.foreground)
             )
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("Press the number keys 1-7 to change settings", Style::default().fg(Color::DarkGray))
+            Span::styled("Press the number keys 1-9 to change settings", Style::default().fg(theme.muted))
         ]),
     ]);
     
     let settings = Paragraph::new(settings_text)
-        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Gray)))
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.border)))
         .wrap(Wrap { trim: true });
     
     f.render_widget(settings, chunks[1]);
     
     // Draw status bar with more informative text
-    let status_text = "ESC: Back to Player | 1-7: Change Settings | ↑/↓: Navigate | F1: Help | Ctrl+Q: Quit";
+    let status_text = "ESC: Back to Player | 1-9: Change Settings | ↑/↓: Navigate | F1: Help | Ctrl+Q: Quit";
     let status = Paragraph::new(Text::from(status_text))
-        .style(Style::default().fg(Color::White).bg(Color::DarkGray));
+        .style(Style::default().fg(theme.foreground).bg(theme.status_bar));
     
     f.render_widget(status, chunks[2]);
 }
 
 /// Draw status message with fade effect
-pub fn draw_status_message(f: &mut Frame, message: &str, color: Color, age: Duration) {
+pub fn draw_status_message(f: &mut Frame, message: &str, kind: StatusKind, age: Duration, theme: Theme) {
     let max_age = Duration::from_secs(3);
-    let status_message = StatusMessage::new(message, color, age)
-        .max_age(max_age);
+    let status_message = StatusMessage::new(message, kind, age)
+        .max_age(max_age)
+        .theme(theme);
     
     // Create a centered floating box for the message
     let area = f.area();
@@ -780,61 +873,122 @@ pub fn draw_status_message(f: &mut Frame, message: &str, color: Color, age: Dura
     f.render_widget(status_message, message_area);
 }
 
-/// Draw command prompt
-pub fn draw_command_prompt(f: &mut Frame, command: &str) {
-    // Create an area at the bottom of the screen for the command prompt
-    let area = Rect::new(0, f.area().height - 2, f.area().width, 1);
-    
-    // Create a solid black background first
-    let bg_block = Block::default()
-        .style(Style::default().bg(Color::Black));
-    
-    f.render_widget(bg_block, area);
-    
-    // Create a command prompt block with highly visible border
-    let prompt_block = Block::default()
+/// Draw the command line, with a hint for the command being typed on its top
+/// border and completions or the last error on its bottom one
+pub fn draw_command_prompt(f: &mut Frame, line: &CommandLine, theme: Theme) {
+    // Just above the status bar
+    let screen = f.area();
+    let area = Rect::new(0, screen.height.saturating_sub(4), screen.width, screen.height.min(3));
+    f.render_widget(Clear, area);
+
+    let mut prompt_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow))
-        .style(Style::default().bg(Color::Black));
-    
-    f.render_widget(&prompt_block, area);
-    
-    // Calculate inner area for text
+        .border_style(Style::default().fg(theme.accent))
+        .style(Style::default().bg(theme.background));
+    if let Some(hint) = line.hint() {
+        prompt_block = prompt_block.title(command_hint(hint, theme));
+    }
+    let (candidates, selected) = line.completions();
+    if let Some(error) = line.error() {
+        prompt_block = prompt_block.title_bottom(Span::styled(
+            format!(" {} ", error),
+            Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+        ));
+    } else if !candidates.is_empty() {
+        prompt_block = prompt_block.title_bottom(command_completions(candidates, selected, theme));
+    }
     let inner_area = prompt_block.inner(area);
-    
-    // Create a string with a colon prefix followed by the command buffer
-    let command_text = format!(":{}", command);
-    
-    // Create the command prompt paragraph with improved visibility
-    let command_para = Paragraph::new(Text::from(command_text))
+    f.render_widget(prompt_block, area);
+
+    // Scroll sideways to keep the cursor in view
+    let cursor = 1 + line.input()[..line.cursor()].width() as u16;
+    let scroll = cursor.saturating_sub(inner_area.width.saturating_sub(1));
+    let command_para = Paragraph::new(format!(":{}", line.input()))
         .style(Style::default()
-            .fg(Color::Yellow)
-            .bg(Color::Black)
+            .fg(theme.accent)
+            .bg(theme.background)
             .add_modifier(Modifier::BOLD))
-        .alignment(Alignment::Left);
-    
-    // Render the command prompt
+        .scroll((0, scroll));
     f.render_widget(command_para, inner_area);
-    
-    // Position the cursor at the end of the command text (accounting for borders)
-    f.set_cursor_position((inner_area.x + 1 + command.len() as u16, inner_area.y));
+
+    f.set_cursor_position((inner_area.x + cursor - scroll, inner_area.y));
+}
+
+/// Usage of a command with the argument being typed picked out
+fn command_hint(hint: Hint, theme: Theme) -> Line<'static> {
+    let mut spans = vec![Span::styled(
+        format!(" {}", hint.command.name),
+        Style::default().fg(theme.foreground),
+    )];
+    for (i, param) in hint.command.params.iter().enumerate() {
+        let style = if hint.param == Some(i) {
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.muted)
+        };
+        let text = if param.optional {
+            format!("[{}]", param.name)
+        } else {
+            format!("<{}>", param.name)
+        };
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(text, style));
+    }
+    spans.push(Span::styled(
+        format!(" - {} ", hint.help),
+        Style::default().fg(theme.muted),
+    ));
+    Line::from(spans)
+}
+
+/// Completions by their last path component, the one in the line highlighted
+fn command_completions(candidates: &[String], selected: Option<usize>, theme: Theme) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    for (i, candidate) in candidates.iter().enumerate() {
+        let start = candidate.trim_end_matches('/').rfind('/').map_or(0, |i| i + 1);
+        let name = &candidate[start..];
+        let style = if selected == Some(i) {
+            Style::default().fg(theme.highlight_text).bg(theme.highlight)
+        } else {
+            Style::default().fg(theme.foreground)
+        };
+        spans.push(Span::styled(name.to_string(), style));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
 }
 
 /// Draw help dialog
-pub fn draw_help_dialog(f: &mut Frame) {
+pub fn draw_help_dialog(f: &mut Frame, app: &App) {
     let area = centered_rect(60, 70, f.area());
     
     // Clear the area
     f.render_widget(Clear, area);
     
-    // Create help overlay
-    let help = HelpOverlay::new(true);
+    // Create help overlay from the active keymap
+    let sections = app
+        .keymap
+        .help(KeyContext::for_view(app.view))
+        .into_iter()
+        .map(|(context, entries)| {
+            let title = match context {
+                KeyContext::Global => "Everywhere",
+                KeyContext::MainMenu => "Main Menu",
+                KeyContext::Player => "Playback",
+                KeyContext::FileBrowser => "File Browser",
+            };
+            (title.to_string(), entries)
+        })
+        .collect();
+    let help = HelpOverlay::new(sections).theme(app.theme);
     
     f.render_widget(help, area);
 }
 
 /// Draw settings dialog
 pub fn draw_settings_dialog(f: &mut Frame, app: &App) {
+    let theme = app.theme;
+    
     let area = centered_rect(60, 70, f.area());
     
     // Clear the area
@@ -843,56 +997,65 @@ pub fn draw_settings_dialog(f: &mut Frame, app: &App) {
     // Create the settings content
     let settings_text = Text::from(vec![
         Line::from(vec![
-            Span::styled("Video Settings:", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow))
+            Span::styled("Video Settings:", Style::default().add_modifier(Modifier::BOLD).fg(theme.accent))
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("1. GPU Acceleration: ", Style::default().fg(Color::Green)),
+            Span::styled("1. GPU Acceleration: ", Style::default().fg(theme.label)),
             Span::styled(
                 if app.render_config.enable_gpu { "Enabled" } else { "Disabled" },
-                Style::default().fg(if app.render_config.enable_gpu { Color::LightGreen } else { Color::Red })
+                Style::default().fg(if app.render_config.enable_gpu { theme.success } else { theme.error })
             )
         ]),
         Line::from(vec![
-            Span::styled("2. Renderer: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{:?}", app.render_config.method), Style::default().fg(Color::White)),
-            Span::styled(" (Auto = default)", Style::default().fg(Color::Gray))
+            Span::styled("2. Renderer: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{:?}", app.render_config.method), Style::default().fg(theme.foreground)),
+            Span::styled(" (Auto = default)", Style::default().fg(theme.muted))
+        ]),
+        Line::from(vec![
+            Span::styled("3. Target FPS: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{}", app.render_config.target_fps), Style::default().fg(theme.foreground))
+        ]),
+        Line::from(vec![
+            Span::styled("4. Quality: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{:.1}", app.render_config.quality), Style::default().fg(theme.foreground))
         ]),
         Line::from(vec![
-            Span::styled("3. Target FPS: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{}", app.render_config.target_fps), Style::default().fg(Color::White))
+            Span::styled("8. Bandwidth Budget: ", Style::default().fg(theme.label)),
+            Span::styled(format_budget(app.render_config.max_bytes_per_second), Style::default().fg(theme.foreground)),
+            Span::styled(" (for slow SSH links)", Style::default().fg(theme.muted))
         ]),
         Line::from(vec![
-            Span::styled("4. Quality: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{:.1}", app.render_config.quality), Style::default().fg(Color::White))
+            Span::styled("9. Scaling Filter: ", Style::default().fg(theme.label)),
+            Span::styled(app.render_config.scale_filter.name(), Style::default().fg(theme.foreground))
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("YouTube Settings:", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow))
+            Span::styled("YouTube Settings:", Style::default().add_modifier(Modifier::BOLD).fg(theme.accent))
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("5. Quality: ", Style::default().fg(Color::Green)),
-            Span::styled(format!("{}", app.youtube_config.quality), Style::default().fg(Color::White))
+            Span::styled("5. Quality: ", Style::default().fg(theme.label)),
+            Span::styled(format!("{}", app.youtube_config.quality), Style::default().fg(theme.foreground))
         ]),
         Line::from(vec![
-            Span::styled("6. Format: ", Style::default().fg(Color::Green)),
+            Span::styled("6. Format: ", Style::default().fg(theme.label)),
             Span::styled(
                 app.youtube_config.format.as_ref().unwrap_or(&"Auto".to_string()).to_string(), 
-                Style::default().fg(Color::White)
+                Style::default().fg(theme.foreground)
             )
         ]),
         Line::from(vec![
-            Span::styled("7. Max Resolution: ", Style::default().fg(Color::Green)),
+            Span::styled("7. Max Resolution: ", Style::default().fg(theme.label)),
             Span::styled(
                 app.youtube_config.max_resolution.as_ref().unwrap_or(&"Auto".to_string()).to_string(), 
-                Style::default().fg(Color::White)
+                Style::default().fg(theme.foreground)
             )
         ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("Press the number key to change a setting, or Escape to close", 
-                        Style::default().fg(Color::DarkGray))
+                        Style::default().fg(theme.muted))
         ]),
     ]);
     
@@ -901,8 +1064,8 @@ pub fn draw_settings_dialog(f: &mut Frame, app: &App) {
         .borders(Borders::ALL)
         .title(" SETTINGS ")
         .title_alignment(Alignment::Center)
-        .border_style(Style::default().fg(Color::Cyan))
-        .style(Style::default().bg(Color::Black));
+        .border_style(Style::default().fg(theme.accent))
+        .style(Style::default().bg(theme.background));
         
     f.render_widget(Clear, area);
     f.render_widget(&settings_block, area);
@@ -910,7 +1073,7 @@ pub fn draw_settings_dialog(f: &mut Frame, app: &App) {
     // Render text in the inner area
     let inner_area = settings_block.inner(area);
     let settings = Paragraph::new(settings_text)
-        .style(Style::default().bg(Color::Black))
+        .style(Style::default().bg(theme.background))
         .wrap(Wrap { trim: true });
     
     f.render_widget(settings, inner_area);
@@ -1,11 +1,14 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
+    style::{Modifier, Style},
     text::{Line, Span, Text},
     widgets::{Block, Borders, Paragraph, Widget, Wrap, Clear, Gauge},
     buffer::Buffer,
 };
 use unicode_width::UnicodeWidthStr;
+use crate::keymap::HelpEntry;
+use crate::ui::theme::{StatusKind, Theme};
+use std::rc::Rc;
 use std::time::Duration;
 
 /// Format duration as HH:MM:SS
@@ -29,6 +32,7 @@ pub struct ProgressBar<'a> {
     is_paused: bool,
     buffered_to: Option<f64>,
     title: Option<&'a str>,
+    theme: Theme,
 }
 
 impl<'a> ProgressBar<'a> {
@@ -39,6 +43,7 @@ impl<'a> ProgressBar<'a> {
             is_paused: false,
             buffered_to: None,
             title: None,
+            theme: Theme::default(),
         }
     }
     
@@ -56,6 +61,24 @@ impl<'a> ProgressBar<'a> {
         self.title = title;
         self
     }
+    
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Playback position under a column of a bar drawn in `area`
+    ///
+    /// Columns past either end of the gauge clamp to the start or the end so
+    /// dragging off the bar still seeks.
+    pub fn position_at(area: Rect, column: u16, duration: f64) -> f64 {
+        let inner_width = area.width.saturating_sub(2);
+        if inner_width == 0 {
+            return 0.0;
+        }
+        let offset = column.saturating_sub(area.x + 1).min(inner_width);
+        duration * offset as f64 / inner_width as f64
+    }
 }
 
 impl<'a> Widget for ProgressBar<'a> {
@@ -82,11 +105,14 @@ impl<'a> Widget for ProgressBar<'a> {
         
         // Create the gauge
         let gauge = Gauge::default()
-            .block(Block::default().borders(Borders::ALL).title(display_title))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(self.theme.border))
+                .title(display_title))
             .gauge_style(
                 Style::default()
-                    .fg(Color::Blue)
-                    .bg(Color::Black)
+                    .fg(self.theme.progress)
+                    .bg(self.theme.background)
                     .add_modifier(Modifier::BOLD),
             )
             .percent((percent * 100.0) as u16)
@@ -112,7 +138,7 @@ impl<'a> Widget for ProgressBar<'a> {
                         // Only draw inside the gauge
                         if x >= area.x + 1 && x < area.x + area.width - 1 {
                             for y in area.y + 1..area.y + area.height - 1 {
-                                buf[(x, y)].set_bg(Color::DarkGray);
+                                buf[(x, y)].set_bg(self.theme.buffered);
                             }
                         }
                     }
@@ -129,6 +155,7 @@ pub struct PlaybackControls {
     can_fast_forward: bool,
     selected: Option<usize>,
     show_keyboard_hints: bool,
+    theme: Theme,
 }
 
 impl PlaybackControls {
@@ -139,6 +166,7 @@ impl PlaybackControls {
             can_fast_forward: true,
             selected: None,
             show_keyboard_hints: true,
+            theme: Theme::default(),
         }
     }
     
@@ -161,27 +189,56 @@ impl PlaybackControls {
         self.show_keyboard_hints = show;
         self
     }
+    
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Enable the seek buttons that have room to seek, in seconds
+    pub fn position(self, position: f64, duration: f64) -> Self {
+        self.can_rewind(position >= 5.0)
+            .can_fast_forward(position <= duration - 5.0)
+    }
+
+    /// Whether a button, as numbered by `button_at`, is drawn enabled
+    pub fn is_enabled(&self, index: usize) -> bool {
+        match index {
+            0 | 1 => self.can_rewind,
+            3 | 4 => self.can_fast_forward,
+            _ => true,
+        }
+    }
+
+    /// Areas of the five buttons when drawn in `area`
+    pub fn button_areas(area: Rect) -> Rc<[Rect]> {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(20); 5])
+            .split(area)
+    }
+
+    /// Index of the button at a cell, from back 30s (0) to forward 30s (4)
+    pub fn button_at(area: Rect, position: Position) -> Option<usize> {
+        Self::button_areas(area)
+            .iter()
+            .position(|button| button.contains(position))
+    }
 }
 
 impl Widget for PlaybackControls {
     fn render(self, area: Rect, buf: &mut Buffer) {
         // Define the buttons to display
         let buttons = vec![
-            ("⏪", "Back 30s", self.can_rewind, 'b'),
-            ("◀◀", "Back 5s", self.can_rewind, '◀'),
-            (if self.is_playing { "⏸" } else { "▶" }, if self.is_playing { "Pause" } else { "Play" }, true, ' '),
-            ("▶▶", "Forward 5s", self.can_fast_forward, '▶'),
-            ("⏩", "Forward 30s", self.can_fast_forward, 'f'),
+            ("⏪", "Back 30s", self.is_enabled(0), 'b'),
+            ("◀◀", "Back 5s", self.is_enabled(1), '◀'),
+            (if self.is_playing { "⏸" } else { "▶" }, if self.is_playing { "Pause" } else { "Play" }, self.is_enabled(2), ' '),
+            ("▶▶", "Forward 5s", self.is_enabled(3), '▶'),
+            ("⏩", "Forward 30s", self.is_enabled(4), 'f'),
         ];
         
-        // Create constraints for the buttons (equal width)
-        let constraints = vec![Constraint::Percentage(20); buttons.len()];
-        
-        // Create layout for the buttons
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(constraints)
-            .split(area);
+        // Equal width buttons, shared with mouse hit-testing
+        let chunks = Self::button_areas(area);
         
         // Render each button
         for (i, ((symbol, tooltip, enabled, key), chunk)) in buttons.into_iter().zip(chunks.iter()).enumerate() {
@@ -189,17 +246,20 @@ impl Widget for PlaybackControls {
             
             // Set styles based on state
             let style = if !enabled {
-                Style::default().fg(Color::DarkGray)
+                Style::default().fg(self.theme.muted)
             } else if selected {
-                Style::default().fg(Color::White).bg(Color::Blue).add_modifier(Modifier::BOLD)
+                Style::default()
+                    .fg(self.theme.highlight_text)
+                    .bg(self.theme.highlight)
+                    .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::White)
+                Style::default().fg(self.theme.foreground)
             };
             
             // Create block for the button
             let block = Block::default()
                 .borders(Borders::ALL)
-                .border_style(if selected { style } else { Style::default() });
+                .border_style(if selected { style } else { Style::default().fg(self.theme.border) });
             
             // Create the button content
             let key_hint = if self.show_keyboard_hints {
@@ -223,18 +283,20 @@ impl Widget for PlaybackControls {
 /// Display a status message with fade effect
 pub struct StatusMessage<'a> {
     message: &'a str,
-    color: Color,
+    kind: StatusKind,
     age: Duration,
     max_age: Duration,
+    theme: Theme,
 }
 
 impl<'a> StatusMessage<'a> {
-    pub fn new(message: &'a str, color: Color, age: Duration) -> Self {
+    pub fn new(message: &'a str, kind: StatusKind, age: Duration) -> Self {
         Self {
             message,
-            color,
+            kind,
             age,
             max_age: Duration::from_secs(3),  // Default fade after 3 seconds
+            theme: Theme::default(),
         }
     }
     
@@ -242,6 +304,11 @@ impl<'a> StatusMessage<'a> {
         self.max_age = duration;
         self
     }
+    
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
 }
 
 impl<'a> Widget for StatusMessage<'a> {
@@ -259,11 +326,11 @@ impl<'a> Widget for StatusMessage<'a> {
         }
         
         // Choose color based on fade level
-        let color = match (self.color, fade_factor) {
-            (Color::Red, _) => Color::Red,  // Errors always stay red
-            (_, f) if f > 0.7 => self.color,  // Regular color for newer messages
-            (_, f) if f > 0.3 => Color::DarkGray,  // Fade to dark gray
-            _ => Color::DarkGray,  // Final fade stage
+        let color = match (self.kind, fade_factor) {
+            (StatusKind::Error, _) => self.theme.error,  // Errors always stay red
+            (kind, f) if f > 0.7 => self.theme.status(kind),  // Regular color for newer messages
+            (_, f) if f > 0.3 => self.theme.muted,  // Fade out
+            _ => self.theme.muted,  // Final fade stage
         };
         
         // Create the message box
@@ -274,7 +341,7 @@ impl<'a> Widget for StatusMessage<'a> {
                 Block::default()
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(color))
-                    .style(Style::default().bg(Color::Black)),
+                    .style(Style::default().bg(self.theme.background)),
             );
         
         // Render the message in a floating box
@@ -300,6 +367,7 @@ impl<'a> Widget for StatusMessage<'a> {
 pub struct VolumeIndicator {
     volume: u8,  // 0-100
     muted: bool,
+    theme: Theme,
 }
 
 impl VolumeIndicator {
@@ -307,23 +375,29 @@ impl VolumeIndicator {
         Self {
             volume: volume.min(100),
             muted,
+            theme: Theme::default(),
         }
     }
+    
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
 }
 
 impl Widget for VolumeIndicator {
     fn render(self, area: Rect, buf: &mut Buffer) {
         // Choose the appropriate volume icon
         let (icon, color) = if self.muted {
-            ("🔇", Color::DarkGray)
+            ("🔇", self.theme.muted)
         } else if self.volume == 0 {
-            ("🔇", Color::White)
+            ("🔇", self.theme.foreground)
         } else if self.volume < 30 {
-            ("🔈", Color::White)
+            ("🔈", self.theme.foreground)
         } else if self.volume < 70 {
-            ("🔉", Color::White)
+            ("🔉", self.theme.foreground)
         } else {
-            ("🔊", Color::White)
+            ("🔊", self.theme.foreground)
         };
         
         // Create the volume display
@@ -339,6 +413,7 @@ impl Widget for VolumeIndicator {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.border))
                     .title("Volume")
             );
         
@@ -346,75 +421,54 @@ impl Widget for VolumeIndicator {
     }
 }
 
-/// Simple help overlay widget
+/// Help overlay listing the active key bindings
 pub struct HelpOverlay {
-    show_advanced: bool,
+    /// Section title and its entries, the view's bindings first
+    sections: Vec<(String, Vec<HelpEntry>)>,
+    theme: Theme,
 }
 
 impl HelpOverlay {
-    pub fn new(show_advanced: bool) -> Self {
-        Self { show_advanced }
+    pub fn new(sections: Vec<(String, Vec<HelpEntry>)>) -> Self {
+        Self { sections, theme: Theme::default() }
+    }
+    
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
     }
 }
 
 impl Widget for HelpOverlay {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        // Create the help text
-        let help_text = vec![
-            Line::from(vec![
-                Span::styled("Keyboard Controls", Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
-            ]),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Play/Pause"),
-            ]),
-            Line::from(vec![
-                Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Seek 5 seconds"),
-            ]),
-            Line::from(vec![
-                Span::styled("b/f", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Seek 30 seconds back/forward"),
-            ]),
-            Line::from(vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Stop playback/Return to menu"),
-            ]),
-        ];
-        
-        // Add advanced help if requested
-        let full_help = if self.show_advanced {
-            let mut advanced = help_text;
-            advanced.push(Line::from(""));
-            advanced.push(Line::from(vec![
-                Span::styled("Advanced Controls", Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
-            ]));
-            advanced.push(Line::from(vec![
-                Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Settings"),
-            ]));
-            advanced.push(Line::from(vec![
-                Span::styled("o", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Open file browser"),
-            ]));
-            advanced.push(Line::from(vec![
-                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - YouTube search"),
-            ]));
-            advanced.push(Line::from(vec![
-                Span::styled("h", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" - Toggle help"),
+        // One block of "keys - description" lines per section
+        let mut help_text = Vec::new();
+        for (title, entries) in self.sections {
+            if entries.is_empty() {
+                continue;
+            }
+            if !help_text.is_empty() {
+                help_text.push(Line::from(""));
+            }
+            help_text.push(Line::from(vec![
+                Span::styled(title, Style::default().fg(self.theme.accent).add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
             ]));
-            advanced
-        } else {
-            help_text
-        };
+            help_text.push(Line::from(""));
+            for entry in entries {
+                help_text.push(Line::from(vec![
+                    Span::styled(entry.keys, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(" - {}", entry.description)),
+                ]));
+            }
+        }
         
         // Create the help overlay
-        let help = Paragraph::new(Text::from(full_help))
-            .block(Block::default().title("Help").borders(Borders::ALL))
-            .style(Style::default().fg(Color::White).bg(Color::Black))
+        let help = Paragraph::new(Text::from(help_text))
+            .block(Block::default()
+                .title("Help")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(self.theme.border)))
+            .style(Style::default().fg(self.theme.foreground).bg(self.theme.background))
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
         
@@ -432,4 +486,39 @@ pub fn get_spinner_frame(duration_ms: u128) -> &'static str {
     const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠚", "⠞", "⠖", "⠦", "⠴", "⠲", "⠳", "⠓"];
     let frame_idx = (duration_ms / 80) % SPINNER_FRAMES.len() as u128;
     SPINNER_FRAMES[frame_idx as usize]
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_bar_position_follows_the_gauge() {
+        // Gauge inner width is 100 columns starting at x = 11
+        let area = Rect::new(10, 5, 102, 2);
+        assert_eq!(ProgressBar::position_at(area, 11, 200.0), 0.0);
+        assert_eq!(ProgressBar::position_at(area, 61, 200.0), 100.0);
+        // Dragging past either end clamps
+        assert_eq!(ProgressBar::position_at(area, 0, 200.0), 0.0);
+        assert_eq!(ProgressBar::position_at(area, 150, 200.0), 200.0);
+    }
+
+    #[test]
+    fn buttons_split_the_row_evenly() {
+        let area = Rect::new(0, 10, 100, 3);
+        assert_eq!(PlaybackControls::button_at(area, Position::new(0, 11)), Some(0));
+        assert_eq!(PlaybackControls::button_at(area, Position::new(50, 12)), Some(2));
+        assert_eq!(PlaybackControls::button_at(area, Position::new(99, 10)), Some(4));
+        assert_eq!(PlaybackControls::button_at(area, Position::new(50, 13)), None);
+    }
+
+    #[test]
+    fn seek_buttons_need_room_to_seek() {
+        let enabled = |position| {
+            let controls = PlaybackControls::new(true).position(position, 60.0);
+            (0..5).map(|i| controls.is_enabled(i)).collect::<Vec<_>>()
+        };
+        assert_eq!(enabled(2.0), [false, false, true, true, true]);
+        assert_eq!(enabled(30.0), [true; 5]);
+        assert_eq!(enabled(58.0), [true, true, true, false, false]);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod app;
 pub mod components;
+pub mod stats;
+pub mod theme;
+pub mod video;
 
 // Re-export components for easier access
 pub use components::*;
@@ -23,19 +26,19 @@ pub fn draw_ui(f: &mut Frame, app: &mut App) -> Result<()> {
     }
     
     // Draw status message if needed
-    if let Some((msg, time, color)) = &app.status_message {
+    if let Some((msg, time, kind)) = &app.status_message {
         let age = time.elapsed();
-        app::draw_status_message(f, msg, *color, age);
+        app::draw_status_message(f, msg, *kind, age, app.theme);
     }
     
     // Draw command prompt if in command mode
     if app.is_command_mode() {
-        app::draw_command_prompt(f, app.get_command_buffer());
+        app::draw_command_prompt(f, &app.command_line, app.theme);
     }
     
     // Draw help dialog if needed
     if app.show_help {
-        app::draw_help_dialog(f);
+        app::draw_help_dialog(f, app);
     }
     
     // Draw settings dialog if needed
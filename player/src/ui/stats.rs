@@ -0,0 +1,194 @@
+use crate::ui::theme::Theme;
+use core::media::PlaybackStats;
+use core::render::ColorDepth;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Writer that counts the bytes going to the terminal
+pub struct CountingWriter<W> {
+    inner: W,
+    written: Arc<AtomicU64>,
+}
+
+impl<W> CountingWriter<W> {
+    pub fn new(inner: W, written: Arc<AtomicU64>) -> Self {
+        Self { inner, written }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written.fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Format a byte count with a binary unit
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn format_rate(bytes_per_second: f64) -> String {
+    format!("{}/s", format_bytes(bytes_per_second))
+}
+
+/// Format a renderer byte budget for display
+pub fn format_budget(budget: Option<u64>) -> String {
+    match budget {
+        Some(bytes) => format_rate(bytes as f64),
+        None => "Unlimited".to_string(),
+    }
+}
+
+/// Playback diagnostics drawn over the corner of the video
+///
+/// With graphics protocols the image is written after the UI, so the overlay
+/// can be covered until the next UI refresh.
+pub struct StatsOverlay<'a> {
+    stats: Option<&'a PlaybackStats>,
+    /// Bytes written to the terminal per second
+    output_rate: f64,
+    theme: Theme,
+}
+
+impl<'a> StatsOverlay<'a> {
+    pub const WIDTH: u16 = 38;
+    pub const HEIGHT: u16 = 10;
+
+    pub fn new(stats: Option<&'a PlaybackStats>, output_rate: f64) -> Self {
+        Self {
+            stats,
+            output_rate,
+            theme: Theme::default(),
+        }
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Top-right corner of `area` the overlay is drawn into
+    pub fn area(area: Rect) -> Rect {
+        let width = Self::WIDTH.min(area.width);
+        let height = Self::HEIGHT.min(area.height);
+        Rect::new(area.right() - width, area.y, width, height)
+    }
+
+    fn row(&self, label: &str, value: String) -> Line<'static> {
+        Line::from(vec![
+            Span::styled(
+                format!("{:<10}", label),
+                Style::default().fg(self.theme.muted),
+            ),
+            Span::styled(value, Style::default().fg(self.theme.foreground)),
+        ])
+    }
+}
+
+impl Widget for StatsOverlay<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = Self::area(area);
+        // Also resets cells the video widget marked as skipped
+        Clear.render(area, buf);
+
+        let lines = match self.stats {
+            Some(stats) => {
+                let drift_color = if stats.av_drift.abs() > 0.1 {
+                    self.theme.error
+                } else {
+                    self.theme.success
+                };
+                vec![
+                    self.row(
+                        "FPS",
+                        format!(
+                            "decode {:.1}  render {:.1}",
+                            stats.decode_fps, stats.render_fps
+                        ),
+                    ),
+                    self.row(
+                        "Frames",
+                        format!(
+                            "dropped {}  late {}",
+                            stats.frames.dropped, stats.frames.late
+                        ),
+                    ),
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{:<10}", "A/V drift"),
+                            Style::default().fg(self.theme.muted),
+                        ),
+                        Span::styled(
+                            format!("{:+.3}s", stats.av_drift),
+                            Style::default().fg(drift_color),
+                        ),
+                    ]),
+                    self.row(
+                        "Buffer",
+                        format!("{}/{} frames", stats.buffered, stats.buffer_capacity),
+                    ),
+                    self.row(
+                        "Renderer",
+                        format!(
+                            "{:?}  GPU {}",
+                            stats.render.method,
+                            if stats.render.gpu { "on" } else { "off" }
+                        ),
+                    ),
+                    self.row("Quality", format!("{:.2}", stats.render.quality)),
+                    self.row(
+                        "Per frame",
+                        format!(
+                            "{}  {}  x{:.2}",
+                            format_bytes(stats.render.bandwidth.bytes_per_frame),
+                            match stats.render.bandwidth.color_depth {
+                                ColorDepth::TrueColor => "24-bit",
+                                ColorDepth::Indexed256 => "256",
+                            },
+                            stats.render.bandwidth.scale
+                        ),
+                    ),
+                    self.row("Output", format_rate(self.output_rate)),
+                ]
+            }
+            None => vec![
+                Line::from("No stats for this source"),
+                self.row("Output", format_rate(self.output_rate)),
+            ],
+        };
+
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        " Stats ",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.accent)),
+            )
+            .style(Style::default().bg(self.theme.background))
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,255 @@
+// Colors for every view and widget, by what they are used for rather than
+// what they look like. config.json picks a built-in theme and can override
+// single slots, `:theme` switches at runtime.
+
+use anyhow::{Result, anyhow};
+use ratatui::style::Color;
+
+/// What a status message reports, which picks its theme color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+    Success,
+    Warning,
+    Error,
+    Info,
+}
+
+/// Semantic colors used to draw the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Fill behind every view and dialog
+    pub background: Color,
+    /// Body text
+    pub foreground: Color,
+    /// Labels, hints and disabled items
+    pub muted: Color,
+    /// Borders of panels and lists
+    pub border: Color,
+    /// Headings, titles, the active tab and the command prompt
+    pub accent: Color,
+    /// Names of settings and other fields
+    pub label: Color,
+    /// Background of the selected row or button
+    pub highlight: Color,
+    /// Text on `highlight`
+    pub highlight_text: Color,
+    /// Played part of the progress bar
+    pub progress: Color,
+    /// Buffered part of the progress bar ahead of the playback position
+    pub buffered: Color,
+    /// Background of the key hints at the bottom of each view
+    pub status_bar: Color,
+    /// Things that worked or are on
+    pub success: Color,
+    /// Things that need attention
+    pub warning: Color,
+    /// Errors and things that are off
+    pub error: Color,
+    /// Neutral notices
+    pub info: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+impl Theme {
+    /// The player's original colors
+    pub const DARK: Theme = Theme {
+        background: Color::Black,
+        foreground: Color::White,
+        muted: Color::Gray,
+        border: Color::Gray,
+        accent: Color::Yellow,
+        label: Color::Green,
+        highlight: Color::Blue,
+        highlight_text: Color::White,
+        progress: Color::Blue,
+        buffered: Color::DarkGray,
+        status_bar: Color::DarkGray,
+        success: Color::Green,
+        warning: Color::Yellow,
+        error: Color::Red,
+        info: Color::Blue,
+    };
+
+    /// Dark text on a white background
+    pub const LIGHT: Theme = Theme {
+        background: Color::White,
+        foreground: Color::Black,
+        muted: Color::DarkGray,
+        border: Color::Gray,
+        accent: Color::Rgb(0x00, 0x5f, 0xaf),
+        label: Color::Rgb(0x00, 0x80, 0x00),
+        highlight: Color::Rgb(0x00, 0x5f, 0xaf),
+        highlight_text: Color::White,
+        progress: Color::Rgb(0x00, 0x5f, 0xaf),
+        buffered: Color::Rgb(0xc8, 0xc8, 0xc8),
+        status_bar: Color::Rgb(0xdc, 0xdc, 0xdc),
+        success: Color::Rgb(0x00, 0x80, 0x00),
+        warning: Color::Rgb(0xaf, 0x5f, 0x00),
+        error: Color::Rgb(0xc0, 0x00, 0x00),
+        info: Color::Rgb(0x00, 0x5f, 0xaf),
+    };
+
+    /// Bright colors only, selection shown inverted
+    pub const HIGH_CONTRAST: Theme = Theme {
+        background: Color::Black,
+        foreground: Color::White,
+        muted: Color::White,
+        border: Color::White,
+        accent: Color::LightYellow,
+        label: Color::LightCyan,
+        highlight: Color::White,
+        highlight_text: Color::Black,
+        progress: Color::LightYellow,
+        buffered: Color::Gray,
+        status_bar: Color::Blue,
+        success: Color::LightGreen,
+        warning: Color::LightYellow,
+        error: Color::LightRed,
+        info: Color::LightCyan,
+    };
+
+    /// Solarized dark
+    pub const SOLARIZED: Theme = Theme {
+        background: Color::Rgb(0x00, 0x2b, 0x36),
+        foreground: Color::Rgb(0x93, 0xa1, 0xa1),
+        muted: Color::Rgb(0x65, 0x7b, 0x83),
+        border: Color::Rgb(0x58, 0x6e, 0x75),
+        accent: Color::Rgb(0xb5, 0x89, 0x00),
+        label: Color::Rgb(0x85, 0x99, 0x00),
+        highlight: Color::Rgb(0x26, 0x8b, 0xd2),
+        highlight_text: Color::Rgb(0xfd, 0xf6, 0xe3),
+        progress: Color::Rgb(0x2a, 0xa1, 0x98),
+        buffered: Color::Rgb(0x07, 0x36, 0x42),
+        status_bar: Color::Rgb(0x07, 0x36, 0x42),
+        success: Color::Rgb(0x85, 0x99, 0x00),
+        warning: Color::Rgb(0xcb, 0x4b, 0x16),
+        error: Color::Rgb(0xdc, 0x32, 0x2f),
+        info: Color::Rgb(0x26, 0x8b, 0xd2),
+    };
+
+    /// Built-in themes with the names `:theme` and config.json use
+    pub const BUILTIN: [(&'static str, Theme); 4] = [
+        ("dark", Theme::DARK),
+        ("light", Theme::LIGHT),
+        ("high-contrast", Theme::HIGH_CONTRAST),
+        ("solarized", Theme::SOLARIZED),
+    ];
+
+    /// Slot names for overrides in config.json
+    pub const SLOTS: [&'static str; 15] = [
+        "background",
+        "foreground",
+        "muted",
+        "border",
+        "accent",
+        "label",
+        "highlight",
+        "highlight_text",
+        "progress",
+        "buffered",
+        "status_bar",
+        "success",
+        "warning",
+        "error",
+        "info",
+    ];
+
+    /// Look up a built-in theme
+    pub fn builtin(name: &str) -> Result<Self> {
+        Self::BUILTIN
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|&(_, theme)| theme)
+            .ok_or_else(|| anyhow!("Unknown theme: {}", name))
+    }
+
+    /// Set a slot by name, the color being a name ("red"), an index ("42")
+    /// or hex ("#268bd2")
+    pub fn set(&mut self, slot: &str, color: &str) -> Result<()> {
+        let color: Color = color
+            .parse()
+            .map_err(|_| anyhow!("Invalid color for {}: {}", slot, color))?;
+        let slot = match slot {
+            "background" => &mut self.background,
+            "foreground" => &mut self.foreground,
+            "muted" => &mut self.muted,
+            "border" => &mut self.border,
+            "accent" => &mut self.accent,
+            "label" => &mut self.label,
+            "highlight" => &mut self.highlight,
+            "highlight_text" => &mut self.highlight_text,
+            "progress" => &mut self.progress,
+            "buffered" => &mut self.buffered,
+            "status_bar" => &mut self.status_bar,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            "info" => &mut self.info,
+            _ => return Err(anyhow!("Unknown theme color: {}", slot)),
+        };
+        *slot = color;
+        Ok(())
+    }
+
+    /// Theme color for a status message
+    pub fn status(&self, kind: StatusKind) -> Color {
+        match kind {
+            StatusKind::Success => self.success,
+            StatusKind::Warning => self.warning,
+            StatusKind::Error => self.error,
+            StatusKind::Info => self.info,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_slot_can_be_set() {
+        let mut theme = Theme::DARK;
+        for slot in Theme::SLOTS {
+            theme.set(slot, "#010203").unwrap();
+        }
+        let rgb = Color::Rgb(1, 2, 3);
+        assert_eq!(
+            theme,
+            Theme {
+                background: rgb,
+                foreground: rgb,
+                muted: rgb,
+                border: rgb,
+                accent: rgb,
+                label: rgb,
+                highlight: rgb,
+                highlight_text: rgb,
+                progress: rgb,
+                buffered: rgb,
+                status_bar: rgb,
+                success: rgb,
+                warning: rgb,
+                error: rgb,
+                info: rgb,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_names_and_colors() {
+        assert!(Theme::builtin("solarized").is_ok());
+        assert!(Theme::builtin("neon").is_err());
+
+        let mut theme = Theme::LIGHT;
+        assert!(theme.set("accent", "not-a-color").is_err());
+        assert!(theme.set("shadow", "red").is_err());
+        assert_eq!(theme, Theme::LIGHT);
+        theme.set("accent", "magenta").unwrap();
+        assert_eq!(theme.accent, Color::Magenta);
+    }
+}
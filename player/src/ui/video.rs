@@ -0,0 +1,91 @@
+use core::render::{CellGrid, RenderedFrame};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::StatefulWidget};
+
+/// Latest video output from the player, drawn by `VideoWidget`
+#[derive(Default)]
+pub struct VideoState {
+    /// Block output, redrawn into the buffer on every UI frame
+    cells: Option<CellGrid>,
+    /// Graphics escapes waiting to be written after the next UI flush
+    pending_graphics: Option<Vec<u8>>,
+    /// Whether the video area currently shows a graphics protocol image
+    graphics_active: bool,
+}
+
+impl VideoState {
+    /// Store a newly rendered frame
+    pub fn update(&mut self, frame: RenderedFrame) {
+        match frame {
+            RenderedFrame::Cells(grid) => {
+                self.cells = Some(grid);
+                self.pending_graphics = None;
+                self.graphics_active = false;
+            }
+            RenderedFrame::Graphics(bytes) => {
+                self.cells = None;
+                // Escapes not written yet still have to go out, a placement
+                // delete can arrive on its own just before the next image
+                match &mut self.pending_graphics {
+                    Some(pending) => pending.extend_from_slice(&bytes),
+                    None => self.pending_graphics = Some(bytes),
+                }
+                self.graphics_active = true;
+            }
+        }
+    }
+
+    /// Take the graphics escapes to write once ratatui has flushed
+    pub fn take_graphics(&mut self) -> Option<Vec<u8>> {
+        self.pending_graphics.take()
+    }
+
+    /// Drop everything, e.g. after a resize or when playback stops
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Video area of the player view
+///
+/// Block output is copied into the buffer like any other widget. For graphics
+/// protocols the area is marked as skipped so ratatui never paints over the
+/// image, which is written separately after the frame has been flushed.
+#[derive(Default)]
+pub struct VideoWidget;
+
+impl StatefulWidget for VideoWidget {
+    type State = VideoState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let area = area.intersection(buf.area);
+
+        if state.graphics_active {
+            for y in area.top()..area.bottom() {
+                for x in area.left()..area.right() {
+                    buf[(x, y)].set_skip(true);
+                }
+            }
+            return;
+        }
+
+        let Some(grid) = &state.cells else {
+            return;
+        };
+
+        // The renderer sizes the grid to the area, clip in case a resize is in flight
+        let width = grid.width.min(area.width);
+        let height = grid.height.min(area.height);
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(cell) = grid.get(x, y) {
+                    let [fr, fg, fb] = cell.fg;
+                    let [br, bg, bb] = cell.bg;
+                    buf[(area.x + x, area.y + y)]
+                        .set_char(cell.symbol)
+                        .set_fg(Color::Rgb(fr, fg, fb))
+                        .set_bg(Color::Rgb(br, bg, bb));
+                }
+            }
+        }
+    }
+}